@@ -0,0 +1,44 @@
+//! Request colors: anything `graph_theme::parse` reads (hex with optional
+//! alpha, or palette entries like `mocha:blue`), and the `auto` syntax for
+//! deriving one color from another: `auto` is the request's `color`, and
+//! each modifier after it adjusts the result. `+N%` mixes N% towards white,
+//! `-N%` towards black and `@N%` sets the opacity, so `auto-20%@50%` is the
+//! stroke a fifth darker at half opacity.
+
+pub use graph_theme::Rgba;
+
+// Applies "+N%", "-N%" and "@N%" modifiers in order
+fn apply_modifiers(mut color: Rgba, mut modifiers: &str) -> Option<Rgba> {
+    while let Some(op) = modifiers.chars().next() {
+        let (amount, rest) = modifiers[op.len_utf8()..].split_once('%')?;
+        let amount = amount.trim().parse::<f64>().ok().filter(|a| a.is_finite())? / 100.0;
+        color = match op {
+            '+' => color.lighten(amount),
+            '-' => color.darken(amount),
+            '@' => color.with_alpha(amount),
+            _ => return None,
+        };
+        modifiers = rest;
+    }
+    Some(color)
+}
+
+// Palette entries and hex with an alpha digit pair are rewritten; anything
+// else is kept as given
+fn normalize(color: &str) -> String {
+    let plain_hex = color.starts_with('#') && matches!(color.len(), 4 | 7);
+    graph_theme::parse(color).filter(|_| !plain_hex).map_or_else(|| color.to_string(), |c| c.to_string())
+}
+
+/// The SVG paint for `spec`, with `auto` taken from `base`. Colors that
+/// aren't hex or palette entries (named colors, `url(...)`) pass through
+/// unchanged, as does the base when `auto` modifiers can't be applied to it.
+pub fn resolve(spec: &str, base: &str) -> String {
+    let Some(modifiers) = spec.strip_prefix("auto") else {
+        return normalize(spec);
+    };
+    match graph_theme::parse(base).and_then(|color| apply_modifiers(color, modifiers)) {
+        Some(color) => color.to_string(),
+        None => normalize(base),
+    }
+}
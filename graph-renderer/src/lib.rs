@@ -0,0 +1,564 @@
+//! SVG rendering of sparkline charts and value badges for the bar. The
+//! `graph-renderer` binary wraps this for AGS; the service links it
+//! directly.
+//!
+//! The crate follows semver: the request format (see `schema`) and the
+//! items exported here only change incompatibly with a major version, and
+//! CHANGELOG.md lists what each release adds.
+
+#![warn(missing_docs)]
+
+use serde::{Deserialize, Deserializer};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// Color specs, including `auto` colors derived from the stroke.
+pub mod color;
+/// Where rendered frames are written and how old ones are pruned.
+pub mod output;
+pub mod schema;
+
+use output::ManagedOutput;
+use schema::SchemaError;
+
+/// One chart or badge, as the binary reads it from stdin. Build it with
+/// `..Default::default()` for the fields you don't set, so requests keep
+/// compiling as optional fields are added in minor releases.
+#[derive(Debug, Default, Deserialize)]
+pub struct GraphRequest {
+    /// `null` entries (how JSON encodes NaN) are treated as missing samples
+    #[serde(deserialize_with = "deserialize_samples")]
+    pub data: Vec<f64>,
+    /// Values <= 0 or non-finite fall back to the largest sample
+    pub max_value: f64,
+    /// Stroke color; any spec `color::resolve` reads
+    pub color: String,
+    /// Pixels
+    pub width: u32,
+    /// Pixels
+    pub height: u32,
+    /// Explicit destination; when omitted the managed cache output is used
+    #[serde(default)]
+    pub output_path: Option<String>,
+    /// Naming and pruning of cache outputs
+    #[serde(default)]
+    pub managed: Option<ManagedOutput>,
+    /// Window size (in samples) of an optional trailing moving-average line
+    #[serde(default)]
+    pub moving_average: Option<usize>,
+    /// Color of the moving-average line, `#cdd6f4` by default
+    #[serde(default)]
+    pub trend_color: Option<String>,
+    /// Sample timestamps (ms), needed to place events given by timestamp
+    #[serde(default)]
+    pub timestamps: Vec<i64>,
+    /// Flagged points drawn over the series
+    #[serde(default)]
+    pub events: Vec<EventMarker>,
+    /// Compose a value badge (number + unit + sparkline) instead of a plain chart
+    #[serde(default)]
+    pub badge: Option<Badge>,
+    /// Value-dependent colors for stroke and fill, e.g. green from 0, yellow
+    /// from 50, red from 80. Overrides `color` when present.
+    #[serde(default)]
+    pub color_stops: Vec<ColorStop>,
+    /// Area fill under the line, e.g. `auto-20%@50%` (see `color`). Defaults
+    /// to the stroke at 20% opacity.
+    #[serde(default)]
+    pub fill: Option<String>,
+    /// Guide lines and axes behind the series
+    #[serde(default)]
+    pub grid: Grid,
+    /// Label styling
+    #[serde(default)]
+    pub font: Font,
+}
+
+/// Text styling for event labels, the "no data" placeholder and badges.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Font {
+    /// A CSS font list, e.g. "Inter, sans-serif". Without `file` the SVG only
+    /// names it, and each viewer falls back through the list to what it has.
+    pub family: String,
+    /// A TTF, OTF, WOFF or WOFF2 file embedded in the SVG and used ahead of
+    /// `family`, so labels look the same on every machine
+    pub file: Option<PathBuf>,
+    /// Size of event and badge labels; the placeholder and badge value
+    /// scale with the chart
+    pub size: Option<f64>,
+}
+
+impl Default for Font {
+    fn default() -> Self {
+        Font { family: "sans-serif".to_string(), file: None, size: None }
+    }
+}
+
+// What the embedded face is called inside the document
+const EMBEDDED_FAMILY: &str = "graph-renderer-embedded";
+
+impl Font {
+    // The font-family attribute, escaped, with the embedded face first
+    fn family_attr(&self) -> String {
+        match &self.file {
+            Some(_) => escape_xml(&format!("{}, {}", EMBEDDED_FAMILY, self.family)),
+            None => escape_xml(&self.family),
+        }
+    }
+
+    // A <defs> with the file as an @font-face data URI; empty without a
+    // file, or when it can't be read, which leaves the family list to the viewer
+    fn face(&self) -> String {
+        let Some(file) = &self.file else {
+            return String::new();
+        };
+        let Ok(bytes) = fs::read(file) else {
+            return String::new();
+        };
+        let extension = file.extension().and_then(|e| e.to_str()).unwrap_or_default().to_ascii_lowercase();
+        let (mime, format) = match extension.as_str() {
+            "otf" => ("font/otf", "opentype"),
+            "woff" => ("font/woff", "woff"),
+            "woff2" => ("font/woff2", "woff2"),
+            _ => ("font/ttf", "truetype"),
+        };
+        format!(
+            r##"<defs><style>@font-face {{ font-family: "{}"; src: url("data:{};base64,{}") format("{}"); }}</style></defs>"##,
+            EMBEDDED_FAMILY, mime, base64(&bytes), format
+        )
+    }
+}
+
+// Standard alphabet, padded
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Horizontal guide lines and axes behind the series.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Grid {
+    /// Evenly spaced, the last one on the baseline; 0 hides them
+    pub lines: u32,
+    /// An SVG dash pattern, e.g. "2,2"; solid when omitted
+    pub dash: Option<String>,
+    /// Defaults to a faint grey
+    pub color: Option<String>,
+    /// Draws the y axis down the left edge and the x axis along the bottom
+    pub axes: bool,
+    /// Draws the zero baseline stronger than the other lines
+    pub baseline: bool,
+}
+
+impl Default for Grid {
+    fn default() -> Self {
+        Grid { lines: 4, dash: None, color: None, axes: false, baseline: false }
+    }
+}
+
+fn deserialize_samples<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<f64>, D::Error> {
+    let samples: Vec<Option<f64>> = Vec::deserialize(deserializer)?;
+    Ok(samples.into_iter().map(|s| s.unwrap_or(f64::NAN)).collect())
+}
+
+// Rendering rules for awkward input: missing/non-finite samples become gaps in
+// the line, negative values are clamped to the baseline, and a series with no
+// usable samples renders a "no data" placeholder instead of invalid coordinates.
+fn sanitize_samples(data: &[f64]) -> Vec<f64> {
+    data.iter().map(|&v| if v.is_finite() { v.max(0.0) } else { f64::NAN }).collect()
+}
+
+fn effective_max(max_value: f64, data: &[f64]) -> f64 {
+    if max_value.is_finite() && max_value > 0.0 {
+        return max_value;
+    }
+    let largest = data.iter().copied().filter(|v| v.is_finite()).fold(0.0, f64::max);
+    if largest > 0.0 { largest } else { 1.0 }
+}
+
+// Index ranges of consecutive finite samples
+fn finite_runs(data: &[f64]) -> Vec<std::ops::Range<usize>> {
+    let mut runs = Vec::new();
+    let mut start = None;
+    for (i, value) in data.iter().enumerate() {
+        match (value.is_finite(), start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                runs.push(s..i);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        runs.push(s..data.len());
+    }
+    runs
+}
+
+// Whole numbers print without decimals, matching the fill polygon's corners
+fn format_coord(value: f64) -> String {
+    if value.fract() == 0.0 { format!("{}", value) } else { format!("{:.2}", value) }
+}
+
+/// One band of `GraphRequest::color_stops`.
+#[derive(Debug, Deserialize)]
+pub struct ColorStop {
+    /// Lowest value drawn in this color, in the units of `data`
+    pub from: f64,
+    /// Any spec `color::resolve` reads
+    pub color: String,
+}
+
+const RAMP_ID: &str = "value-ramp";
+
+// Vertical gradient in user space so the color at any height matches the value
+// thresholds; doubled stops make the transitions hard edges.
+fn ramp_gradient(stops: &[ColorStop], base: &str, max_value: f64, height: u32) -> String {
+    let mut sorted: Vec<&ColorStop> = stops.iter().filter(|s| s.from.is_finite()).collect();
+    sorted.sort_by(|a, b| a.from.total_cmp(&b.from));
+    
+    let mut stop_elements = String::new();
+    let mut previous: Option<String> = None;
+    for stop in sorted {
+        let offset = (stop.from / max_value).clamp(0.0, 1.0);
+        let color = color::resolve(&stop.color, base);
+        if let Some(previous) = &previous {
+            stop_elements.push_str(&format!(r##"<stop offset="{:.4}" stop-color="{}"/>"##, offset, previous));
+        }
+        stop_elements.push_str(&format!(r##"<stop offset="{:.4}" stop-color="{}"/>"##, offset, color));
+        previous = Some(color);
+    }
+    
+    format!(
+        r##"<defs><linearGradient id="{}" gradientUnits="userSpaceOnUse" x1="0" y1="{}" x2="0" y2="0">{}</linearGradient></defs>"##,
+        RAMP_ID, height, stop_elements
+    )
+}
+
+/// The value, unit and optional label a badge shows beside its sparkline.
+#[derive(Debug, Deserialize)]
+pub struct Badge {
+    /// Defaults to the last data point
+    #[serde(default)]
+    pub value: Option<f64>,
+    /// Appended to the value, e.g. "%" or " MB/s"
+    #[serde(default)]
+    pub unit: String,
+    /// Small text above the value, e.g. "CPU"
+    #[serde(default)]
+    pub label: Option<String>,
+    /// Digits after the decimal point
+    #[serde(default)]
+    pub decimals: usize,
+    /// Text color, `#cdd6f4` by default
+    #[serde(default)]
+    pub color: Option<String>,
+}
+
+/// A flagged point on the chart, e.g. "suspend" or "alert fired". Positioned
+/// by sample index or, with `timestamps` in the request, by timestamp.
+#[derive(Debug, Deserialize)]
+pub struct EventMarker {
+    /// Sample index, fractional between samples; wins over `timestamp`
+    #[serde(default)]
+    pub index: Option<f64>,
+    /// Time in ms, placed against `GraphRequest::timestamps`
+    #[serde(default)]
+    pub timestamp: Option<i64>,
+    /// Text beside the marker line
+    pub label: String,
+    /// Marker color, `#f9e2af` by default
+    #[serde(default)]
+    pub color: Option<String>,
+}
+
+// Horizontal position of an event, or None if it falls outside the chart
+fn event_x(event: &EventMarker, req: &GraphRequest, width: u32) -> Option<f64> {
+    let fraction = if let Some(index) = event.index {
+        index / (req.data.len() - 1).max(1) as f64
+    } else {
+        let timestamp = event.timestamp?;
+        let (&first, &last) = (req.timestamps.first()?, req.timestamps.last()?);
+        if last <= first {
+            return None;
+        }
+        (timestamp - first) as f64 / (last - first) as f64
+    };
+    (0.0..=1.0).contains(&fraction).then_some(fraction * width as f64)
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+// Trailing average over the finite samples in each window; windows without
+// any stay gaps
+fn moving_average(data: &[f64], window: usize) -> Vec<f64> {
+    let window = window.max(1);
+    (0..data.len())
+        .map(|i| {
+            let (sum, count) = data[(i + 1).saturating_sub(window)..=i]
+                .iter()
+                .filter(|v| v.is_finite())
+                .fold((0.0, 0), |(sum, count), v| (sum + v, count + 1));
+            if count > 0 { sum / count as f64 } else { f64::NAN }
+        })
+        .collect()
+}
+
+fn to_points(data: &[f64], run: std::ops::Range<usize>, max_value: f64, width: u32, height: u32) -> String {
+    let last = (data.len() - 1).max(1) as f64;
+    run.map(|i| {
+            let x = (i as f64 / last) * width as f64;
+            let y = height as f64 - (data[i].min(max_value) / max_value) * height as f64;
+            format!("{:.2},{:.2}", x, y)
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn polylines(data: &[f64], max_value: f64, width: u32, height: u32, attributes: &str) -> String {
+    finite_runs(data)
+        .into_iter()
+        .map(|run| format!(r##"<polyline points="{}" {}/>"##, to_points(data, run, max_value, width, height), attributes))
+        .collect::<Vec<String>>()
+        .join("")
+}
+
+fn grid(grid: &Grid, base: &str, width: u32, height: u32) -> String {
+    let (color, opacity) = match &grid.color {
+        Some(color) => (color::resolve(color, base), ""),
+        None => ("#45475a".to_string(), r##" opacity="0.3""##),
+    };
+    let dash = match &grid.dash {
+        Some(dash) => format!(r##" stroke-dasharray="{}""##, dash),
+        None => String::new(),
+    };
+    let mut lines = String::new();
+    for i in 1..=grid.lines {
+        let y = (height as f64 / grid.lines as f64) * i as f64;
+        // The baseline itself is drawn separately when emphasized
+        if grid.baseline && i == grid.lines {
+            continue;
+        }
+        lines.push_str(&format!(
+            r##"<line x1="0" y1="{:.0}" x2="{}" y2="{:.0}" stroke="{}" stroke-width="0.5"{}{}/>"##,
+            y, width, y, color, opacity, dash
+        ));
+    }
+    if grid.baseline {
+        lines.push_str(&format!(
+            r##"<line x1="0" y1="{h:.1}" x2="{w}" y2="{h:.1}" stroke="{c}" stroke-width="1"/>"##,
+            h = height as f64 - 0.5, w = width, c = color
+        ));
+    }
+    if grid.axes {
+        lines.push_str(&format!(
+            r##"<path d="M0.5,0 V{h:.1} H{w}" fill="none" stroke="{c}" stroke-width="1"/>"##,
+            h = height as f64 - 0.5, w = width, c = color
+        ));
+    }
+    lines
+}
+
+// Chart contents (grid, series, overlays) for a width x height area, without
+// the surrounding <svg> element or background
+fn chart_body(req: &GraphRequest, width: u32, height: u32) -> String {
+    let data = &sanitize_samples(&req.data);
+    let max_value = effective_max(req.max_value, data);
+    let family = req.font.family_attr();
+    
+    if !data.iter().any(|v| v.is_finite()) {
+        return format!(
+            r##"<text x="{:.1}" y="{:.1}" text-anchor="middle" dominant-baseline="middle" font-size="{:.1}" font-family="{}" fill="#6c7086">no data</text>"##,
+            width as f64 / 2.0, height as f64 / 2.0, (height as f64 * 0.3).clamp(6.0, 12.0), family
+        );
+    }
+    if data.len() < 2 {
+        return String::new();
+    }
+    
+    let (defs, color) = if req.color_stops.is_empty() {
+        (String::new(), color::resolve(&req.color, &req.color))
+    } else {
+        (ramp_gradient(&req.color_stops, &req.color, max_value, height), format!("url(#{})", RAMP_ID))
+    };
+    // An explicit fill carries its own opacity
+    let fill_paint = match &req.fill {
+        Some(fill) => format!(r##"fill="{}""##, color::resolve(fill, &req.color)),
+        None => format!(r##"fill="{}" opacity="0.2""##, color),
+    };
+    
+    // Line and fill, one piece per run of valid samples
+    let line = polylines(data, max_value, width, height, &format!(
+        r##"fill="none" stroke="{}" stroke-width="2" stroke-linejoin="round""##, color
+    ));
+    let last = (data.len() - 1) as f64;
+    let fill: String = finite_runs(data)
+        .into_iter()
+        .map(|run| {
+            let start_x = format_coord(run.start as f64 / last * width as f64);
+            let end_x = format_coord((run.end - 1) as f64 / last * width as f64);
+            format!(
+                r##"<polygon points="{},{} {} {},{}" {}/>"##,
+                start_x, height, to_points(data, run, max_value, width, height), end_x, height, fill_paint
+            )
+        })
+        .collect();
+    
+    // Generate grid lines
+    let grid_lines = grid(&req.grid, &req.color, width, height);
+    
+    // Optional smoothed trend drawn over the raw series
+    let trend_line = match req.moving_average {
+        Some(window) if window > 1 => polylines(&moving_average(data, window), max_value, width, height, &format!(
+            r##"fill="none" stroke="{}" stroke-width="1.5" stroke-linejoin="round" opacity="0.9""##,
+            color::resolve(req.trend_color.as_deref().unwrap_or("#cdd6f4"), &req.color)
+        )),
+        _ => String::new(),
+    };
+    
+    let label_size = req.font.size.filter(|s| s.is_finite() && *s > 0.0).unwrap_or(8.0);
+    let mut event_markers = String::new();
+    for event in &req.events {
+        let Some(x) = event_x(event, req, width) else { continue };
+        let event_color = color::resolve(event.color.as_deref().unwrap_or("#f9e2af"), &req.color);
+        event_markers.push_str(&format!(
+            r##"<g class="event"><line x1="{x:.2}" y1="0" x2="{x:.2}" y2="{h}" stroke="{c}" stroke-width="1" stroke-dasharray="2,2" opacity="0.8"/><path d="M{x:.2},0 l5,2.5 l-5,2.5 z" fill="{c}"/><text x="{tx:.2}" y="{ty}" font-size="{size}" font-family="{family}" fill="{c}">{label}</text></g>"##,
+            x = x,
+            ty = format_coord(label_size + 4.0),
+            size = format_coord(label_size),
+            family = family,
+            h = height,
+            c = event_color,
+            tx = x + 2.0,
+            label = escape_xml(&event.label)
+        ));
+    }
+    
+    format!(
+        r##"{}{}
+            {}
+            {}
+            {}
+            {}"##,
+        defs, grid_lines, fill, line, trend_line, event_markers
+    )
+}
+
+// Big current value + unit on the left, sparkline on the right
+fn generate_badge(req: &GraphRequest, badge: &Badge) -> String {
+    let width = req.width;
+    let height = req.height;
+    let value = badge.value.or_else(|| req.data.iter().rev().copied().find(|v| v.is_finite()));
+    let text_width = (width as f64 * 0.45).round() as u32;
+    let spark_width = width.saturating_sub(text_width + 4);
+    let spark_height = height.saturating_sub(8);
+    let value_size = height as f64 * if badge.label.is_some() { 0.45 } else { 0.55 };
+    let baseline = height as f64 * if badge.label.is_some() { 0.85 } else { 0.7 };
+    
+    let value_text = match value.filter(|v| v.is_finite()) {
+        Some(value) => format!("{:.*}", badge.decimals, value),
+        None => "–".to_string(),
+    };
+    
+    let family = req.font.family_attr();
+    let label_size = req.font.size.filter(|s| s.is_finite() && *s > 0.0).unwrap_or(height as f64 * 0.22);
+    let label = match &badge.label {
+        Some(label) => format!(
+            r##"<text x="4" y="{:.1}" font-size="{:.1}" font-family="{}" fill="#a6adc8">{}</text>"##,
+            height as f64 * 0.3, label_size, family, escape_xml(label)
+        ),
+        None => String::new(),
+    };
+    
+    format!(
+        r##"<svg width="{}" height="{}" xmlns="http://www.w3.org/2000/svg">{}
+            <rect width="{}" height="{}" fill="#1e1e2e" opacity="0.3" rx="4"/>
+            {}
+            <text x="4" y="{:.1}" font-size="{:.1}" font-weight="bold" font-family="{}" fill="{}">{}<tspan font-size="{:.1}" font-weight="normal" fill="#a6adc8">{}</tspan></text>
+            <g transform="translate({},4)">{}</g>
+        </svg>"##,
+        width, height, req.font.face(), width, height, label,
+        baseline, value_size, family, color::resolve(badge.color.as_deref().unwrap_or("#cdd6f4"), &req.color), value_text,
+        value_size * 0.5, escape_xml(&badge.unit),
+        text_width, chart_body(req, spark_width, spark_height)
+    )
+}
+
+/// Renders a parsed request to an SVG document. Never fails: bad samples
+/// become gaps or a "no data" placeholder rather than invalid coordinates.
+pub fn render_request(req: &GraphRequest) -> String {
+    if let Some(badge) = &req.badge {
+        return generate_badge(req, badge);
+    }
+    
+    let width = req.width;
+    let height = req.height;
+    format!(
+        r##"<svg width="{}" height="{}" xmlns="http://www.w3.org/2000/svg">{}
+            <rect width="{}" height="{}" fill="#1e1e2e" opacity="0.3" rx="4"/>
+            {}
+        </svg>"##,
+        width, height, req.font.face(), width, height, chart_body(req, width, height)
+    )
+}
+
+/// Why a JSON request couldn't be rendered. New variants may come with
+/// minor releases, so matches need a wildcard arm.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum RenderError {
+    /// The input isn't JSON, or doesn't deserialize as a `GraphRequest`.
+    InvalidRequest(serde_json::Error),
+    /// The JSON doesn't match `schema::request_schema`.
+    Schema(SchemaError),
+    /// `font.file` can't be read.
+    Font(PathBuf, io::Error),
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenderError::InvalidRequest(e) => write!(f, "invalid request: {}", e),
+            RenderError::Schema(e) => write!(f, "invalid request: {}", e),
+            RenderError::Font(path, e) => write!(f, "font file {}: {}", path.display(), e),
+        }
+    }
+}
+
+impl std::error::Error for RenderError {}
+
+/// Parses and validates a request, so mistakes are reported by path (e.g.
+/// `request.events[1].label`) rather than as a byte offset.
+pub fn parse_request(json: &str) -> Result<GraphRequest, RenderError> {
+    let value: serde_json::Value = serde_json::from_str(json).map_err(RenderError::InvalidRequest)?;
+    schema::validate(&value).map_err(RenderError::Schema)?;
+    let request: GraphRequest = serde_json::from_value(value).map_err(RenderError::InvalidRequest)?;
+    // render_request would only fall back to the family list
+    if let Some(file) = request.font.file.as_deref() {
+        fs::File::open(file).map_err(|e| RenderError::Font(file.to_path_buf(), e))?;
+    }
+    Ok(request)
+}
+
+/// Renders a JSON request, as the binary accepts it, to an SVG document.
+pub fn render(json: &str) -> Result<String, RenderError> {
+    parse_request(json).map(|request| render_request(&request))
+}
@@ -0,0 +1,123 @@
+use serde::Deserialize;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const DEFAULT_MAX_AGE_SECS: u64 = 300;
+const DEFAULT_MAX_FILES: usize = 200;
+
+/// Managed output: frames go to an XDG cache subdirectory and old ones are
+/// pruned on every render, so per-second callers don't fill /tmp.
+#[derive(Debug, Deserialize)]
+pub struct ManagedOutput {
+    /// File name prefix, e.g. "cpu" gives cpu-<timestamp>.svg
+    #[serde(default = "default_name")]
+    pub name: String,
+    /// Frames older than this are pruned
+    #[serde(default = "default_max_age")]
+    pub max_age_secs: u64,
+    /// Frames beyond the newest this many are pruned
+    #[serde(default = "default_max_files")]
+    pub max_files: usize,
+}
+
+fn default_name() -> String {
+    "graph".to_string()
+}
+
+fn default_max_age() -> u64 {
+    DEFAULT_MAX_AGE_SECS
+}
+
+fn default_max_files() -> usize {
+    DEFAULT_MAX_FILES
+}
+
+impl Default for ManagedOutput {
+    fn default() -> Self {
+        ManagedOutput {
+            name: default_name(),
+            max_age_secs: DEFAULT_MAX_AGE_SECS,
+            max_files: DEFAULT_MAX_FILES,
+        }
+    }
+}
+
+/// `$XDG_CACHE_HOME/graph-renderer`, where managed frames go.
+pub fn cache_dir() -> PathBuf {
+    let base = env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .unwrap_or_else(|| PathBuf::from(env::var("HOME").unwrap_or_default()).join(".cache"));
+    base.join("graph-renderer")
+}
+
+fn sanitize(name: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    if cleaned.is_empty() { default_name() } else { cleaned }
+}
+
+impl ManagedOutput {
+    /// A fresh, unique path in the cache dir. Unique names matter because GTK
+    /// caches images by file name.
+    pub fn next_path(&self, extension: &str) -> io::Result<PathBuf> {
+        let dir = cache_dir();
+        fs::create_dir_all(&dir)?;
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+        Ok(dir.join(format!("{}-{}-{}.{}", sanitize(&self.name), nanos, std::process::id(), extension)))
+    }
+
+    /// `prune_named` on the cache dir with this output's name and limits, so
+    /// outputs sharing the cache leave each other's frames alone.
+    pub fn prune(&self) -> io::Result<usize> {
+        prune_named(&cache_dir(), &self.name, Duration::from_secs(self.max_age_secs), self.max_files.max(1))
+    }
+}
+
+// The name a frame from `next_path` was written under:
+// `<name>-<nanos>-<pid>.<extension>`
+fn frame_name(path: &Path) -> Option<&str> {
+    let (rest, _pid) = path.file_stem()?.to_str()?.rsplit_once('-')?;
+    Some(rest.rsplit_once('-')?.0)
+}
+
+/// Deletes frames older than `max_age` and all but the newest `max_files`.
+/// Returns how many files were removed.
+pub fn prune_dir(dir: &Path, max_age: Duration, max_files: usize) -> io::Result<usize> {
+    prune_matching(dir, max_age, max_files, |_| true)
+}
+
+/// `prune_dir` limited to the frames of the managed output called `name`.
+pub fn prune_named(dir: &Path, name: &str, max_age: Duration, max_files: usize) -> io::Result<usize> {
+    let name = sanitize(name);
+    prune_matching(dir, max_age, max_files, |path| frame_name(path) == Some(name.as_str()))
+}
+
+fn prune_matching(dir: &Path, max_age: Duration, max_files: usize, matches: impl Fn(&Path) -> bool) -> io::Result<usize> {
+    let now = SystemTime::now();
+    let mut frames: Vec<(SystemTime, PathBuf)> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .flatten()
+            .filter(|e| e.path().extension().is_some_and(|ext| ext == "svg" || ext == "png"))
+            .filter(|e| matches(&e.path()))
+            .filter_map(|e| Some((e.metadata().ok()?.modified().ok()?, e.path())))
+            .collect(),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e),
+    };
+    frames.sort_by_key(|frame| std::cmp::Reverse(frame.0));
+
+    let mut removed = 0;
+    for (i, (modified, path)) in frames.iter().enumerate() {
+        let expired = now.duration_since(*modified).unwrap_or_default() > max_age;
+        if (expired || i >= max_files) && fs::remove_file(path).is_ok() {
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
@@ -0,0 +1,246 @@
+//! The JSON Schema requests are checked against before rendering, also
+//! printed by `graph-renderer --schema` so request builders on the GJS side
+//! can be validated in CI. Only the keywords used here are understood by
+//! `validate`: `type`, `properties`, `required`, `additionalProperties`,
+//! `items`, `minimum`, `anyOf` and `$ref` into `$defs`.
+
+use serde_json::{json, Map, Value};
+use std::fmt;
+
+/// Every request format, with `GraphRequest` as the root.
+pub fn request_schema() -> Value {
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "GraphRequest",
+        "$ref": "#/$defs/GraphRequest",
+        "$defs": {
+            "GraphRequest": {
+                "type": "object",
+                "required": ["data", "max_value", "color", "width", "height"],
+                "additionalProperties": false,
+                "properties": {
+                    "data": {
+                        "description": "Samples; null marks a missing one",
+                        "type": "array",
+                        "items": { "type": ["number", "null"] }
+                    },
+                    "max_value": {
+                        "description": "Values <= 0 fall back to the largest sample",
+                        "type": "number"
+                    },
+                    "color": { "type": "string" },
+                    "width": { "type": "integer", "minimum": 0 },
+                    "height": { "type": "integer", "minimum": 0 },
+                    "output_path": { "type": ["string", "null"] },
+                    "managed": {
+                        "anyOf": [{ "$ref": "#/$defs/ManagedOutput" }, { "type": "null" }]
+                    },
+                    "moving_average": { "type": ["integer", "null"], "minimum": 0 },
+                    "trend_color": { "type": ["string", "null"] },
+                    "timestamps": {
+                        "description": "Sample times in ms",
+                        "type": "array",
+                        "items": { "type": "integer" }
+                    },
+                    "events": { "type": "array", "items": { "$ref": "#/$defs/EventMarker" } },
+                    "badge": {
+                        "anyOf": [{ "$ref": "#/$defs/Badge" }, { "type": "null" }]
+                    },
+                    "color_stops": { "type": "array", "items": { "$ref": "#/$defs/ColorStop" } },
+                    "fill": {
+                        "description": "Fill color, or the stroke adjusted as in auto-20%@50%",
+                        "type": ["string", "null"]
+                    },
+                    "grid": { "$ref": "#/$defs/Grid" },
+                    "font": { "$ref": "#/$defs/Font" }
+                }
+            },
+            "ManagedOutput": {
+                "type": "object",
+                "additionalProperties": false,
+                "properties": {
+                    "name": { "type": "string" },
+                    "max_age_secs": { "type": "integer", "minimum": 0 },
+                    "max_files": { "type": "integer", "minimum": 0 }
+                }
+            },
+            "EventMarker": {
+                "type": "object",
+                "required": ["label"],
+                "additionalProperties": false,
+                "properties": {
+                    "index": { "type": ["number", "null"] },
+                    "timestamp": { "type": ["integer", "null"] },
+                    "label": { "type": "string" },
+                    "color": { "type": ["string", "null"] }
+                }
+            },
+            "Badge": {
+                "type": "object",
+                "additionalProperties": false,
+                "properties": {
+                    "value": { "type": ["number", "null"] },
+                    "unit": { "type": "string" },
+                    "label": { "type": ["string", "null"] },
+                    "decimals": { "type": "integer", "minimum": 0 },
+                    "color": { "type": ["string", "null"] }
+                }
+            },
+            "Grid": {
+                "type": "object",
+                "additionalProperties": false,
+                "properties": {
+                    "lines": { "type": "integer", "minimum": 0 },
+                    "dash": { "type": ["string", "null"] },
+                    "color": { "type": ["string", "null"] },
+                    "axes": { "type": "boolean" },
+                    "baseline": { "type": "boolean" }
+                }
+            },
+            "Font": {
+                "type": "object",
+                "additionalProperties": false,
+                "properties": {
+                    "family": { "type": "string" },
+                    "file": { "type": ["string", "null"] },
+                    "size": { "type": ["number", "null"], "minimum": 0 }
+                }
+            },
+            "ColorStop": {
+                "type": "object",
+                "required": ["from", "color"],
+                "additionalProperties": false,
+                "properties": {
+                    "from": { "type": "number" },
+                    "color": { "type": "string" }
+                }
+            }
+        }
+    })
+}
+
+/// Where a request breaks the schema, e.g. `request.events[1]: missing
+/// required property "label"`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaError {
+    /// `request` for the root, then keys and indices as in JavaScript
+    pub path: String,
+    /// What's wrong there, e.g. `expected string, got an integer`
+    pub message: String,
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(n) if n.is_i64() || n.is_u64() => "an integer",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
+
+fn has_type(value: &Value, name: &str) -> bool {
+    match name {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        "string" => value.is_string(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        _ => false,
+    }
+}
+
+struct Validator<'a> {
+    defs: &'a Map<String, Value>,
+}
+
+impl Validator<'_> {
+    fn resolve<'s>(&'s self, schema: &'s Value) -> &'s Value {
+        match schema.get("$ref").and_then(Value::as_str).and_then(|r| r.strip_prefix("#/$defs/")) {
+            Some(name) => self.defs.get(name).map(|def| self.resolve(def)).unwrap_or(schema),
+            None => schema,
+        }
+    }
+
+    fn check(&self, schema: &Value, value: &Value, path: &str) -> Result<(), SchemaError> {
+        let schema = self.resolve(schema);
+        let fail = |message: String| Err(SchemaError { path: path.to_string(), message });
+
+        if let Some(options) = schema.get("anyOf").and_then(Value::as_array) {
+            // Report against the option of the value's own kind, so a badge
+            // that is an object with a bad field says which field
+            let matching = options.iter().find(|option| {
+                let option = self.resolve(option);
+                option.get("type").and_then(Value::as_str).is_some_and(|t| has_type(value, t))
+            });
+            return match matching {
+                Some(option) => self.check(option, value, path),
+                None => {
+                    let kinds: Vec<&str> = options.iter().filter_map(|o| self.resolve(o).get("type")?.as_str()).collect();
+                    fail(format!("expected {}, got {}", kinds.join(" or "), type_name(value)))
+                }
+            };
+        }
+
+        let types: Vec<&str> = match schema.get("type") {
+            Some(Value::String(t)) => vec![t.as_str()],
+            Some(Value::Array(ts)) => ts.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !types.is_empty() && !types.iter().any(|t| has_type(value, t)) {
+            return fail(format!("expected {}, got {}", types.join(" or "), type_name(value)));
+        }
+
+        if let (Some(minimum), Some(number)) = (schema.get("minimum").and_then(Value::as_f64), value.as_f64()) {
+            if number < minimum {
+                return fail(format!("must be at least {}, got {}", minimum, value));
+            }
+        }
+
+        if let (Some(items), Some(array)) = (schema.get("items"), value.as_array()) {
+            for (i, item) in array.iter().enumerate() {
+                self.check(items, item, &format!("{}[{}]", path, i))?;
+            }
+        }
+
+        if let Some(object) = value.as_object() {
+            let properties = schema.get("properties").and_then(Value::as_object);
+            for (key, item) in object {
+                match properties.and_then(|p| p.get(key)) {
+                    Some(property) => self.check(property, item, &format!("{}.{}", path, key))?,
+                    None if schema.get("additionalProperties") == Some(&Value::Bool(false)) => {
+                        return fail(format!("unknown property \"{}\"", key));
+                    }
+                    None => {}
+                }
+            }
+            // After the fields that are there, which say more about what's wrong
+            for required in schema.get("required").and_then(Value::as_array).into_iter().flatten().filter_map(Value::as_str) {
+                if !object.contains_key(required) {
+                    return fail(format!("missing required property \"{}\"", required));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Checks a parsed request against `request_schema`, stopping at the first
+/// problem.
+pub fn validate(value: &Value) -> Result<(), SchemaError> {
+    let schema = request_schema();
+    let defs = schema["$defs"].as_object().expect("schema has $defs");
+    Validator { defs }.check(&schema, value, "request")
+}
@@ -0,0 +1,43 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+pub struct RenderResult {
+    pub code: Option<i32>,
+    pub svg: Option<String>,
+    pub stderr: String,
+}
+
+fn scratch_path() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("graph-renderer-tests-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir.join(format!("out-{}.svg", COUNTER.fetch_add(1, Ordering::SeqCst)))
+}
+
+/// Runs the binary on raw input, writing into a scratch file.
+pub fn render_raw(input: &str) -> RenderResult {
+    let output = Command::new(env!("CARGO_BIN_EXE_graph-renderer"))
+        .arg(input)
+        .output()
+        .expect("failed to run graph-renderer");
+    let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    let svg = if output.status.success() { fs::read_to_string(&stdout).ok() } else { None };
+    if output.status.success() {
+        let _ = fs::remove_file(&stdout);
+    }
+    RenderResult {
+        code: output.status.code(),
+        svg,
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+    }
+}
+
+/// Renders a request object, filling in `output_path`.
+pub fn render(mut request: serde_json::Value) -> RenderResult {
+    let path = scratch_path();
+    request["output_path"] = serde_json::Value::String(path.to_string_lossy().into_owned());
+    render_raw(&request.to_string())
+}
@@ -0,0 +1,126 @@
+//! Deterministic fuzzing of request parsing and rendering: malformed JSON must
+//! be rejected with an error exit (never a panic), and any accepted request
+//! must produce a well-formed SVG with only finite coordinates.
+
+mod common;
+
+use serde_json::{json, Value};
+
+const CASES: usize = 200;
+
+// xorshift64*, seeded so failures are reproducible
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n.max(1)
+    }
+
+    fn float(&mut self, max: f64) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64 * max
+    }
+
+    fn chance(&mut self, percent: u64) -> bool {
+        self.below(100) < percent
+    }
+}
+
+fn random_request(rng: &mut Rng) -> Value {
+    let len = rng.below(70) as usize;
+    let max_value = match rng.below(10) {
+        0 => 0.0,
+        1 => -rng.float(100.0),
+        2 => 1e300,
+        _ => 1.0 + rng.float(1000.0),
+    };
+    let scale = max_value.abs().max(1.0) * 1.5;
+    let data: Vec<Value> = (0..len)
+        .map(|_| match rng.below(10) {
+            0 => Value::Null,
+            1 => json!(-rng.float(scale)),
+            2 => json!(0.0),
+            _ => json!(rng.float(scale)),
+        })
+        .collect();
+    let mut request = json!({
+        "data": data,
+        "max_value": max_value,
+        "color": "#89b4fa",
+        "width": rng.below(400),
+        "height": rng.below(200),
+    });
+    if rng.chance(30) {
+        request["moving_average"] = json!(rng.below(20));
+    }
+    if rng.chance(30) {
+        request["timestamps"] = json!((0..len as i64).map(|i| i * 1000).collect::<Vec<_>>());
+        request["events"] = json!([
+            { "index": rng.float(len as f64 + 5.0), "label": "a & b" },
+            { "timestamp": rng.below(len as u64 * 1000 + 1), "label": "t" }
+        ]);
+    }
+    if rng.chance(30) {
+        request["badge"] = json!({ "unit": "%", "decimals": rng.below(4) });
+    }
+    if rng.chance(30) {
+        request["color_stops"] = json!([{ "from": rng.float(max_value), "color": "red" }, { "from": 0, "color": "green" }]);
+    }
+    request
+}
+
+fn mutate(rng: &mut Rng, input: &str) -> String {
+    let mut bytes = input.as_bytes().to_vec();
+    match rng.below(4) {
+        0 => bytes.truncate(rng.below(bytes.len() as u64) as usize),
+        1 => {
+            for _ in 0..=rng.below(5) {
+                let i = rng.below(bytes.len() as u64) as usize;
+                bytes[i] = b"{}[],:\"0-e.x "[rng.below(13) as usize];
+            }
+        }
+        2 => return input.replacen("\"data\":[", "\"data\":\"", 1),
+        _ => return input.replacen("\"width\":", "\"width\":-", 1),
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+fn assert_well_formed(svg: &str, input: &str) {
+    let trimmed = svg.trim();
+    assert!(trimmed.starts_with("<svg") && trimmed.ends_with("</svg>"), "malformed SVG for {}", input);
+    for bad in ["NaN", "inf"] {
+        assert!(!svg.contains(bad), "non-finite coordinate ({}) for {}", bad, input);
+    }
+}
+
+#[test]
+fn random_valid_requests_render() {
+    let mut rng = Rng(0x5eed_1234_abcd_0001);
+    for _ in 0..CASES {
+        let request = random_request(&mut rng);
+        let result = common::render(request.clone());
+        assert_eq!(result.code, Some(0), "rejected {}: {}", request, result.stderr);
+        assert_well_formed(&result.svg.unwrap(), &request.to_string());
+    }
+}
+
+#[test]
+fn malformed_requests_are_rejected_without_panicking() {
+    let mut rng = Rng(0x5eed_1234_abcd_0002);
+    for _ in 0..CASES {
+        let mut request = random_request(&mut rng);
+        request["output_path"] = json!(std::env::temp_dir().join("graph-renderer-fuzz.svg"));
+        let input = mutate(&mut rng, &request.to_string());
+        let result = common::render_raw(&input);
+        assert!(matches!(result.code, Some(0) | Some(1)), "crashed on {}: {}", input, result.stderr);
+        if let Some(svg) = result.svg {
+            assert_well_formed(&svg, &input);
+        }
+    }
+}
@@ -0,0 +1,180 @@
+//! Compares rendered SVGs against the files in tests/golden. Run with
+//! `UPDATE_GOLDEN=1 cargo test --test golden` to regenerate after an
+//! intentional rendering change, then review the diff.
+
+mod common;
+
+use serde_json::json;
+use std::fs;
+use std::path::PathBuf;
+
+fn base() -> serde_json::Value {
+    json!({
+        "data": [10.0, 35.5, 20.0, 80.0, 55.0, 95.0, 40.0, 60.0],
+        "max_value": 100.0,
+        "color": "#89b4fa",
+        "width": 120,
+        "height": 40
+    })
+}
+
+fn with(extra: serde_json::Value) -> serde_json::Value {
+    let mut request = base();
+    for (key, value) in extra.as_object().unwrap() {
+        request[key] = value.clone();
+    }
+    request
+}
+
+fn check(name: &str, request: serde_json::Value) {
+    let result = common::render(request);
+    assert_eq!(result.code, Some(0), "{} failed: {}", name, result.stderr);
+    let svg = result.svg.expect("no output");
+
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(format!("{}.svg", name));
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&path, &svg).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&path)
+        .unwrap_or_else(|_| panic!("missing golden file {}; run with UPDATE_GOLDEN=1", path.display()));
+    assert_eq!(svg, expected, "{} differs from golden file {}", name, path.display());
+}
+
+#[test]
+fn line_chart() {
+    check("line", base());
+}
+
+#[test]
+fn single_point() {
+    check("single_point", with(json!({ "data": [42.0] })));
+}
+
+#[test]
+fn values_above_max_are_clipped() {
+    check("clipped", with(json!({ "max_value": 50.0 })));
+}
+
+#[test]
+fn moving_average() {
+    check("moving_average", with(json!({ "moving_average": 3, "trend_color": "#f9e2af" })));
+}
+
+#[test]
+fn events_by_index_and_timestamp() {
+    check("events", with(json!({
+        "timestamps": [0, 1000, 2000, 3000, 4000, 5000, 6000, 7000],
+        "events": [
+            { "index": 2, "label": "suspend" },
+            { "timestamp": 5500, "label": "alert <cpu>", "color": "#f38ba8" },
+            { "index": 99, "label": "outside" }
+        ]
+    })));
+}
+
+#[test]
+fn badge() {
+    check("badge", with(json!({ "badge": { "unit": "%" } })));
+}
+
+#[test]
+fn badge_with_label() {
+    check("badge_label", with(json!({
+        "width": 160,
+        "height": 32,
+        "badge": { "value": 12.345, "unit": "MB/s", "label": "NET", "decimals": 1, "color": "#a6e3a1" }
+    })));
+}
+
+#[test]
+fn color_stops() {
+    check("color_stops", with(json!({
+        "color_stops": [
+            { "from": 80, "color": "#f38ba8" },
+            { "from": 0, "color": "#a6e3a1" },
+            { "from": 50, "color": "#f9e2af" }
+        ]
+    })));
+}
+
+#[test]
+fn colors_derived_from_the_stroke() {
+    check("derived_colors", with(json!({
+        "color": "#89b4faff",
+        "fill": "auto-20%@50%",
+        "grid": { "color": "auto+60%@25%" },
+        "moving_average": 3,
+        "trend_color": "auto+40%",
+        "events": [{ "index": 2, "label": "x", "color": "#f9e2af80" }]
+    })));
+}
+
+#[test]
+fn configured_grid() {
+    check("grid", with(json!({
+        "grid": { "lines": 2, "dash": "2,2", "axes": true, "baseline": true }
+    })));
+    check("no_grid", with(json!({ "grid": { "lines": 0 } })));
+}
+
+#[test]
+fn custom_font() {
+    check("font", with(json!({
+        "font": { "family": "Inter, \"Noto Sans\", sans-serif", "size": 10 },
+        "events": [{ "index": 3, "label": "deploy" }],
+        "badge": { "unit": "%", "label": "CPU" }
+    })));
+}
+
+#[test]
+fn embedded_font() {
+    let file = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fonts/stub.ttf");
+    let request = with(json!({
+        "font": { "family": "sans-serif", "file": file },
+        "events": [{ "index": 3, "label": "deploy" }],
+        "badge": { "unit": "%", "label": "CPU" }
+    }));
+    check("font_embedded", request.clone());
+    // The file's bytes travel with the document, so no viewer needs the font
+    let svg = common::render(request).svg.unwrap();
+    assert!(svg.contains(r#"src: url("data:font/ttf;base64,c3RhbmQtaW4gZm9udCBieXRlcwABAv8=") format("truetype")"#), "{}", svg);
+    assert!(!svg.contains("stub.ttf"), "{}", svg);
+    assert_eq!(svg.matches(r#"font-family="graph-renderer-embedded, sans-serif""#).count(), 3, "{}", svg);
+
+    check("font_embedded_chart", with(json!({ "font": { "file": file }, "data": [] })));
+}
+
+#[test]
+fn combined() {
+    check("combined", with(json!({
+        "moving_average": 4,
+        "events": [{ "index": 3.5, "label": "game" }],
+        "color_stops": [{ "from": 0, "color": "#a6e3a1" }, { "from": 90, "color": "#f38ba8" }],
+        "badge": { "unit": "%" }
+    })));
+}
+
+#[test]
+fn missing_samples_leave_gaps() {
+    check("gaps", with(json!({
+        "data": [10.0, null, 30.0, 40.0, null, null, 70.0, 20.0],
+        "moving_average": 2
+    })));
+}
+
+#[test]
+fn negative_values_clamp_to_baseline() {
+    check("negative", with(json!({ "data": [-20.0, 10.0, -5.0, 50.0, 30.0] })));
+}
+
+#[test]
+fn all_zero_series_with_zero_max() {
+    check("all_zero", with(json!({ "data": [0.0, 0.0, 0.0, 0.0], "max_value": 0.0 })));
+}
+
+#[test]
+fn empty_or_all_missing_shows_placeholder() {
+    check("no_data", with(json!({ "data": [] })));
+    check("no_data_nulls", with(json!({ "data": [null, null, null], "badge": { "unit": "%" } })));
+}
@@ -0,0 +1,109 @@
+//! The library API renders exactly what the binary writes, so callers that
+//! link it (the service's alert thumbnails) match the bar.
+
+use graph_renderer::{GraphRequest, RenderError};
+use std::fs;
+use std::path::PathBuf;
+
+fn golden(name: &str) -> String {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(format!("{}.svg", name));
+    fs::read_to_string(path).unwrap()
+}
+
+#[test]
+fn json_requests_match_the_golden_files() {
+    let request = r##"{"data":[10.0,35.5,20.0,80.0,55.0,95.0,40.0,60.0],"max_value":100.0,"color":"#89b4fa","width":120,"height":40}"##;
+    assert_eq!(graph_renderer::render(request).unwrap(), golden("line"));
+}
+
+#[test]
+fn typed_requests_need_only_the_chart_fields() {
+    let request = GraphRequest {
+        data: vec![10.0, 35.5, 20.0, 80.0, 55.0, 95.0, 40.0, 60.0],
+        max_value: 100.0,
+        color: "#89b4fa".to_string(),
+        width: 120,
+        height: 40,
+        ..Default::default()
+    };
+    assert_eq!(graph_renderer::render_request(&request), golden("line"));
+}
+
+#[test]
+fn invalid_json_is_an_error() {
+    let error = graph_renderer::render(r#"{"data": [1.0"#).unwrap_err();
+    assert!(matches!(error, RenderError::InvalidRequest(_)));
+    assert!(error.to_string().starts_with("invalid request"));
+}
+
+#[test]
+fn a_missing_font_file_is_an_error() {
+    let json = r##"{"data": [1, 2], "max_value": 10, "color": "#89b4fa", "width": 40, "height": 20, "font": {"file": "/nonexistent/font.ttf"}}"##;
+    let error = graph_renderer::render(json).unwrap_err();
+    assert!(matches!(error, RenderError::Font(_, _)));
+    assert!(error.to_string().starts_with("font file /nonexistent/font.ttf: "), "{}", error);
+}
+
+#[test]
+fn schema_errors_name_the_offending_field() {
+    let cases = [
+        (r#"{"data": "nope"}"#, "request.data: expected array, got a string"),
+        (
+            r##"{"data":[1,null,"x"],"max_value":1,"color":"#fff","width":1,"height":1}"##,
+            "request.data[2]: expected number or null, got a string",
+        ),
+        (
+            r##"{"data":[],"max_value":1,"color":"#fff","width":-4,"height":1}"##,
+            "request.width: must be at least 0, got -4",
+        ),
+        (
+            r##"{"data":[],"max_value":1,"color":"#fff","width":1,"height":1,"events":[{"label":"a"},{"index":2}]}"##,
+            "request.events[1]: missing required property \"label\"",
+        ),
+        (
+            r##"{"data":[],"max_value":1,"colour":"#fff","width":1,"height":1}"##,
+            "request: unknown property \"colour\"",
+        ),
+        (
+            r##"{"data":[],"max_value":1,"color":"#fff","width":1,"height":1,"badge":{"unit":"%","decimal":1}}"##,
+            "request.badge: unknown property \"decimal\"",
+        ),
+    ];
+    for (json, message) in cases {
+        match graph_renderer::parse_request(json) {
+            Err(RenderError::Schema(error)) => assert_eq!(error.to_string(), message, "for {}", json),
+            other => panic!("expected a schema error for {}, got {:?}", json, other),
+        }
+    }
+}
+
+#[test]
+fn palette_colors_render_as_their_hex() {
+    let request = r##"{"data":[10.0,35.5,20.0,80.0,55.0,95.0,40.0,60.0],"max_value":100.0,"color":"mocha:blue","width":120,"height":40}"##;
+    assert_eq!(graph_renderer::render(request).unwrap(), golden("line"));
+}
+
+#[test]
+fn outputs_sharing_a_directory_prune_only_their_own_frames() {
+    use graph_renderer::output;
+    use std::time::{Duration, SystemTime};
+
+    let dir = std::env::temp_dir().join(format!("graph-renderer-prune-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let now = SystemTime::now();
+    // `cpu-temp` starts like `cpu`, but is another output
+    let frames = ["cpu-1-10.svg", "cpu-2-10.svg", "cpu-3-10.svg", "cpu-temp-1-10.svg", "cpu-temp-2-10.svg", "net-1-10.png"];
+    for (i, name) in frames.iter().enumerate() {
+        let file = fs::File::create(dir.join(name)).unwrap();
+        file.set_modified(now - Duration::from_secs(60 - i as u64)).unwrap();
+    }
+
+    let hour = Duration::from_secs(3600);
+    assert_eq!(output::prune_named(&dir, "cpu", hour, 1).unwrap(), 2);
+    assert_eq!(output::prune_named(&dir, "net", hour, 5).unwrap(), 0);
+    let mut left: Vec<String> = fs::read_dir(&dir).unwrap().map(|e| e.unwrap().file_name().into_string().unwrap()).collect();
+    left.sort();
+    assert_eq!(left, ["cpu-3-10.svg", "cpu-temp-1-10.svg", "cpu-temp-2-10.svg", "net-1-10.png"]);
+    fs::remove_dir_all(&dir).unwrap();
+}
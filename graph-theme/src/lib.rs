@@ -0,0 +1,181 @@
+//! Colors shared by graph-renderer and graph-window: hex parsing with
+//! alpha, the Catppuccin and Nord palettes, and theme files. A color spec
+//! is either hex (`#RGB`, `#RGBA`, `#RRGGBB`, `#RRGGBBAA`) or a palette
+//! entry as `<palette>:<name>`, e.g. `mocha:blue` or `nord:nord8`.
+//!
+//! The crate follows semver; CHANGELOG.md lists what each release adds.
+
+#![warn(missing_docs)]
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// The built-in palettes.
+pub mod palettes;
+
+pub use palettes::{Palette, PALETTES};
+
+/// An 8-bit color with alpha.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rgba {
+    /// Red
+    pub r: u8,
+    /// Green
+    pub g: u8,
+    /// Blue
+    pub b: u8,
+    /// Alpha, 255 for opaque
+    pub a: u8,
+}
+
+impl Rgba {
+    /// Parses `#RGB`, `#RGBA`, `#RRGGBB` or `#RRGGBBAA`.
+    pub fn parse(hex: &str) -> Option<Rgba> {
+        let digits = hex.strip_prefix('#')?;
+        if !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        let width = match digits.len() {
+            3 | 4 => 1,
+            6 | 8 => 2,
+            _ => return None,
+        };
+        let channel = |i: usize| {
+            let value = u8::from_str_radix(digits.get(i * width..(i + 1) * width)?, 16).ok()?;
+            Some(if width == 1 { value * 17 } else { value })
+        };
+        Some(Rgba { r: channel(0)?, g: channel(1)?, b: channel(2)?, a: channel(3).unwrap_or(255) })
+    }
+
+    fn mix(self, target: u8, amount: f64) -> Rgba {
+        let amount = amount.clamp(0.0, 1.0);
+        let mix = |c: u8| (c as f64 + (target as f64 - c as f64) * amount).round() as u8;
+        Rgba { r: mix(self.r), g: mix(self.g), b: mix(self.b), a: self.a }
+    }
+
+    /// Mixes towards white; 1.0 gives white.
+    pub fn lighten(self, amount: f64) -> Rgba {
+        self.mix(255, amount)
+    }
+
+    /// Mixes towards black; 1.0 gives black.
+    pub fn darken(self, amount: f64) -> Rgba {
+        self.mix(0, amount)
+    }
+
+    /// The same color at an opacity of 0-1.
+    pub fn with_alpha(self, alpha: f64) -> Rgba {
+        Rgba { a: (alpha.clamp(0.0, 1.0) * 255.0).round() as u8, ..self }
+    }
+
+    /// Channels as 0-1 fractions, the way cairo takes them.
+    pub fn to_unit(self) -> (f64, f64, f64, f64) {
+        let unit = |c: u8| c as f64 / 255.0;
+        (unit(self.r), unit(self.g), unit(self.b), unit(self.a))
+    }
+}
+
+// rgba() rather than 8-digit hex, which older librsvg doesn't understand
+impl fmt::Display for Rgba {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.a == 255 {
+            write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+        } else {
+            let alpha = (self.a as f64 / 255.0 * 1000.0).round() / 1000.0;
+            write!(f, "rgba({},{},{},{})", self.r, self.g, self.b, alpha)
+        }
+    }
+}
+
+/// Hex or a `<palette>:<name>` entry.
+pub fn parse(spec: &str) -> Option<Rgba> {
+    match spec.split_once(':') {
+        Some((palette, name)) => palettes::find(palette)?.get(name),
+        None => Rgba::parse(spec),
+    }
+}
+
+/// The colors the charts and windows are painted with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    /// Window and chart backgrounds
+    pub background: Rgba,
+    /// Panels and cells drawn on the background
+    pub surface: Rgba,
+    /// Guide lines
+    pub grid: Rgba,
+    /// Titles and labels
+    pub text: Rgba,
+    /// Secondary labels, e.g. the stats line
+    pub subtext: Rgba,
+    /// Lines without a color of their own
+    pub accent: Rgba,
+}
+
+// Role names, as theme files spell them
+const ROLES: [&str; 6] = ["background", "surface", "grid", "text", "subtext", "accent"];
+
+impl Theme {
+    /// The palette's colors for each role.
+    pub fn from_palette(palette: &Palette) -> Theme {
+        let role = |i: usize| palette.get(palette.roles[i]).expect("palette roles name its own colors");
+        Theme { background: role(0), surface: role(1), grid: role(2), text: role(3), subtext: role(4), accent: role(5) }
+    }
+
+    fn role_mut(&mut self, name: &str) -> Option<&mut Rgba> {
+        Some(match name {
+            "background" => &mut self.background,
+            "surface" => &mut self.surface,
+            "grid" => &mut self.grid,
+            "text" => &mut self.text,
+            "subtext" => &mut self.subtext,
+            "accent" => &mut self.accent,
+            _ => return None,
+        })
+    }
+
+    /// A theme file: an optional `palette` to start from (Catppuccin Mocha
+    /// otherwise), then any roles to override, each a color spec:
+    ///
+    /// ```toml
+    /// palette = "nord"
+    /// accent = "nord:nord13"
+    /// background = "#2e3440cc"
+    /// ```
+    pub fn from_toml(content: &str) -> Result<Theme, String> {
+        let table: toml::Table = content.parse().map_err(|e: toml::de::Error| e.message().to_string())?;
+        let mut theme = match table.get("palette") {
+            Some(toml::Value::String(name)) => {
+                Theme::from_palette(palettes::find(name).ok_or_else(|| format!("unknown palette \"{}\"", name))?)
+            }
+            Some(_) => return Err("palette must be a string".to_string()),
+            None => Theme::default(),
+        };
+        for (key, value) in table.iter().filter(|(key, _)| *key != "palette") {
+            let spec = value.as_str().ok_or_else(|| format!("{} must be a color string", key))?;
+            let color = parse(spec).ok_or_else(|| format!("{}: \"{}\" is not a color", key, spec))?;
+            let role = theme
+                .role_mut(key)
+                .ok_or_else(|| format!("unknown role \"{}\"; expected palette or one of {}", key, ROLES.join(", ")))?;
+            *role = color;
+        }
+        Ok(theme)
+    }
+
+    /// A palette name, or the path of a theme file.
+    pub fn load(spec: &str) -> Result<Theme, String> {
+        if let Some(palette) = palettes::find(spec) {
+            return Ok(Theme::from_palette(palette));
+        }
+        let path = Path::new(spec);
+        let content = fs::read_to_string(path).map_err(|e| format!("theme {}: {}", path.display(), e))?;
+        Theme::from_toml(&content).map_err(|e| format!("theme {}: {}", path.display(), e))
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::from_palette(&palettes::CATPPUCCIN_MOCHA)
+    }
+}
@@ -0,0 +1,124 @@
+use crate::Rgba;
+
+/// A named set of colors, with the entries that fill each `Theme` role.
+#[derive(Debug)]
+pub struct Palette {
+    /// What themes and color specs call it, e.g. "catppuccin-mocha"
+    pub name: &'static str,
+    /// Shorter names it can be given by, e.g. "mocha"
+    pub aliases: &'static [&'static str],
+    /// Entry names and their hex
+    pub colors: &'static [(&'static str, &'static str)],
+    // background, surface, grid, text, subtext, accent
+    pub(crate) roles: [&'static str; 6],
+}
+
+impl Palette {
+    /// An entry by name, e.g. "blue".
+    pub fn get(&self, name: &str) -> Option<Rgba> {
+        self.colors.iter().find(|(key, _)| *key == name).and_then(|(_, hex)| Rgba::parse(hex))
+    }
+}
+
+/// Catppuccin's darkest flavor, the default theme.
+pub const CATPPUCCIN_MOCHA: Palette = Palette {
+    name: "catppuccin-mocha",
+    aliases: &["mocha"],
+    colors: &[
+        ("rosewater", "#f5e0dc"),
+        ("flamingo", "#f2cdcd"),
+        ("pink", "#f5c2e7"),
+        ("mauve", "#cba6f7"),
+        ("red", "#f38ba8"),
+        ("maroon", "#eba0ac"),
+        ("peach", "#fab387"),
+        ("yellow", "#f9e2af"),
+        ("green", "#a6e3a1"),
+        ("teal", "#94e2d5"),
+        ("sky", "#89dceb"),
+        ("sapphire", "#74c7ec"),
+        ("blue", "#89b4fa"),
+        ("lavender", "#b4befe"),
+        ("text", "#cdd6f4"),
+        ("subtext1", "#bac2de"),
+        ("subtext0", "#a6adc8"),
+        ("overlay2", "#9399b2"),
+        ("overlay1", "#7f849c"),
+        ("overlay0", "#6c7086"),
+        ("surface2", "#585b70"),
+        ("surface1", "#45475a"),
+        ("surface0", "#313244"),
+        ("base", "#1e1e2e"),
+        ("mantle", "#181825"),
+        ("crust", "#11111b"),
+    ],
+    roles: ["base", "surface0", "surface1", "text", "subtext0", "blue"],
+};
+
+/// Catppuccin's light flavor.
+pub const CATPPUCCIN_LATTE: Palette = Palette {
+    name: "catppuccin-latte",
+    aliases: &["latte"],
+    colors: &[
+        ("rosewater", "#dc8a78"),
+        ("flamingo", "#dd7878"),
+        ("pink", "#ea76cb"),
+        ("mauve", "#8839ef"),
+        ("red", "#d20f39"),
+        ("maroon", "#e64553"),
+        ("peach", "#fe640b"),
+        ("yellow", "#df8e1d"),
+        ("green", "#40a02b"),
+        ("teal", "#179299"),
+        ("sky", "#04a5e5"),
+        ("sapphire", "#209fb5"),
+        ("blue", "#1e66f5"),
+        ("lavender", "#7287fd"),
+        ("text", "#4c4f69"),
+        ("subtext1", "#5c5f77"),
+        ("subtext0", "#6c6f85"),
+        ("overlay2", "#7c7f93"),
+        ("overlay1", "#8c8fa1"),
+        ("overlay0", "#9ca0b0"),
+        ("surface2", "#acb0be"),
+        ("surface1", "#bcc0cc"),
+        ("surface0", "#ccd0da"),
+        ("base", "#eff1f5"),
+        ("mantle", "#e6e9ef"),
+        ("crust", "#dce0e8"),
+    ],
+    roles: ["base", "surface0", "surface1", "text", "subtext0", "blue"],
+};
+
+/// Nord, with its colors named nord0-nord15.
+pub const NORD: Palette = Palette {
+    name: "nord",
+    aliases: &[],
+    colors: &[
+        ("nord0", "#2e3440"),
+        ("nord1", "#3b4252"),
+        ("nord2", "#434c5e"),
+        ("nord3", "#4c566a"),
+        ("nord4", "#d8dee9"),
+        ("nord5", "#e5e9f0"),
+        ("nord6", "#eceff4"),
+        ("nord7", "#8fbcbb"),
+        ("nord8", "#88c0d0"),
+        ("nord9", "#81a1c1"),
+        ("nord10", "#5e81ac"),
+        ("nord11", "#bf616a"),
+        ("nord12", "#d08770"),
+        ("nord13", "#ebcb8b"),
+        ("nord14", "#a3be8c"),
+        ("nord15", "#b48ead"),
+    ],
+    roles: ["nord0", "nord1", "nord3", "nord6", "nord4", "nord8"],
+};
+
+/// Every built-in palette.
+pub const PALETTES: &[&Palette] = &[&CATPPUCCIN_MOCHA, &CATPPUCCIN_LATTE, &NORD];
+
+/// By name or alias.
+pub fn find(name: &str) -> Option<&'static Palette> {
+    PALETTES.iter().copied().find(|p| p.name == name || p.aliases.contains(&name))
+}
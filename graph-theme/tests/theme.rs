@@ -0,0 +1,52 @@
+//! Palettes and theme files, as the renderer and graph window load them.
+
+use graph_theme::{Rgba, Theme};
+use std::fs;
+
+#[test]
+fn hex_and_palette_entries_parse_alike() {
+    assert_eq!(graph_theme::parse("mocha:blue"), graph_theme::parse("#89b4fa"));
+    assert_eq!(graph_theme::parse("catppuccin-latte:base"), Rgba::parse("#eff1f5"));
+    assert_eq!(graph_theme::parse("nord:nord8"), Some(Rgba { r: 0x88, g: 0xc0, b: 0xd0, a: 255 }));
+    assert_eq!(graph_theme::parse("#0f08"), Some(Rgba { r: 0, g: 255, b: 0, a: 136 }));
+    assert_eq!(graph_theme::parse("mocha:nord8"), None);
+    assert_eq!(graph_theme::parse("solarized:base03"), None);
+    assert_eq!(graph_theme::parse("blue"), None);
+}
+
+#[test]
+fn alpha_survives_display_and_unit_channels() {
+    let color = Rgba::parse("#1e1e2ecc").unwrap();
+    assert_eq!(color.to_string(), "rgba(30,30,46,0.8)");
+    assert_eq!(color.with_alpha(1.0).to_string(), "#1e1e2e");
+    let (r, _, _, a) = color.to_unit();
+    assert!((r - 30.0 / 255.0).abs() < 1e-9 && (a - 0.8).abs() < 1e-9);
+}
+
+#[test]
+fn themes_come_from_palettes_with_file_overrides() {
+    let mocha = Theme::load("mocha").unwrap();
+    assert_eq!(mocha, Theme::default());
+    assert_eq!(mocha.background.to_string(), "#1e1e2e");
+    assert_eq!(mocha.accent.to_string(), "#89b4fa");
+
+    let theme = Theme::from_toml("palette = \"nord\"\naccent = \"nord:nord13\"\nbackground = \"#2e3440cc\"\n").unwrap();
+    assert_eq!(theme.accent.to_string(), "#ebcb8b");
+    assert_eq!(theme.background.to_string(), "rgba(46,52,64,0.8)");
+    assert_eq!(theme.text.to_string(), "#eceff4");
+
+    let path = std::env::temp_dir().join(format!("graph-theme-{}.toml", std::process::id()));
+    fs::write(&path, "grid = \"latte:surface1\"\n").unwrap();
+    let theme = Theme::load(path.to_str().unwrap()).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(theme.grid.to_string(), "#bcc0cc");
+    assert_eq!(theme.background, mocha.background);
+}
+
+#[test]
+fn theme_errors_say_what_is_wrong() {
+    assert_eq!(Theme::from_toml("palette = \"solarized\"").unwrap_err(), "unknown palette \"solarized\"");
+    assert_eq!(Theme::from_toml("accent = \"#12\"").unwrap_err(), "accent: \"#12\" is not a color");
+    assert!(Theme::from_toml("border = \"#fff\"").unwrap_err().starts_with("unknown role \"border\""));
+    assert!(Theme::load("/nonexistent/theme.toml").unwrap_err().starts_with("theme /nonexistent/theme.toml: "));
+}
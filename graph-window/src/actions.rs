@@ -0,0 +1,89 @@
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::process::Command;
+
+/// What the process panel can do to a process.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    Terminate,
+    Kill,
+    // Moves the nice value by this much, within -20..=19
+    Renice(i32),
+}
+
+impl Action {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Action::Terminate => "Terminate",
+            Action::Kill => "Kill",
+            Action::Renice(step) if *step > 0 => "Lower priority",
+            Action::Renice(_) => "Raise priority",
+        }
+    }
+
+    /// The confirmation dialog's detail text.
+    pub fn describe(&self, name: &str, pid: u32) -> String {
+        match self {
+            Action::Terminate => format!("Send SIGTERM to {} ({}), asking it to exit.", name, pid),
+            Action::Kill => format!("Send SIGKILL to {} ({}). Unsaved work in it is lost.", name, pid),
+            Action::Renice(step) => {
+                let nice = (nice(pid).unwrap_or(0) + step).clamp(-20, 19);
+                format!("Set the nice value of {} ({}) to {}.", name, pid, nice)
+            }
+        }
+    }
+}
+
+// Field 19 of /proc/<pid>/stat
+fn nice(pid: u32) -> Option<i32> {
+    let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // The command name can contain spaces and parentheses
+    stat.rsplit_once(')')?.1.split_whitespace().nth(16)?.parse().ok()
+}
+
+/// Whether the process runs as the user the window runs as.
+pub fn owned_by_user(pid: u32) -> bool {
+    match (fs::metadata("/proc/self"), fs::metadata(format!("/proc/{}", pid))) {
+        (Ok(me), Ok(them)) => me.uid() == them.uid(),
+        _ => false,
+    }
+}
+
+/// Another user's process, or a lower nice value than it has now, which
+/// only root may set, goes through polkit.
+pub fn needs_escalation(action: Action, pid: u32) -> bool {
+    !owned_by_user(pid) || matches!(action, Action::Renice(step) if step < 0)
+}
+
+/// The command that performs `action`, behind pkexec when `escalate`.
+pub fn command(action: Action, pid: u32, escalate: bool) -> Vec<String> {
+    let pid = pid.to_string();
+    let mut argv: Vec<String> = match action {
+        Action::Terminate => vec!["kill".into(), "-TERM".into(), pid],
+        Action::Kill => vec!["kill".into(), "-KILL".into(), pid],
+        Action::Renice(step) => {
+            let nice = (nice(pid.parse().unwrap_or(0)).unwrap_or(0) + step).clamp(-20, 19);
+            vec!["renice".into(), "-n".into(), nice.to_string(), "-p".into(), pid]
+        }
+    };
+    if escalate {
+        argv.insert(0, "pkexec".into());
+    }
+    argv
+}
+
+/// Runs `action` and waits for it, polkit prompt included, so call it off
+/// the main thread. The error is what to show the user.
+pub fn perform(action: Action, pid: u32) -> Result<(), String> {
+    let argv = command(action, pid, needs_escalation(action, pid));
+    let output = Command::new(&argv[0]).args(&argv[1..]).output().map_err(|e| format!("{}: {}", argv[0], e))?;
+    if output.status.success() {
+        return Ok(());
+    }
+    // pkexec's exit status when the prompt was dismissed or denied
+    if argv[0] == "pkexec" && matches!(output.status.code(), Some(126) | Some(127)) {
+        return Err("Not authorized".to_string());
+    }
+    let message = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    Err(if message.is_empty() { format!("{} failed", argv[0]) } else { message })
+}
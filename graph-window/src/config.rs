@@ -1,12 +1,115 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use graph_theme::Theme;
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+// Territories that report temperatures in Fahrenheit
+const FAHRENHEIT_TERRITORIES: &[&str] = &["US", "LR", "MM", "BS", "BZ", "KY", "PW"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TemperatureUnit {
+    Celsius,
+    Fahrenheit,
+}
+
+impl TemperatureUnit {
+    /// From LC_MEASUREMENT, resolved the way glibc does: LC_ALL, then
+    /// LC_MEASUREMENT, then LANG. `en_US.UTF-8` gives Fahrenheit.
+    pub fn from_locale() -> Self {
+        let locale = ["LC_ALL", "LC_MEASUREMENT", "LANG"]
+            .iter()
+            .find_map(|var| env::var(var).ok().filter(|v| !v.is_empty()))
+            .unwrap_or_default();
+        // language_TERRITORY.codeset@modifier
+        let territory = locale
+            .split(['.', '@'])
+            .next()
+            .and_then(|name| name.split_once('_'))
+            .map_or("", |(_, territory)| territory);
+        if FAHRENHEIT_TERRITORIES.contains(&territory) {
+            TemperatureUnit::Fahrenheit
+        } else {
+            TemperatureUnit::Celsius
+        }
+    }
+
+    pub fn toggled(self) -> Self {
+        match self {
+            TemperatureUnit::Celsius => TemperatureUnit::Fahrenheit,
+            TemperatureUnit::Fahrenheit => TemperatureUnit::Celsius,
+        }
+    }
+
+    /// Converts from the °C the service reports.
+    pub fn convert(self, celsius: f64) -> f64 {
+        match self {
+            TemperatureUnit::Celsius => celsius,
+            TemperatureUnit::Fahrenheit => celsius * 9.0 / 5.0 + 32.0,
+        }
+    }
+
+    /// Back to °C, for values going to the service.
+    pub fn to_celsius(self, value: f64) -> f64 {
+        match self {
+            TemperatureUnit::Celsius => value,
+            TemperatureUnit::Fahrenheit => (value - 32.0) * 5.0 / 9.0,
+        }
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            TemperatureUnit::Celsius => "°C",
+            TemperatureUnit::Fahrenheit => "°F",
+        }
+    }
+}
+
+/// How the per-core view (`cpu --multi`) lays out its cores.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CoreLayout {
+    /// The chart grid up to `heatmap_above` cores, the heatmap beyond
+    #[default]
+    Auto,
+    Grid,
+    Heatmap,
+}
+
+// The grid shows at most 16 cores before it runs out of room
+const HEATMAP_ABOVE: usize = 16;
+
+fn default_heatmap_above() -> usize {
+    HEATMAP_ABOVE
+}
+
+fn default_opacity() -> f64 {
+    1.0
+}
+
+fn default_namespace() -> String {
+    "graph-window".to_string()
+}
+
+fn default_theme() -> String {
+    "catppuccin-mocha".to_string()
+}
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct GraphConfig {
     pub title: String,
+    // Color, scale and unit fall back to the service's series metadata when
+    // left out; see `apply_metadata`
+    #[serde(default)]
     pub color: String,
     #[serde(default)]
     pub color2: String,
+    #[serde(default)]
     pub max_value: f64,
+    #[serde(default)]
+    pub unit: String,
     pub width: i32,
     pub height: i32,
     pub data_source: String,
@@ -20,8 +123,62 @@ pub struct GraphConfig {
     pub position_y: i32,
     #[serde(default)]
     pub multi_chart: bool,
+    /// Grid of mini charts or a heatmap (time across, one row per core);
+    /// see [`CoreLayout`].
+    #[serde(default)]
+    pub core_layout: CoreLayout,
+    #[serde(default = "default_heatmap_above")]
+    pub heatmap_above: usize,
     #[serde(default)]
     pub advanced: bool,
+    /// A row of CPU, memory, temperature and network dials above the chart.
+    /// Also set by `--gauges`.
+    #[serde(default)]
+    pub gauges: bool,
+    /// Collect CPU, memory and network in-process whenever the service
+    /// isn't answering. Also set by `--standalone`.
+    #[serde(default)]
+    pub standalone: bool,
+    /// Process followed by the `leak` view; unset picks the largest one.
+    #[serde(default)]
+    pub pid: Option<u32>,
+    /// Unit for thermal labels and scales. Unset uses the last choice made
+    /// with the toggle in the window, then the locale. `max_value` stays in
+    /// °C either way, like the service's series.
+    #[serde(default)]
+    pub temperature_unit: Option<TemperatureUnit>,
+    /// Report state changes as JSON lines on stdout; see `events`. Also
+    /// set by `--events`.
+    #[serde(default)]
+    pub events: bool,
+    /// Close once this process exits, e.g. the AGS instance behind the bar.
+    /// Also set by `--parent-pid`.
+    #[serde(default)]
+    pub parent_pid: Option<u32>,
+    /// Close once the process that started the window exits. Off by default
+    /// since launchers often start it through a shell that exits right away;
+    /// the AGS widgets, which spawn it directly, turn it on.
+    #[serde(default)]
+    pub watch_parent: bool,
+    /// Opacity of the window and chart backgrounds, 0-1; lines and text stay
+    /// solid. What shows through is blurred by compositors that blur layer
+    /// surfaces by rule, e.g. Hyprland's `layerrule = blur, graph-window`
+    /// (with `ignorezero` to keep the rounded corners clean). KWin only blurs
+    /// behind clients that ask through its own Wayland protocol, which GTK
+    /// doesn't expose.
+    #[serde(default = "default_opacity")]
+    pub opacity: f64,
+    /// The layer-shell namespace compositor rules match the window by.
+    #[serde(default = "default_namespace")]
+    pub namespace: String,
+    /// A palette (`catppuccin-mocha`, `catppuccin-latte`, `nord`) or the path
+    /// of a theme file, relative to config.toml's directory; see
+    /// `graph_theme::Theme::from_toml`.
+    #[serde(default = "default_theme")]
+    pub theme: String,
+    /// `theme`, resolved by `load`.
+    #[serde(skip)]
+    pub colors: Theme,
 }
 
 impl Default for GraphConfig {
@@ -31,6 +188,7 @@ impl Default for GraphConfig {
             color: "#89b4fa".to_string(),
             color2: String::new(),
             max_value: 100.0,
+            unit: String::new(),
             width: 300,
             height: 100,
             data_source: "cpu".to_string(),
@@ -39,7 +197,267 @@ impl Default for GraphConfig {
             position_x: 0,
             position_y: 0,
             multi_chart: false,
+            core_layout: CoreLayout::Auto,
+            heatmap_above: HEATMAP_ABOVE,
             advanced: false,
+            gauges: false,
+            standalone: false,
+            pid: None,
+            temperature_unit: None,
+            events: false,
+            parent_pid: None,
+            watch_parent: false,
+            opacity: default_opacity(),
+            namespace: default_namespace(),
+            theme: default_theme(),
+            colors: Theme::default(),
+        }
+    }
+}
+
+fn config_path() -> PathBuf {
+    env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .unwrap_or_else(|| PathBuf::from(env::var("HOME").unwrap_or_default()).join(".config"))
+        .join("ags-graph-window")
+        .join("config.toml")
+}
+
+// config.toml as JSON; empty when missing, with a warning when unreadable
+fn file_defaults() -> Map<String, Value> {
+    let path = config_path();
+    let Ok(content) = fs::read_to_string(&path) else {
+        return Map::new();
+    };
+    match toml::from_str::<toml::Table>(&content).map(serde_json::to_value) {
+        Ok(Ok(Value::Object(table))) => table,
+        Ok(_) => Map::new(),
+        Err(e) => {
+            eprintln!("Ignoring {}: {}", path.display(), e);
+            Map::new()
+        }
+    }
+}
+
+pub const USAGE: &str = "\
+usage: graph-window '<json config>' [--standalone] [--events]
+       graph-window <view> [options]
+
+views:   cpu, memory (mem), swap, net (network), energy (power),
+         processes (apps), leak, storage (disk), io (diskio), temperature
+         (temp), fan (fans), battery (bat), pressure (psi), sockets
+         (sockstat), fds (files), slices (cgroups)
+options: --multi        one chart per core (cpu)
+         --heatmap      one heatmap row per core (cpu)
+         --advanced     memory breakdown (memory)
+         --gauges       CPU, memory, temperature and network dials above
+                        the chart
+         --pid <pid>    process to follow (leak; default: the largest)
+         --fahrenheit, --celsius
+         --standalone   collect locally while the service is down
+         --events       report shown/hidden/closed and setting changes as
+                        JSON lines on stdout
+         --parent-pid <pid>
+                        close when this process exits
+
+Defaults for every view, and [<data_source>] tables per view, are read from
+$XDG_CONFIG_HOME/ags-graph-window/config.toml; options and JSON override them.";
+
+impl GraphConfig {
+    /// Expands a command line like `cpu --multi` or `net` into the same
+    /// overrides the widgets pass as JSON, for `load`.
+    pub fn from_args(args: &[String]) -> Result<Value, String> {
+        let mut overrides = Map::new();
+        let mut view = None;
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let (key, value) = match arg.as_str() {
+                "--multi" => ("multi_chart", json!(true)),
+                "--advanced" => ("advanced", json!(true)),
+                "--gauges" => ("gauges", json!(true)),
+                "--heatmap" => {
+                    overrides.insert("multi_chart".to_string(), json!(true));
+                    ("core_layout", json!(CoreLayout::Heatmap))
+                }
+                "--standalone" => ("standalone", json!(true)),
+                "--events" => ("events", json!(true)),
+                "--fahrenheit" => ("temperature_unit", json!(TemperatureUnit::Fahrenheit)),
+                "--celsius" => ("temperature_unit", json!(TemperatureUnit::Celsius)),
+                "--pid" => {
+                    let pid = args.next().ok_or("--pid needs a value")?;
+                    ("pid", json!(pid.parse::<u32>().map_err(|_| format!("invalid pid: {}", pid))?))
+                }
+                "--parent-pid" => {
+                    let pid = args.next().ok_or("--parent-pid needs a value")?;
+                    ("parent_pid", json!(pid.parse::<u32>().map_err(|_| format!("invalid pid: {}", pid))?))
+                }
+                flag if flag.starts_with("--") => return Err(format!("unknown option: {}", flag)),
+                name if view.is_none() => {
+                    view = Some(name);
+                    continue;
+                }
+                extra => return Err(format!("unexpected argument: {}", extra)),
+            };
+            overrides.insert(key.to_string(), value);
+        }
+
+        let flag = |key: &str| overrides.get(key).is_some_and(|v| *v == json!(true));
+        let (data_source, title) = match view.ok_or("no view given")? {
+            "cpu" if flag("multi_chart") => ("cpu", "CPU Cores"),
+            "cpu" => ("cpu", "CPU"),
+            "memory" | "mem" if flag("advanced") => ("memory", "Memory Breakdown"),
+            "memory" | "mem" => ("memory", "Memory"),
+            "swap" => ("swap", "Swap"),
+            "net" | "network" => ("network", "Network"),
+            "energy" | "power" => ("energy", "Power"),
+            "processes" | "apps" => ("processes", "Applications"),
+            "leak" => ("leak", "Memory Leak"),
+            "storage" | "disk" => ("storage", "Storage"),
+            "io" | "diskio" => ("disk_io", "Disk I/O"),
+            "temperature" | "temp" => ("temperature", "Temperature"),
+            "fan" | "fans" => ("fan", "Fan"),
+            "battery" | "bat" => ("battery", "Battery"),
+            "pressure" | "psi" => ("pressure", "Pressure"),
+            "sockets" | "sockstat" => ("sockets", "Sockets"),
+            "fds" | "files" => ("file_descriptors", "File Descriptors"),
+            "slices" | "cgroups" => ("slices", "Slices"),
+            other => return Err(format!("unknown view: {}", other)),
+        };
+        overrides.insert("data_source".to_string(), json!(data_source));
+        overrides.insert("title".to_string(), json!(title));
+        Ok(Value::Object(overrides))
+    }
+
+    /// Builds the config from, lowest precedence first: built-in defaults,
+    /// the top-level keys of `config.toml`, its table named after the view's
+    /// `data_source`, and `overrides` (the argv JSON or a preset). Whatever
+    /// is still unset after that comes from the service's metadata.
+    pub fn load(overrides: Value) -> Result<GraphConfig, String> {
+        let Value::Object(overrides) = overrides else {
+            return Err("config must be a JSON object".to_string());
+        };
+        let file = file_defaults();
+        let data_source = overrides
+            .get("data_source")
+            .or_else(|| file.get("data_source"))
+            .and_then(Value::as_str)
+            .unwrap_or("cpu")
+            .to_string();
+        let section = match file.get(&data_source) {
+            Some(Value::Object(section)) => section.clone(),
+            _ => Map::new(),
+        };
+        let flag = |key: &str| [&overrides, &section, &file].iter().find_map(|m| m.get(key)) == Some(&json!(true));
+        // Views with several charts or rows need the room
+        let (width, height) = if flag("multi_chart") || flag("advanced") || data_source == "storage" {
+            (400, 240)
+        } else {
+            (300, 100)
+        };
+
+        let mut merged = Map::new();
+        let builtin = [
+            ("title", json!("System Graph")),
+            ("width", json!(width)),
+            ("height", json!(height)),
+            ("data_source", json!(data_source)),
+        ];
+        for (key, value) in builtin {
+            merged.insert(key.to_string(), value);
+        }
+        merged.extend(file.into_iter().filter(|(_, v)| !v.is_object()));
+        merged.extend(section);
+        merged.extend(overrides);
+        let mut config: GraphConfig = serde_json::from_value(Value::Object(merged)).map_err(|e| e.to_string())?;
+        // Relative theme files sit next to config.toml
+        let theme = match graph_theme::palettes::find(&config.theme) {
+            None if Path::new(&config.theme).is_relative() => config_path().with_file_name(&config.theme),
+            _ => PathBuf::from(&config.theme),
+        };
+        config.colors = Theme::load(&theme.to_string_lossy())?;
+        Ok(config)
+    }
+
+    /// The history series this view plots, and the second one if it has two.
+    pub fn series_keys(&self) -> (&'static str, Option<&'static str>) {
+        match self.data_source.as_str() {
+            "memory" | "leak" => ("memory", None),
+            "swap" => ("swap_in", Some("swap_out")),
+            "network" => ("network_download", Some("network_upload")),
+            "energy" => ("power", None),
+            "temperature" => ("temperature", None),
+            "fan" => ("fan", None),
+            "disk_io" => ("disk_read", Some("disk_write")),
+            "battery" => ("battery", None),
+            // Stalls on memory and IO; CPU pressure mostly tracks the load
+            "pressure" => ("pressure_memory", Some("pressure_io")),
+            // A leak shows as open connections climbing; TIME_WAIT as churn
+            "sockets" => ("sockets_tcp", Some("sockets_time_wait")),
+            "file_descriptors" => ("file_descriptors", None),
+            _ => ("cpu", None),
+        }
+    }
+
+    /// Whether the per-core view should draw `cores` as a heatmap.
+    pub fn heatmap(&self, cores: usize) -> bool {
+        match self.core_layout {
+            CoreLayout::Auto => cores > self.heatmap_above,
+            CoreLayout::Grid => false,
+            CoreLayout::Heatmap => true,
+        }
+    }
+
+    /// The command-line view that opens this data source again, e.g. `io`
+    /// for `disk_io`.
+    pub fn view_name(&self) -> &str {
+        match self.data_source.as_str() {
+            "disk_io" => "io",
+            "file_descriptors" => "fds",
+            other => other,
+        }
+    }
+
+    /// Whether this view is a single `draw_graph` chart, which is what
+    /// snapshots pin and overlay.
+    pub fn single_graph(&self) -> bool {
+        match self.data_source.as_str() {
+            "memory" => !self.advanced,
+            "cpu" => !self.multi_chart,
+            "leak" | "storage" => false,
+            _ => true,
+        }
+    }
+
+    /// Fills whatever the caller left unset from the `metadata` reply, so a
+    /// new series renders sensibly without a hard-coded branch here.
+    pub fn apply_metadata(&mut self, metadata: &serde_json::Value) {
+        let (primary, secondary) = self.series_keys();
+        let info = &metadata["series"][primary];
+        if self.color.is_empty() {
+            self.color = info["color"].as_str().unwrap_or("#89b4fa").to_string();
+        }
+        if self.color2.is_empty() {
+            if let Some(color) = secondary.and_then(|key| metadata["series"][key]["color"].as_str()) {
+                self.color2 = color.to_string();
+            }
+        }
+        if self.max_value <= 0.0 {
+            // Rates have no ceiling; 1 MB/s is a readable starting scale
+            self.max_value = info["max"].as_f64().unwrap_or(1024.0);
+        }
+        if self.unit.is_empty() {
+            self.unit = info["unit"].as_str().unwrap_or_default().to_string();
+        }
+    }
+
+    /// This config with the °C scale and unit shown in `unit` instead.
+    pub fn in_temperature_unit(&self, unit: TemperatureUnit) -> GraphConfig {
+        let mut config = self.clone();
+        if config.unit == TemperatureUnit::Celsius.symbol() {
+            config.max_value = unit.convert(config.max_value).round();
+            config.unit = unit.symbol().to_string();
         }
+        config
     }
 }
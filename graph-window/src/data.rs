@@ -1,7 +1,21 @@
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::io::Read;
+use std::io::{BufRead, BufReader, Read, Write};
 use std::os::unix::net::UnixStream;
+use std::sync::mpsc::Sender;
+use std::thread;
+use std::time::Duration;
+
+const SOCKET_PATH: &str = "/tmp/ags-stats/stats.sock";
+const HISTORY_FILE: &str = "/tmp/ags-stats/history.json";
+pub const LATEST_FILE: &str = "/tmp/ags-stats/latest.json";
+const RECONNECT_MIN_MS: u64 = 500;
+const RECONNECT_MAX_MS: u64 = 30_000;
+// The service sends a sample every tick, so this long without one means it hung
+const SUBSCRIPTION_TIMEOUT: Duration = Duration::from_secs(10);
+// A reply the service hasn't finished by then isn't coming
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct GraphData {
@@ -16,6 +30,18 @@ impl GraphData {
             max_size: size,
         }
     }
+
+    /// Replaces the values with the newest `max_size` samples of a history series.
+    pub fn set_from_history(&mut self, history: &serde_json::Value, key: &str) {
+        self.set_values(series(history, key));
+    }
+
+    /// Replaces the values with the newest `max_size` of `values`.
+    pub fn set_values(&mut self, mut values: Vec<f64>) {
+        let skip = values.len().saturating_sub(self.max_size);
+        values.drain(..skip);
+        self.values = values;
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -42,23 +68,895 @@ impl AdvancedMemoryData {
 }
 
 pub fn load_history() -> serde_json::Value {
-    let history_json = if let Ok(mut stream) = UnixStream::connect("/tmp/ags-stats/stats.sock") {
-        eprintln!("Connected to stats service socket");
-        let mut buffer = String::new();
-        match stream.read_to_string(&mut buffer) {
-            Ok(size) => {
-                eprintln!("Received {} bytes from socket", size);
-                buffer
+    load_history_from(SOCKET_PATH, HISTORY_FILE)
+}
+
+// Sends one command and reads the reply until the service closes the connection
+fn request(socket_path: &str, command: &str) -> Option<String> {
+    let mut stream = UnixStream::connect(socket_path).ok()?;
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT)).ok()?;
+    stream.set_write_timeout(Some(REQUEST_TIMEOUT)).ok()?;
+    stream.write_all(format!("{}\n", command).as_bytes()).ok()?;
+    let mut buffer = String::new();
+    stream.read_to_string(&mut buffer).ok()?;
+    Some(buffer)
+}
+
+/// Sends `fetch()` to `replies` once a second, for replies the history
+/// doesn't carry. Meant for its own thread, like [`follow_service`], so a
+/// slow service never stalls the window. Returns once the receiving side
+/// has gone away.
+pub fn poll(fetch: impl Fn() -> serde_json::Value, replies: Sender<serde_json::Value>) {
+    while replies.send(fetch()).is_ok() {
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Asks the service at `socket_path` for its history, falling back to the
+/// file it writes each tick when the service isn't reachable.
+pub fn load_history_from(socket_path: &str, history_file: &str) -> serde_json::Value {
+    fetch_history_from(socket_path).unwrap_or_else(|| {
+        eprintln!("No history from socket, trying file");
+        let history_json = fs::read_to_string(history_file).unwrap_or_default();
+        serde_json::from_str(&history_json).unwrap_or_else(|_| serde_json::json!({}))
+    })
+}
+
+pub fn fetch_history() -> Option<serde_json::Value> {
+    fetch_history_from(SOCKET_PATH)
+}
+
+/// The history from the service, or None when its socket isn't answering.
+pub fn fetch_history_from(socket_path: &str) -> Option<serde_json::Value> {
+    request(socket_path, "history").and_then(|json| serde_json::from_str(&json).ok())
+}
+
+pub enum StreamEvent {
+    /// Sent on every (re)connect, before any samples.
+    History(serde_json::Value),
+    Sample(serde_json::Value),
+    Disconnected,
+}
+
+/// Backoff before reconnect attempt `attempt` (0-based): 0.5 s doubling up to 30 s.
+pub fn reconnect_delay(attempt: u32) -> Duration {
+    Duration::from_millis(RECONNECT_MIN_MS.saturating_mul(1 << attempt.min(16)).min(RECONNECT_MAX_MS))
+}
+
+fn subscribe(socket_path: &str) -> std::io::Result<UnixStream> {
+    let mut stream = UnixStream::connect(socket_path)?;
+    stream.write_all(b"subscribe\n")?;
+    stream.set_read_timeout(Some(SUBSCRIPTION_TIMEOUT))?;
+    Ok(stream)
+}
+
+pub fn follow_service(events: Sender<StreamEvent>) {
+    follow_service_at(SOCKET_PATH, events)
+}
+
+/// Keeps a subscription to the service open for the life of the window,
+/// reconnecting with backoff whenever it drops. Each connection starts with
+/// a fresh history so state lost during a restart is rebuilt. Returns once
+/// the receiving side has gone away.
+pub fn follow_service_at(socket_path: &str, events: Sender<StreamEvent>) {
+    let mut attempt = 0;
+    loop {
+        if let Some(history) = fetch_history_from(socket_path) {
+            attempt = 0;
+            if events.send(StreamEvent::History(history)).is_err() {
+                return;
+            }
+            if let Ok(stream) = subscribe(socket_path) {
+                for line in BufReader::new(stream).lines() {
+                    let Ok(line) = line else {
+                        break;
+                    };
+                    let Ok(sample) = serde_json::from_str(&line) else {
+                        continue;
+                    };
+                    if events.send(StreamEvent::Sample(sample)).is_err() {
+                        return;
+                    }
+                }
             }
-            Err(e) => {
-                eprintln!("Failed to read from socket: {}", e);
-                fs::read_to_string("/tmp/ags-stats/history.json").unwrap_or_default()
+            eprintln!("Stats subscription dropped, reconnecting");
+            if events.send(StreamEvent::Disconnected).is_err() {
+                return;
+            }
+        }
+        thread::sleep(reconnect_delay(attempt));
+        attempt += 1;
+    }
+}
+
+/// How long the window went without data across a reconnect: from the last
+/// sample it saw to the first newer one in the fresh history.
+pub fn reconnect_gap_ms(last_seen: i64, history: &serde_json::Value) -> Option<i64> {
+    series(history, "timestamps")
+        .into_iter()
+        .map(|t| t as i64)
+        .find(|t| *t > last_seen)
+        .map(|t| t - last_seen)
+}
+
+/// `reconnected, 12 s gap`, or just `reconnected` when nothing was missed.
+pub fn reconnect_label(gap_ms: Option<i64>) -> String {
+    match gap_ms.map(|ms| (ms as f64 / 1000.0).round() as i64) {
+        Some(secs) if secs > 1 => format!("reconnected, {} s gap", secs),
+        _ => "reconnected".to_string(),
+    }
+}
+
+/// The single sample the service writes to latest.json each tick.
+pub fn load_latest_from(path: &str) -> Option<serde_json::Value> {
+    serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+// History series and where their value lives in a latest.json sample
+const LATEST_SERIES: &[(&str, &str)] = &[
+    ("cpu", "/cpu_usage"),
+    ("cpu_iowait", "/cpu_iowait"),
+    ("load", "/load/one"),
+    ("context_switches", "/kernel_activity/context_switches"),
+    ("interrupts", "/kernel_activity/interrupts"),
+    ("forks", "/kernel_activity/forks"),
+    ("memory", "/memory/used_percentage"),
+    ("memory_apps", "/memory/apps"),
+    ("memory_cached", "/memory/cached"),
+    ("memory_buffers", "/memory/buffers"),
+    ("memory_slab", "/memory/slab"),
+    ("memory_shmem", "/memory/shmem"),
+    ("swap", "/memory/swap_used_percentage"),
+    ("swap_in", "/memory/swap_in"),
+    ("swap_out", "/memory/swap_out"),
+    ("pressure_cpu", "/pressure/cpu/some_avg10"),
+    ("pressure_memory", "/pressure/memory/some_avg10"),
+    ("pressure_io", "/pressure/io/some_avg10"),
+    ("sockets_tcp", "/sockets/tcp_in_use"),
+    ("sockets_udp", "/sockets/udp_in_use"),
+    ("sockets_time_wait", "/sockets/tcp_time_wait"),
+    ("sockets_orphaned", "/sockets/tcp_orphaned"),
+    ("file_descriptors", "/file_descriptors/allocated"),
+    ("file_descriptors_used", "/file_descriptors/used_percentage"),
+    ("network_download", "/network_download"),
+    ("network_upload", "/network_upload"),
+    ("network_quality", "/network_quality/score"),
+    ("disk_read", "/disk_read"),
+    ("disk_write", "/disk_write"),
+    ("fan", "/fan"),
+    ("battery", "/power_supply/capacity"),
+    ("timestamps", "/timestamp"),
+];
+
+fn push_trimmed(series: &mut serde_json::Value, value: serde_json::Value, max_len: usize) {
+    if !series.is_array() {
+        *series = serde_json::json!([]);
+    }
+    if let Some(values) = series.as_array_mut() {
+        values.push(value);
+        let excess = values.len().saturating_sub(max_len);
+        values.drain(..excess);
+    }
+}
+
+/// Extends a history with one latest.json sample, keeping at most `max_len`
+/// values per series, so the file fallback can update incrementally rather
+/// than reloading the whole history. Returns false for a sample the history
+/// already has.
+pub fn append_latest(history: &mut serde_json::Value, latest: &serde_json::Value, max_len: usize) -> bool {
+    let Some(timestamp) = latest["timestamp"].as_i64() else {
+        return false;
+    };
+    if !history.is_object() {
+        *history = serde_json::json!({});
+    }
+    if history["last_update"].as_i64().is_some_and(|last| timestamp <= last) {
+        return false;
+    }
+    for (key, pointer) in LATEST_SERIES {
+        if let Some(value) = latest.pointer(pointer).filter(|v| v.is_number()) {
+            push_trimmed(&mut history[*key], value.clone(), max_len);
+        }
+    }
+    // Same figure the service graphs: the battery while discharging, else the package
+    let energy = &latest["energy"];
+    if energy.is_object() {
+        let on_battery = energy["on_battery"].as_bool().unwrap_or(false);
+        let battery = energy["battery_watts"].as_f64().filter(|_| on_battery);
+        let watts = battery.or(energy["package_watts"].as_f64());
+        push_trimmed(&mut history["power"], watts.unwrap_or(0.0).into(), max_len);
+    }
+    for key in ["cpu_cores", "cpu_frequencies"] {
+        let Some(cores) = latest[key].as_array() else {
+            continue;
+        };
+        if !history[key].is_array() {
+            history[key] = serde_json::json!([]);
+        }
+        let history_cores = history[key].as_array_mut().unwrap();
+        history_cores.resize(cores.len(), serde_json::json!([]));
+        for (series, value) in history_cores.iter_mut().zip(cores) {
+            push_trimmed(series, value.clone(), max_len);
+        }
+    }
+    for (name, iface) in latest["network_interfaces"].as_object().into_iter().flatten() {
+        if !history["network_interfaces"].is_object() {
+            history["network_interfaces"] = serde_json::json!({});
+        }
+        let series = &mut history["network_interfaces"][name];
+        push_trimmed(&mut series["download"], iface["download"].clone(), max_len);
+        push_trimmed(&mut series["upload"], iface["upload"].clone(), max_len);
+        // A link that drops keeps its series, with a gap, as the service's does
+        let signal = &latest["wireless"][name]["signal_dbm"];
+        if series["signal"].is_array() || !signal.is_null() {
+            push_trimmed(&mut series["signal"], signal.clone(), max_len);
+        }
+    }
+    if let Some(gpus) = latest["gpu"].as_array() {
+        if !history["gpu"].is_array() {
+            history["gpu"] = serde_json::json!([]);
+        }
+        let history_gpus = history["gpu"].as_array_mut().unwrap();
+        history_gpus.resize(gpus.len(), serde_json::json!({}));
+        for (series, gpu) in history_gpus.iter_mut().zip(gpus) {
+            for field in ["utilization", "memory_used", "temperature", "power", "video"] {
+                push_trimmed(&mut series[field], gpu[field].clone(), max_len);
             }
         }
+    }
+    for (name, celsius) in latest["temperatures"].as_object().into_iter().flatten() {
+        push_trimmed(&mut history["temperatures"][name], celsius.clone(), max_len);
+    }
+    // States are only sent when they change; the history keeps the last of each
+    for (name, value) in latest["states"].as_object().into_iter().flatten() {
+        if history["states"][name]["value"] != *value {
+            history["states"][name] = serde_json::json!({ "value": value, "since": timestamp });
+        }
+    }
+    // Only the newest lists matter, so they replace rather than accumulate
+    if latest["top_processes"].is_object() {
+        history["top_processes"] = latest["top_processes"].clone();
+    }
+    // Markers come whole on the ticks where one began or ended
+    if latest["markers"].is_array() {
+        history["markers"] = latest["markers"].clone();
+    }
+    if let Some(total) = latest.pointer("/memory/total") {
+        history["memory_total"] = total.clone();
+    }
+    history["last_update"] = timestamp.into();
+    true
+}
+
+pub fn load_energy() -> serde_json::Value {
+    load_energy_from(SOCKET_PATH)
+}
+
+/// The service's latest power estimate; `{"error": ...}` when it has no
+/// readable power source.
+pub fn load_energy_from(socket_path: &str) -> serde_json::Value {
+    request(socket_path, "energy")
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_else(|| serde_json::json!({ "error": "stats service unavailable" }))
+}
+
+/// One line per process for the energy panel, e.g. `firefox  12.5% cpu  3.2 W`.
+pub fn energy_summary(report: &serde_json::Value) -> String {
+    if let Some(error) = report["error"].as_str() {
+        return error.to_string();
+    }
+    let watts = |v: &serde_json::Value| v.as_f64().map(|w| format!("{:.1} W", w)).unwrap_or_else(|| "? W".to_string());
+    let source = if report["on_battery"].as_bool().unwrap_or(false) { "battery" } else { "package" };
+    let total = if source == "battery" { &report["battery_watts"] } else { &report["package_watts"] };
+    let mut lines = vec![format!("System ({}): {}", source, watts(total))];
+    for process in report["processes"].as_array().into_iter().flatten() {
+        lines.push(format!(
+            "{}  {:.1}% cpu  {}",
+            process["name"].as_str().unwrap_or("?"),
+            process["cpu_share"].as_f64().unwrap_or(0.0),
+            watts(&process["watts"]),
+        ));
+    }
+    lines.join("\n")
+}
+
+pub fn load_metadata() -> serde_json::Value {
+    load_metadata_from(SOCKET_PATH)
+}
+
+/// Rendering hints per series (`{"series": {"cpu": {"unit": "%", ...}}}`);
+/// empty when the service is unreachable or predates the command.
+pub fn load_metadata_from(socket_path: &str) -> serde_json::Value {
+    request(socket_path, "metadata")
+        .and_then(|json| serde_json::from_str::<serde_json::Value>(&json).ok())
+        .filter(|metadata| metadata["series"].is_object())
+        .unwrap_or_else(|| serde_json::json!({}))
+}
+
+pub fn load_watches() -> serde_json::Value {
+    load_watches_from(SOCKET_PATH)
+}
+
+/// Watches the service holds, as `{"watches": [{"metric", "above", "view"}]}`.
+pub fn load_watches_from(socket_path: &str) -> serde_json::Value {
+    request(socket_path, "watches")
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_else(|| serde_json::json!({ "error": "stats service unavailable" }))
+}
+
+/// The threshold the service watches `metric` at, if it does.
+pub fn watched_above(watches: &serde_json::Value, metric: &str) -> Option<f64> {
+    watches["watches"].as_array()?.iter().find(|watch| watch["metric"] == metric)?["above"].as_f64()
+}
+
+// Sends a control command whose reply is `{"ok": true}` or an error
+fn control(socket_path: &str, command: &str) -> Result<(), String> {
+    let reply: serde_json::Value = request(socket_path, command)
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .ok_or("stats service unavailable")?;
+    match reply["error"].as_str() {
+        Some(error) => Err(error.to_string()),
+        None => Ok(()),
+    }
+}
+
+pub fn watch(metric: &str, above: f64, view: &str) -> Result<(), String> {
+    watch_from(SOCKET_PATH, metric, above, view)
+}
+
+/// Has the service notify when `metric` goes above `above`, whether or not
+/// a window is open; clicking the notification opens `view`.
+pub fn watch_from(socket_path: &str, metric: &str, above: f64, view: &str) -> Result<(), String> {
+    control(socket_path, &format!("watch {} {} {}", metric, above, view))
+}
+
+pub fn unwatch(metric: &str) -> Result<(), String> {
+    unwatch_from(SOCKET_PATH, metric)
+}
+
+pub fn unwatch_from(socket_path: &str, metric: &str) -> Result<(), String> {
+    control(socket_path, &format!("unwatch {}", metric))
+}
+
+pub fn load_process(pid: Option<u32>) -> serde_json::Value {
+    load_process_from(SOCKET_PATH, pid)
+}
+
+/// RSS of `pid` (or the largest process) and the memory still available.
+pub fn load_process_from(socket_path: &str, pid: Option<u32>) -> serde_json::Value {
+    let command = pid.map_or_else(|| "process".to_string(), |pid| format!("process {}", pid));
+    request(socket_path, &command)
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_else(|| serde_json::json!({ "error": "stats service unavailable" }))
+}
+
+/// Least-squares fit of (ms, value) samples as (change per second, value at
+/// the first sample); None for fewer than two distinct times.
+fn linear_trend(samples: &[(i64, f64)]) -> Option<(f64, f64)> {
+    if samples.len() < 2 {
+        return None;
+    }
+    let start = samples[0].0;
+    let n = samples.len() as f64;
+    let points = samples.iter().map(|(t, y)| ((t - start) as f64 / 1000.0, *y));
+    let (sum_x, sum_y, sum_xx, sum_xy) = points.fold((0.0, 0.0, 0.0, 0.0), |(sx, sy, sxx, sxy), (x, y)| {
+        (sx + x, sy + y, sxx + x * x, sxy + x * y)
+    });
+    let denominator = n * sum_xx - sum_x * sum_x;
+    if denominator.abs() < f64::EPSILON {
+        return None;
+    }
+    let slope = (n * sum_xy - sum_x * sum_y) / denominator;
+    Some((slope, (sum_y - slope * sum_x) / n))
+}
+
+/// RSS samples of one process with a least-squares trend, for the leak view.
+#[derive(Clone)]
+pub struct LeakTracker {
+    pub pid: Option<u32>,
+    pub name: String,
+    // (ms, KB)
+    pub samples: Vec<(i64, f64)>,
+    pub max_size: usize,
+    pub memory_available: f64,
+}
+
+impl LeakTracker {
+    /// `pid` None follows whichever process is largest on the first sample.
+    pub fn new(pid: Option<u32>, max_size: usize) -> Self {
+        LeakTracker { pid, name: String::new(), samples: Vec::new(), max_size, memory_available: 0.0 }
+    }
+
+    /// Adds a `process` reply. The tracker locks onto the first pid it sees
+    /// and starts over if that pid is replaced by a different process.
+    pub fn push(&mut self, reply: &serde_json::Value) -> bool {
+        let (Some(pid), Some(rss), Some(timestamp)) =
+            (reply["pid"].as_u64(), reply["rss"].as_f64(), reply["timestamp"].as_i64())
+        else {
+            return false;
+        };
+        let name = reply["name"].as_str().unwrap_or("?");
+        if self.pid.is_some_and(|p| p as u64 != pid) || (!self.name.is_empty() && self.name != name) {
+            self.samples.clear();
+        }
+        self.pid = Some(pid as u32);
+        self.name = name.to_string();
+        self.memory_available = reply["memory_available"].as_f64().unwrap_or(0.0);
+        if self.samples.last().is_some_and(|(t, _)| *t >= timestamp) {
+            return false;
+        }
+        self.samples.push((timestamp, rss));
+        let excess = self.samples.len().saturating_sub(self.max_size);
+        self.samples.drain(..excess);
+        true
+    }
+
+    /// (KB per second, KB at the first sample) of the fitted line.
+    pub fn trend(&self) -> Option<(f64, f64)> {
+        linear_trend(&self.samples)
+    }
+
+    /// Seconds until available memory runs out at the current growth rate;
+    /// None while the process isn't growing.
+    pub fn seconds_to_oom(&self) -> Option<f64> {
+        let (slope, _) = self.trend()?;
+        (slope > 0.0).then(|| self.memory_available / slope)
+    }
+}
+
+pub fn format_duration(secs: f64) -> String {
+    let minutes = (secs / 60.0).round() as u64;
+    match minutes {
+        0 => "under a minute".to_string(),
+        m if m < 60 => format!("{} min", m),
+        m if m < 48 * 60 => format!("{} h {} min", m / 60, m % 60),
+        m => format!("{} days", m / 60 / 24),
+    }
+}
+
+/// e.g. `rust-analyzer (4242): 2.1 GB, +12.0 MB/min, OOM in ~3 h 5 min`.
+pub fn leak_summary(tracker: &LeakTracker) -> String {
+    let Some(&(_, rss)) = tracker.samples.last() else {
+        return "Waiting for process samples".to_string();
+    };
+    let mut summary = format!("{} ({}): {}", tracker.name, tracker.pid.unwrap_or(0), format_memory(rss));
+    if let Some((slope, _)) = tracker.trend() {
+        summary.push_str(&format!(", {:+.1} MB/min", slope * 60.0 / 1024.0));
+        match tracker.seconds_to_oom() {
+            Some(secs) => summary.push_str(&format!(", OOM in ~{}", format_duration(secs))),
+            None => summary.push_str(", not growing"),
+        }
+    }
+    summary
+}
+
+pub fn load_filesystems() -> serde_json::Value {
+    load_filesystems_from(SOCKET_PATH)
+}
+
+/// Per-mount usage history, as `{"mounts": {"/": {...}}}`.
+pub fn load_filesystems_from(socket_path: &str) -> serde_json::Value {
+    request(socket_path, "filesystems")
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_else(|| serde_json::json!({ "error": "stats service unavailable" }))
+}
+
+/// One row of the storage view.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MountRow {
+    pub mount: String,
+    // KB
+    pub total: f64,
+    pub used: f64,
+    // Used KB over the last day, oldest first, for the sparkline
+    pub history: Vec<f64>,
+    // Seconds until full at the growth rate over that history; None while
+    // the mount isn't growing
+    pub full_in: Option<f64>,
+}
+
+/// Rows for the storage view, fullest first.
+pub fn mount_rows(filesystems: &serde_json::Value) -> Vec<MountRow> {
+    let mut rows: Vec<MountRow> = filesystems["mounts"]
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter_map(|(mount, info)| {
+            let total = info["total"].as_f64().filter(|t| *t > 0.0)?;
+            let history = series(info, "used");
+            let used = newest(&history)?;
+            let samples: Vec<(i64, f64)> = info["timestamps"]
+                .as_array()?
+                .iter()
+                .zip(&history)
+                .filter(|(_, used)| used.is_finite())
+                .filter_map(|(t, used)| Some((t.as_i64()?, *used)))
+                .collect();
+            let full_in = linear_trend(&samples)
+                .filter(|(slope, _)| *slope > 0.0)
+                .map(|(slope, _)| (total - used).max(0.0) / slope);
+            Some(MountRow { mount: mount.clone(), total, used, history, full_in })
+        })
+        .collect();
+    rows.sort_by(|a, b| (b.used / b.total).total_cmp(&(a.used / a.total)));
+    rows
+}
+
+/// e.g. `/home full in ~12 days`, for the fullest mount that's filling up.
+pub fn storage_summary(rows: &[MountRow]) -> String {
+    if rows.is_empty() {
+        return "No filesystem samples yet".to_string();
+    }
+    match rows.iter().filter_map(|r| Some((r, r.full_in?))).min_by(|a, b| a.1.total_cmp(&b.1)) {
+        Some((row, secs)) => format!("{} full in ~{}", row.mount, format_duration(secs)),
+        None => "No mount is filling up".to_string(),
+    }
+}
+
+pub fn load_apps() -> serde_json::Value {
+    load_apps_from(SOCKET_PATH)
+}
+
+/// Top processes grouped by desktop application, as `{"apps": [...]}`.
+pub fn load_apps_from(socket_path: &str) -> serde_json::Value {
+    request(socket_path, "apps")
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_else(|| serde_json::json!({ "error": "stats service unavailable" }))
+}
+
+/// One dial of the summary header.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gauge {
+    pub label: &'static str,
+    // How far round the dial, 0-1
+    pub fraction: f64,
+    pub text: String,
+    pub color: String,
+}
+
+/// CPU, memory, temperature and network dials from the newest samples, in
+/// the series' metadata colors. Temperature comes in °C and is shown through
+/// `temperature`, which gives the value and symbol to print; it fills
+/// towards 100 °C. Network has no ceiling, so it fills against the busiest
+/// sample in the window. Series the history doesn't have are left out.
+pub fn gauges(history: &serde_json::Value, metadata: &serde_json::Value, temperature: impl Fn(f64) -> (f64, &'static str)) -> Vec<Gauge> {
+    let color = |key: &str| metadata["series"][key]["color"].as_str().unwrap_or("#89b4fa").to_string();
+    let mut gauges = Vec::new();
+    for (label, key) in [("CPU", "cpu"), ("RAM", "memory")] {
+        if let Some(percent) = newest(&series(history, key)) {
+            gauges.push(Gauge { label, fraction: percent / 100.0, text: format!("{:.0}%", percent), color: color(key) });
+        }
+    }
+    if let Some(celsius) = newest(&series(history, "temperature")) {
+        let (value, symbol) = temperature(celsius);
+        gauges.push(Gauge { label: "Temp", fraction: celsius / 100.0, text: format!("{:.0}{}", value, symbol), color: color("temperature") });
+    }
+    let (download, upload) = (series(history, "network_download"), series(history, "network_upload"));
+    let totals: Vec<f64> = download.iter().zip(&upload).map(|(down, up)| down + up).collect();
+    if let Some(total) = newest(&totals) {
+        let peak = totals.iter().copied().fold(0.0, f64::max);
+        let unit = metadata["series"]["network_download"]["unit"].as_str().unwrap_or("KB/s");
+        let text = if total >= 1000.0 { format!("{:.1}k {}", total / 1000.0, unit) } else { format!("{:.0} {}", total, unit) };
+        gauges.push(Gauge {
+            label: "Net",
+            fraction: if peak > 0.0 { total / peak } else { 0.0 },
+            text,
+            color: color("network_download"),
+        });
+    }
+    for gauge in &mut gauges {
+        gauge.fraction = gauge.fraction.clamp(0.0, 1.0);
+    }
+    gauges
+}
+
+pub fn format_memory(kb: f64) -> String {
+    if kb >= 1024.0 * 1024.0 {
+        format!("{:.1} GB", kb / 1024.0 / 1024.0)
     } else {
-        eprintln!("Could not connect to socket, trying file");
-        fs::read_to_string("/tmp/ags-stats/history.json").unwrap_or_default()
+        format!("{:.0} MB", kb / 1024.0)
+    }
+}
+
+/// The top-level slices, then the `apps` busiest app units indented under
+/// them, from a latest.json sample, e.g. `user.slice: 12% CPU, 3.1 GB`.
+pub fn slices_summary(latest: &serde_json::Value, apps: usize) -> String {
+    let slices = latest["slices"].as_array().map(Vec::as_slice).unwrap_or_default();
+    if slices.is_empty() {
+        return "No systemd slices reported".to_string();
+    }
+    let row = |slice: &serde_json::Value| {
+        format!(
+            "{}: {:.0}% CPU, {}",
+            slice["name"].as_str().unwrap_or("?"),
+            slice["cpu"].as_f64().unwrap_or(0.0),
+            format_memory(slice["memory"].as_f64().unwrap_or(0.0)),
+        )
     };
+    let (top, units): (Vec<_>, Vec<_>) = slices.iter().partition(|slice| slice["kind"] == "slice");
+    top.into_iter()
+        .map(row)
+        .chain(units.into_iter().take(apps).map(|app| format!("  {}", row(app))))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// One row per application for the process panel, e.g.
+/// `Firefox (14 processes): 2.1 GB, 38% CPU`.
+pub fn apps_summary(apps: &serde_json::Value) -> String {
+    if let Some(error) = apps["error"].as_str() {
+        return error.to_string();
+    }
+    let rows: Vec<String> = apps["apps"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|app| {
+            let processes = app["processes"].as_u64().unwrap_or(0);
+            let count = if processes == 1 { "1 process".to_string() } else { format!("{} processes", processes) };
+            format!(
+                "{} ({}): {}, {:.0}% CPU",
+                app["name"].as_str().unwrap_or("?"),
+                count,
+                format_memory(app["memory"].as_f64().unwrap_or(0.0)),
+                app["cpu"].as_f64().unwrap_or(0.0),
+            )
+        })
+        .collect();
+    if rows.is_empty() {
+        "No processes reported".to_string()
+    } else {
+        rows.join("\n")
+    }
+}
+
+/// The heaviest processes in one line, e.g. `Top: firefox 38% · code 12%`,
+/// from `top_processes` ranked `by_cpu` or `by_memory`. None until the
+/// service has sent a list.
+pub fn top_processes_summary(history: &serde_json::Value, ranking: &str, count: usize) -> Option<String> {
+    let rows: Vec<String> = history["top_processes"][ranking]
+        .as_array()?
+        .iter()
+        .take(count)
+        .map(|process| {
+            let name = process["name"].as_str().unwrap_or("?");
+            match ranking {
+                "by_memory" => format!("{} {}", name, format_memory(process["rss"].as_f64().unwrap_or(0.0))),
+                _ => format!("{} {:.0}%", name, process["cpu"].as_f64().unwrap_or(0.0)),
+            }
+        })
+        .collect();
+    (!rows.is_empty()).then(|| format!("Top: {}", rows.join(" · ")))
+}
+
+/// The samples of one history series, one per timestamp; missing keys give
+/// an empty series. Ticks that never ran and unreadable sensors (null) are
+/// kept as NaN so the slots line up with `plotted_timestamps`.
+pub fn series(history: &serde_json::Value, key: &str) -> Vec<f64> {
+    history[key]
+        .as_array()
+        .map(|values| values.iter().map(|v| v.as_f64().unwrap_or(f64::NAN)).collect())
+        .unwrap_or_default()
+}
 
-    serde_json::from_str(&history_json).unwrap_or_else(|_| serde_json::json!({}))
+/// The newest sample that was actually taken, for readouts of a `series`.
+pub fn newest(values: &[f64]) -> Option<f64> {
+    values.iter().rev().copied().find(|v| v.is_finite())
+}
+
+/// Interfaces the history has per-interface series for, in name order.
+pub fn interface_names(history: &serde_json::Value) -> Vec<String> {
+    history["network_interfaces"]
+        .as_object()
+        .map(|interfaces| interfaces.keys().cloned().collect())
+        .unwrap_or_default()
+}
+
+/// (download, upload) series for one interface, or the totals for None.
+pub fn network_series(history: &serde_json::Value, interface: Option<&str>) -> (Vec<f64>, Vec<f64>) {
+    match interface {
+        Some(name) => {
+            let iface = &history["network_interfaces"][name];
+            (series(iface, "download"), series(iface, "upload"))
+        }
+        None => (series(history, "network_download"), series(history, "network_upload")),
+    }
+}
+
+/// Wi-Fi signal (dBm) to overlay on `network_series`: the interface's own,
+/// or for the totals the first interface that has one. Empty for wired.
+pub fn network_signal(history: &serde_json::Value, interface: Option<&str>) -> Vec<f64> {
+    let interfaces = &history["network_interfaces"];
+    let name = interface.map(str::to_string).or_else(|| {
+        interface_names(history).into_iter().find(|name| interfaces[name]["signal"].is_array())
+    });
+    // Gaps (null, while disassociated) are kept as NaN so the slots line up
+    name.and_then(|name| interfaces[&name]["signal"].as_array().cloned())
+        .map(|values| values.iter().map(|v| v.as_f64().unwrap_or(f64::NAN)).collect())
+        .unwrap_or_default()
+}
+
+/// Height (0-1) of a signal on the overlay's fixed scale: -90 dBm, about
+/// where links drop, at the bottom and -30 dBm, next to the router, at the top.
+pub fn signal_fraction(dbm: f64) -> f64 {
+    ((dbm + 90.0) / 60.0).clamp(0.0, 1.0)
+}
+
+/// Fills the memory breakdown from a history response. The service reports
+/// the total as `memory_total`; `memory` itself is the usage percentage series.
+/// Fills one chart per core, and IO wait, from a history response.
+pub fn update_cpu_cores(cores: &mut Vec<GraphData>, iowait: &mut GraphData, history: &serde_json::Value) {
+    let per_core = history["cpu_cores"].as_array().map_or(&[][..], Vec::as_slice);
+    cores.resize_with(per_core.len(), || GraphData::new_with_zeros(iowait.max_size));
+    for (core, values) in cores.iter_mut().zip(per_core) {
+        core.set_values(values.as_array().into_iter().flatten().map(|v| v.as_f64().unwrap_or(f64::NAN)).collect());
+    }
+    iowait.set_from_history(history, "cpu_iowait");
+}
+
+/// The newest clock of each core in MHz, NaN where it's unknown. Empty when
+/// the service doesn't collect frequencies.
+pub fn core_frequencies(history: &serde_json::Value) -> Vec<f64> {
+    history["cpu_frequencies"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|core| core.as_array().and_then(|values| values.last()?.as_f64()).unwrap_or(f64::NAN))
+        .collect()
+}
+
+/// "3.42 GHz", or MHz below one gigahertz.
+pub fn format_frequency(mhz: f64) -> String {
+    if mhz >= 1000.0 {
+        format!("{:.2} GHz", mhz / 1000.0)
+    } else {
+        format!("{:.0} MHz", mhz)
+    }
+}
+
+pub fn update_memory(mem_data: &mut AdvancedMemoryData, history: &serde_json::Value) {
+    if let Some(total) = history["memory_total"].as_f64() {
+        mem_data.total = total;
+    }
+    mem_data.apps.set_from_history(history, "memory_apps");
+    mem_data.cached.set_from_history(history, "memory_cached");
+    mem_data.buffers.set_from_history(history, "memory_buffers");
+    mem_data.slab.set_from_history(history, "memory_slab");
+    mem_data.shmem.set_from_history(history, "memory_shmem");
+}
+
+/// The sample times of the newest `len` slots of a history, lined up with a
+/// series of that length; shorter histories are padded with 0 at the start.
+pub fn plotted_timestamps(history: &serde_json::Value, len: usize) -> Vec<i64> {
+    let timestamps: Vec<i64> = history["timestamps"].as_array().into_iter().flatten().filter_map(|t| t.as_i64()).collect();
+    let mut plotted = vec![0; len.saturating_sub(timestamps.len())];
+    plotted.extend_from_slice(&timestamps[timestamps.len().saturating_sub(len)..]);
+    plotted
+}
+
+/// An alert's span over the plotted slots, from the history's markers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlertBand {
+    pub label: String,
+    pub first: usize,
+    pub last: usize,
+    // Whether the raise and the clear fall inside the plotted range; an
+    // alert still firing has no clear
+    pub raised: bool,
+    pub cleared: bool,
+}
+
+/// The alerts that overlap the plotted `timestamps`, oldest first.
+pub fn alert_bands(history: &serde_json::Value, timestamps: &[i64]) -> Vec<AlertBand> {
+    let oldest = timestamps.iter().copied().find(|&t| t != 0).unwrap_or(i64::MAX);
+    let newest = timestamps.last().copied().unwrap_or(0);
+    history["markers"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|marker| marker["kind"] == "alert")
+        .filter_map(|marker| {
+            let start = marker["start"].as_i64()?;
+            let end = marker["end"].as_i64();
+            let (first, last) = brush_span(timestamps, start, end.unwrap_or(i64::MAX))?;
+            Some(AlertBand {
+                label: marker["label"].as_str().unwrap_or("alert").to_string(),
+                first,
+                last,
+                raised: start >= oldest,
+                cleared: end.is_some_and(|end| end <= newest),
+            })
+        })
+        .collect()
+}
+
+/// Statistics for the samples of a brushed interval.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RangeStats {
+    // ms, of the first and last sample in the selection
+    pub start: i64,
+    pub end: i64,
+    pub min: f64,
+    pub max: f64,
+    pub avg: f64,
+    // The series integrated over time: value × seconds, so KB/s sums to KB
+    pub total: f64,
+    pub samples: usize,
+}
+
+/// The fewest samples a pinch can zoom in to.
+pub const MIN_ZOOM_SLOTS: usize = 10;
+
+/// Samples to show after pinching by `scale` from a span of `start`:
+/// spreading the fingers (a scale above 1) shows fewer. Kept between
+/// `MIN_ZOOM_SLOTS` and `full`.
+pub fn zoom_slots(start: usize, scale: f64, full: usize) -> usize {
+    if !scale.is_finite() || scale <= 0.0 {
+        return start;
+    }
+    ((start as f64 / scale).round() as usize).clamp(MIN_ZOOM_SLOTS.min(full), full)
+}
+
+/// The slot under `x` on a graph `width` wide that plots `slots` samples.
+pub fn slot_at(x: f64, width: f64, slots: usize) -> usize {
+    let slot = (x.clamp(0.0, width) / width.max(1.0) * slots.saturating_sub(1) as f64).round() as usize;
+    slot.min(slots.saturating_sub(1))
+}
+
+/// Slots of `timestamps` that fall between `a` and `b`, in either order.
+pub fn brush_span(timestamps: &[i64], a: i64, b: i64) -> Option<(usize, usize)> {
+    let (start, end) = (a.min(b), a.max(b));
+    let first = timestamps.iter().position(|&t| t != 0 && t >= start)?;
+    let last = timestamps.iter().rposition(|&t| t != 0 && t <= end)?;
+    (first <= last).then_some((first, last))
+}
+
+/// Min, max, average and total of `values` between the times `a` and `b`.
+/// Padding and ticks that never ran (NaN) are left out.
+pub fn range_stats(values: &[f64], timestamps: &[i64], a: i64, b: i64) -> Option<RangeStats> {
+    let (first, last) = brush_span(timestamps, a, b)?;
+    let (mut min, mut max, mut sum, mut total, mut samples) = (f64::INFINITY, f64::NEG_INFINITY, 0.0, 0.0, 0);
+    for i in first..=last.min(values.len().checked_sub(1)?) {
+        let value = values[i];
+        if value.is_nan() {
+            continue;
+        }
+        // Each sample stands for the time since the one before it
+        let step = match i.checked_sub(1).map(|p| timestamps[p]) {
+            Some(previous) if previous != 0 => (timestamps[i] - previous) as f64 / 1000.0,
+            _ => 1.0,
+        };
+        min = min.min(value);
+        max = max.max(value);
+        sum += value;
+        total += value * step;
+        samples += 1;
+    }
+    (samples > 0).then(|| RangeStats { start: timestamps[first], end: timestamps[last], min, max, avg: sum / samples as f64, total, samples })
+}
+
+/// The footer text for a selection, e.g.
+/// `Selection 12 s: min 0.4 | max 9.8 | avg 3.1 KB/s | total 37 MB`.
+pub fn range_summary(stats: &RangeStats, unit: &str) -> String {
+    let total = match unit {
+        "KB/s" if stats.total < 1024.0 => format!("{:.0} KB", stats.total),
+        "KB/s" => format_memory(stats.total),
+        "W" => format!("{:.2} Wh", stats.total / 3600.0),
+        _ => match unit.strip_suffix("/s") {
+            Some(per) => format!("{:.1} {}", stats.total, per),
+            None => format!("{:.1}{}·s", stats.total, unit),
+        },
+    };
+    let secs = (stats.end - stats.start) as f64 / 1000.0;
+    // format_duration rounds to minutes, too coarse for a spike
+    let span = if secs < 120.0 { format!("{:.0} s", secs) } else { format_duration(secs) };
+    format!(
+        "Selection {}: min {:.1} | max {:.1} | avg {:.1}{} | total {}",
+        span,
+        stats.min,
+        stats.max,
+        stats.avg,
+        unit,
+        total
+    )
 }
@@ -1,22 +1,51 @@
 use cairo::Context;
-use crate::data::{GraphData, AdvancedMemoryData};
+use std::f64::consts::PI;
+use crate::data::{self, AlertBand, Gauge, GraphData, AdvancedMemoryData, LeakTracker, MountRow};
 use crate::config::GraphConfig;
+use crate::snapshots::Snapshot;
 
+/// Hex or a palette entry such as `mocha:blue`; see `graph_theme::parse`.
+/// Alpha is dropped, as every caller sets its own.
 pub fn parse_color(color: &str) -> (f64, f64, f64) {
-    if color.starts_with('#') && color.len() == 7 {
-        let r = u8::from_str_radix(&color[1..3], 16).unwrap_or(128) as f64 / 255.0;
-        let g = u8::from_str_radix(&color[3..5], 16).unwrap_or(128) as f64 / 255.0;
-        let b = u8::from_str_radix(&color[5..7], 16).unwrap_or(128) as f64 / 255.0;
-        (r, g, b)
-    } else {
-        (0.5, 0.5, 1.0)
+    match graph_theme::parse(color) {
+        Some(color) => {
+            let (r, g, b, _) = color.to_unit();
+            (r, g, b)
+        }
+        None => (0.5, 0.5, 1.0),
     }
 }
 
-pub fn draw_advanced_memory_chart(cr: &Context, mem_data: &AdvancedMemoryData, width: f64, height: f64) {
-    cr.set_source_rgba(0.118, 0.118, 0.180, 0.9);
+// Scaled by the configured opacity along with the window's own background
+fn fill_background(cr: &Context, config: &GraphConfig, width: f64, height: f64) {
+    let (r, g, b, a) = config.colors.background.to_unit();
+    cr.set_source_rgba(r, g, b, 0.9 * a * config.opacity.clamp(0.0, 1.0));
     cr.rectangle(0.0, 0.0, width, height);
     cr.fill().unwrap();
+}
+
+// How far up the plot a sample sits, 0-1; missing ones (NaN) sit on the floor
+fn level(value: f64, max_value: f64) -> f64 {
+    if value.is_finite() { (value / max_value).min(1.0) } else { 0.0 }
+}
+
+// One line through (x, value) samples, lifted over the missing (NaN) ones
+fn trace(cr: &Context, samples: impl IntoIterator<Item = (f64, f64)>, y: impl Fn(f64) -> f64) {
+    let mut drawing = false;
+    for (x, value) in samples {
+        if !value.is_finite() {
+            drawing = false;
+        } else if drawing {
+            cr.line_to(x, y(value));
+        } else {
+            cr.move_to(x, y(value));
+            drawing = true;
+        }
+    }
+}
+
+pub fn draw_advanced_memory_chart(cr: &Context, mem_data: &AdvancedMemoryData, config: &GraphConfig, width: f64, height: f64) {
+    fill_background(cr, config, width, height);
 
     let data_points = mem_data.apps.values.len();
     if data_points == 0 { return; }
@@ -46,7 +75,7 @@ pub fn draw_advanced_memory_chart(cr: &Context, mem_data: &AdvancedMemoryData, w
         }
 
         for i in (0..data_points).rev() {
-            let new_cumulative = cumulative_values[i] + data.values[i];
+            let new_cumulative = cumulative_values[i] + if data.values[i].is_finite() { data.values[i] } else { 0.0 };
             let x = (i as f64 / (data_points - 1).max(1) as f64) * width;
             let y = height - (new_cumulative / max_value).min(1.0) * height;
             cr.line_to(x, y);
@@ -57,10 +86,8 @@ pub fn draw_advanced_memory_chart(cr: &Context, mem_data: &AdvancedMemoryData, w
     }
 }
 
-pub fn draw_multi_cpu_charts(cr: &Context, cpu_data: &[GraphData], iowait_data: &GraphData, config: &GraphConfig, width: f64, height: f64) {
-    cr.set_source_rgba(0.118, 0.118, 0.180, 0.9);
-    cr.rectangle(0.0, 0.0, width, height);
-    cr.fill().unwrap();
+pub fn draw_multi_cpu_charts(cr: &Context, cpu_data: &[GraphData], frequencies: &[f64], iowait_data: &GraphData, config: &GraphConfig, width: f64, height: f64) {
+    fill_background(cr, config, width, height);
     
     let num_cores = cpu_data.len().min(16);
     let cols = 4;
@@ -93,6 +120,11 @@ pub fn draw_multi_cpu_charts(cr: &Context, cpu_data: &[GraphData], iowait_data:
         cr.set_source_rgba(0.8, 0.8, 0.9, 1.0);
         cr.move_to(x_offset + 4.0, y_offset + 15.0);
         cr.show_text(&format!("C{}", i)).unwrap();
+        if let Some(mhz) = frequencies.get(i).filter(|f| f.is_finite()) {
+            cr.set_source_rgba(0.65, 0.68, 0.78, 1.0);
+            cr.move_to(x_offset + 24.0, y_offset + 15.0);
+            cr.show_text(&data::format_frequency(*mhz)).unwrap();
+        }
         
         cr.set_source_rgba(r, g, b, 0.3);
         let mini_width = chart_width - 8.0;
@@ -102,7 +134,7 @@ pub fn draw_multi_cpu_charts(cr: &Context, cpu_data: &[GraphData], iowait_data:
         
         for (j, value) in core_data.values.iter().enumerate() {
             let x = x_offset + 4.0 + (j as f64 / (core_data.values.len() - 1).max(1) as f64) * mini_width;
-            let y = y_offset + chart_height - 4.0 - (value / config.max_value).min(1.0) * mini_height;
+            let y = y_offset + chart_height - 4.0 - level(*value, config.max_value) * mini_height;
             cr.line_to(x, y);
         }
         
@@ -113,19 +145,13 @@ pub fn draw_multi_cpu_charts(cr: &Context, cpu_data: &[GraphData], iowait_data:
         cr.set_source_rgba(r, g, b, 1.0);
         cr.set_line_width(1.0);
         
-        for (j, value) in core_data.values.iter().enumerate() {
-            let x = x_offset + 4.0 + (j as f64 / (core_data.values.len() - 1).max(1) as f64) * mini_width;
-            let y = y_offset + chart_height - 4.0 - (value / config.max_value).min(1.0) * mini_height;
-            
-            if j == 0 {
-                cr.move_to(x, y);
-            } else {
-                cr.line_to(x, y);
-            }
-        }
+        let step = mini_width / (core_data.values.len() - 1).max(1) as f64;
+        trace(cr, core_data.values.iter().enumerate().map(|(j, value)| (x_offset + 4.0 + j as f64 * step, *value)), |value| {
+            y_offset + chart_height - 4.0 - (value / config.max_value).min(1.0) * mini_height
+        });
         cr.stroke().unwrap();
         
-        let current = core_data.values.last().copied().unwrap_or(0.0);
+        let current = data::newest(&core_data.values).unwrap_or(0.0);
         cr.set_source_rgba(1.0, 1.0, 1.0, 0.8);
         cr.move_to(x_offset + chart_width - 30.0, y_offset + chart_height - 8.0);
         cr.show_text(&format!("{:.0}%", current)).unwrap();
@@ -150,7 +176,7 @@ pub fn draw_multi_cpu_charts(cr: &Context, cpu_data: &[GraphData], iowait_data:
         
         for (i, value) in iowait_data.values.iter().enumerate() {
             let x = 10.0 + (i as f64 / (iowait_data.values.len() - 1).max(1) as f64) * (width - 20.0);
-            let y = iowait_y + iowait_height - (value / 10.0).min(1.0) * (iowait_height - 20.0);
+            let y = iowait_y + iowait_height - level(*value, 10.0) * (iowait_height - 20.0);
             cr.line_to(x, y);
         }
         
@@ -161,29 +187,87 @@ pub fn draw_multi_cpu_charts(cr: &Context, cpu_data: &[GraphData], iowait_data:
         cr.set_source_rgba(r, g, b, 1.0);
         cr.set_line_width(1.5);
         
-        for (i, value) in iowait_data.values.iter().enumerate() {
-            let x = 10.0 + (i as f64 / (iowait_data.values.len() - 1).max(1) as f64) * (width - 20.0);
-            let y = iowait_y + iowait_height - (value / 10.0).min(1.0) * (iowait_height - 20.0);
-            
-            if i == 0 {
-                cr.move_to(x, y);
-            } else {
-                cr.line_to(x, y);
-            }
-        }
+        let step = (width - 20.0) / (iowait_data.values.len() - 1).max(1) as f64;
+        trace(cr, iowait_data.values.iter().enumerate().map(|(i, value)| (10.0 + i as f64 * step, *value)), |value| {
+            iowait_y + iowait_height - (value / 10.0).min(1.0) * (iowait_height - 20.0)
+        });
         cr.stroke().unwrap();
         
-        let current = iowait_data.values.last().copied().unwrap_or(0.0);
+        let current = data::newest(&iowait_data.values).unwrap_or(0.0);
         cr.set_source_rgba(1.0, 1.0, 1.0, 0.8);
         cr.move_to(width - 50.0, iowait_y + iowait_height - 5.0);
         cr.show_text(&format!("{:.1}%", current)).unwrap();
     }
 }
 
+// Cool to hot: idle cells stay close to the background
+const HEAT_STOPS: [(f64, &str); 4] = [(0.0, "#313244"), (0.35, "#89b4fa"), (0.7, "#f9e2af"), (1.0, "#f38ba8")];
+
+fn heat_color(fraction: f64) -> (f64, f64, f64) {
+    let fraction = fraction.clamp(0.0, 1.0);
+    let upper = HEAT_STOPS.iter().position(|(stop, _)| *stop >= fraction).unwrap_or(HEAT_STOPS.len() - 1).max(1);
+    let ((low, low_color), (high, high_color)) = (HEAT_STOPS[upper - 1], HEAT_STOPS[upper]);
+    let t = (fraction - low) / (high - low);
+    let (r0, g0, b0) = parse_color(low_color);
+    let (r1, g1, b1) = parse_color(high_color);
+    (r0 + (r1 - r0) * t, g0 + (g1 - g0) * t, b0 + (b1 - b0) * t)
+}
+
+/// Every core at once for machines the chart grid can't fit: time runs
+/// across, one row per core, colored by usage. IO wait and the average are
+/// summarized underneath.
+pub fn draw_cpu_heatmap(cr: &Context, cpu_data: &[GraphData], iowait_data: &GraphData, config: &GraphConfig, width: f64, height: f64) {
+    fill_background(cr, config, width, height);
+
+    let samples = cpu_data.iter().map(|c| c.values.len()).max().unwrap_or(0);
+    if cpu_data.is_empty() || samples == 0 {
+        return;
+    }
+    let (gutter, footer) = (28.0, 18.0);
+    let cell_width = (width - gutter) / samples as f64;
+    let row_height = (height - footer) / cpu_data.len() as f64;
+    // Label often enough to count rows without the labels overlapping
+    let label_every = (12.0 / row_height).ceil().max(1.0) as usize;
+
+    cr.set_font_size(9.0);
+    for (core, core_data) in cpu_data.iter().enumerate() {
+        let y = core as f64 * row_height;
+        // Shorter series line up with the newest samples
+        let offset = samples - core_data.values.len();
+        for (i, value) in core_data.values.iter().enumerate() {
+            if !value.is_finite() {
+                continue;
+            }
+            let (r, g, b) = heat_color(value / config.max_value);
+            cr.set_source_rgba(r, g, b, 1.0);
+            // Overlapping by a pixel hides seams between cells
+            cr.rectangle(gutter + (offset + i) as f64 * cell_width, y, cell_width + 0.5, row_height + 0.5);
+            cr.fill().unwrap();
+        }
+        if core % label_every == 0 {
+            cr.set_source_rgba(0.8, 0.8, 0.9, 1.0);
+            cr.move_to(2.0, y + row_height.min(12.0) - 2.0);
+            cr.show_text(&format!("C{}", core)).unwrap();
+        }
+    }
+
+    let latest: Vec<f64> = cpu_data.iter().filter_map(|c| c.values.last().copied()).filter(|v| v.is_finite()).collect();
+    let average = latest.iter().sum::<f64>() / latest.len().max(1) as f64;
+    let busiest = latest.iter().copied().fold(0.0, f64::max);
+    cr.set_source_rgba(1.0, 1.0, 1.0, 0.8);
+    cr.move_to(gutter, height - 5.0);
+    cr.show_text(&format!(
+        "{} cores | avg {:.0}% | max {:.0}% | IO wait {:.1}%",
+        cpu_data.len(),
+        average,
+        busiest,
+        data::newest(&iowait_data.values).unwrap_or(0.0)
+    ))
+    .unwrap();
+}
+
 pub fn draw_graph(cr: &Context, data: &GraphData, data2: Option<&GraphData>, config: &GraphConfig, width: f64, height: f64) {
-    cr.set_source_rgba(0.118, 0.118, 0.180, 0.9);
-    cr.rectangle(0.0, 0.0, width, height);
-    cr.fill().unwrap();
+    fill_background(cr, config, width, height);
     
     cr.set_source_rgba(0.27, 0.28, 0.35, 0.3);
     cr.set_line_width(0.5);
@@ -195,6 +279,14 @@ pub fn draw_graph(cr: &Context, data: &GraphData, data2: Option<&GraphData>, con
         cr.stroke().unwrap();
     }
     
+    // Scale of the top grid line
+    if !config.unit.is_empty() {
+        cr.set_source_rgba(0.73, 0.76, 0.87, 0.8);
+        cr.set_font_size(9.0);
+        cr.move_to(4.0, 11.0);
+        cr.show_text(&format!("{} {}", config.max_value, config.unit)).unwrap();
+    }
+    
     if data.values.is_empty() {
         return;
     }
@@ -206,7 +298,7 @@ pub fn draw_graph(cr: &Context, data: &GraphData, data2: Option<&GraphData>, con
     
     for (i, value) in data.values.iter().enumerate() {
         let x = (i as f64 / (data.values.len() - 1).max(1) as f64) * width;
-        let y = height - (value / config.max_value).min(1.0) * height;
+        let y = height - level(*value, config.max_value) * height;
         cr.line_to(x, y);
     }
     
@@ -217,16 +309,10 @@ pub fn draw_graph(cr: &Context, data: &GraphData, data2: Option<&GraphData>, con
     cr.set_source_rgba(r, g, b, 1.0);
     cr.set_line_width(2.0);
     
-    for (i, value) in data.values.iter().enumerate() {
-        let x = (i as f64 / (data.values.len() - 1).max(1) as f64) * width;
-        let y = height - (value / config.max_value).min(1.0) * height;
-        
-        if i == 0 {
-            cr.move_to(x, y);
-        } else {
-            cr.line_to(x, y);
-        }
-    }
+    let step = width / (data.values.len() - 1).max(1) as f64;
+    trace(cr, data.values.iter().enumerate().map(|(i, value)| (i as f64 * step, *value)), |value| {
+        height - (value / config.max_value).min(1.0) * height
+    });
     cr.stroke().unwrap();
     
     if let Some(data2) = data2 {
@@ -242,7 +328,7 @@ pub fn draw_graph(cr: &Context, data: &GraphData, data2: Option<&GraphData>, con
             
             for (i, value) in data2.values.iter().enumerate() {
                 let x = (i as f64 / (data2.values.len() - 1).max(1) as f64) * width;
-                let y = height - (value / config.max_value).min(1.0) * height;
+                let y = height - level(*value, config.max_value) * height;
                 cr.line_to(x, y);
             }
             
@@ -253,17 +339,267 @@ pub fn draw_graph(cr: &Context, data: &GraphData, data2: Option<&GraphData>, con
             cr.set_source_rgba(r2, g2, b2, 1.0);
             cr.set_line_width(2.0);
             
-            for (i, value) in data2.values.iter().enumerate() {
-                let x = (i as f64 / (data2.values.len() - 1).max(1) as f64) * width;
-                let y = height - (value / config.max_value).min(1.0) * height;
-                
-                if i == 0 {
-                    cr.move_to(x, y);
-                } else {
-                    cr.line_to(x, y);
-                }
+            let step = width / (data2.values.len() - 1).max(1) as f64;
+            trace(cr, data2.values.iter().enumerate().map(|(i, value)| (i as f64 * step, *value)), |value| {
+                height - (value / config.max_value).min(1.0) * height
+            });
+            cr.stroke().unwrap();
+        }
+    }
+}
+/// A pinned snapshot dashed over the live graph, on the same scale and
+/// right-aligned with it so equal positions are equally old.
+pub fn draw_snapshot_overlay(cr: &Context, snapshot: &Snapshot, config: &GraphConfig, width: f64, height: f64, slots: usize) {
+    let slots = slots.max(snapshot.values.len()).max(2);
+    let colors = [(&snapshot.values, config.color.as_str()), (&snapshot.values2, config.color2.as_str())];
+    cr.set_line_width(1.0);
+    cr.set_dash(&[4.0, 3.0], 0.0);
+    for (values, color) in colors {
+        if values.is_empty() {
+            continue;
+        }
+        let (r, g, b) = if color.is_empty() { (1.0, 0.5, 0.5) } else { parse_color(color) };
+        cr.set_source_rgba(r, g, b, 0.6);
+        let offset = slots - values.len();
+        let step = width / (slots - 1) as f64;
+        trace(cr, values.iter().enumerate().map(|(i, value)| ((offset + i) as f64 * step, *value)), |value| {
+            height - (value / config.max_value).min(1.0) * height
+        });
+        cr.stroke().unwrap();
+    }
+    cr.set_dash(&[], 0.0);
+
+    cr.set_source_rgba(0.73, 0.76, 0.87, 0.8);
+    cr.set_font_size(9.0);
+    let extents = cr.text_extents(&snapshot.name).unwrap();
+    cr.move_to(width - extents.width() - 4.0, 11.0);
+    cr.show_text(&snapshot.name).unwrap();
+}
+
+/// Wi-Fi signal as a thin line over the network graph on its own dBm scale
+/// (see `data::signal_fraction`), broken where the link was down, with the
+/// latest reading in the top-right corner.
+pub fn draw_signal_overlay(cr: &Context, signal: &[f64], width: f64, height: f64, slots: usize) {
+    if signal.is_empty() {
+        return;
+    }
+    let slots = slots.max(signal.len()).max(2);
+    let offset = slots - signal.len();
+    let (r, g, b) = parse_color("#f9e2af");
+    cr.set_source_rgba(r, g, b, 0.9);
+    cr.set_line_width(1.0);
+    let mut drawing = false;
+    for (i, dbm) in signal.iter().enumerate() {
+        if !dbm.is_finite() {
+            drawing = false;
+            continue;
+        }
+        let x = ((offset + i) as f64 / (slots - 1) as f64) * width;
+        let y = height - data::signal_fraction(*dbm) * height;
+        if drawing {
+            cr.line_to(x, y);
+        } else {
+            cr.move_to(x, y);
+            drawing = true;
+        }
+    }
+    cr.stroke().unwrap();
+
+    let label = match signal.last().filter(|dbm| dbm.is_finite()) {
+        Some(dbm) => format!("{:.0} dBm", dbm),
+        None => "no link".to_string(),
+    };
+    cr.set_font_size(9.0);
+    let extents = cr.text_extents(&label).unwrap();
+    cr.move_to(width - extents.width() - 4.0, 11.0);
+    cr.show_text(&label).unwrap();
+}
+
+/// Red bands over the slots where alerts fired, a solid line where one was
+/// raised and a dashed one where it cleared.
+pub fn draw_alert_bands(cr: &Context, bands: &[AlertBand], slots: usize, width: f64, height: f64) {
+    let step = width / (slots.max(2) - 1) as f64;
+    let (r, g, b) = parse_color("#f38ba8");
+    cr.set_font_size(9.0);
+    cr.set_line_width(1.0);
+    for band in bands {
+        let (x0, x1) = (band.first as f64 * step, band.last as f64 * step);
+        cr.set_source_rgba(r, g, b, 0.12);
+        cr.rectangle(x0, 0.0, (x1 - x0).max(1.0), height);
+        cr.fill().unwrap();
+        cr.set_source_rgba(r, g, b, 0.8);
+        if band.raised {
+            cr.move_to(x0 + 0.5, 0.0);
+            cr.line_to(x0 + 0.5, height);
+            cr.stroke().unwrap();
+            cr.move_to(x0 + 3.0, height - 4.0);
+            cr.show_text(&band.label).unwrap();
+        }
+        if band.cleared {
+            cr.set_dash(&[3.0, 3.0], 0.0);
+            cr.move_to(x1 + 0.5, 0.0);
+            cr.line_to(x1 + 0.5, height);
+            cr.stroke().unwrap();
+            cr.set_dash(&[], 0.0);
+        }
+    }
+}
+
+/// Shades the brushed slots `first..=last` of a graph `slots` wide.
+pub fn draw_brush(cr: &Context, first: usize, last: usize, slots: usize, width: f64, height: f64) {
+    let step = width / (slots.max(2) - 1) as f64;
+    let (x0, x1) = (first as f64 * step, last as f64 * step);
+    cr.set_source_rgba(0.73, 0.76, 0.87, 0.15);
+    cr.rectangle(x0, 0.0, (x1 - x0).max(1.0), height);
+    cr.fill().unwrap();
+    cr.set_source_rgba(0.73, 0.76, 0.87, 0.5);
+    cr.set_line_width(1.0);
+    for x in [x0, x1] {
+        cr.move_to(x + 0.5, 0.0);
+        cr.line_to(x + 0.5, height);
+    }
+    cr.stroke().unwrap();
+}
+
+/// RSS of the tracked process over time with its fitted trend dashed on top.
+pub fn draw_leak_chart(cr: &Context, tracker: &LeakTracker, config: &GraphConfig, width: f64, height: f64) {
+    fill_background(cr, config, width, height);
+
+    let (Some(first), Some(last)) = (tracker.samples.first(), tracker.samples.last()) else {
+        return;
+    };
+    let span = ((last.0 - first.0) as f64).max(1.0);
+    // Headroom so a rising line doesn't sit on the top edge
+    let max = tracker.samples.iter().map(|s| s.1).fold(0.0, f64::max).max(1.0) * 1.2;
+    let point = |t: i64, kb: f64| ((t - first.0) as f64 / span * width, height - (kb / max).min(1.0) * height);
+
+    let (r, g, b) = parse_color(&config.color);
+    cr.set_source_rgba(r, g, b, 1.0);
+    cr.set_line_width(2.0);
+    for (i, (t, kb)) in tracker.samples.iter().enumerate() {
+        let (x, y) = point(*t, *kb);
+        if i == 0 {
+            cr.move_to(x, y);
+        } else {
+            cr.line_to(x, y);
+        }
+    }
+    cr.stroke().unwrap();
+
+    if let Some((slope, intercept)) = tracker.trend() {
+        let (x0, y0) = point(first.0, intercept);
+        let (x1, y1) = point(last.0, intercept + slope * span / 1000.0);
+        cr.set_source_rgba(0.95, 0.55, 0.66, 0.9);
+        cr.set_line_width(1.0);
+        cr.set_dash(&[4.0, 3.0], 0.0);
+        cr.move_to(x0, y0);
+        cr.line_to(x1, y1);
+        cr.stroke().unwrap();
+        cr.set_dash(&[], 0.0);
+    }
+}
+
+/// One row per mount: a usage bar, a sparkline of the last day scaled to its
+/// own range so slow growth is visible, and the projected time until full.
+pub fn draw_storage_chart(cr: &Context, rows: &[MountRow], config: &GraphConfig, width: f64, height: f64) {
+    fill_background(cr, config, width, height);
+
+    if rows.is_empty() {
+        return;
+    }
+    let row_height = (height / rows.len() as f64).min(48.0);
+    let bar_width = width * 0.6 - 8.0;
+    let spark_x = width * 0.6 + 4.0;
+    let spark_width = width - spark_x - 4.0;
+
+    for (i, row) in rows.iter().enumerate() {
+        let y = i as f64 * row_height;
+        let fraction = (row.used / row.total).clamp(0.0, 1.0);
+
+        cr.set_source_rgba(0.8, 0.8, 0.9, 1.0);
+        cr.move_to(4.0, y + 14.0);
+        cr.show_text(&format!(
+            "{}  {} / {}",
+            row.mount,
+            data::format_memory(row.used),
+            data::format_memory(row.total)
+        ))
+        .unwrap();
+        let projection = match row.full_in {
+            Some(secs) => format!("full in ~{}", data::format_duration(secs)),
+            None => "steady".to_string(),
+        };
+        cr.move_to(spark_x, y + 14.0);
+        cr.show_text(&projection).unwrap();
+
+        cr.set_source_rgba(0.0, 0.0, 0.0, 0.2);
+        cr.rectangle(4.0, y + 20.0, bar_width, row_height - 26.0);
+        cr.fill().unwrap();
+        let color = match fraction {
+            f if f >= 0.9 => "#f38ba8",
+            f if f >= 0.75 => "#f9e2af",
+            _ => "#a6e3a1",
+        };
+        let (r, g, b) = parse_color(color);
+        cr.set_source_rgba(r, g, b, 0.8);
+        cr.rectangle(4.0, y + 20.0, bar_width * fraction, row_height - 26.0);
+        cr.fill().unwrap();
+
+        if row.history.len() < 2 {
+            continue;
+        }
+        let min = row.history.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = row.history.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let range = (max - min).max(1.0);
+        cr.set_source_rgba(r, g, b, 1.0);
+        cr.set_line_width(1.0);
+        for (j, used) in row.history.iter().enumerate() {
+            let x = spark_x + j as f64 / (row.history.len() - 1) as f64 * spark_width;
+            let spark_y = y + row_height - 6.0 - (used - min) / range * (row_height - 26.0);
+            if j == 0 {
+                cr.move_to(x, spark_y);
+            } else {
+                cr.line_to(x, spark_y);
             }
+        }
+        cr.stroke().unwrap();
+    }
+}
+
+/// The summary header: one dial per gauge, side by side, each a 270° arc
+/// open at the bottom with the value inside and the label under it.
+pub fn draw_gauges(cr: &Context, gauges: &[Gauge], config: &GraphConfig, width: f64, height: f64) {
+    fill_background(cr, config, width, height);
+    if gauges.is_empty() {
+        return;
+    }
+    let cell = width / gauges.len() as f64;
+    let radius = (cell.min(height) / 2.0 - 6.0).max(4.0);
+    let (start, sweep) = (0.75 * PI, 1.5 * PI);
+    let (tr, tg, tb, _) = config.colors.text.to_unit();
+    let (gr, gg, gb, _) = config.colors.grid.to_unit();
+    cr.set_line_width(4.0);
+    cr.set_line_cap(cairo::LineCap::Round);
+    for (i, gauge) in gauges.iter().enumerate() {
+        let (x, y) = (cell * (i as f64 + 0.5), height / 2.0);
+        cr.set_source_rgba(gr, gg, gb, 0.6);
+        cr.arc(x, y, radius, start, start + sweep);
+        cr.stroke().unwrap();
+        if gauge.fraction > 0.0 {
+            let (r, g, b) = parse_color(&gauge.color);
+            cr.set_source_rgba(r, g, b, 1.0);
+            cr.arc(x, y, radius, start, start + sweep * gauge.fraction);
             cr.stroke().unwrap();
         }
+
+        cr.set_source_rgba(tr, tg, tb, 0.9);
+        cr.set_font_size(10.0);
+        let extents = cr.text_extents(&gauge.text).unwrap();
+        cr.move_to(x - extents.width() / 2.0, y + extents.height() / 2.0);
+        cr.show_text(&gauge.text).unwrap();
+        cr.set_font_size(8.0);
+        let extents = cr.text_extents(gauge.label).unwrap();
+        cr.move_to(x - extents.width() / 2.0, y + radius + 2.0);
+        cr.show_text(gauge.label).unwrap();
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,32 @@
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Turns on the event stream: one JSON object per line on stdout, so the
+/// AGS process that spawned the window can tell when it's closed or changed.
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Writes `{"event": <event>, "timestamp": <ms>, ...fields}` when enabled.
+/// A parent that stopped reading is not an error worth reporting.
+pub fn emit(event: &str, fields: serde_json::Value) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis() as i64);
+    let mut line = serde_json::json!({ "event": event, "timestamp": timestamp });
+    if let (Some(line), serde_json::Value::Object(fields)) = (line.as_object_mut(), fields) {
+        line.extend(fields);
+    }
+    let mut stdout = std::io::stdout().lock();
+    let _ = writeln!(stdout, "{}", line).and_then(|_| stdout.flush());
+}
+
+/// Reports why the window is closing, then exits.
+pub fn close(reason: &str) -> ! {
+    emit("closed", serde_json::json!({ "reason": reason }));
+    std::process::exit(0);
+}
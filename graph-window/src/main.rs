@@ -1,25 +1,65 @@
+mod actions;
+// Shared with the service until the collectors move into their own crate
+#[allow(dead_code)]
+#[path = "../../stats-service/src/collectors.rs"]
+mod collectors;
 mod config;
 mod data;
 mod drawing;
+mod events;
+mod prefs;
+mod snapshots;
+mod standalone;
 mod ui;
+mod watchdog;
 
 use gtk4::prelude::*;
 use gtk4::Application;
 use config::GraphConfig;
 
+// The shared collectors resolve paths against the service's configurable
+// proc root; the window always reads the real one.
+fn proc_root() -> &'static std::path::Path {
+    std::path::Path::new("/proc")
+}
+
 fn main() {
     eprintln!("Starting graph window...");
     let args: Vec<String> = std::env::args().collect();
     eprintln!("Args: {:?}", args);
 
-    let config = if args.len() > 1 {
-        serde_json::from_str(&args[1]).unwrap_or_else(|e| {
+    if args.iter().any(|a| a == "--help" || a == "-h") {
+        println!("{}", config::USAGE);
+        return;
+    }
+
+    // JSON overrides from the widgets, or a preset like `cpu --multi`, on
+    // top of config.toml
+    let overrides = match args.iter().skip(1).find(|a| !a.starts_with("--")) {
+        Some(json) if json.trim_start().starts_with('{') => serde_json::from_str(json).unwrap_or_else(|e| {
             eprintln!("Failed to parse JSON config: {}", e);
-            GraphConfig::default()
-        })
-    } else {
-        GraphConfig::default()
+            serde_json::json!({})
+        }),
+        Some(_) => GraphConfig::from_args(&args[1..]).unwrap_or_else(|e| {
+            eprintln!("{}\n\n{}", e, config::USAGE);
+            std::process::exit(2);
+        }),
+        None => serde_json::json!({}),
     };
+    let mut config = GraphConfig::load(overrides).unwrap_or_else(|e| {
+        eprintln!("Invalid config: {}", e);
+        GraphConfig::default()
+    });
+    if args.iter().any(|a| a == "--standalone") {
+        config.standalone = true;
+    }
+    if let Some(pid) = args.iter().position(|a| a == "--parent-pid").and_then(|i| args.get(i + 1)?.parse().ok()) {
+        config.parent_pid = Some(pid);
+    }
+    if config.events || args.iter().any(|a| a == "--events") {
+        events::enable();
+    }
+    config.apply_metadata(&data::load_metadata());
 
     let app_id = format!("com.example.graphwindow.{}", std::process::id());
     let app = Application::builder()
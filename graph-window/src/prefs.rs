@@ -0,0 +1,46 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use crate::config::TemperatureUnit;
+
+/// Choices made in the window that should survive reopening it, kept under
+/// `$XDG_STATE_HOME/ags-stats/graph-window.json`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Prefs {
+    /// Interface shown by the network view; None for the total of all.
+    pub network_interface: Option<String>,
+    /// Last pick of the °C/°F toggle; None until it's been used.
+    pub temperature_unit: Option<TemperatureUnit>,
+}
+
+fn state_path() -> PathBuf {
+    let state_home = env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .unwrap_or_else(|| PathBuf::from(env::var("HOME").unwrap_or_default()).join(".local/state"));
+    state_home.join("ags-stats").join("graph-window.json")
+}
+
+impl Prefs {
+    pub fn load() -> Self {
+        fs::read_to_string(state_path())
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        let path = state_path();
+        let tmp = path.with_extension("json.tmp");
+        let result = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&tmp, serde_json::to_string_pretty(self).unwrap_or_default()))
+            .and_then(|_| fs::rename(&tmp, &path));
+        if let Err(e) = result {
+            eprintln!("Failed to save {}: {}", path.display(), e);
+        }
+    }
+}
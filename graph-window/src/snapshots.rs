@@ -0,0 +1,92 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+
+/// A pinned range of a view, kept under
+/// `$XDG_DATA_HOME/ags-stats/snapshots/<data_source>/<name>.json` so it can
+/// be recalled later and drawn over the live graph for comparison.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub name: String,
+    pub data_source: String,
+    // ms
+    pub pinned_at: i64,
+    // Sample time (ms) of each value, oldest first; 0 for padding
+    pub timestamps: Vec<i64>,
+    pub values: Vec<f64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub values2: Vec<f64>,
+}
+
+fn data_home() -> PathBuf {
+    env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .unwrap_or_else(|| PathBuf::from(env::var("HOME").unwrap_or_default()).join(".local/share"))
+}
+
+fn dir(data_source: &str) -> PathBuf {
+    data_home().join("ags-stats").join("snapshots").join(file_stem(data_source))
+}
+
+// Names are free text; anything that isn't safe in a file name becomes '_'
+fn file_stem(name: &str) -> String {
+    let stem: String = name
+        .trim()
+        .chars()
+        .map(|c| if c.is_alphanumeric() || matches!(c, '-' | '_' | ' ') { c } else { '_' })
+        .collect();
+    if stem.is_empty() { "_".to_string() } else { stem }
+}
+
+impl Snapshot {
+    /// The visible range: `values` as plotted, with the matching tail of the
+    /// history's timestamps.
+    pub fn pin(name: &str, data_source: &str, history: &serde_json::Value, values: &[f64], values2: &[f64]) -> Self {
+        let timestamps: Vec<i64> = history["timestamps"]
+            .as_array()
+            .map(|t| t.iter().map(|v| v.as_i64().unwrap_or(0)).collect())
+            .unwrap_or_default();
+        let skip = timestamps.len().saturating_sub(values.len());
+        let pinned_at = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis() as i64);
+        Snapshot {
+            name: name.trim().to_string(),
+            data_source: data_source.to_string(),
+            pinned_at,
+            timestamps: timestamps[skip..].to_vec(),
+            values: values.to_vec(),
+            values2: values2.to_vec(),
+        }
+    }
+
+    /// Saves the snapshot, replacing any earlier one with the same name.
+    pub fn save(&self) -> io::Result<()> {
+        let dir = dir(&self.data_source);
+        fs::create_dir_all(&dir)?;
+        let path = dir.join(format!("{}.json", file_stem(&self.name)));
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string(self).map_err(io::Error::from)?)?;
+        fs::rename(&tmp, &path)
+    }
+
+    pub fn load(data_source: &str, name: &str) -> Option<Snapshot> {
+        let path = dir(data_source).join(format!("{}.json", file_stem(name)));
+        serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+    }
+
+    /// Names of the snapshots pinned for a view, oldest first.
+    pub fn list(data_source: &str) -> Vec<String> {
+        let mut snapshots: Vec<Snapshot> = fs::read_dir(dir(data_source))
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter(|e| e.path().extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|e| serde_json::from_str(&fs::read_to_string(e.path()).ok()?).ok())
+            .collect();
+        snapshots.sort_by_key(|s: &Snapshot| s.pinned_at);
+        snapshots.into_iter().map(|s| s.name).collect()
+    }
+}
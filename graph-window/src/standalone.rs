@@ -0,0 +1,40 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::collectors::{CpuCollector, DiskCollector, MemoryCollector, NetworkCollector};
+
+/// Collects CPU, memory, network and disk I/O in-process for `--standalone`, so the
+/// window still works on machines without the stats service.
+#[derive(Default)]
+pub struct LocalCollector {
+    cpu: CpuCollector,
+    memory: MemoryCollector,
+    network: NetworkCollector,
+    disk: DiskCollector,
+}
+
+impl LocalCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// One sample in the shape of the service's latest.json, ready for
+    /// `data::append_latest`. Rates are zero on the first call.
+    pub fn sample(&mut self) -> serde_json::Value {
+        let cpu = self.cpu.collect();
+        let (download, upload) = self.network.collect();
+        let (disk_read, disk_write) = self.disk.collect();
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis() as i64);
+        serde_json::json!({
+            "timestamp": timestamp,
+            "cpu_usage": cpu.overall_usage,
+            "cpu_cores": cpu.core_usage,
+            "cpu_iowait": cpu.iowait_percentage,
+            "memory": self.memory.collect(),
+            "network_download": download,
+            "network_upload": upload,
+            "network_interfaces": self.network.interfaces(),
+            "disk_read": disk_read,
+            "disk_write": disk_write,
+            "disks": self.disk.disks(),
+        })
+    }
+}
@@ -1,13 +1,34 @@
 use gtk4::prelude::*;
 use gtk4::{Application, ApplicationWindow, DrawingArea, Label, Box, Orientation};
 use gtk4_layer_shell::{LayerShell, Layer, Edge};
-use gtk4::gdk::{Key};
-use glib::{timeout_add_local, ControlFlow};
-use std::sync::{Arc, Mutex};
-use std::time::Duration;
-use crate::config::GraphConfig;
-use crate::data::{self, GraphData, AdvancedMemoryData};
+use gtk4::gdk::{InputSource, Key, Rectangle};
+use gtk4::gio;
+use glib::{timeout_add_local, timeout_add_local_once, ControlFlow};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::actions::{self, Action};
+use crate::config::{GraphConfig, TemperatureUnit};
+use crate::data::{self, GraphData, AdvancedMemoryData, LeakTracker, StreamEvent};
 use crate::drawing;
+use crate::events;
+use crate::prefs::Prefs;
+use crate::snapshots::Snapshot;
+use crate::standalone::LocalCollector;
+use crate::watchdog::Watchdog;
+
+// px/s; slower horizontal movement is a drag, not a swipe
+const SWIPE_VELOCITY: f64 = 300.0;
+
+// Graph size in miniature mode, and its distance from the screen corner
+const MINI_WIDTH: i32 = 160;
+const MINI_HEIGHT: i32 = 40;
+const MINI_MARGIN: i32 = 12;
+
+// Height of the gauge header row
+const GAUGE_HEIGHT: i32 = 56;
 
 pub fn build_ui(app: &Application, config: GraphConfig) {
     let window = ApplicationWindow::builder()
@@ -20,13 +41,14 @@ pub fn build_ui(app: &Application, config: GraphConfig) {
     window.set_decorated(false);
     window.set_resizable(false);
     window.init_layer_shell();
+    window.set_namespace(&config.namespace);
     window.set_layer(Layer::Overlay);
     window.set_keyboard_mode(gtk4_layer_shell::KeyboardMode::OnDemand);
 
     let key_controller = gtk4::EventControllerKey::new();
     key_controller.connect_key_pressed(move |_, key, _, _| {
         if key == Key::Escape {
-            std::process::exit(0);
+            events::close("escape");
         }
         glib::Propagation::Proceed
     });
@@ -35,7 +57,7 @@ pub fn build_ui(app: &Application, config: GraphConfig) {
     let click_controller = gtk4::GestureClick::new();
     click_controller.set_button(3);
     click_controller.connect_pressed(move |_, _, _, _| {
-        std::process::exit(0);
+        events::close("right_click");
     });
     window.add_controller(click_controller);
 
@@ -77,149 +99,1017 @@ pub fn build_ui(app: &Application, config: GraphConfig) {
     title_label.set_hexpand(true);
     title_label.set_halign(gtk4::Align::Start);
     
+    // Connection state, e.g. "reconnected, 12 s gap" after a service restart
+    let status_label = Label::new(None);
+    status_label.set_css_classes(&["status-label"]);
+    
     let close_button = gtk4::Button::new();
     close_button.set_label("✕");
     close_button.set_css_classes(&["close-button"]);
     close_button.connect_clicked(|_| {
-        std::process::exit(0);
+        events::close("close_button");
     });
     
     title_box.append(&title_label);
-    title_box.append(&close_button);
+    title_box.append(&status_label);
     
     let drawing_area = DrawingArea::builder()
         .width_request(config.width)
         .height_request(config.height)
         .build();
+    // The summary header between the title and the chart
+    let gauge_area = DrawingArea::builder()
+        .width_request(config.width)
+        .height_request(GAUGE_HEIGHT)
+        .build();
     
     let stats_label = Label::new(Some("Initializing..."));
     stats_label.set_css_classes(&["stats-label"]);
     
-    let history = data::load_history();
+    let history = Arc::new(Mutex::new(data::load_history()));
+
+    // Interface picker for the network view; "All" is the summed totals
+    let prefs = Rc::new(RefCell::new(Prefs::load()));
+    let interface_names = Rc::new(RefCell::new(data::interface_names(&history.lock().unwrap())));
+    let interface_list = gtk4::StringList::new(&["All"]);
+    for name in interface_names.borrow().iter() {
+        interface_list.append(name);
+    }
+    let interface_dropdown = gtk4::DropDown::new(Some(interface_list.clone()), gtk4::Expression::NONE);
+    interface_dropdown.set_css_classes(&["interface-dropdown"]);
+    // A saved interface that's gone for now (an unplugged dock, a VPN) shows
+    // the totals without being forgotten on disk
+    let saved = prefs.borrow().network_interface.clone();
+    let selected_interface = Rc::new(RefCell::new(None));
+    if let Some(i) = saved.and_then(|saved| interface_names.borrow().iter().position(|name| *name == saved)) {
+        interface_dropdown.set_selected(i as u32 + 1);
+        *selected_interface.borrow_mut() = interface_names.borrow().get(i).cloned();
+    }
+    if config.data_source == "network" {
+        title_box.append(&interface_dropdown);
+    }
+
+    // Thermal labels and scales follow this; the service always reports °C
+    let temperature_unit = Rc::new(Cell::new(
+        config.temperature_unit.or(prefs.borrow().temperature_unit).unwrap_or_else(TemperatureUnit::from_locale),
+    ));
+    let unit_button = gtk4::Button::with_label(temperature_unit.get().symbol());
+    unit_button.set_css_classes(&["unit-toggle"]);
+    if config.data_source == "temperature" {
+        title_box.append(&unit_button);
+    }
+
+    // Pins the visible range under a name; picking a pin from the dropdown
+    // draws it dashed over the live graph
+    let snapshot_names = Rc::new(RefCell::new(Snapshot::list(&config.data_source)));
+    let snapshot_list = gtk4::StringList::new(&["Compare…"]);
+    for name in snapshot_names.borrow().iter() {
+        snapshot_list.append(name);
+    }
+    let snapshot_dropdown = gtk4::DropDown::new(Some(snapshot_list.clone()), gtk4::Expression::NONE);
+    snapshot_dropdown.set_css_classes(&["snapshot-dropdown"]);
+    let pin_entry = gtk4::Entry::new();
+    pin_entry.set_placeholder_text(Some("Snapshot name"));
+    let pin_popover = gtk4::Popover::new();
+    pin_popover.set_child(Some(&pin_entry));
+    let pin_button = gtk4::MenuButton::new();
+    pin_button.set_label("📌");
+    pin_button.set_popover(Some(&pin_popover));
+    pin_button.set_css_classes(&["pin-button"]);
+    // Registers a line on the main series with the service, which notifies
+    // when it's crossed even after this window has closed
+    let (watch_metric, _) = config.series_keys();
+    let watch_entry = gtk4::Entry::new();
+    watch_entry.set_placeholder_text(Some("Notify above"));
+    watch_entry.set_input_purpose(gtk4::InputPurpose::Number);
+    let watch_toggle = gtk4::Button::with_label("Watch");
+    let watch_status = Label::new(None);
+    let watch_box = Box::new(Orientation::Vertical, 4);
+    watch_box.append(&watch_entry);
+    watch_box.append(&watch_toggle);
+    watch_box.append(&watch_status);
+    let watch_popover = gtk4::Popover::new();
+    watch_popover.set_child(Some(&watch_box));
+    let watch_button = gtk4::MenuButton::new();
+    watch_button.set_label("🔔");
+    watch_button.set_popover(Some(&watch_popover));
+    watch_button.set_css_classes(&["watch-button"]);
+    let watched = Rc::new(Cell::new(None));
+    if config.single_graph() {
+        title_box.append(&snapshot_dropdown);
+        title_box.append(&pin_button);
+        title_box.append(&watch_button);
+        watched.set(data::watched_above(&data::load_watches(), watch_metric));
+    }
+    let temperature = config.data_source == "temperature";
+    if let Some(above) = watched.get() {
+        let shown = if temperature { temperature_unit.get().convert(above) } else { above };
+        watch_entry.set_text(&format!("{}", shown));
+        watch_toggle.set_label("Stop watching");
+        watch_button.add_css_class("watching");
+    }
+    {
+        let view = config.view_name().to_string();
+        let temperature_unit = temperature_unit.clone();
+        let watch_button = watch_button.clone();
+        watch_toggle.connect_clicked(move |button| {
+            let result = match watched.get() {
+                Some(_) => data::unwatch(watch_metric).map(|()| None),
+                None => match watch_entry.text().trim().parse::<f64>() {
+                    Ok(value) => {
+                        let above = if temperature { temperature_unit.get().to_celsius(value) } else { value };
+                        data::watch(watch_metric, above, &view).map(|()| Some(above))
+                    }
+                    Err(_) => Err("Enter a number".to_string()),
+                },
+            };
+            match result {
+                Ok(above) => {
+                    watched.set(above);
+                    button.set_label(if above.is_some() { "Stop watching" } else { "Watch" });
+                    if above.is_some() {
+                        watch_button.add_css_class("watching");
+                    } else {
+                        watch_button.remove_css_class("watching");
+                    }
+                    watch_status.set_text("");
+                    events::emit("watch_changed", serde_json::json!({ "metric": watch_metric, "above": above }));
+                }
+                Err(error) => watch_status.set_text(&error),
+            }
+        });
+    }
+    let overlay: Rc<RefCell<Option<Snapshot>>> = Rc::new(RefCell::new(None));
+    let mini_button = gtk4::Button::with_label("▁");
+    mini_button.set_css_classes(&["mini-button"]);
+    mini_button.set_tooltip_text(Some("Miniature (m)"));
+    title_box.append(&mini_button);
+    title_box.append(&close_button);
     
     let advanced_mem_data = Arc::new(Mutex::new(AdvancedMemoryData::new(60)));
     let graph_data = Arc::new(Mutex::new(GraphData::new_with_zeros(60)));
     let graph_data2 = Arc::new(Mutex::new(GraphData::new_with_zeros(60)));
+    // dBm of the selected (or first) Wi-Fi interface, over the network graph
+    let signal_data: Rc<RefCell<Vec<f64>>> = Rc::new(RefCell::new(Vec::new()));
     let cpu_core_data = Arc::new(Mutex::new(vec![]));
     let iowait_data = Arc::new(Mutex::new(GraphData::new_with_zeros(60)));
+    // Ten minutes of samples is enough to tell a leak from a burst
+    let leak_data = Arc::new(Mutex::new(LeakTracker::new(config.pid, 600)));
+    let storage_rows = Arc::new(Mutex::new(Vec::new()));
 
     if config.data_source == "memory" && config.advanced {
         let mut mem_data = advanced_mem_data.lock().unwrap();
-        if let Some(total) = history["memory"]["total"].as_f64() {
-            mem_data.total = total;
-        }
-        if let Some(apps) = history["memory_apps"].as_array() {
-            mem_data.apps.values = apps.iter().filter_map(|v| v.as_f64()).collect();
-        }
-        if let Some(cached) = history["memory_cached"].as_array() {
-            mem_data.cached.values = cached.iter().filter_map(|v| v.as_f64()).collect();
-        }
-        if let Some(buffers) = history["memory_buffers"].as_array() {
-            mem_data.buffers.values = buffers.iter().filter_map(|v| v.as_f64()).collect();
-        }
-        if let Some(slab) = history["memory_slab"].as_array() {
-            mem_data.slab.values = slab.iter().filter_map(|v| v.as_f64()).collect();
-        }
-        if let Some(shmem) = history["memory_shmem"].as_array() {
-            mem_data.shmem.values = shmem.iter().filter_map(|v| v.as_f64()).collect();
+        data::update_memory(&mut mem_data, &history.lock().unwrap());
+    } else if config.data_source == "energy" {
+        graph_data.lock().unwrap().set_from_history(&history.lock().unwrap(), "power");
+    } else if config.data_source == "processes" {
+        graph_data.lock().unwrap().set_from_history(&history.lock().unwrap(), "cpu");
+    } else if config.data_source == "slices" {
+        graph_data.lock().unwrap().set_from_history(&history.lock().unwrap(), "cpu");
+        if let Some(latest) = data::load_latest_from(data::LATEST_FILE) {
+            stats_label.set_text(&data::slices_summary(&latest, 5));
         }
+    } else if config.data_source == "temperature" {
+        let unit = temperature_unit.get();
+        let celsius = data::series(&history.lock().unwrap(), "temperature");
+        graph_data.lock().unwrap().set_values(celsius.into_iter().map(|c| unit.convert(c)).collect());
+    } else if config.data_source == "storage" {
+        let rows = data::mount_rows(&data::load_filesystems());
+        stats_label.set_text(&data::storage_summary(&rows));
+        *storage_rows.lock().unwrap() = rows;
+    } else if config.data_source == "network" {
+        let interface = selected_interface.borrow().clone();
+        let (download, upload) = data::network_series(&history.lock().unwrap(), interface.as_deref());
+        graph_data.lock().unwrap().set_values(download);
+        graph_data2.lock().unwrap().set_values(upload);
+        *signal_data.borrow_mut() = data::network_signal(&history.lock().unwrap(), interface.as_deref());
+    } else if config.data_source == "cpu" && config.multi_chart {
+        let mut cores = cpu_core_data.lock().unwrap();
+        data::update_cpu_cores(&mut cores, &mut iowait_data.lock().unwrap(), &history.lock().unwrap());
     } else {
-        // Load data for other charts
+        let (primary, secondary) = config.series_keys();
+        graph_data.lock().unwrap().set_from_history(&history.lock().unwrap(), primary);
+        if let Some(key) = secondary {
+            graph_data2.lock().unwrap().set_from_history(&history.lock().unwrap(), key);
+        }
     }
 
+    // Click-drag on a single graph selects an interval, kept as sample times
+    // so it scrolls with the data; the footer shows its statistics instead
+    // of the current value until a click clears it
+    let brush: Rc<Cell<Option<(i64, i64)>>> = Rc::new(Cell::new(None));
+    let brush_label = {
+        let brush = brush.clone();
+        let graph_data = graph_data.clone();
+        let stats_label = stats_label.clone();
+        let temperature_unit = temperature_unit.clone();
+        let config = config.clone();
+        Rc::new(move |history: &serde_json::Value| {
+            let Some((a, b)) = brush.get() else {
+                return;
+            };
+            let data = graph_data.lock().unwrap();
+            let timestamps = data::plotted_timestamps(history, data.values.len());
+            let unit = match config.data_source.as_str() {
+                "temperature" => temperature_unit.get().symbol().to_string(),
+                _ => config.unit.clone(),
+            };
+            match data::range_stats(&data.values, &timestamps, a, b) {
+                Some(stats) => stats_label.set_text(&data::range_summary(&stats, &unit)),
+                // Scrolled out of the window
+                None => brush.set(None),
+            }
+        })
+    };
+
     let config_draw = config.clone();
     let advanced_mem_data_draw = advanced_mem_data.clone();
     let graph_data_draw = graph_data.clone();
     let graph_data2_draw = graph_data2.clone();
+    let signal_draw = signal_data.clone();
     let cpu_cores_draw = cpu_core_data.clone();
     let iowait_draw = iowait_data.clone();
+    let leak_draw = leak_data.clone();
+    let storage_draw = storage_rows.clone();
+    let temperature_unit_draw = temperature_unit.clone();
+    let overlay_draw = overlay.clone();
+    let brush_draw = brush.clone();
+    let history_draw = history.clone();
+
+    if config.gauges {
+        // Colors and the network unit; fetched once, like the config's own
+        let metadata = data::load_metadata();
+        let config = config.clone();
+        let history = history.clone();
+        let temperature_unit = temperature_unit.clone();
+        gauge_area.set_draw_func(move |_, cr, width, height| {
+            let unit = temperature_unit.get();
+            let gauges = data::gauges(&history.lock().unwrap(), &metadata, |celsius| (unit.convert(celsius), unit.symbol()));
+            drawing::draw_gauges(cr, &gauges, &config, width as f64, height as f64);
+        });
+    }
 
     drawing_area.set_draw_func(move |_, cr, width, height| {
         if config_draw.data_source == "memory" && config_draw.advanced {
             let mem_data = advanced_mem_data_draw.lock().unwrap();
-            drawing::draw_advanced_memory_chart(cr, &mem_data, width as f64, height as f64);
+            drawing::draw_advanced_memory_chart(cr, &mem_data, &config_draw, width as f64, height as f64);
         } else if config_draw.data_source == "cpu" && config_draw.multi_chart {
             let cores = cpu_cores_draw.lock().unwrap();
             let iowait = iowait_draw.lock().unwrap();
-            drawing::draw_multi_cpu_charts(cr, &cores, &iowait, &config_draw, width as f64, height as f64);
+            if config_draw.heatmap(cores.len()) {
+                drawing::draw_cpu_heatmap(cr, &cores, &iowait, &config_draw, width as f64, height as f64);
+            } else {
+                let frequencies = data::core_frequencies(&history_draw.lock().unwrap());
+                drawing::draw_multi_cpu_charts(cr, &cores, &frequencies, &iowait, &config_draw, width as f64, height as f64);
+            }
+        } else if config_draw.data_source == "temperature" {
+            let data = graph_data_draw.lock().unwrap();
+            let config = config_draw.in_temperature_unit(temperature_unit_draw.get());
+            drawing::draw_graph(cr, &data, None, &config, width as f64, height as f64);
+        } else if config_draw.data_source == "storage" {
+            let rows = storage_draw.lock().unwrap();
+            drawing::draw_storage_chart(cr, &rows, &config_draw, width as f64, height as f64);
+        } else if config_draw.data_source == "leak" {
+            let tracker = leak_draw.lock().unwrap();
+            drawing::draw_leak_chart(cr, &tracker, &config_draw, width as f64, height as f64);
         } else {
             let data = graph_data_draw.lock().unwrap();
             let data2 = graph_data2_draw.lock().unwrap();
             drawing::draw_graph(cr, &data, Some(&data2), &config_draw, width as f64, height as f64);
+            if config_draw.data_source == "network" {
+                drawing::draw_signal_overlay(cr, &signal_draw.borrow(), width as f64, height as f64, data.values.len());
+            }
+        }
+        if let Some(snapshot) = overlay_draw.borrow().as_ref() {
+            let slots = graph_data_draw.lock().unwrap().values.len();
+            let config = match config_draw.data_source.as_str() {
+                "temperature" => config_draw.in_temperature_unit(temperature_unit_draw.get()),
+                _ => config_draw.clone(),
+            };
+            drawing::draw_snapshot_overlay(cr, snapshot, &config, width as f64, height as f64, slots);
+        }
+        if config_draw.single_graph() {
+            let slots = graph_data_draw.lock().unwrap().values.len();
+            let history = history_draw.lock().unwrap();
+            let bands = data::alert_bands(&history, &data::plotted_timestamps(&history, slots));
+            drawing::draw_alert_bands(cr, &bands, slots, width as f64, height as f64);
+        }
+        if let Some((a, b)) = brush_draw.get() {
+            let slots = graph_data_draw.lock().unwrap().values.len();
+            let timestamps = data::plotted_timestamps(&history_draw.lock().unwrap(), slots);
+            if let Some((first, last)) = data::brush_span(&timestamps, a, b) {
+                drawing::draw_brush(cr, first, last, slots, width as f64, height as f64);
+            }
         }
     });
 
+    // The top processes by CPU, with actions on the selected one. Rows are
+    // rebuilt on every refresh; the selection follows the pid.
+    let process_list = gtk4::ListBox::new();
+    process_list.set_css_classes(&["process-list"]);
+    process_list.set_selection_mode(gtk4::SelectionMode::Single);
+    let process_rows: Rc<RefCell<Vec<(u32, String)>>> = Rc::new(RefCell::new(Vec::new()));
+    let process_actions = Box::new(Orientation::Horizontal, 4);
+    process_actions.set_css_classes(&["process-actions"]);
+    let process_panel = Box::new(Orientation::Vertical, 4);
+    process_panel.append(&process_list);
+    process_panel.append(&process_actions);
+    let fill_processes = {
+        let process_list = process_list.clone();
+        let process_rows = process_rows.clone();
+        Rc::new(move |history: &serde_json::Value| {
+            let selected = process_list
+                .selected_row()
+                .and_then(|row| process_rows.borrow().get(row.index() as usize).map(|(pid, _)| *pid));
+            process_list.remove_all();
+            let mut rows = Vec::new();
+            for process in history["top_processes"]["by_cpu"].as_array().into_iter().flatten() {
+                let Some(pid) = process["pid"].as_u64().map(|pid| pid as u32) else {
+                    continue;
+                };
+                let name = process["name"].as_str().unwrap_or("?").to_string();
+                let label = Label::new(Some(&format!(
+                    "{} ({}) {:.0}% · {}",
+                    name,
+                    pid,
+                    process["cpu"].as_f64().unwrap_or(0.0),
+                    data::format_memory(process["rss"].as_f64().unwrap_or(0.0))
+                )));
+                label.set_halign(gtk4::Align::Start);
+                process_list.append(&label);
+                if selected == Some(pid) {
+                    process_list.select_row(process_list.row_at_index(rows.len() as i32).as_ref());
+                }
+                rows.push((pid, name));
+            }
+            *process_rows.borrow_mut() = rows;
+        })
+    };
+    if config.data_source == "processes" {
+        fill_processes(&history.lock().unwrap());
+    }
+
     let config_update = config.clone();
+    let fill_processes_update = fill_processes.clone();
     let advanced_mem_data_update = advanced_mem_data.clone();
+    let graph_data_update = graph_data.clone();
+    let graph_data2_update = graph_data2.clone();
+    let signal_update = signal_data.clone();
+    let selected_interface_update = selected_interface.clone();
+    let interface_names_update = interface_names.clone();
     let stats_label_update = stats_label.clone();
     let drawing_area_update = drawing_area.clone();
+    let gauge_area_update = gauge_area.clone();
+    let storage_rows_update = storage_rows.clone();
+    let cpu_cores_update = cpu_core_data.clone();
+    let iowait_update = iowait_data.clone();
+    let temperature_unit_update = temperature_unit.clone();
+    let brush_label_update = brush_label.clone();
+    // The service only samples filesystems once a minute
+    let storage_loaded = Cell::new(Instant::now());
 
-    timeout_add_local(Duration::from_millis(1000), move || {
-        let history = data::load_history();
+    let refresh = Rc::new(move |history: &serde_json::Value| {
         if config_update.data_source == "memory" && config_update.advanced {
             let mut mem_data = advanced_mem_data_update.lock().unwrap();
-            if let Some(total) = history["memory"]["total"].as_f64() {
-                mem_data.total = total;
-            }
-            if let Some(apps) = history["memory_apps"].as_array() {
-                mem_data.apps.values = apps.iter().filter_map(|v| v.as_f64()).collect();
-            }
-            if let Some(cached) = history["memory_cached"].as_array() {
-                mem_data.cached.values = cached.iter().filter_map(|v| v.as_f64()).collect();
+            data::update_memory(&mut mem_data, history);
+            let apps = data::newest(&mem_data.apps.values).unwrap_or(0.0) / 1024.0;
+            let cached = data::newest(&mem_data.cached.values).unwrap_or(0.0) / 1024.0;
+            let buffers = data::newest(&mem_data.buffers.values).unwrap_or(0.0) / 1024.0;
+            let slab = data::newest(&mem_data.slab.values).unwrap_or(0.0) / 1024.0;
+            let shmem = data::newest(&mem_data.shmem.values).unwrap_or(0.0) / 1024.0;
+            stats_label_update.set_text(&format!(
+                "Apps: {:.1}MB | Cached: {:.1}MB | Buffers: {:.1}MB | Slab: {:.1}MB | Shmem: {:.1}MB",
+                apps, cached, buffers, slab, shmem
+            ));
+        } else if config_update.data_source == "energy" {
+            graph_data_update.lock().unwrap().set_from_history(history, "power");
+        } else if config_update.data_source == "processes" {
+            graph_data_update.lock().unwrap().set_from_history(history, "cpu");
+            fill_processes_update(history);
+        } else if config_update.data_source == "slices" {
+            graph_data_update.lock().unwrap().set_from_history(history, "cpu");
+            if let Some(latest) = data::load_latest_from(data::LATEST_FILE) {
+                stats_label_update.set_text(&data::slices_summary(&latest, 5));
             }
-            if let Some(buffers) = history["memory_buffers"].as_array() {
-                mem_data.buffers.values = buffers.iter().filter_map(|v| v.as_f64()).collect();
+        } else if config_update.data_source == "leak" {
+            // Fed by the process replies polled below
+        } else if config_update.data_source == "temperature" {
+            let unit = temperature_unit_update.get();
+            let values: Vec<f64> = data::series(history, "temperature").into_iter().map(|c| unit.convert(c)).collect();
+            let peak = values.iter().copied().fold(f64::NAN, f64::max);
+            stats_label_update.set_text(&match data::newest(&values) {
+                Some(current) => format!("CPU: {:.1}{} | Peak: {:.1}{}", current, unit.symbol(), peak, unit.symbol()),
+                None => "No temperature sensors".to_string(),
+            });
+            graph_data_update.lock().unwrap().set_values(values);
+        } else if config_update.data_source == "storage" {
+            if storage_loaded.get().elapsed() < Duration::from_secs(60) {
+                return;
             }
-            if let Some(slab) = history["memory_slab"].as_array() {
-                mem_data.slab.values = slab.iter().filter_map(|v| v.as_f64()).collect();
+            storage_loaded.set(Instant::now());
+            let rows = data::mount_rows(&data::load_filesystems());
+            stats_label_update.set_text(&data::storage_summary(&rows));
+            *storage_rows_update.lock().unwrap() = rows;
+        } else if config_update.data_source == "network" {
+            // New interfaces go to the end so the selected index stays put
+            let mut names = interface_names_update.borrow_mut();
+            for name in data::interface_names(history) {
+                if !names.contains(&name) {
+                    interface_list.append(&name);
+                    names.push(name);
+                }
             }
-            if let Some(shmem) = history["memory_shmem"].as_array() {
-                mem_data.shmem.values = shmem.iter().filter_map(|v| v.as_f64()).collect();
+            let interface = selected_interface_update.borrow().clone();
+            let (download, upload) = data::network_series(history, interface.as_deref());
+            let signal = data::network_signal(history, interface.as_deref());
+            let mut text = format!(
+                "{}: ↓ {:.1} {unit} | ↑ {:.1} {unit}",
+                interface.as_deref().unwrap_or("All"),
+                data::newest(&download).unwrap_or(0.0),
+                data::newest(&upload).unwrap_or(0.0),
+                unit = config_update.unit
+            );
+            if let Some(dbm) = signal.last().filter(|dbm| dbm.is_finite()) {
+                text.push_str(&format!(" | {:.0} dBm", dbm));
             }
-            let apps = mem_data.apps.values.last().unwrap_or(&0.0) / 1024.0;
-            let cached = mem_data.cached.values.last().unwrap_or(&0.0) / 1024.0;
-            let buffers = mem_data.buffers.values.last().unwrap_or(&0.0) / 1024.0;
-            let slab = mem_data.slab.values.last().unwrap_or(&0.0) / 1024.0;
-            let shmem = mem_data.shmem.values.last().unwrap_or(&0.0) / 1024.0;
+            stats_label_update.set_text(&text);
+            graph_data_update.lock().unwrap().set_values(download);
+            graph_data2_update.lock().unwrap().set_values(upload);
+            *signal_update.borrow_mut() = signal;
+        } else if config_update.data_source == "cpu" && config_update.multi_chart {
+            let mut cores = cpu_cores_update.lock().unwrap();
+            data::update_cpu_cores(&mut cores, &mut iowait_update.lock().unwrap(), history);
             stats_label_update.set_text(&format!(
-                "Apps: {:.1}MB | Cached: {:.1}MB | Buffers: {:.1}MB | Slab: {:.1}MB | Shmem: {:.1}MB",
-                apps, cached, buffers, slab, shmem
+                "{} cores | IO wait: {:.1}%",
+                cores.len(),
+                data::newest(&iowait_update.lock().unwrap().values).unwrap_or(0.0)
             ));
         } else {
-            // Update other charts
+            let (primary, secondary) = config_update.series_keys();
+            let mut data = graph_data_update.lock().unwrap();
+            data.set_from_history(history, primary);
+            let current = data::newest(&data.values).unwrap_or(0.0);
+            // Which processes are behind the current value
+            let ranking = match config_update.data_source.as_str() {
+                "cpu" => Some("by_cpu"),
+                "memory" => Some("by_memory"),
+                _ => None,
+            };
+            let mut text = format!("{:.1}{}", current, config_update.unit);
+            if let Some(top) = ranking.and_then(|r| data::top_processes_summary(history, r, 3)) {
+                text.push('\n');
+                text.push_str(&top);
+            }
+            // Next to the load that usually drives it
+            if config_update.data_source == "fan" {
+                let cpu = data::newest(&data::series(history, "cpu")).unwrap_or(0.0);
+                text.push_str(&format!(" | CPU: {:.0}%", cpu));
+            }
+            stats_label_update.set_text(&text);
+            if let Some(key) = secondary {
+                graph_data2_update.lock().unwrap().set_from_history(history, key);
+            }
         }
+        brush_label_update(history);
         drawing_area_update.queue_draw();
+        gauge_area_update.queue_draw();
+    });
+
+    // The energy, apps and process replies aren't in the history, so they
+    // are polled off the main thread and applied as they come in
+    let fetch: Option<Box<dyn Fn() -> serde_json::Value + Send>> = match config.data_source.as_str() {
+        "energy" => Some(Box::new(data::load_energy)),
+        "processes" => Some(Box::new(data::load_apps)),
+        "leak" => {
+            let tracker = leak_data.clone();
+            Some(Box::new(move || {
+                let pid = tracker.lock().unwrap().pid;
+                data::load_process(pid)
+            }))
+        }
+        _ => None,
+    };
+    if let Some(fetch) = fetch {
+        let (replies_tx, replies) = mpsc::channel();
+        thread::spawn(move || data::poll(fetch, replies_tx));
+        let config = config.clone();
+        let stats_label = stats_label.clone();
+        let leak_data = leak_data.clone();
+        let drawing_area = drawing_area.clone();
+        timeout_add_local(Duration::from_millis(250), move || {
+            while let Ok(reply) = replies.try_recv() {
+                match config.data_source.as_str() {
+                    "energy" => stats_label.set_text(&data::energy_summary(&reply)),
+                    "processes" => stats_label.set_text(&data::apps_summary(&reply)),
+                    _ => {
+                        let mut tracker = leak_data.lock().unwrap();
+                        match reply["error"].as_str() {
+                            Some(error) => stats_label.set_text(error),
+                            None => {
+                                tracker.push(&reply);
+                                stats_label.set_text(&data::leak_summary(&tracker));
+                            }
+                        }
+                        drawing_area.queue_draw();
+                    }
+                }
+            }
+            ControlFlow::Continue
+        });
+    }
+
+    if config.single_graph() {
+        let drag = gtk4::GestureDrag::new();
+        drag.set_button(1);
+        let start_x = Rc::new(Cell::new(0.0));
+        {
+            let start_x = start_x.clone();
+            drag.connect_drag_begin(move |gesture, x, _| {
+                // On a touchscreen a drag is a swipe; brushing is for the pointer
+                if gesture.device().is_some_and(|device| device.source() == InputSource::Touchscreen) {
+                    gesture.set_state(gtk4::EventSequenceState::Denied);
+                    return;
+                }
+                start_x.set(x);
+            });
+        }
+        {
+            let history = history.clone();
+            let graph_data = graph_data.clone();
+            let brush = brush.clone();
+            let brush_label = brush_label.clone();
+            let drawing_area = drawing_area.clone();
+            let start_x = start_x.clone();
+            drag.connect_drag_update(move |_, dx, _| {
+                let history = history.lock().unwrap();
+                let slots = graph_data.lock().unwrap().values.len();
+                let timestamps = data::plotted_timestamps(&history, slots);
+                let width = drawing_area.width().max(1) as f64;
+                let time_at = |x: f64| {
+                    let slot = (x.clamp(0.0, width) / width * (slots.max(2) - 1) as f64).round() as usize;
+                    timestamps.get(slot).copied().unwrap_or(0)
+                };
+                brush.set(Some((time_at(start_x.get()), time_at(start_x.get() + dx))));
+                brush_label(&history);
+                drawing_area.queue_draw();
+            });
+        }
+        {
+            let history = history.clone();
+            let refresh = refresh.clone();
+            drag.connect_drag_end(move |_, dx, _| {
+                // A click rather than a drag
+                if dx.abs() < 3.0 {
+                    brush.set(None);
+                    refresh(&history.lock().unwrap());
+                }
+            });
+        }
+        drawing_area.add_controller(drag);
+    }
+
+    // Touch: pinching zooms the span the graph shows, down to
+    // MIN_ZOOM_SLOTS samples; a long press reads out the sample under the
+    // finger, since touch has no hover tooltip
+    if config.single_graph() {
+        let full = graph_data.lock().unwrap().max_size;
+        let zoom = gtk4::GestureZoom::new();
+        let zoom_start = Rc::new(Cell::new(full));
+        {
+            let graph_data = graph_data.clone();
+            let zoom_start = zoom_start.clone();
+            zoom.connect_begin(move |_, _| zoom_start.set(graph_data.lock().unwrap().max_size));
+        }
+        {
+            let history = history.clone();
+            let graph_data = graph_data.clone();
+            let graph_data2 = graph_data2.clone();
+            let refresh = refresh.clone();
+            zoom.connect_scale_changed(move |_, scale| {
+                let slots = data::zoom_slots(zoom_start.get(), scale, full);
+                if slots == graph_data.lock().unwrap().max_size {
+                    return;
+                }
+                graph_data.lock().unwrap().max_size = slots;
+                graph_data2.lock().unwrap().max_size = slots;
+                refresh(&history.lock().unwrap());
+            });
+        }
+        drawing_area.add_controller(zoom);
+
+        let readout = Label::new(None);
+        readout.set_css_classes(&["stats-label"]);
+        let readout_popover = gtk4::Popover::new();
+        readout_popover.set_child(Some(&readout));
+        readout_popover.set_parent(&drawing_area);
+        let press = gtk4::GestureLongPress::new();
+        press.set_touch_only(true);
+        {
+            let history = history.clone();
+            let graph_data = graph_data.clone();
+            let drawing_area = drawing_area.clone();
+            let temperature_unit = temperature_unit.clone();
+            let config = config.clone();
+            press.connect_pressed(move |_, x, y| {
+                let data = graph_data.lock().unwrap();
+                let slots = data.values.len();
+                if slots == 0 {
+                    return;
+                }
+                let slot = data::slot_at(x, drawing_area.width() as f64, slots);
+                let timestamp = data::plotted_timestamps(&history.lock().unwrap(), slots)[slot];
+                let time = glib::DateTime::from_unix_local(timestamp / 1000)
+                    .and_then(|t| t.format("%H:%M:%S"))
+                    .map(|t| t.to_string())
+                    .unwrap_or_default();
+                let unit = match config.data_source.as_str() {
+                    "temperature" => temperature_unit.get().symbol().to_string(),
+                    _ => config.unit.clone(),
+                };
+                readout.set_text(&format!("{}  {:.1}{}", time, data.values[slot], unit));
+                readout_popover.set_pointing_to(Some(&Rectangle::new(x as i32, y as i32, 1, 1)));
+                readout_popover.popup();
+            });
+        }
+        drawing_area.add_controller(press);
+    }
+
+    // A sideways swipe steps through the network view's interfaces, the way
+    // the dropdown does
+    if config.data_source == "network" {
+        let swipe = gtk4::GestureSwipe::new();
+        swipe.set_touch_only(true);
+        let dropdown = interface_dropdown.clone();
+        swipe.connect_swipe(move |_, velocity_x, velocity_y| {
+            if velocity_x.abs() < SWIPE_VELOCITY || velocity_x.abs() < velocity_y.abs() * 2.0 {
+                return;
+            }
+            let count = dropdown.model().map_or(1, |model| model.n_items()).max(1);
+            // Leftwards goes to the next one
+            let step = if velocity_x < 0.0 { 1 } else { count - 1 };
+            dropdown.set_selected((dropdown.selected() + step) % count);
+        });
+        drawing_area.add_controller(swipe);
+    }
+
+    {
+        let history = history.clone();
+        let graph_data = graph_data.clone();
+        let graph_data2 = graph_data2.clone();
+        let data_source = config.data_source.clone();
+        let snapshot_names = snapshot_names.clone();
+        pin_entry.connect_activate(move |entry| {
+            let name = entry.text();
+            if name.trim().is_empty() {
+                return;
+            }
+            let snapshot = Snapshot::pin(
+                &name,
+                &data_source,
+                &history.lock().unwrap(),
+                &graph_data.lock().unwrap().values,
+                &graph_data2.lock().unwrap().values,
+            );
+            if let Err(e) = snapshot.save() {
+                eprintln!("Failed to save snapshot {}: {}", snapshot.name, e);
+                return;
+            }
+            events::emit("snapshot_pinned", serde_json::json!({ "name": snapshot.name }));
+            let mut names = snapshot_names.borrow_mut();
+            if !names.contains(&snapshot.name) {
+                snapshot_list.append(&snapshot.name);
+                names.push(snapshot.name);
+            }
+            entry.set_text("");
+            pin_popover.popdown();
+        });
+    }
+    {
+        let drawing_area = drawing_area.clone();
+        let data_source = config.data_source.clone();
+        snapshot_dropdown.connect_selected_notify(move |dropdown| {
+            let selected = (dropdown.selected() as usize).checked_sub(1);
+            *overlay.borrow_mut() = selected
+                .and_then(|i| snapshot_names.borrow().get(i).cloned())
+                .and_then(|name| Snapshot::load(&data_source, &name));
+            let name = overlay.borrow().as_ref().map(|s| s.name.clone());
+            events::emit("comparison_changed", serde_json::json!({ "snapshot": name }));
+            drawing_area.queue_draw();
+        });
+    }
+    {
+        let history = history.clone();
+        let refresh = refresh.clone();
+        let prefs = prefs.clone();
+        unit_button.connect_clicked(move |button| {
+            let unit = temperature_unit.get().toggled();
+            temperature_unit.set(unit);
+            button.set_label(unit.symbol());
+            let mut prefs = prefs.borrow_mut();
+            prefs.temperature_unit = Some(unit);
+            prefs.save();
+            events::emit("temperature_unit_changed", serde_json::json!({ "unit": unit }));
+            drop(prefs);
+            refresh(&history.lock().unwrap());
+        });
+    }
+    {
+        let history = history.clone();
+        let refresh = refresh.clone();
+        interface_dropdown.connect_selected_notify(move |dropdown| {
+            let selected = dropdown.selected() as usize;
+            let interface = selected.checked_sub(1).and_then(|i| interface_names.borrow().get(i).cloned());
+            *selected_interface.borrow_mut() = interface.clone();
+            let mut prefs = prefs.borrow_mut();
+            events::emit("interface_changed", serde_json::json!({ "interface": interface }));
+            prefs.network_interface = interface;
+            prefs.save();
+            drop(prefs);
+            refresh(&history.lock().unwrap());
+        });
+    }
+
+    // The service pushes a sample each tick over a subscription that
+    // reconnects by itself. While it's down the window follows latest.json
+    // instead, appending one sample per change rather than reloading the
+    // whole history file, or collects locally in standalone mode.
+    let socket_up = Rc::new(Cell::new(false));
+    let latest_monitor = gio::File::for_path(data::LATEST_FILE)
+        .monitor_file(gio::FileMonitorFlags::NONE, gio::Cancellable::NONE)
+        .map_err(|e| eprintln!("Failed to watch {}: {}", data::LATEST_FILE, e))
+        .ok();
+    if let Some(monitor) = &latest_monitor {
+        let history = history.clone();
+        let socket_up = socket_up.clone();
+        let refresh = refresh.clone();
+        monitor.connect_changed(move |_, _, _, event| {
+            if socket_up.get() || event != gio::FileMonitorEvent::ChangesDoneHint {
+                return;
+            }
+            let Some(latest) = data::load_latest_from(data::LATEST_FILE) else {
+                return;
+            };
+            let mut history = history.lock().unwrap();
+            if data::append_latest(&mut history, &latest, 60) {
+                refresh(&history);
+            }
+        });
+    }
+
+    let (events_tx, events) = mpsc::channel();
+    thread::spawn(move || data::follow_service(events_tx));
+
+    let history_events = history.clone();
+    let socket_up_events = socket_up.clone();
+    let refresh_events = refresh.clone();
+    let status_label_events = status_label.clone();
+    let reconnecting = Cell::new(false);
+    timeout_add_local(Duration::from_millis(250), move || {
+        // Dropping the monitor would stop the notifications
+        let _ = &latest_monitor;
+        while let Ok(event) = events.try_recv() {
+            let mut history = history_events.lock().unwrap();
+            match event {
+                StreamEvent::History(fresh) => {
+                    socket_up_events.set(true);
+                    if reconnecting.replace(false) {
+                        let last_seen = history["last_update"].as_i64().unwrap_or(0);
+                        status_label_events.set_text(&data::reconnect_label(data::reconnect_gap_ms(last_seen, &fresh)));
+                        let status_label = status_label_events.clone();
+                        timeout_add_local_once(Duration::from_secs(10), move || status_label.set_text(""));
+                    }
+                    *history = fresh;
+                    refresh_events(&history);
+                }
+                StreamEvent::Sample(sample) => {
+                    if data::append_latest(&mut history, &sample, 60) {
+                        refresh_events(&history);
+                    }
+                }
+                StreamEvent::Disconnected => {
+                    socket_up_events.set(false);
+                    reconnecting.set(true);
+                    status_label_events.set_text("reconnecting…");
+                }
+            }
+        }
         ControlFlow::Continue
     });
+
+    if config.standalone {
+        let mut local = LocalCollector::new();
+        timeout_add_local(Duration::from_millis(1000), move || {
+            if !socket_up.get() {
+                let mut history = history.lock().unwrap();
+                if data::append_latest(&mut history, &local.sample(), 60) {
+                    refresh(&history);
+                }
+            }
+            ControlFlow::Continue
+        });
+    }
+
+    if let Some(watchdog) = Watchdog::new(config.parent_pid, config.watch_parent) {
+        timeout_add_local(Duration::from_secs(1), move || {
+            if watchdog.orphaned() {
+                events::close("parent_exited");
+            }
+            ControlFlow::Continue
+        });
+    }
     
+    for action in [Action::Terminate, Action::Kill, Action::Renice(5), Action::Renice(-5)] {
+        let button = gtk4::Button::with_label(action.label());
+        button.set_css_classes(&["process-action"]);
+        process_actions.append(&button);
+        let window = window.clone();
+        let process_list = process_list.clone();
+        let process_rows = process_rows.clone();
+        let stats_label = stats_label.clone();
+        button.connect_clicked(move |_| {
+            let Some((pid, name)) =
+                process_list.selected_row().and_then(|row| process_rows.borrow().get(row.index() as usize).cloned())
+            else {
+                stats_label.set_text("Select a process first");
+                return;
+            };
+            let mut detail = action.describe(&name, pid);
+            if actions::needs_escalation(action, pid) {
+                detail.push_str("\nThis needs administrator rights.");
+            }
+            let dialog = gtk4::AlertDialog::builder()
+                .modal(true)
+                .message(format!("{} {}?", action.label(), name))
+                .detail(detail)
+                .buttons(["Cancel", action.label()])
+                .cancel_button(0)
+                .default_button(0)
+                .build();
+            let stats_label = stats_label.clone();
+            dialog.choose(Some(&window), gio::Cancellable::NONE, move |choice| {
+                if choice != Ok(1) {
+                    return;
+                }
+                let stats_label = stats_label.clone();
+                glib::spawn_future_local(async move {
+                    // pkexec waits on the polkit agent's prompt
+                    let result = gio::spawn_blocking(move || actions::perform(action, pid))
+                        .await
+                        .unwrap_or_else(|_| Err("action failed".to_string()));
+                    stats_label.set_text(&match &result {
+                        Ok(()) => format!("{}: {} ({})", action.label(), name, pid),
+                        Err(error) => format!("{} {} ({}): {}", action.label(), name, pid, error),
+                    });
+                    events::emit(
+                        "process_action",
+                        serde_json::json!({ "action": action.label(), "pid": pid, "ok": result.is_ok() }),
+                    );
+                });
+            });
+        });
+    }
+
     vbox.append(&title_box);
+    if config.gauges {
+        vbox.append(&gauge_area);
+    }
     vbox.append(&drawing_area);
     vbox.append(&stats_label);
+    if config.data_source == "processes" {
+        vbox.append(&process_panel);
+    }
     
     window.set_child(Some(&vbox));
+
+    // Miniature mode: only the graph, shrunk into the bottom-right corner of
+    // the screen where the overlay layer keeps it above other windows. The
+    // placement it had is put back on the way out.
+    let mini = Rc::new(Cell::new(false));
+    let saved_placement: Rc<RefCell<Vec<(Edge, bool, i32)>>> = Rc::new(RefCell::new(Vec::new()));
+    let toggle_mini = {
+        let window = window.clone();
+        let vbox = vbox.clone();
+        let title_box = title_box.clone();
+        let stats_label = stats_label.clone();
+        let process_panel = process_panel.clone();
+        let gauge_area = gauge_area.clone();
+        let drawing_area = drawing_area.clone();
+        let mini = mini.clone();
+        let (width, height) = (config.width, config.height);
+        Rc::new(move || {
+            let enabled = !mini.get();
+            mini.set(enabled);
+            title_box.set_visible(!enabled);
+            gauge_area.set_visible(!enabled);
+            stats_label.set_visible(!enabled);
+            process_panel.set_visible(!enabled);
+            let margin = if enabled { 2 } else { 8 };
+            vbox.set_margin_top(margin);
+            vbox.set_margin_bottom(margin);
+            vbox.set_margin_start(margin);
+            vbox.set_margin_end(margin);
+            let edges = [Edge::Top, Edge::Bottom, Edge::Left, Edge::Right];
+            if enabled {
+                *saved_placement.borrow_mut() = edges.iter().map(|&edge| (edge, window.is_anchor(edge), window.margin(edge))).collect();
+                for edge in edges {
+                    let corner = matches!(edge, Edge::Bottom | Edge::Right);
+                    window.set_anchor(edge, corner);
+                    window.set_margin(edge, if corner { MINI_MARGIN } else { 0 });
+                }
+                drawing_area.set_size_request(MINI_WIDTH, MINI_HEIGHT);
+                window.set_default_size(MINI_WIDTH, MINI_HEIGHT);
+            } else {
+                for (edge, anchored, margin) in saved_placement.borrow().iter() {
+                    window.set_anchor(*edge, *anchored);
+                    window.set_margin(*edge, *margin);
+                }
+                drawing_area.set_size_request(width, height);
+                window.set_default_size(width, height + 50);
+            }
+            events::emit("miniature_changed", serde_json::json!({ "enabled": enabled }));
+        })
+    };
+    {
+        let toggle_mini = toggle_mini.clone();
+        mini_button.connect_clicked(move |_| toggle_mini());
+    }
+    {
+        let toggle_mini = toggle_mini.clone();
+        let key = gtk4::EventControllerKey::new();
+        key.connect_key_pressed(move |_, key, _, _| {
+            if key == Key::m {
+                toggle_mini();
+                return glib::Propagation::Stop;
+            }
+            glib::Propagation::Proceed
+        });
+        window.add_controller(key);
+    }
+    {
+        // With the title bar hidden, a click anywhere brings the window back
+        let click = gtk4::GestureClick::new();
+        click.set_button(1);
+        click.connect_released(move |_, _, _, _| {
+            if mini.get() {
+                toggle_mini();
+            }
+        });
+        drawing_area.add_controller(click);
+    }
     
     let css_provider = gtk4::CssProvider::new();
-    css_provider.load_from_string(r#"
+    let colors = &config.colors;
+    let background = colors.background.with_alpha(colors.background.a as f64 / 255.0 * config.opacity.clamp(0.0, 1.0));
+    css_provider.load_from_string(&r#"
         window {
-            background-color: #1e1e2e;
+            background-color: BACKGROUND;
             border-radius: 12px;
-            border: 1px solid #313244;
+            border: 1px solid SURFACE;
             box-shadow: 0 8px 16px rgba(0, 0, 0, 0.8);
         }
         
         .title-label {
-            color: #cdd6f4;
+            color: TEXT;
             font-size: 14px;
             font-weight: bold;
             font-family: sans-serif;
         }
         
         .stats-label {
-            color: #a6adc8;
+            color: SUBTEXT;
             font-size: 11px;
             font-family: monospace;
         }
         
+        .status-label {
+            color: #f9e2af;
+            font-size: 10px;
+            font-family: sans-serif;
+        }
+        
+        .unit-toggle {
+            background: none;
+            border: none;
+            color: #fab387;
+            font-size: 11px;
+            padding: 0 4px;
+        }
+        
+        .pin-button,
+        .mini-button {
+            background: none;
+            border: none;
+            font-size: 11px;
+            padding: 0 4px;
+        }
+        
+        .snapshot-dropdown,
+        .interface-dropdown {
+            color: TEXT;
+            font-size: 11px;
+            font-family: sans-serif;
+        }
+        
         .legend-label {
             color: #bac2de;
             font-size: 10px;
@@ -242,7 +1132,11 @@ pub fn build_ui(app: &Application, config: GraphConfig) {
             background-color: rgba(243, 139, 168, 0.2);
             border-radius: 4px;
         }
-    "#);
+    "#
+        .replace("BACKGROUND", &background.to_string())
+        .replace("SURFACE", &colors.surface.to_string())
+        .replace("SUBTEXT", &colors.subtext.to_string())
+        .replace("TEXT", &colors.text.to_string()));
     
     gtk4::style_context_add_provider_for_display(
         &gtk4::prelude::WidgetExt::display(&window),
@@ -250,5 +1144,8 @@ pub fn build_ui(app: &Application, config: GraphConfig) {
         gtk4::STYLE_PROVIDER_PRIORITY_APPLICATION,
     );
     
+    let data_source = config.data_source.clone();
+    window.connect_map(move |_| events::emit("shown", serde_json::json!({ "data_source": data_source })));
+    window.connect_unmap(|_| events::emit("hidden", serde_json::json!({})));
     window.present();
 }
\ No newline at end of file
@@ -0,0 +1,44 @@
+use std::fs;
+use std::os::unix::process::parent_id;
+
+/// Notices when the AGS process that spawned the window has gone, so a
+/// crash or restart doesn't leave its popups on screen with nothing to close
+/// them.
+pub enum Watchdog {
+    // An explicit PID, with its start time so a recycled PID isn't taken for
+    // the parent; None when it had already exited
+    Process { pid: u32, started: Option<u64> },
+    // The PPID the window started with, which changes when the parent dies
+    // and the window is reparented
+    Parent(u32),
+}
+
+// State and start time (clock ticks after boot) from /proc/<pid>/stat
+fn process_state(pid: u32) -> Option<(char, u64)> {
+    let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // The command name can contain spaces and parentheses
+    let mut fields = stat.rsplit_once(')')?.1.split_whitespace();
+    let state = fields.next()?.chars().next()?;
+    Some((state, fields.nth(18)?.parse().ok()?))
+}
+
+impl Watchdog {
+    /// Watches `parent_pid`, or with `watch_parent` the window's own parent.
+    /// None when there's nothing to watch.
+    pub fn new(parent_pid: Option<u32>, watch_parent: bool) -> Option<Self> {
+        match parent_pid {
+            Some(pid) => Some(Watchdog::Process { pid, started: process_state(pid).map(|(_, started)| started) }),
+            None => watch_parent.then(|| Watchdog::Parent(parent_id())),
+        }
+    }
+
+    pub fn orphaned(&self) -> bool {
+        match self {
+            Watchdog::Process { pid, started } => match (process_state(*pid), started) {
+                (Some((state, now)), Some(started)) => state == 'Z' || now != *started,
+                _ => true,
+            },
+            Watchdog::Parent(ppid) => parent_id() != *ppid,
+        }
+    }
+}
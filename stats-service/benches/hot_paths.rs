@@ -0,0 +1,230 @@
+//! Timings for the work the service does every tick, each against a budget:
+//!
+//! | bench                          | budget  |
+//! |--------------------------------|---------|
+//! | tick collection (live /proc)   | 1 ms    |
+//! | history, 60 points per series  | 1 ms    |
+//! | history, 3600 points           | 25 ms   |
+//! | broadcast to 10 subscribers    | 1 ms    |
+//! | broadcast to 100 subscribers   | 5 ms    |
+//!
+//! The tick budget keeps a 1 s interval (and the 250 ms load-test one) far
+//! from busy. History is serialized once per revision for every `history`
+//! request; 3600 points is an hour at the default interval. Broadcast is one
+//! update written to every subscriber's socket and read back.
+//!
+//! `cargo bench` prints median and p95 for each and flags any median over
+//! budget; `cargo bench -- --enforce` also exits non-zero then, for checking
+//! a refactor on a quiet machine. Any other argument filters benches by name.
+
+#[allow(dead_code)]
+#[path = "../src/collectors.rs"]
+mod collectors;
+#[allow(dead_code)]
+#[path = "../src/series.rs"]
+mod series;
+
+use collectors::{CpuCollector, DiskCollector, KernelActivityCollector, MemoryCollector, NetworkCollector, ProcReader, TemperatureCollector};
+use series::{HistoryConfig, MetricId, SeriesRegistry, GPU_FIELDS};
+use std::hint::black_box;
+use std::path::Path;
+use std::process;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::UnixStream;
+use tokio::runtime::Runtime;
+use tokio::sync::{broadcast, mpsc};
+
+// What collectors.rs reads through `crate::proc_root`
+fn proc_root() -> &'static Path {
+    Path::new("/proc")
+}
+
+// Samples per bench after the warm-up, and the time after which a slow
+// bench stops early with what it has
+const SAMPLES: usize = 200;
+const WARM_UP: usize = 10;
+const TIME_LIMIT: Duration = Duration::from_secs(5);
+
+struct Report {
+    name: String,
+    median: Duration,
+    p95: Duration,
+    budget: Duration,
+}
+
+fn measure(name: &str, budget: Duration, mut run: impl FnMut()) -> Report {
+    for _ in 0..WARM_UP {
+        run();
+    }
+    let started = Instant::now();
+    let mut times = Vec::with_capacity(SAMPLES);
+    while times.len() < SAMPLES && started.elapsed() < TIME_LIMIT {
+        let start = Instant::now();
+        run();
+        times.push(start.elapsed());
+    }
+    times.sort();
+    let at = |fraction: f64| times[((times.len() - 1) as f64 * fraction) as usize];
+    Report { name: name.to_string(), median: at(0.5), p95: at(0.95), budget }
+}
+
+// Everything collectors.rs reads on a tick with every collector on
+fn tick_collection() -> impl FnMut() {
+    let mut cpu = CpuCollector::new();
+    let mut memory = MemoryCollector::new();
+    let mut network = NetworkCollector::new();
+    let mut disk = DiskCollector::new();
+    let mut temperature = TemperatureCollector::new();
+    let mut activity = KernelActivityCollector::new();
+    let (mut loadavg, mut pressure, mut uptime, mut oom, mut sockets) =
+        (ProcReader::default(), ProcReader::default(), ProcReader::default(), ProcReader::default(), ProcReader::default());
+    move || {
+        black_box(cpu.collect());
+        black_box(memory.collect());
+        black_box(network.collect());
+        black_box(disk.collect());
+        black_box(temperature.collect());
+        black_box(activity.collect());
+        black_box(collectors::read_loadavg(&mut loadavg));
+        black_box(collectors::read_pressure_stats(&mut pressure));
+        black_box(collectors::read_uptime(&mut uptime, 0));
+        black_box(collectors::read_oom_kills(&mut oom));
+        black_box(collectors::read_socket_stats(&mut sockets));
+        black_box(collectors::read_file_descriptors(&mut sockets));
+    }
+}
+
+// A full history for a 16-core machine with a few interfaces, sensors and
+// one GPU, each series `size` points long
+fn full_history(size: usize) -> SeriesRegistry {
+    let mut registry = SeriesRegistry::new(&HistoryConfig { size, ..Default::default() });
+    let named = [
+        "cpu", "cpu_iowait", "load", "context_switches", "interrupts", "forks", "memory", "memory_apps",
+        "memory_cached", "memory_buffers", "memory_slab", "memory_shmem", "swap", "swap_in", "swap_out",
+        "pressure_cpu", "pressure_memory", "pressure_io", "sockets_tcp", "sockets_udp", "sockets_time_wait",
+        "sockets_orphaned", "file_descriptors", "file_descriptors_used", "network_download", "network_upload", "disk_read",
+        "disk_write", "temperature", "fan", "battery", "power", "network_quality",
+    ];
+    let mut ids: Vec<MetricId> = named.iter().map(|name| MetricId::named(name)).collect();
+    for core in 0..16 {
+        ids.extend([MetricId::Core(core), MetricId::CoreFrequency(core)]);
+    }
+    for interface in ["enp3s0", "wlan0", "wg0", "docker0"] {
+        ids.extend([MetricId::InterfaceDownload(interface.to_string()), MetricId::InterfaceUpload(interface.to_string())]);
+    }
+    for sensor in ["coretemp/Package id 0", "nvme/Composite", "amdgpu/edge", "acpitz/temp1"] {
+        ids.push(MetricId::Temperature(sensor.to_string()));
+    }
+    ids.extend(GPU_FIELDS.iter().map(|field| MetricId::Gpu(0, field)));
+    for id in &ids {
+        registry.register(id.clone());
+    }
+    for tick in 0..size {
+        for (i, id) in ids.iter().enumerate() {
+            registry.record(id, ((tick * 31 + i * 17) % 1000) as f64 / 7.0);
+        }
+        registry.push_timestamp(1_700_000_000_000 + tick as i64 * 1000);
+    }
+    registry
+}
+
+// About the size of one streamed sample with every collector on
+fn sample_frame() -> Arc<str> {
+    let cores: Vec<f64> = (0..16).map(|i| i as f64 * 5.5).collect();
+    let sample = serde_json::json!({
+        "timestamp": 1_700_000_000_000i64,
+        "cpu_usage": 23.5,
+        "cpu_cores": cores,
+        "cpu_frequencies": cores.iter().map(|c| 800.0 + c * 40.0).collect::<Vec<_>>(),
+        "memory": {"total": 32_000_000.0, "available": 20_000_000.0, "used_percentage": 37.5, "apps": 8_000_000.0},
+        "network_interfaces": {"enp3s0": {"download": 120.5, "upload": 12.25, "rx_total": 123_456_789u64, "tx_total": 12_345_678u64}},
+        "temperatures": {"coretemp/Package id 0": 54.0, "nvme/Composite": 41.0},
+        "top_io": [{"pid": 1234, "name": "firefox", "read": 10.0, "write": 2.0}],
+        "states": {"vpn": "down"},
+    });
+    Arc::from(format!("{}\n", sample))
+}
+
+// One update through the same path stream_updates takes: the broadcast
+// channel, a write to each subscriber's socket, then read at the far end
+fn broadcast_to(runtime: &Runtime, subscribers: usize) -> impl FnMut() + '_ {
+    let frame = sample_frame();
+    let (updates, _) = broadcast::channel::<Arc<str>>(16);
+    let (delivered_tx, mut delivered) = mpsc::unbounded_channel();
+    runtime.block_on(async {
+        for _ in 0..subscribers {
+            let (mut server, mut client) = UnixStream::pair().unwrap();
+            let mut receiver = updates.subscribe();
+            tokio::spawn(async move {
+                while let Ok(frame) = receiver.recv().await {
+                    if server.write_all(frame.as_bytes()).await.is_err() {
+                        break;
+                    }
+                }
+            });
+            let delivered_tx = delivered_tx.clone();
+            let length = frame.len();
+            tokio::spawn(async move {
+                let mut buf = vec![0; length];
+                while client.read_exact(&mut buf).await.is_ok() {
+                    let _ = delivered_tx.send(());
+                }
+            });
+        }
+    });
+    move || {
+        runtime.block_on(async {
+            updates.send(frame.clone()).unwrap();
+            for _ in 0..subscribers {
+                delivered.recv().await.unwrap();
+            }
+        })
+    }
+}
+
+fn main() {
+    // cargo bench passes --bench; everything else that isn't --enforce is a filter
+    let args: Vec<String> = std::env::args().skip(1).filter(|a| a != "--bench").collect();
+    let enforce = args.iter().any(|a| a == "--enforce");
+    let filters: Vec<&String> = args.iter().filter(|a| *a != "--enforce").collect();
+    let selected = |name: &str| filters.is_empty() || filters.iter().any(|f| name.contains(f.as_str()));
+    let ms = Duration::from_millis;
+
+    let runtime = tokio::runtime::Builder::new_multi_thread().enable_all().build().expect("tokio runtime");
+    let mut reports = Vec::new();
+    if selected("tick collection") {
+        reports.push(measure("tick collection", ms(1), tick_collection()));
+    }
+    for (size, budget) in [(60, ms(1)), (3600, ms(25))] {
+        let name = format!("history, {} points", size);
+        if selected(&name) {
+            let registry = full_history(size);
+            reports.push(measure(&name, budget, || {
+                black_box(serde_json::to_string(&registry).unwrap());
+            }));
+        }
+    }
+    for (subscribers, budget) in [(10, ms(1)), (100, ms(5))] {
+        let name = format!("broadcast to {} subscribers", subscribers);
+        if selected(&name) {
+            reports.push(measure(&name, budget, broadcast_to(&runtime, subscribers)));
+        }
+    }
+
+    let mut over = 0;
+    println!("{:<32} {:>10} {:>10} {:>10}", "bench", "median", "p95", "budget");
+    for report in &reports {
+        let flag = if report.median > report.budget { "  OVER BUDGET" } else { "" };
+        over += flag.len().min(1);
+        println!(
+            "{:<32} {:>10.3?} {:>10.3?} {:>10.0?}{}",
+            report.name, report.median, report.p95, report.budget, flag
+        );
+    }
+    if enforce && over > 0 {
+        eprintln!("{} bench(es) over budget", over);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,95 @@
+use std::process::Stdio;
+use serde::Deserialize;
+
+/// `[advisories]` in the config. A fan-curve advisory fires when the CPU
+/// stays at or above `temperature` while the fans are at `fan_duty` or more
+/// of their maximum, for `for_secs`: the cooling is already doing all it can,
+/// which a plain temperature alert can't tell apart from a fan that just
+/// hasn't spun up yet.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AdvisoryConfig {
+    pub enabled: bool,
+    // °C
+    pub temperature: f64,
+    // 0-1 of the fan's maximum
+    pub fan_duty: f64,
+    pub for_secs: u64,
+}
+
+impl Default for AdvisoryConfig {
+    fn default() -> Self {
+        AdvisoryConfig { enabled: true, temperature: 90.0, fan_duty: 0.95, for_secs: 120 }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AdvisoryTransition {
+    Raised { since: i64, message: String },
+    Cleared { at: i64 },
+}
+
+#[derive(Debug)]
+pub struct FanCurveAdvisor {
+    config: AdvisoryConfig,
+    since: Option<i64>,
+    // Hottest reading while the condition held, for the message
+    peak: f64,
+    firing: bool,
+}
+
+// "2 min", or seconds when it isn't a whole number of minutes
+fn duration_text(secs: u64) -> String {
+    if secs >= 60 && secs.is_multiple_of(60) {
+        format!("{} min", secs / 60)
+    } else {
+        format!("{} s", secs)
+    }
+}
+
+impl FanCurveAdvisor {
+    pub fn new(config: &AdvisoryConfig) -> Self {
+        FanCurveAdvisor { config: config.clone(), since: None, peak: 0.0, firing: false }
+    }
+
+    /// Feeds one tick's CPU temperature and fan duty (see
+    /// `FanCollector::duty`). Either being unreadable ends the condition.
+    pub fn update(&mut self, timestamp: i64, temperature: Option<f64>, fan_duty: Option<f64>) -> Option<AdvisoryTransition> {
+        if !self.config.enabled {
+            return None;
+        }
+        let hot = match (temperature, fan_duty) {
+            (Some(celsius), Some(duty)) => (celsius >= self.config.temperature && duty >= self.config.fan_duty).then_some(celsius),
+            _ => None,
+        };
+        let Some(celsius) = hot else {
+            self.since = None;
+            return std::mem::take(&mut self.firing).then_some(AdvisoryTransition::Cleared { at: timestamp });
+        };
+        let since = *self.since.get_or_insert(timestamp);
+        self.peak = if timestamp == since { celsius } else { self.peak.max(celsius) };
+        if self.firing || timestamp - since < self.config.for_secs as i64 * 1000 {
+            return None;
+        }
+        self.firing = true;
+        let message = format!("CPU at {:.0} °C with fan at max for {}", self.peak, duration_text(self.config.for_secs));
+        Some(AdvisoryTransition::Raised { since, message })
+    }
+}
+
+/// A normal-urgency desktop notification: advisories are early warnings,
+/// not the critical ones threshold alerts send.
+pub fn notify(message: &str) {
+    let result = tokio::process::Command::new("notify-send")
+        .arg("--app-name=ags-stats")
+        .arg("--urgency=normal")
+        .arg("Cooling at its limit")
+        .arg(message)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    if let Err(e) = result {
+        eprintln!("Failed to send advisory notification: {}", e);
+    }
+}
@@ -0,0 +1,341 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use graph_renderer::{EventMarker, GraphRequest};
+use serde::Deserialize;
+use crate::metadata;
+
+// Notification thumbnails are small enough for an icon slot
+const THUMBNAIL_WIDTH: u32 = 128;
+const THUMBNAIL_HEIGHT: u32 = 64;
+// However long the metric's history is configured, the thumbnail shows the last minute
+const THUMBNAIL_WINDOW_MS: i64 = 60_000;
+
+/// One `[[alerts]]` entry: fires once its conditions have held for
+/// `for_secs`, and clears as soon as one stops holding. Conditions that are
+/// set must all hold:
+///
+/// - `above`: the latest value is above it.
+/// - `rising_faster_than`: the metric grows faster than this many of its
+///   units per minute, e.g. 204800 on `memory_apps` (KB) for 200 MB/min.
+/// - `reaches` with `within_secs`: on its current trend the metric reaches
+///   `reaches` within that long, e.g. 100 on `filesystem:/` within 3600 for
+///   a disk that will be full within the hour.
+///
+/// Rates and trends are a least-squares fit over the last `window_secs`, and
+/// only hold once that much has been seen. With `open` set to a graph window
+/// view, e.g. "memory", the notification has a button that opens it.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AlertRule {
+    pub name: String,
+    // A history series, e.g. "cpu", "memory" or "temperature", or a mount's
+    // used percentage as "filesystem:<mount point>"
+    pub metric: String,
+    #[serde(default)]
+    pub above: Option<f64>,
+    #[serde(default)]
+    pub rising_faster_than: Option<f64>,
+    #[serde(default)]
+    pub reaches: Option<f64>,
+    #[serde(default)]
+    pub within_secs: u64,
+    #[serde(default = "default_window_secs")]
+    pub window_secs: u64,
+    #[serde(default)]
+    pub for_secs: u64,
+    #[serde(default)]
+    pub open: Option<String>,
+}
+
+fn default_window_secs() -> u64 {
+    60
+}
+
+impl AlertRule {
+    /// A watch set from a graph window: fires when `metric` goes above
+    /// `above`, and offers to open `view` again.
+    pub fn watch(metric: &str, above: f64, view: &str) -> Self {
+        let label = metadata::info(metric).map_or(metric, |s| s.label);
+        AlertRule {
+            name: format!("Watch: {}", label),
+            metric: metric.to_string(),
+            above: Some(above),
+            rising_faster_than: None,
+            reaches: None,
+            within_secs: 0,
+            window_secs: default_window_secs(),
+            for_secs: 0,
+            open: Some(view.to_string()),
+        }
+    }
+
+    fn uses_trend(&self) -> bool {
+        self.rising_faster_than.is_some() || self.reaches.is_some()
+    }
+
+    /// Why the rule can never fire, if it can't.
+    pub fn problem(&self) -> Option<&'static str> {
+        if self.above.is_none() && !self.uses_trend() {
+            Some("needs above, rising_faster_than or reaches")
+        } else if self.reaches.is_some() && self.within_secs == 0 {
+            Some("reaches needs within_secs")
+        } else if self.uses_trend() && self.window_secs == 0 {
+            Some("window_secs must be positive")
+        } else {
+            None
+        }
+    }
+
+    // `rate` is per minute, None until the window is covered
+    fn holds(&self, value: f64, rate: Option<f64>) -> bool {
+        if self.above.is_some_and(|above| value <= above) {
+            return false;
+        }
+        if let Some(limit) = self.rising_faster_than {
+            if rate.is_none_or(|rate| rate <= limit) {
+                return false;
+            }
+        }
+        if let Some(target) = self.reaches {
+            // Already there counts; otherwise it has to be heading there in time
+            let on_course = value >= target
+                || rate.is_some_and(|rate| rate > 0.0 && (target - value) / rate * 60.0 <= self.within_secs as f64);
+            if !on_course {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AlertTransition {
+    // `rate` per minute, for rules with a rate or trend condition
+    Raised { since: i64, value: f64, rate: Option<f64> },
+    Cleared { at: i64 },
+}
+
+#[derive(Debug, Clone, Default)]
+struct RuleState {
+    holding_since: Option<i64>,
+    firing: bool,
+    // (timestamp, value) over the rule's window, for rate and trend rules
+    samples: VecDeque<(i64, f64)>,
+    watching_since: Option<i64>,
+}
+
+impl RuleState {
+    // Per minute over the window, once it has been covered
+    fn rate(&mut self, rule: &AlertRule, timestamp: i64, value: f64) -> Option<f64> {
+        let window = rule.window_secs as i64 * 1000;
+        self.samples.push_back((timestamp, value));
+        while self.samples.front().is_some_and(|(t, _)| *t < timestamp - window) {
+            self.samples.pop_front();
+        }
+        let since = *self.watching_since.get_or_insert(timestamp);
+        if timestamp - since < window {
+            return None;
+        }
+        slope(&self.samples).map(|per_ms| per_ms * 60_000.0)
+    }
+}
+
+// Least-squares slope in units per ms; None without two distinct times
+fn slope(samples: &VecDeque<(i64, f64)>) -> Option<f64> {
+    let (t0, _) = *samples.front()?;
+    let n = samples.len() as f64;
+    let mean_t = samples.iter().map(|(t, _)| (t - t0) as f64).sum::<f64>() / n;
+    let mean_v = samples.iter().map(|(_, v)| v).sum::<f64>() / n;
+    let (mut covariance, mut variance) = (0.0, 0.0);
+    for (t, v) in samples {
+        let dt = (t - t0) as f64 - mean_t;
+        covariance += dt * (v - mean_v);
+        variance += dt * dt;
+    }
+    (variance > 0.0).then(|| covariance / variance)
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AlertEngine {
+    rules: Vec<AlertRule>,
+    states: Vec<RuleState>,
+}
+
+impl AlertEngine {
+    /// Rules that can never fire are reported and left out.
+    pub fn new(rules: Vec<AlertRule>) -> Self {
+        let rules: Vec<AlertRule> = rules
+            .into_iter()
+            .filter(|rule| match rule.problem() {
+                Some(problem) => {
+                    eprintln!("Ignoring alert {}: {}", rule.name, problem);
+                    false
+                }
+                None => true,
+            })
+            .collect();
+        let states = rules.iter().map(|_| RuleState::default()).collect();
+        AlertEngine { rules, states }
+    }
+
+    pub fn rules(&self) -> &[AlertRule] {
+        &self.rules
+    }
+
+    /// Adds `rule`, replacing the one with the same name and its state.
+    pub fn set(&mut self, rule: AlertRule) {
+        self.remove(&rule.name);
+        self.rules.push(rule);
+        self.states.push(RuleState::default());
+    }
+
+    /// Whether there was a rule by that name.
+    pub fn remove(&mut self, name: &str) -> bool {
+        let Some(i) = self.rules.iter().position(|rule| rule.name == name) else {
+            return false;
+        };
+        self.rules.remove(i);
+        self.states.remove(i);
+        true
+    }
+
+    /// Feeds one tick, given a lookup of each metric's latest value. A metric
+    /// that is missing or unreadable this tick leaves its rules as they were.
+    pub fn update(&mut self, timestamp: i64, latest: impl Fn(&str) -> Option<f64>) -> Vec<(&AlertRule, AlertTransition)> {
+        let mut transitions = Vec::new();
+        for (rule, state) in self.rules.iter().zip(&mut self.states) {
+            let Some(value) = latest(&rule.metric).filter(|v| v.is_finite()) else {
+                continue;
+            };
+            let rate = if rule.uses_trend() { state.rate(rule, timestamp, value) } else { None };
+            if !rule.holds(value, rate) {
+                state.holding_since = None;
+                if state.firing {
+                    state.firing = false;
+                    transitions.push((rule, AlertTransition::Cleared { at: timestamp }));
+                }
+                continue;
+            }
+            let since = *state.holding_since.get_or_insert(timestamp);
+            if !state.firing && timestamp - since >= rule.for_secs as i64 * 1000 {
+                state.firing = true;
+                transitions.push((rule, AlertTransition::Raised { since, value, rate }));
+            }
+        }
+        transitions
+    }
+}
+
+// Rule names are free text; anything that isn't safe in a file name becomes '_'
+fn file_stem(name: &str) -> String {
+    name.chars().map(|c| if c.is_alphanumeric() || matches!(c, '-' | '_') { c } else { '_' }).collect()
+}
+
+/// Renders the metric's last minute of samples, with the moment it crossed
+/// the threshold marked, to `<data_dir>/alerts/<name>.svg`. `samples` line up
+/// with the newest `timestamps`. The file is replaced each time the alert fires.
+pub fn render_thumbnail(rule: &AlertRule, since: i64, samples: &[f64], timestamps: &[i64], data_dir: &str) -> io::Result<PathBuf> {
+    let timestamps = &timestamps[timestamps.len().saturating_sub(samples.len())..];
+    let samples = &samples[samples.len() - timestamps.len()..];
+    let newest = timestamps.last().copied().unwrap_or(0);
+    let start = timestamps.iter().position(|t| *t >= newest - THUMBNAIL_WINDOW_MS).unwrap_or(0);
+    let (samples, timestamps) = (&samples[start..], &timestamps[start..]);
+    let info = metadata::info(&rule.metric);
+    let request = GraphRequest {
+        data: samples.to_vec(),
+        // Without a natural ceiling, keep the threshold in view
+        max_value: info
+            .and_then(|s| s.max)
+            .unwrap_or_else(|| samples.iter().copied().filter(|v| v.is_finite()).fold(rule.above.or(rule.reaches).unwrap_or(0.0), f64::max)),
+        color: info.map_or("#f38ba8", |s| s.color).to_string(),
+        width: THUMBNAIL_WIDTH,
+        height: THUMBNAIL_HEIGHT,
+        timestamps: timestamps.to_vec(),
+        events: vec![EventMarker { index: None, timestamp: Some(since), label: rule.name.clone(), color: Some("#f38ba8".to_string()) }],
+        ..Default::default()
+    };
+    let dir = Path::new(data_dir).join("alerts");
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("{}.svg", file_stem(&rule.name)));
+    fs::write(&path, graph_renderer::render_request(&request))?;
+    Ok(path)
+}
+
+// "Memory at 82.0%, rising 1.5%/min, 100% in 12 min"
+fn describe(rule: &AlertRule, value: f64, rate: Option<f64>) -> String {
+    let (label, unit) = match rule.metric.strip_prefix("filesystem:") {
+        Some(mount) => (mount, "%"),
+        None => metadata::info(&rule.metric).map_or((rule.metric.as_str(), ""), |s| (s.label, s.unit())),
+    };
+    let mut body = format!("{} at {:.1}{}", label, value, unit);
+    if let Some(above) = rule.above {
+        body += &format!(", above {}{}", above, unit);
+    }
+    if let Some(rate) = rate.filter(|_| rule.uses_trend()) {
+        body += &format!(", rising {:.1}{}/min", rate, unit);
+    }
+    if let (Some(target), Some(rate)) = (rule.reaches, rate.filter(|r| *r > 0.0)) {
+        let minutes = ((target - value) / rate).max(0.0);
+        body += &format!(", {}{} in {:.0} min", target, unit, minutes);
+    }
+    if rule.for_secs > 0 {
+        body += &format!(" for {}s", rule.for_secs);
+    }
+    body
+}
+
+/// Sends a desktop notification through `notify-send`, with the thumbnail
+/// as the `image-path` hint when there is one. For a rule with `open`,
+/// clicking the notification starts `<window> <view>`.
+pub fn notify(rule: &AlertRule, value: f64, rate: Option<f64>, thumbnail: Option<&Path>, window: &Path) {
+    let body = describe(rule, value, rate);
+    let mut command = tokio::process::Command::new("notify-send");
+    command.arg("--app-name=ags-stats").arg("--urgency=critical");
+    if let Some(path) = thumbnail {
+        command.arg(format!("--hint=string:image-path:{}", path.display()));
+    }
+    command.stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null());
+    if rule.open.is_some() {
+        // notify-send then waits for the click and prints the action's name
+        command.arg("--action=default=Open").arg("--wait").stdout(Stdio::piped());
+    }
+    command.arg(&rule.name).arg(body);
+    let child = match command.spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            eprintln!("Failed to send notification for alert {}: {}", rule.name, e);
+            return;
+        }
+    };
+    let Some(view) = rule.open.clone() else {
+        // Not awaited; tokio reaps the child once it exits
+        return;
+    };
+    let window = window.to_path_buf();
+    tokio::spawn(async move {
+        let clicked = child.wait_with_output().await.is_ok_and(|output| String::from_utf8_lossy(&output.stdout).trim() == "default");
+        if clicked {
+            if let Err(e) = open_window(&window, &view) {
+                eprintln!("Failed to open graph window {}: {}", view, e);
+            }
+        }
+    });
+}
+
+/// Under systemd the window goes in a transient unit of its own, which the
+/// user manager starts, so it doesn't inherit this service's sandbox
+/// (ProtectHome, NoNewPrivileges, MemoryDenyWriteExecute): it saves prefs
+/// and snapshots and can escalate through pkexec. A `--scope` would still
+/// run it as our child, sandbox and all.
+fn open_window(window: &Path, view: &str) -> std::io::Result<tokio::process::Child> {
+    let mut command = if std::env::var_os("INVOCATION_ID").is_some() {
+        let mut command = tokio::process::Command::new("systemd-run");
+        command.args(["--user", "--collect", "--quiet", "--"]).arg(window);
+        command
+    } else {
+        tokio::process::Command::new(window)
+    };
+    command.arg(view).stdin(Stdio::null()).stdout(Stdio::null()).spawn()
+}
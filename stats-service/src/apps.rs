@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use crate::collectors::ProcReader;
+use crate::process;
+
+pub const TOP_APP_COUNT: usize = 8;
+
+/// Processes rolled up by desktop application, e.g. every Firefox content
+/// process under one row.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppGroup {
+    // Desktop ID when the app runs in its own cgroup, otherwise the process name
+    pub id: String,
+    pub name: String,
+    pub processes: usize,
+    // KB
+    pub memory: f64,
+    // Percent of total machine CPU capacity
+    pub cpu: f64,
+}
+
+// Undoes systemd unit name escaping, where `-` inside the app ID is `\x2d`
+fn unescape(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    let mut rest = name;
+    while let Some(i) = rest.find("\\x") {
+        out.push_str(&rest[..i]);
+        match rest.get(i + 2..i + 4).and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+            Some(byte) => {
+                out.push(byte as char);
+                rest = &rest[i + 4..];
+            }
+            None => {
+                out.push_str("\\x");
+                rest = &rest[i + 2..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Extracts the application ID from a cgroup path following the XDG
+/// convention: `app[-<launcher>]-<id>[@<random>].service` or
+/// `app[-<launcher>]-<id>-<random>.scope`.
+pub fn app_id_from_cgroup(cgroup: &str) -> Option<String> {
+    let unit = cgroup
+        .rsplit('/')
+        .find(|segment| segment.starts_with("app-") && (segment.ends_with(".service") || segment.ends_with(".scope")))?;
+    let (stem, is_scope) = match unit.strip_suffix(".scope") {
+        Some(stem) => (stem, true),
+        None => (unit.strip_suffix(".service")?, false),
+    };
+    let stem = stem.strip_prefix("app-")?;
+    let stem = if is_scope {
+        stem.rsplit_once('-').map_or(stem, |(head, _)| head)
+    } else {
+        stem.split_once('@').map_or(stem, |(head, _)| head)
+    };
+    // Whatever precedes the last unescaped dash is the launcher
+    let id = stem.rsplit_once('-').map_or(stem, |(_, id)| id);
+    (!id.is_empty()).then(|| unescape(id))
+}
+
+fn read_app_id(reader: &mut ProcReader, pid: u32) -> Option<String> {
+    let content = reader.read_pid(pid, "cgroup")?;
+    content.lines().find_map(|line| app_id_from_cgroup(line.splitn(3, ':').nth(2)?))
+}
+
+fn application_dirs() -> Vec<PathBuf> {
+    let mut dirs = vec![crate::config::data_home()];
+    let system = env::var("XDG_DATA_DIRS").unwrap_or_else(|_| "/usr/local/share:/usr/share".to_string());
+    dirs.extend(system.split(':').filter(|d| !d.is_empty()).map(PathBuf::from));
+    dirs.push(PathBuf::from("/var/lib/flatpak/exports/share"));
+    dirs.into_iter().map(|d| d.join("applications")).collect()
+}
+
+// Name= from the [Desktop Entry] group, ignoring localized variants
+fn desktop_name(id: &str) -> Option<String> {
+    application_dirs().iter().find_map(|dir| {
+        let content = fs::read_to_string(dir.join(format!("{}.desktop", id))).ok()?;
+        let mut in_entry = false;
+        for line in content.lines() {
+            let line = line.trim();
+            if line.starts_with('[') {
+                in_entry = line == "[Desktop Entry]";
+            } else if in_entry {
+                if let Some(name) = line.strip_prefix("Name=") {
+                    return Some(name.to_string());
+                }
+            }
+        }
+        None
+    })
+}
+
+#[derive(Default)]
+pub struct AppCollector {
+    reader: ProcReader,
+    pids: Vec<u32>,
+    prev_ticks: HashMap<u32, u64>,
+    // Swapped with `prev_ticks` each tick so neither map is reallocated
+    current: HashMap<u32, u64>,
+    prev_total_ticks: Option<u64>,
+    // Desktop file lookups are cached; apps rarely change names
+    names: HashMap<String, String>,
+}
+
+impl AppCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn display_name(&mut self, id: &str) -> String {
+        self.names
+            .entry(id.to_string())
+            .or_insert_with(|| {
+                // org.mozilla.firefox -> firefox when there's no desktop file
+                desktop_name(id).unwrap_or_else(|| id.rsplit('.').next().unwrap_or(id).to_string())
+            })
+            .clone()
+    }
+
+    /// Groups every process by application and returns the heaviest groups by
+    /// CPU, then memory. CPU is zero on the first call.
+    pub fn collect(&mut self) -> Vec<AppGroup> {
+        let total_ticks = process::read_total_cpu_ticks(&mut self.reader);
+        let total_delta = match (total_ticks, self.prev_total_ticks) {
+            (Some(now), Some(prev)) => now.saturating_sub(prev),
+            _ => 0,
+        };
+        self.prev_total_ticks = total_ticks;
+
+        let mut groups: HashMap<String, (bool, AppGroup)> = HashMap::new();
+        self.current.clear();
+        process::list_pids_into(&mut self.pids);
+        for &pid in &self.pids {
+            let Some(ticks) = process::read_process_cpu_ticks(&mut self.reader, pid) else {
+                continue;
+            };
+            self.current.insert(pid, ticks);
+            let delta = self.prev_ticks.get(&pid).map_or(0, |prev| ticks.saturating_sub(*prev));
+            let memory = process::read_process_rss_kb(&mut self.reader, pid).unwrap_or(0.0);
+            // Kernel threads have no memory and would only add noise
+            if memory == 0.0 {
+                continue;
+            }
+
+            let (id, is_app) = match read_app_id(&mut self.reader, pid) {
+                Some(id) => (id, true),
+                None => (process::read_process_name(&mut self.reader, pid), false),
+            };
+            let (_, group) = groups.entry(id.clone()).or_insert_with(|| {
+                (is_app, AppGroup { id, name: String::new(), processes: 0, memory: 0.0, cpu: 0.0 })
+            });
+            group.processes += 1;
+            group.memory += memory;
+            if total_delta > 0 {
+                group.cpu += delta as f64 / total_delta as f64 * 100.0;
+            }
+        }
+        std::mem::swap(&mut self.prev_ticks, &mut self.current);
+
+        let mut top: Vec<(bool, AppGroup)> = groups.into_values().collect();
+        top.sort_by(|a, b| b.1.cpu.total_cmp(&a.1.cpu).then(b.1.memory.total_cmp(&a.1.memory)));
+        top.truncate(TOP_APP_COUNT);
+        top.into_iter()
+            .map(|(is_app, mut group)| {
+                group.name = if is_app { self.display_name(&group.id) } else { group.id.clone() };
+                group
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,116 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+
+const POWER_SUPPLY_DIR: &str = "/sys/class/power_supply";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatteryStats {
+    // The supply's name in sysfs, e.g. BAT0
+    pub name: String,
+    // Percent
+    pub capacity: Option<f64>,
+    // The kernel's status string, e.g. "Charging" or "Discharging"
+    pub status: String,
+    // W, whichever way it flows
+    pub power: Option<f64>,
+    // Seconds left at the current draw, while discharging
+    pub time_to_empty: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PowerSupplyStats {
+    // None when there's no mains adapter entry, as on most desktops
+    pub ac_online: Option<bool>,
+    pub batteries: Vec<BatteryStats>,
+    // Percent of all batteries together, weighted by their size
+    pub capacity: Option<f64>,
+}
+
+fn read_f64(path: impl AsRef<Path>) -> Option<f64> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+fn read_trimmed(path: impl AsRef<Path>) -> Option<String> {
+    Some(fs::read_to_string(path).ok()?.trim().to_string())
+}
+
+// Batteries that power the system; a wireless mouse's has scope "Device"
+fn system_batteries() -> Vec<PathBuf> {
+    let mut batteries: Vec<PathBuf> = fs::read_dir(POWER_SUPPLY_DIR)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| read_trimmed(p.join("type")).is_some_and(|t| t == "Battery"))
+        .filter(|p| read_trimmed(p.join("scope")).is_none_or(|s| s != "Device"))
+        .collect();
+    batteries.sort();
+    batteries
+}
+
+pub fn available() -> bool {
+    !system_batteries().is_empty()
+}
+
+// Energy in µWh and power in µW, or charge in µAh and current in µA,
+// depending on what the fuel gauge reports
+fn read_battery(path: &Path) -> Option<(BatteryStats, Option<f64>)> {
+    let status = read_trimmed(path.join("status"))?;
+    let (stored, full, rate) = match read_f64(path.join("energy_now")) {
+        Some(now) => (now, read_f64(path.join("energy_full")), read_f64(path.join("power_now"))),
+        None => (read_f64(path.join("charge_now"))?, read_f64(path.join("charge_full")), read_f64(path.join("current_now"))),
+    };
+    let power = read_f64(path.join("power_now"))
+        .map(|uw| uw / 1e6)
+        .or_else(|| Some(read_f64(path.join("current_now"))? * read_f64(path.join("voltage_now"))? / 1e12));
+    let capacity = read_f64(path.join("capacity")).or_else(|| full.filter(|f| *f > 0.0).map(|f| stored / f * 100.0));
+    // Some drivers work the estimate out themselves
+    let time_to_empty = match read_f64(path.join("time_to_empty_now")) {
+        Some(secs) => Some(secs),
+        None => rate.filter(|r| *r > 0.0).map(|r| stored / r * 3600.0),
+    }
+    .filter(|_| status == "Discharging");
+    let name = path.file_name()?.to_string_lossy().into_owned();
+    Some((BatteryStats { name, capacity, status, power, time_to_empty }, full))
+}
+
+fn ac_online() -> Option<bool> {
+    let mut online = None;
+    for entry in fs::read_dir(POWER_SUPPLY_DIR).into_iter().flatten().flatten() {
+        let path = entry.path();
+        if read_trimmed(path.join("type")).is_some_and(|t| t == "Mains") {
+            let supply = read_trimmed(path.join("online")).map(|o| o == "1");
+            online = Some(online.unwrap_or(false) || supply.unwrap_or(false));
+        }
+    }
+    online
+}
+
+/// Reads every battery and the mains adapter from `/sys/class/power_supply`,
+/// for bar widgets and the battery graph to share.
+#[derive(Debug, Default)]
+pub struct PowerSupplyCollector;
+
+impl PowerSupplyCollector {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn collect(&mut self) -> PowerSupplyStats {
+        let (mut batteries, mut weighted, mut weights) = (Vec::new(), 0.0, 0.0);
+        for path in system_batteries() {
+            let Some((battery, full)) = read_battery(&path) else {
+                continue;
+            };
+            if let Some(capacity) = battery.capacity {
+                let weight = full.unwrap_or(1.0);
+                weighted += capacity * weight;
+                weights += weight;
+            }
+            batteries.push(battery);
+        }
+        let capacity = (weights > 0.0).then(|| weighted / weights);
+        PowerSupplyStats { ac_online: ac_online(), batteries, capacity }
+    }
+}
@@ -0,0 +1,88 @@
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// Low enough that a client still sees mostly good data, high enough that a
+// few minutes of running hits every fault.
+const DELAY_PROBABILITY: f64 = 0.05;
+const TRUNCATE_PROBABILITY: f64 = 0.05;
+const MALFORMED_PROBABILITY: f64 = 0.03;
+const MAX_DELAY_MS: u64 = 3000;
+
+/// Fault injection for `--chaos`: stalls ticks and corrupts socket responses
+/// so clients can be checked against a misbehaving service. Pass
+/// `--chaos=<seed>` to replay a run.
+pub struct Chaos {
+    seed: u64,
+    state: Mutex<u64>,
+}
+
+impl Chaos {
+    pub fn new(seed: Option<u64>) -> Self {
+        let seed = seed.unwrap_or_else(|| {
+            SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos() as u64
+        });
+        // xorshift gets stuck on zero
+        Chaos { seed, state: Mutex::new(seed.max(1)) }
+    }
+
+    /// Reads `--chaos` or `--chaos=<seed>` from the command line.
+    pub fn from_args(args: &[String]) -> Option<Self> {
+        args.iter().find_map(|arg| match arg.strip_prefix("--chaos") {
+            Some("") => Some(Chaos::new(None)),
+            Some(seed) => Some(Chaos::new(seed.strip_prefix('=').and_then(|s| s.parse().ok()))),
+            None => None,
+        })
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    fn next(&self) -> u64 {
+        let mut state = self.state.lock().unwrap();
+        let mut x = *state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        *state = x;
+        x
+    }
+
+    fn chance(&self, probability: f64) -> bool {
+        ((self.next() >> 11) as f64 / (1u64 << 53) as f64) < probability
+    }
+
+    fn below(&self, n: u64) -> u64 {
+        self.next() % n.max(1)
+    }
+
+    /// An extra stall to insert before the next tick, if any.
+    pub fn tick_delay(&self) -> Option<Duration> {
+        self.chance(DELAY_PROBABILITY)
+            .then(|| Duration::from_millis(1 + self.below(MAX_DELAY_MS)))
+    }
+
+    /// Returns the bytes to actually send for a response: usually unchanged,
+    /// occasionally cut short or replaced with a malformed frame.
+    pub fn mangle(&self, response: Vec<u8>) -> Vec<u8> {
+        if self.chance(TRUNCATE_PROBABILITY) {
+            let len = self.below(response.len() as u64) as usize;
+            return response[..len].to_vec();
+        }
+        if self.chance(MALFORMED_PROBABILITY) {
+            return match self.below(5) {
+                0 => (0..1 + self.below(256)).map(|_| self.next() as u8).collect(),
+                1 => b"{\"cpu\": [1.0, 2.0,, \"memory\": }".to_vec(),
+                // Well-formed JSON of the wrong shape
+                2 => b"{\"cpu\": \"NaN\", \"memory\": null, \"memory_total\": -1}".to_vec(),
+                3 => {
+                    let mut doubled = response.clone();
+                    doubled.extend_from_slice(&response);
+                    doubled
+                }
+                _ => Vec::new(),
+            };
+        }
+        response
+    }
+}
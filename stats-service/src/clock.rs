@@ -0,0 +1,58 @@
+use std::time::Duration;
+use chrono::Local;
+use serde::Deserialize;
+use tokio::time::{self, Instant, Interval, MissedTickBehavior};
+
+/// An interval whose ticks land on wall-clock multiples of `period_ms`
+/// (every whole second for 1000), so samples from separate clients and
+/// recordings line up.
+pub fn aligned_interval(period_ms: u64) -> Interval {
+    let period_ms = period_ms.max(1);
+    let now_ms = Local::now().timestamp_millis().max(0) as u64;
+    let delay = period_ms - now_ms % period_ms;
+    let mut interval = time::interval_at(Instant::now() + Duration::from_millis(delay), Duration::from_millis(period_ms));
+    // Bursting to catch up would stamp several samples with the same time;
+    // missed ticks are handled by the caller instead
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    interval
+}
+
+/// The boundary at or before `timestamp_ms`, used as the sample's timestamp
+/// so scheduling jitter doesn't show up in the history. Ticks fire just after
+/// their boundary, and a late catch-up tick keeps the boundary it missed.
+pub fn align(timestamp_ms: i64, period_ms: u64) -> i64 {
+    let period = period_ms.max(1) as i64;
+    timestamp_ms.div_euclid(period) * period
+}
+
+fn clock_ms(clock: libc::clockid_t) -> i64 {
+    let mut ts = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+    unsafe { libc::clock_gettime(clock, &mut ts) };
+    ts.tv_sec * 1000 + ts.tv_nsec / 1_000_000
+}
+
+/// Total time spent suspended since boot: CLOCK_BOOTTIME keeps counting
+/// through suspend and CLOCK_MONOTONIC doesn't. Growth between two ticks
+/// tells a suspend apart from a stalled loop.
+pub fn suspended_ms() -> i64 {
+    clock_ms(libc::CLOCK_BOOTTIME) - clock_ms(libc::CLOCK_MONOTONIC)
+}
+
+/// What to do with the intervals the loop slept through (suspend, a stalled
+/// collector) so time isn't silently compressed in the ring buffers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MissedTickPolicy {
+    /// Insert one null sample per missed interval, up to a full history.
+    Backfill,
+    /// Leave the series alone and record a "gap" marker over the missed span.
+    #[default]
+    Annotate,
+    Ignore,
+}
+
+/// How many whole intervals were skipped between two sample timestamps.
+pub fn missed_ticks(previous_ms: i64, current_ms: i64, period_ms: u64) -> u64 {
+    let period = period_ms.max(1) as i64;
+    ((current_ms - previous_ms) / period - 1).max(0) as u64
+}
@@ -0,0 +1,1111 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use serde::{Deserialize, Serialize};
+
+/// Reads procfs files into buffers that live as long as the collector, so a
+/// tick doesn't allocate a fresh path and String for every file it opens.
+#[derive(Default)]
+pub struct ProcReader {
+    path: PathBuf,
+    rel: String,
+    buf: String,
+}
+
+impl ProcReader {
+    // Reads `self.rel` under the proc root. The returned slice stays valid
+    // until the next read.
+    fn load(&mut self) -> io::Result<&str> {
+        self.path.as_mut_os_string().clear();
+        self.path.push(crate::proc_root());
+        self.path.push(&self.rel);
+        self.buf.clear();
+        File::open(&self.path)?.read_to_string(&mut self.buf)?;
+        Ok(&self.buf)
+    }
+
+    /// Reads a file relative to the proc root, e.g. `"stat"` or `"net/dev"`.
+    pub fn read(&mut self, rel: &str) -> Option<&str> {
+        self.rel.clear();
+        self.rel.push_str(rel);
+        self.load().ok()
+    }
+
+    /// Reads `/proc/<pid>/<file>`.
+    pub fn read_pid(&mut self, pid: u32, file: &str) -> Option<&str> {
+        self.rel.clear();
+        let _ = write!(self.rel, "{}/{}", pid, file);
+        self.load().ok()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct MemoryStats {
+    pub total: f64,
+    pub available: f64,
+    pub used_percentage: f64,
+    // Detailed breakdown in KB
+    pub apps: f64,
+    pub cached: f64,
+    pub buffers: f64,
+    pub slab: f64,
+    pub shmem: f64,
+    // KB, 0 without swap
+    #[serde(default)]
+    pub swap_total: f64,
+    #[serde(default)]
+    pub swap_free: f64,
+    #[serde(default)]
+    pub swap_used_percentage: f64,
+    // KB/s paged in from and out to swap, from /proc/vmstat
+    #[serde(default)]
+    pub swap_in: f64,
+    #[serde(default)]
+    pub swap_out: f64,
+    // KB in the preallocated hugepage pool (the default size only), and how
+    // much of it is free or reserved by a mapping that hasn't faulted it in
+    #[serde(default)]
+    pub hugepages_total: f64,
+    #[serde(default)]
+    pub hugepages_free: f64,
+    #[serde(default)]
+    pub hugepages_reserved: f64,
+    // KB of anonymous memory in transparent hugepages, outside the pool
+    #[serde(default)]
+    pub anon_hugepages: f64,
+}
+
+#[derive(Debug)]
+pub struct CpuStats {
+    pub overall_usage: f64,
+    pub core_usage: Vec<f64>,
+    pub iowait_percentage: f64,
+}
+
+// (total, idle, iowait) from a `cpu` or `cpuN` line of /proc/stat
+fn parse_cpu_line(line: &str) -> Option<(f64, f64, f64)> {
+    let mut fields = line.split_whitespace().skip(1).map(|v| v.parse::<f64>().unwrap_or(0.0));
+    let mut next = || fields.next();
+    let (user, nice, system, idle, iowait, irq, softirq) =
+        (next()?, next()?, next()?, next()?, next()?, next()?, next()?);
+    Some((user + nice + system + idle + iowait + irq + softirq, idle, iowait))
+}
+
+#[derive(Default)]
+pub struct CpuCollector {
+    reader: ProcReader,
+    prev: Option<(f64, f64, f64)>,
+    // Per-core (total, idle); swapped each tick so neither Vec is reallocated
+    prev_cores: Vec<(f64, f64)>,
+    cores: Vec<(f64, f64)>,
+}
+
+impl CpuCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Usage since the previous call. The first call reports zeros.
+    pub fn collect(&mut self) -> CpuStats {
+        let mut result = CpuStats { overall_usage: 0.0, core_usage: Vec::new(), iowait_percentage: 0.0 };
+        let Some(content) = self.reader.read("stat") else {
+            return result;
+        };
+
+        self.cores.clear();
+        let mut overall = None;
+        for line in content.lines() {
+            if line.starts_with("cpu ") {
+                overall = parse_cpu_line(line);
+            } else if line.starts_with("cpu") {
+                if let Some((total, idle, _)) = parse_cpu_line(line) {
+                    self.cores.push((total, idle));
+                }
+            } else {
+                break; // End of CPU lines
+            }
+        }
+
+        if let Some((total, idle, iowait)) = overall {
+            if let Some((prev_total, prev_idle, prev_iowait)) = self.prev {
+                let total_delta = total - prev_total;
+                // iowait, and idle on tickless kernels, can step back between
+                // reads; a field that did counts as not having moved
+                let idle_delta = (idle - prev_idle).max(0.0);
+                let iowait_delta = (iowait - prev_iowait).max(0.0);
+                if total_delta > 0.0 {
+                    let busy = (total_delta - idle_delta - iowait_delta).max(0.0);
+                    result.overall_usage = (busy / total_delta * 100.0).min(100.0);
+                    result.iowait_percentage = (iowait_delta / total_delta * 100.0).min(100.0);
+                }
+            }
+            self.prev = Some((total, idle, iowait));
+        }
+
+        result.core_usage.reserve_exact(self.cores.len());
+        if self.prev_cores.len() == self.cores.len() {
+            for ((total, idle), (prev_total, prev_idle)) in self.cores.iter().zip(&self.prev_cores) {
+                let total_delta = total - prev_total;
+                let idle_delta = (idle - prev_idle).max(0.0);
+                result.core_usage.push(if total_delta > 0.0 { ((total_delta - idle_delta).max(0.0) / total_delta * 100.0).min(100.0) } else { 0.0 });
+            }
+        } else {
+            // First tick or the core count changed
+            result.core_usage.resize(self.cores.len(), 0.0);
+        }
+        std::mem::swap(&mut self.prev_cores, &mut self.cores);
+        result
+    }
+}
+
+const CPU_SYSFS_DIR: &str = "/sys/devices/system/cpu";
+
+/// Current clock of each core from cpufreq, in MHz. Cores without a cpufreq
+/// policy (offline, or no driver like in most VMs) read as NaN so the list
+/// stays indexed by core number.
+#[derive(Debug, Default)]
+pub struct CpuFrequencyCollector {
+    // scaling_cur_freq of each core, by core number
+    inputs: Vec<Option<PathBuf>>,
+}
+
+impl CpuFrequencyCollector {
+    pub fn new() -> Self {
+        let mut inputs = Vec::new();
+        for entry in fs::read_dir(CPU_SYSFS_DIR).into_iter().flatten().flatten() {
+            let name = entry.file_name();
+            let Some(core) = name.to_str().and_then(|n| n.strip_prefix("cpu")).and_then(|n| n.parse::<usize>().ok()) else {
+                continue;
+            };
+            let input = entry.path().join("cpufreq/scaling_cur_freq");
+            if input.exists() {
+                if inputs.len() <= core {
+                    inputs.resize(core + 1, None);
+                }
+                inputs[core] = Some(input);
+            }
+        }
+        CpuFrequencyCollector { inputs }
+    }
+
+    pub fn available(&self) -> bool {
+        self.inputs.iter().any(Option::is_some)
+    }
+
+    pub fn collect(&mut self) -> Vec<f64> {
+        self.inputs
+            .iter()
+            .map(|input| {
+                let khz = input.as_ref().and_then(|path| fs::read_to_string(path).ok()?.trim().parse::<f64>().ok());
+                khz.map_or(f64::NAN, |khz| khz / 1000.0)
+            })
+            .collect()
+    }
+}
+
+#[derive(Default)]
+pub struct MemoryCollector {
+    reader: ProcReader,
+    // Bytes; vmstat counts swap traffic in pages. 0 means 4 KiB.
+    page_size: u64,
+    // (pswpin, pswpout) at the previous call
+    prev_swap: Option<(u64, u64, Instant)>,
+}
+
+impl MemoryCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_page_size(mut self, bytes: u64) -> Self {
+        self.page_size = bytes;
+        self
+    }
+
+    // Pages swapped in and out since boot
+    fn read_swap_pages(&mut self) -> Option<(u64, u64)> {
+        let (mut swapped_in, mut swapped_out) = (None, None);
+        for line in self.reader.read("vmstat")?.lines() {
+            if let Some(value) = line.strip_prefix("pswpin ") {
+                swapped_in = value.trim().parse().ok();
+            } else if let Some(value) = line.strip_prefix("pswpout ") {
+                swapped_out = value.trim().parse().ok();
+            }
+        }
+        Some((swapped_in?, swapped_out?))
+    }
+
+    pub fn collect(&mut self) -> MemoryStats {
+        let mut stats = MemoryStats::default();
+        let Some(content) = self.reader.read("meminfo") else {
+            return stats;
+        };
+        let (mut active_anon, mut inactive_anon) = (0.0, 0.0);
+        // The pool is counted in pages of Hugepagesize KB
+        let (mut huge_total, mut huge_free, mut huge_reserved, mut huge_size) = (0.0, 0.0, 0.0, 0.0);
+        for line in content.lines() {
+            let Some((key, rest)) = line.split_once(':') else {
+                continue;
+            };
+            let Some(value) = rest.split_whitespace().next().and_then(|v| v.parse::<f64>().ok()) else {
+                continue;
+            };
+            match key {
+                "MemTotal" => stats.total = value,
+                "MemAvailable" => stats.available = value,
+                "Active(anon)" => active_anon = value,
+                "Inactive(anon)" => inactive_anon = value,
+                "Shmem" => stats.shmem = value,
+                "Slab" => stats.slab = value,
+                "Buffers" => stats.buffers = value,
+                "Cached" => stats.cached = value,
+                "SwapTotal" => stats.swap_total = value,
+                "SwapFree" => stats.swap_free = value,
+                "HugePages_Total" => huge_total = value,
+                "HugePages_Free" => huge_free = value,
+                "HugePages_Rsvd" => huge_reserved = value,
+                "Hugepagesize" => huge_size = value,
+                "AnonHugePages" => stats.anon_hugepages = value,
+                _ => {}
+            }
+        }
+        stats.hugepages_total = huge_total * huge_size;
+        stats.hugepages_free = huge_free * huge_size;
+        stats.hugepages_reserved = huge_reserved * huge_size;
+        if stats.total > 0.0 {
+            stats.used_percentage = ((stats.total - stats.available) / stats.total) * 100.0;
+        }
+        if stats.swap_total > 0.0 {
+            stats.swap_used_percentage = ((stats.swap_total - stats.swap_free) / stats.swap_total) * 100.0;
+        }
+        stats.apps = active_anon + inactive_anon;
+
+        // Rates since the previous call; zero on the first
+        let now = Instant::now();
+        let pages = self.read_swap_pages();
+        if let (Some((swapped_in, swapped_out)), Some((prev_in, prev_out, prev_time))) = (pages, self.prev_swap) {
+            let elapsed = now.duration_since(prev_time).as_secs_f64();
+            if elapsed > 0.0 {
+                let page_kb = if self.page_size == 0 { 4.0 } else { self.page_size as f64 / 1024.0 };
+                stats.swap_in = swapped_in.saturating_sub(prev_in) as f64 * page_kb / elapsed;
+                stats.swap_out = swapped_out.saturating_sub(prev_out) as f64 * page_kb / elapsed;
+            }
+        }
+        self.prev_swap = pages.map(|(swapped_in, swapped_out)| (swapped_in, swapped_out, now));
+        stats
+    }
+}
+
+const NODE_SYSFS_DIR: &str = "/sys/devices/system/node";
+
+/// Memory of one NUMA node, in KB.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NumaNode {
+    pub node: u32,
+    pub total: f64,
+    pub free: f64,
+    pub used: f64,
+    // The node's share of the hugepage pool, in KB of the default page size
+    pub hugepages_total: f64,
+    pub hugepages_free: f64,
+}
+
+/// Parses a node's meminfo (`Node 0 MemTotal:  32768 kB` lines). Hugepage
+/// counts are converted with `hugepage_size`, the system's Hugepagesize in KB.
+pub fn parse_node_meminfo(node: u32, content: &str, hugepage_size: f64) -> NumaNode {
+    let mut stats = NumaNode { node, ..Default::default() };
+    for line in content.lines() {
+        // "Node", the node number, the key, then the value
+        let mut fields = line.split_whitespace().skip(2);
+        let (Some(key), Some(Ok(value))) = (fields.next(), fields.next().map(str::parse::<f64>)) else {
+            continue;
+        };
+        match key.trim_end_matches(':') {
+            "MemTotal" => stats.total = value,
+            "MemFree" => stats.free = value,
+            "MemUsed" => stats.used = value,
+            "HugePages_Total" => stats.hugepages_total = value * hugepage_size,
+            "HugePages_Free" => stats.hugepages_free = value * hugepage_size,
+            _ => {}
+        }
+    }
+    stats
+}
+
+/// Numbers of the NUMA nodes with memory, ascending; empty without NUMA
+/// support in the kernel.
+pub fn numa_nodes() -> Vec<u32> {
+    let mut nodes: Vec<u32> = fs::read_dir(NODE_SYSFS_DIR)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|e| e.file_name().to_str()?.strip_prefix("node")?.parse().ok())
+        .filter(|node| Path::new(NODE_SYSFS_DIR).join(format!("node{}/meminfo", node)).exists())
+        .collect();
+    nodes.sort_unstable();
+    nodes
+}
+
+/// Free and used memory per NUMA node. The node list is read once; nodes
+/// don't come and go outside of memory hotplug.
+#[derive(Default)]
+pub struct NumaCollector {
+    nodes: Vec<(u32, PathBuf)>,
+    reader: ProcReader,
+}
+
+impl NumaCollector {
+    pub fn new() -> Self {
+        let nodes = numa_nodes().into_iter().map(|node| (node, Path::new(NODE_SYSFS_DIR).join(format!("node{}/meminfo", node)))).collect();
+        NumaCollector { nodes, reader: ProcReader::default() }
+    }
+
+    pub fn collect(&mut self) -> Vec<NumaNode> {
+        // Node files give hugepages as counts; the size is system-wide
+        let hugepage_size = self
+            .reader
+            .read("meminfo")
+            .and_then(|content| content.lines().find_map(|l| l.strip_prefix("Hugepagesize:")))
+            .and_then(|rest| rest.split_whitespace().next()?.parse::<f64>().ok())
+            .unwrap_or(0.0);
+        self.nodes
+            .iter()
+            .filter_map(|(node, path)| Some(parse_node_meminfo(*node, &fs::read_to_string(path).ok()?, hugepage_size)))
+            .collect()
+    }
+}
+
+/// Per-second rates of the system-wide counters at the end of /proc/stat.
+/// Context switches far above the usual rate point at lock contention or
+/// a process spinning on short sleeps.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct KernelActivity {
+    pub context_switches: f64,
+    // All interrupts, including ones without a numbered line
+    pub interrupts: f64,
+    // Processes and threads created
+    pub forks: f64,
+}
+
+#[derive(Default)]
+pub struct KernelActivityCollector {
+    reader: ProcReader,
+    // (ctxt, intr, processes) at the previous call
+    prev: Option<([u64; 3], Instant)>,
+}
+
+impl KernelActivityCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn read_counters(&mut self) -> Option<[u64; 3]> {
+        let mut counters = [None; 3];
+        for line in self.reader.read("stat")?.lines() {
+            let Some((key, rest)) = line.split_once(' ') else {
+                continue;
+            };
+            let slot = match key {
+                "ctxt" => 0,
+                "intr" => 1,
+                "processes" => 2,
+                _ => continue,
+            };
+            // intr's total comes first, then one count per interrupt line
+            counters[slot] = rest.split_whitespace().next().and_then(|v| v.parse().ok());
+        }
+        Some([counters[0]?, counters[1]?, counters[2]?])
+    }
+
+    /// Rates since the previous call; zeros on the first. None while
+    /// /proc/stat lacks a counter.
+    pub fn collect(&mut self) -> Option<KernelActivity> {
+        let counters = self.read_counters();
+        let now = Instant::now();
+        let previous = std::mem::replace(&mut self.prev, counters.map(|c| (c, now)));
+        let counters = counters?;
+        let Some((prev, then)) = previous else {
+            return Some(KernelActivity::default());
+        };
+        let elapsed = now.duration_since(then).as_secs_f64();
+        let rate = |i: usize| if elapsed > 0.0 { counters[i].saturating_sub(prev[i]) as f64 / elapsed } else { 0.0 };
+        Some(KernelActivity { context_switches: rate(0), interrupts: rate(1), forks: rate(2) })
+    }
+}
+
+/// Per-second rates on each CPU, in the order of `cpus`, of the softirqs
+/// packet processing and timers raise and of hardware interrupts. One CPU
+/// taking all of a busy NIC's NET_RX work shows up here well before it does
+/// in the overall CPU figure.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct IrqDistribution {
+    // CPU numbers of the columns; offline CPUs are left out
+    pub cpus: Vec<u32>,
+    pub net_rx: Vec<f64>,
+    pub net_tx: Vec<f64>,
+    pub timer: Vec<f64>,
+    // Every per-CPU line of /proc/interrupts, local timer and IPIs included
+    pub hardware: Vec<f64>,
+}
+
+// CPU numbers from a "CPU0 CPU1 ..." header
+fn irq_header(line: &str) -> Vec<u32> {
+    line.split_whitespace().filter_map(|cpu| cpu.strip_prefix("CPU")?.parse().ok()).collect()
+}
+
+// The first `cpus` counts after a "NAME:" label; None when there are fewer
+fn irq_counts(rest: &str, cpus: usize) -> Option<Vec<u64>> {
+    let counts: Vec<u64> = rest.split_whitespace().take(cpus).map_while(|v| v.parse().ok()).collect();
+    (counts.len() == cpus).then_some(counts)
+}
+
+/// (CPU numbers, NET_RX, NET_TX, TIMER) counts from /proc/softirqs.
+pub fn parse_softirqs(content: &str) -> Option<(Vec<u32>, [Vec<u64>; 3])> {
+    let mut lines = content.lines();
+    let cpus = irq_header(lines.next()?);
+    let mut counts: [Option<Vec<u64>>; 3] = [None, None, None];
+    for line in lines {
+        let Some((name, rest)) = line.split_once(':') else {
+            continue;
+        };
+        let slot = match name.trim() {
+            "NET_RX" => 0,
+            "NET_TX" => 1,
+            "TIMER" => 2,
+            _ => continue,
+        };
+        counts[slot] = irq_counts(rest, cpus.len());
+    }
+    let [net_rx, net_tx, timer] = counts;
+    Some((cpus, [net_rx?, net_tx?, timer?]))
+}
+
+/// (CPU numbers, interrupts per CPU) summed over /proc/interrupts.
+pub fn parse_interrupts(content: &str) -> Option<(Vec<u32>, Vec<u64>)> {
+    let mut lines = content.lines();
+    let cpus = irq_header(lines.next()?);
+    let mut totals = vec![0u64; cpus.len()];
+    for line in lines {
+        let Some((name, rest)) = line.split_once(':') else {
+            continue;
+        };
+        // System-wide error counts, not per CPU
+        if matches!(name.trim(), "ERR" | "MIS") {
+            continue;
+        }
+        for (total, count) in totals.iter_mut().zip(irq_counts(rest, cpus.len()).unwrap_or_default()) {
+            *total += count;
+        }
+    }
+    Some((cpus, totals))
+}
+
+#[derive(Default)]
+pub struct IrqCollector {
+    reader: ProcReader,
+    // (cpus, [NET_RX, NET_TX, TIMER, hardware] per CPU) at the previous call
+    prev: Option<(Vec<u32>, [Vec<u64>; 4], Instant)>,
+}
+
+impl IrqCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn read_counters(&mut self) -> Option<(Vec<u32>, [Vec<u64>; 4])> {
+        let (cpus, [net_rx, net_tx, timer]) = parse_softirqs(self.reader.read("softirqs")?)?;
+        let (irq_cpus, hardware) = parse_interrupts(self.reader.read("interrupts")?)?;
+        (irq_cpus == cpus).then_some((cpus, [net_rx, net_tx, timer, hardware]))
+    }
+
+    /// Rates since the previous call; zeros on the first and after a CPU went
+    /// on or offline. None while either file is unreadable.
+    pub fn collect(&mut self) -> Option<IrqDistribution> {
+        let (cpus, counters) = self.read_counters()?;
+        let now = Instant::now();
+        let previous = self.prev.replace((cpus.clone(), counters.clone(), now));
+        let elapsed = previous.as_ref().map_or(0.0, |(_, _, then)| now.duration_since(*then).as_secs_f64());
+        let rates = |i: usize| -> Vec<f64> {
+            match &previous {
+                Some((prev_cpus, prev, _)) if *prev_cpus == cpus && elapsed > 0.0 => {
+                    counters[i].iter().zip(&prev[i]).map(|(now, then)| now.saturating_sub(*then) as f64 / elapsed).collect()
+                }
+                _ => vec![0.0; cpus.len()],
+            }
+        };
+        Some(IrqDistribution { net_rx: rates(0), net_tx: rates(1), timer: rates(2), hardware: rates(3), cpus })
+    }
+}
+
+/// Run-queue averages and task counts from /proc/loadavg.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LoadAverage {
+    pub one: f64,
+    pub five: f64,
+    pub fifteen: f64,
+    // Runnable and existing tasks, threads included
+    pub running: u32,
+    pub total: u32,
+}
+
+pub fn read_loadavg(reader: &mut ProcReader) -> Option<LoadAverage> {
+    // e.g. "0.52 0.58 0.59 2/1234 5678"; the last field is the newest PID
+    let mut fields = reader.read("loadavg")?.split_whitespace();
+    let mut average = || fields.next()?.parse().ok();
+    let (one, five, fifteen) = (average()?, average()?, average()?);
+    let (running, total) = fields.next()?.split_once('/')?;
+    Some(LoadAverage { one, five, fifteen, running: running.parse().ok()?, total: total.parse().ok()? })
+}
+
+/// Time since boot, from /proc/uptime. Suspended time counts, as it does
+/// for `uptime(1)`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Uptime {
+    pub seconds: f64,
+    // ms since the epoch, to the second; derived from `seconds`, so it moves
+    // if the wall clock is set
+    pub boot_time: i64,
+}
+
+pub fn read_uptime(reader: &mut ProcReader, now_ms: i64) -> Option<Uptime> {
+    // e.g. "12345.67 45678.90"; the second field is summed idle time
+    let seconds: f64 = reader.read("uptime")?.split_whitespace().next()?.parse().ok()?;
+    let boot_time = (now_ms - (seconds * 1000.0) as i64).div_euclid(1000) * 1000;
+    Some(Uptime { seconds, boot_time })
+}
+
+/// Share of time (%) tasks were stalled waiting on one resource, from
+/// /proc/pressure. "some" counts time at least one task waited, "full" time
+/// all of them did; CPU has no system-wide "full" line before Linux 5.13.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Pressure {
+    pub some_avg10: f64,
+    pub some_avg60: f64,
+    pub full_avg10: Option<f64>,
+    pub full_avg60: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PressureStats {
+    pub cpu: Option<Pressure>,
+    pub memory: Option<Pressure>,
+    pub io: Option<Pressure>,
+}
+
+fn read_pressure(reader: &mut ProcReader, resource: &str) -> Option<Pressure> {
+    // e.g. "some avg10=0.12 avg60=0.05 avg300=0.01 total=123456"
+    let averages = |line: &str| {
+        let field = |name: &str| line.split_whitespace().find_map(|f| f.strip_prefix(name)?.parse().ok());
+        Some((field("avg10=")?, field("avg60=")?))
+    };
+    let (mut some, mut full) = (None, None);
+    for line in reader.read(resource)?.lines() {
+        if let Some(rest) = line.strip_prefix("some ") {
+            some = averages(rest);
+        } else if let Some(rest) = line.strip_prefix("full ") {
+            full = averages(rest);
+        }
+    }
+    let (some_avg10, some_avg60) = some?;
+    Some(Pressure { some_avg10, some_avg60, full_avg10: full.map(|f| f.0), full_avg60: full.map(|f| f.1) })
+}
+
+/// Needs a kernel built with CONFIG_PSI and not booted with psi=0.
+pub fn read_pressure_stats(reader: &mut ProcReader) -> PressureStats {
+    PressureStats {
+        cpu: read_pressure(reader, "pressure/cpu"),
+        memory: read_pressure(reader, "pressure/memory"),
+        io: read_pressure(reader, "pressure/io"),
+    }
+}
+
+/// File handles allocated system-wide against the kernel's limit
+/// (fs.file-max), from /proc/sys/fs/file-nr. Once `allocated` reaches `max`
+/// every open() fails with ENFILE.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FileDescriptors {
+    pub allocated: u64,
+    pub max: u64,
+    pub used_percentage: f64,
+}
+
+pub fn read_file_descriptors(reader: &mut ProcReader) -> Option<FileDescriptors> {
+    // "allocated  free  max"; free has been 0 since Linux 2.6
+    let mut fields = reader.read("sys/fs/file-nr")?.split_whitespace().map(|v| v.parse::<u64>().ok());
+    let (allocated, max) = (fields.next()??, fields.nth(1)??);
+    let used_percentage = if max > 0 { allocated as f64 / max as f64 * 100.0 } else { 0.0 };
+    Some(FileDescriptors { allocated, max, used_percentage })
+}
+
+/// Open sockets by state, from /proc/net/sockstat and sockstat6. A count
+/// that only climbs points at a connection leak, many in TIME_WAIT at a
+/// client opening a connection per request.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SocketStats {
+    // IPv4 and IPv6 together
+    pub tcp_in_use: u64,
+    pub udp_in_use: u64,
+    // The kernel counts these across both families
+    pub tcp_time_wait: u64,
+    // Closed by their process but still holding buffers
+    pub tcp_orphaned: u64,
+}
+
+// The value after `key` on the line for `protocol`, e.g. ("TCP:", "tw")
+fn sockstat_field(content: &str, protocol: &str, key: &str) -> Option<u64> {
+    let line = content.lines().find(|line| line.starts_with(protocol))?;
+    let mut fields = line.split_whitespace().skip(1);
+    while let Some(name) = fields.next() {
+        let value = fields.next()?;
+        if name == key {
+            return value.parse().ok();
+        }
+    }
+    None
+}
+
+/// None when /proc/net/sockstat is unreadable; without IPv6 only IPv4
+/// sockets are counted.
+pub fn read_socket_stats(reader: &mut ProcReader) -> Option<SocketStats> {
+    let content = reader.read("net/sockstat")?;
+    let mut stats = SocketStats {
+        tcp_in_use: sockstat_field(content, "TCP:", "inuse")?,
+        udp_in_use: sockstat_field(content, "UDP:", "inuse")?,
+        tcp_time_wait: sockstat_field(content, "TCP:", "tw")?,
+        tcp_orphaned: sockstat_field(content, "TCP:", "orphan")?,
+    };
+    if let Some(content) = reader.read("net/sockstat6") {
+        stats.tcp_in_use += sockstat_field(content, "TCP6:", "inuse").unwrap_or(0);
+        stats.udp_in_use += sockstat_field(content, "UDP6:", "inuse").unwrap_or(0);
+    }
+    Some(stats)
+}
+
+/// Processes the kernel's OOM killer has killed since boot, from
+/// /proc/vmstat (Linux 4.13 and later).
+pub fn read_oom_kills(reader: &mut ProcReader) -> Option<u64> {
+    reader.read("vmstat")?.lines().find_map(|line| line.strip_prefix("oom_kill ")?.trim().parse().ok())
+}
+
+/// Traffic of one interface, as reported alongside the totals.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InterfaceStats {
+    // KB/s
+    pub download: f64,
+    pub upload: f64,
+    // Bytes since the interface came up, as the kernel counts them
+    pub rx_total: u64,
+    pub tx_total: u64,
+}
+
+#[derive(Default)]
+pub struct NetworkCollector {
+    reader: ProcReader,
+    prev_time: Option<Instant>,
+    // (name, rx bytes, tx bytes) per interface; the two are swapped each
+    // tick and overwritten in place so names are only allocated once
+    counters: Vec<(String, u64, u64)>,
+    prev_counters: Vec<(String, u64, u64)>,
+    interfaces: BTreeMap<String, InterfaceStats>,
+}
+
+fn rate(bytes: u64, prev_bytes: u64, secs: f64) -> f64 {
+    bytes.saturating_sub(prev_bytes) as f64 / 1024.0 / secs
+}
+
+impl NetworkCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// (download, upload) in KB/s across non-loopback interfaces since the
+    /// previous call. Per-interface rates are available from `interfaces`.
+    pub fn collect(&mut self) -> (f64, f64) {
+        let Some(content) = self.reader.read("net/dev") else {
+            self.interfaces.clear();
+            return (0.0, 0.0);
+        };
+        let mut count = 0;
+        // The two header lines have no ':'
+        for (name, values) in content.lines().filter_map(|line| line.split_once(':')) {
+            let name = name.trim();
+            if name == "lo" {
+                continue;
+            }
+            let mut values = values.split_whitespace();
+            let (Some(rx), Some(tx)) = (values.next(), values.nth(7)) else {
+                continue;
+            };
+            let (rx, tx) = (rx.parse::<u64>().unwrap_or(0), tx.parse::<u64>().unwrap_or(0));
+            match self.counters.get_mut(count) {
+                Some(entry) => {
+                    entry.0.clear();
+                    entry.0.push_str(name);
+                    (entry.1, entry.2) = (rx, tx);
+                }
+                None => self.counters.push((name.to_string(), rx, tx)),
+            }
+            count += 1;
+        }
+        self.counters.truncate(count);
+
+        let now = Instant::now();
+        let secs = self.prev_time.map_or(0.0, |t| now.duration_since(t).as_secs_f64());
+        self.interfaces.retain(|name, _| self.counters.iter().any(|c| c.0 == *name));
+        let (mut download, mut upload) = (0.0, 0.0);
+        for (name, rx, tx) in &self.counters {
+            // Interfaces that just appeared have no rate yet
+            let (rx_rate, tx_rate) = match self.prev_counters.iter().find(|p| p.0 == *name) {
+                Some((_, prev_rx, prev_tx)) if secs > 0.0 => (rate(*rx, *prev_rx, secs), rate(*tx, *prev_tx, secs)),
+                _ => (0.0, 0.0),
+            };
+            download += rx_rate;
+            upload += tx_rate;
+            let stats = InterfaceStats { download: rx_rate, upload: tx_rate, rx_total: *rx, tx_total: *tx };
+            match self.interfaces.get_mut(name) {
+                Some(entry) => *entry = stats,
+                None => {
+                    self.interfaces.insert(name.clone(), stats);
+                }
+            }
+        }
+        std::mem::swap(&mut self.counters, &mut self.prev_counters);
+        self.prev_time = Some(now);
+        (download, upload)
+    }
+
+    /// Per-interface traffic from the last `collect`, by interface name.
+    pub fn interfaces(&self) -> &BTreeMap<String, InterfaceStats> {
+        &self.interfaces
+    }
+}
+
+/// Throughput of one block device.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiskRates {
+    pub name: String,
+    // KB/s
+    pub read: f64,
+    pub write: f64,
+}
+
+// Whole disks only: partitions, device-mapper and md devices would count the
+// same I/O twice, and loop/ram devices aren't disks
+pub fn is_whole_disk(name: &str) -> bool {
+    const VIRTUAL: &[&str] = &["loop", "ram", "zram", "dm-", "md", "sr"];
+    if VIRTUAL.iter().any(|prefix| name.starts_with(prefix)) {
+        return false;
+    }
+    // nvme0n1p2, mmcblk0p1: a 'p' and digits after the device number
+    if name.starts_with("nvme") || name.starts_with("mmcblk") {
+        return !name.rsplit_once('p').is_some_and(|(head, part)| {
+            head.ends_with(|c: char| c.is_ascii_digit()) && !part.is_empty() && part.chars().all(|c| c.is_ascii_digit())
+        });
+    }
+    // sda1, vdb2, xvda1
+    !name.ends_with(|c: char| c.is_ascii_digit())
+}
+
+#[derive(Default)]
+pub struct DiskCollector {
+    reader: ProcReader,
+    prev_time: Option<Instant>,
+    // (name, sectors read, sectors written); swapped each tick like the
+    // network counters
+    counters: Vec<(String, u64, u64)>,
+    prev_counters: Vec<(String, u64, u64)>,
+    disks: Vec<DiskRates>,
+}
+
+impl DiskCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// (read, write) in KB/s across whole disks since the previous call.
+    /// Per-device rates are available from `disks`.
+    pub fn collect(&mut self) -> (f64, f64) {
+        let Some(content) = self.reader.read("diskstats") else {
+            self.disks.clear();
+            return (0.0, 0.0);
+        };
+        let mut count = 0;
+        for line in content.lines() {
+            // major minor name reads merged sectors_read ms writes merged sectors_written ...
+            let mut fields = line.split_whitespace().skip(2);
+            let Some(name) = fields.next().filter(|n| is_whole_disk(n)) else {
+                continue;
+            };
+            let (Some(read), Some(written)) = (fields.nth(2), fields.nth(3)) else {
+                continue;
+            };
+            let (read, written) = (read.parse::<u64>().unwrap_or(0), written.parse::<u64>().unwrap_or(0));
+            match self.counters.get_mut(count) {
+                Some(entry) => {
+                    entry.0.clear();
+                    entry.0.push_str(name);
+                    (entry.1, entry.2) = (read, written);
+                }
+                None => self.counters.push((name.to_string(), read, written)),
+            }
+            count += 1;
+        }
+        self.counters.truncate(count);
+
+        let now = Instant::now();
+        let secs = self.prev_time.map_or(0.0, |t| now.duration_since(t).as_secs_f64());
+        self.disks.truncate(self.counters.len());
+        let (mut total_read, mut total_write) = (0.0, 0.0);
+        for (i, (name, read, written)) in self.counters.iter().enumerate() {
+            // diskstats counts 512-byte sectors whatever the device's block size
+            let (read_rate, write_rate) = match self.prev_counters.iter().find(|p| p.0 == *name) {
+                Some((_, prev_read, prev_written)) if secs > 0.0 => {
+                    (rate(*read * 512, *prev_read * 512, secs), rate(*written * 512, *prev_written * 512, secs))
+                }
+                _ => (0.0, 0.0),
+            };
+            total_read += read_rate;
+            total_write += write_rate;
+            match self.disks.get_mut(i) {
+                Some(entry) => {
+                    entry.name.clone_from(name);
+                    (entry.read, entry.write) = (read_rate, write_rate);
+                }
+                None => self.disks.push(DiskRates { name: name.clone(), read: read_rate, write: write_rate }),
+            }
+        }
+        std::mem::swap(&mut self.counters, &mut self.prev_counters);
+        self.prev_time = Some(now);
+        (total_read, total_write)
+    }
+
+    /// Per-device rates from the last `collect`.
+    pub fn disks(&self) -> &[DiskRates] {
+        &self.disks
+    }
+}
+
+const HWMON_DIR: &str = "/sys/class/hwmon";
+// Drivers whose sensors are the CPU package, dies or cores
+const CPU_SENSORS: &[&str] = &["coretemp", "k10temp", "zenpower", "cpu_thermal", "soc_thermal"];
+
+// Drivers whose sensors are reported one by one under `temperatures`:
+// the CPU's, drives (NVMe, and SATA/SAS through drivetemp) and Intel
+// chipsets (pch_cannonlake, pch_skylake, ...)
+fn is_tracked(driver: &str) -> bool {
+    CPU_SENSORS.contains(&driver) || driver == "nvme" || driver == "drivetemp" || driver.starts_with("pch_")
+}
+
+// The disk a drivetemp sensor belongs to, e.g. "sda": its SCSI device has
+// the block device as a child
+fn drivetemp_disk(hwmon: &Path) -> Option<String> {
+    let entry = fs::read_dir(hwmon.join("device/block")).ok()?.flatten().next()?;
+    entry.file_name().into_string().ok()
+}
+
+/// Whether a whole disk (from /sys/block) already has a hwmon temperature
+/// sensor, drivetemp's or the NVMe driver's, so SMART needn't be asked.
+pub fn disk_has_hwmon(disk: &str) -> bool {
+    let device = Path::new("/sys/block").join(disk).join("device");
+    // drivetemp: <scsi device>/hwmon/hwmonN; NVMe: the controller, nvme0, holds hwmonN
+    device.join("hwmon").is_dir()
+        || fs::read_dir(&device)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .any(|e| e.file_name().to_str().is_some_and(|n| n.starts_with("hwmon")))
+}
+
+fn temp_inputs(hwmon: &Path) -> Vec<PathBuf> {
+    let mut inputs: Vec<PathBuf> = fs::read_dir(hwmon)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.file_name().and_then(|n| n.to_str()).is_some_and(|n| n.starts_with("temp") && n.ends_with("_input")))
+        .collect();
+    inputs.sort();
+    inputs
+}
+
+struct Sensor {
+    // "<driver>/<label>", e.g. "coretemp/Package id 0" or "nvme/Composite"
+    name: String,
+    input: PathBuf,
+    cpu: bool,
+    tracked: bool,
+}
+
+#[derive(Default)]
+pub struct TemperatureCollector {
+    // Found on the first call; hwmon devices don't come and go at runtime
+    sensors: Option<Vec<Sensor>>,
+    temperatures: BTreeMap<String, f64>,
+}
+
+impl TemperatureCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn discover() -> Vec<Sensor> {
+        let mut devices: Vec<PathBuf> = fs::read_dir(HWMON_DIR).into_iter().flatten().flatten().map(|e| e.path()).collect();
+        devices.sort();
+        let mut sensors: Vec<Sensor> = Vec::new();
+        for device in devices {
+            let driver = fs::read_to_string(device.join("name")).unwrap_or_default().trim().to_string();
+            for input in temp_inputs(&device) {
+                // temp1_input is labelled by temp1_label, when the driver provides one
+                let file = input.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+                let label = fs::read_to_string(input.with_file_name(file.replace("_input", "_label")))
+                    .map(|l| l.trim().to_string())
+                    .ok()
+                    // drivetemp has one unlabelled sensor per disk; naming it by
+                    // the disk tells several drives apart
+                    .or_else(|| (driver == "drivetemp").then(|| drivetemp_disk(&device)).flatten())
+                    .unwrap_or_else(|| file.trim_end_matches("_input").to_string());
+                let mut name = format!("{}/{}", driver, label);
+                // Two NVMe drives both report "nvme/Composite"
+                if sensors.iter().any(|s| s.name == name) {
+                    name = format!("{} ({})", name, device.file_name().and_then(|n| n.to_str()).unwrap_or_default());
+                }
+                sensors.push(Sensor { name, input, cpu: CPU_SENSORS.contains(&driver.as_str()), tracked: is_tracked(&driver) });
+            }
+        }
+        sensors
+    }
+
+    /// Hottest CPU sensor in °C, or the hottest sensor of any kind when no
+    /// CPU driver is loaded. None when nothing is readable. Also reads the
+    /// sensors kept in `temperatures`.
+    pub fn collect(&mut self) -> Option<f64> {
+        let sensors = self.sensors.get_or_insert_with(Self::discover);
+        let has_cpu = sensors.iter().any(|s| s.cpu);
+        let mut hottest: Option<f64> = None;
+        for sensor in sensors.iter() {
+            let Some(celsius) = fs::read_to_string(&sensor.input).ok().and_then(|t| t.trim().parse::<f64>().ok()).map(|m| m / 1000.0) else {
+                self.temperatures.remove(&sensor.name);
+                continue;
+            };
+            if sensor.cpu || !has_cpu {
+                hottest = Some(hottest.map_or(celsius, |h| h.max(celsius)));
+            }
+            // Updated in place so names are only allocated once
+            match self.temperatures.get_mut(&sensor.name) {
+                Some(value) => *value = celsius,
+                None if sensor.tracked => {
+                    self.temperatures.insert(sensor.name.clone(), celsius);
+                }
+                None => {}
+            }
+        }
+        hottest
+    }
+
+    /// °C per sensor from the last `collect`: CPU package and cores, drives
+    /// ("nvme/Composite", "drivetemp/sda") and the chipset, by sensor name.
+    pub fn temperatures(&self) -> &BTreeMap<String, f64> {
+        &self.temperatures
+    }
+}
+
+fn fan_inputs(hwmon: &Path) -> Vec<PathBuf> {
+    let mut inputs: Vec<PathBuf> = fs::read_dir(hwmon)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.file_name().and_then(|n| n.to_str()).is_some_and(|n| n.starts_with("fan") && n.ends_with("_input")))
+        .collect();
+    inputs.sort();
+    inputs
+}
+
+/// Whether any hwmon device reports a fan speed.
+pub fn has_fans() -> bool {
+    fs::read_dir(HWMON_DIR).into_iter().flatten().flatten().any(|e| !fan_inputs(&e.path()).is_empty())
+}
+
+struct Fan {
+    // "<driver>/<label>", e.g. "thinkpad/fan1"
+    name: String,
+    input: PathBuf,
+    // RPM; fanN_max when the driver reports it, otherwise the fastest seen
+    peak: f64,
+    rated: bool,
+}
+
+#[derive(Default)]
+pub struct FanCollector {
+    // Found on the first call, like the temperature sensors
+    fans: Option<Vec<Fan>>,
+    speeds: BTreeMap<String, f64>,
+}
+
+impl FanCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn discover() -> Vec<Fan> {
+        let mut devices: Vec<PathBuf> = fs::read_dir(HWMON_DIR).into_iter().flatten().flatten().map(|e| e.path()).collect();
+        devices.sort();
+        let mut fans = Vec::new();
+        for device in devices {
+            let driver = fs::read_to_string(device.join("name")).unwrap_or_default().trim().to_string();
+            for input in fan_inputs(&device) {
+                let file = input.file_name().and_then(|n| n.to_str()).unwrap_or_default().to_string();
+                let label = fs::read_to_string(input.with_file_name(file.replace("_input", "_label")))
+                    .map(|l| l.trim().to_string())
+                    .unwrap_or_else(|_| file.trim_end_matches("_input").to_string());
+                let max = fs::read_to_string(input.with_file_name(file.replace("_input", "_max"))).ok().and_then(|m| m.trim().parse::<f64>().ok()).filter(|m| *m > 0.0);
+                fans.push(Fan { name: format!("{}/{}", driver, label), input, peak: max.unwrap_or(0.0), rated: max.is_some() });
+            }
+        }
+        fans
+    }
+
+    /// RPM of the fastest fan, or None when no fan is readable. Per-fan
+    /// speeds are available from `speeds`.
+    pub fn collect(&mut self) -> Option<f64> {
+        let fans = self.fans.get_or_insert_with(Self::discover);
+        let mut fastest: Option<f64> = None;
+        for fan in fans.iter_mut() {
+            let Some(rpm) = fs::read_to_string(&fan.input).ok().and_then(|r| r.trim().parse::<f64>().ok()) else {
+                self.speeds.remove(&fan.name);
+                continue;
+            };
+            if !fan.rated {
+                fan.peak = fan.peak.max(rpm);
+            }
+            fastest = Some(fastest.map_or(rpm, |f| f.max(rpm)));
+            match self.speeds.get_mut(&fan.name) {
+                Some(speed) => *speed = rpm,
+                None => {
+                    self.speeds.insert(fan.name.clone(), rpm);
+                }
+            }
+        }
+        fastest
+    }
+
+    /// RPM per fan from the last `collect`, by fan name.
+    pub fn speeds(&self) -> &BTreeMap<String, f64> {
+        &self.speeds
+    }
+
+    /// How close the busiest fan is to its maximum, 0-1, from the last
+    /// `collect`. Fans without a rated maximum are measured against the
+    /// fastest they've been seen to spin.
+    pub fn duty(&self) -> Option<f64> {
+        self.fans
+            .iter()
+            .flatten()
+            .filter(|fan| fan.peak > 0.0)
+            .filter_map(|fan| Some(self.speeds.get(&fan.name)? / fan.peak))
+            .reduce(f64::max)
+    }
+}
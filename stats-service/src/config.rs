@@ -0,0 +1,204 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use serde::Deserialize;
+use crate::advisories::AdvisoryConfig;
+use crate::alerts::AlertRule;
+use crate::clock::MissedTickPolicy;
+use crate::filesystem::FilesystemConfig;
+use crate::http::HttpConfig;
+use crate::net_quality::ProbeConfig;
+use crate::privacy::PrivacyConfig;
+use crate::process::ProcessConfig;
+use crate::smart::SmartConfig;
+pub use crate::series::HistoryConfig;
+use crate::text_outputs::TextOutput;
+use crate::units::UnitsConfig;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ServiceConfig {
+    pub data_dir: String,
+    pub socket_path: String,
+    /// Long-lived data (pattern samples, the history saved at shutdown) lives
+    /// here rather than in `data_dir`.
+    pub persist_dir: String,
+    /// How many weeks of per-minute samples to keep for hour-of-day patterns.
+    pub pattern_weeks: u32,
+    /// Where to read procfs from; only worth changing for fixtures and chroots.
+    /// cgroup limits are read from the `sys` directory next to it.
+    pub proc_root: String,
+    pub socket: SocketConfig,
+    pub text_outputs: Vec<TextOutput>,
+    pub network_probe: ProbeConfig,
+    pub privacy: PrivacyConfig,
+    /// `annotate` (default), `backfill` or `ignore`.
+    pub missed_ticks: MissedTickPolicy,
+    pub metrics: MetricFilter,
+    /// `[[alerts]]` threshold, rate and trend rules; each one raises a desktop
+    /// notification.
+    pub alerts: Vec<AlertRule>,
+    /// The graph window an alert's `open` starts. Absolute, because a user
+    /// unit's PATH usually leaves out ~/.local/bin.
+    pub graph_window: String,
+    /// Fan-curve advisories, on by default; see [`AdvisoryConfig`].
+    pub advisories: AdvisoryConfig,
+    pub history: HistoryConfig,
+    pub filesystems: FilesystemConfig,
+    pub processes: ProcessConfig,
+    /// Off by default; see [`SmartConfig`].
+    pub smart: SmartConfig,
+    /// Off by default; see [`HttpConfig`].
+    pub http: HttpConfig,
+    /// KB/s unless set otherwise; see [`UnitsConfig`].
+    pub units: UnitsConfig,
+}
+
+/// Optional metrics to collect and serve. Names are those in [`METRICS`].
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct MetricFilter {
+    /// When non-empty, only these optional metrics are collected.
+    pub include: Vec<String>,
+    /// Never collected, even if listed in `include`.
+    pub exclude: Vec<String>,
+}
+
+/// Metrics that can be turned off. CPU, memory and network totals are always
+/// collected since every widget needs them.
+pub const METRICS: &[&str] = &[
+    "cpu_cores",
+    "cpu_frequency",
+    "memory_breakdown",
+    "process_io",
+    "energy",
+    "network_quality",
+    "network_interfaces",
+    "apps",
+    "processes",
+    "filesystems",
+    "temperature",
+    "fans",
+    "gpu",
+    "battery",
+    "pressure",
+    "sockets",
+    "wireless",
+    "nvme",
+    "numa",
+    "containers",
+    "slices",
+    "irqs",
+    "file_descriptors",
+    "disk_io",
+];
+
+impl MetricFilter {
+    pub fn enabled(&self, metric: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|m| m == metric))
+            && !self.exclude.iter().any(|m| m == metric)
+    }
+
+    /// Names in the filter that don't match any metric, usually typos.
+    pub fn unknown(&self) -> Vec<&str> {
+        self.include
+            .iter()
+            .chain(&self.exclude)
+            .map(String::as_str)
+            .filter(|m| !METRICS.contains(m))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SocketConfig {
+    /// Explicit permission bits for the socket file, e.g. `0o660`.
+    pub mode: Option<u32>,
+    /// Groups whose members may read stats but not issue control commands.
+    /// Supplementary groups count, as listed for the client's user in the
+    /// group database.
+    pub read_only_gids: Vec<u32>,
+    /// A client whose write doesn't complete within this is disconnected.
+    pub write_timeout_ms: u64,
+    /// Updates buffered per subscriber; one that falls further behind is
+    /// disconnected rather than buffered without bound.
+    pub subscriber_queue: usize,
+}
+
+impl Default for SocketConfig {
+    fn default() -> Self {
+        SocketConfig {
+            mode: None,
+            read_only_gids: Vec::new(),
+            write_timeout_ms: 2000,
+            subscriber_queue: 16,
+        }
+    }
+}
+
+impl Default for ServiceConfig {
+    fn default() -> Self {
+        ServiceConfig {
+            data_dir: crate::DATA_DIR.to_string(),
+            socket_path: crate::SOCKET_PATH.to_string(),
+            persist_dir: data_home().join("ags-stats").to_string_lossy().into_owned(),
+            pattern_weeks: 4,
+            proc_root: "/proc".to_string(),
+            socket: SocketConfig::default(),
+            text_outputs: Vec::new(),
+            network_probe: ProbeConfig::default(),
+            privacy: PrivacyConfig::default(),
+            missed_ticks: MissedTickPolicy::default(),
+            metrics: MetricFilter::default(),
+            alerts: Vec::new(),
+            graph_window: PathBuf::from(env::var("HOME").unwrap_or_default()).join(".local/bin/graph-window").to_string_lossy().into_owned(),
+            advisories: AdvisoryConfig::default(),
+            history: HistoryConfig::default(),
+            filesystems: FilesystemConfig::default(),
+            processes: ProcessConfig::default(),
+            smart: SmartConfig::default(),
+            http: HttpConfig::default(),
+            units: UnitsConfig::default(),
+        }
+    }
+}
+
+impl SocketConfig {
+    /// Owner-only unless read-only groups are configured, in which case the
+    /// group needs connect (write) permission too.
+    pub fn effective_mode(&self) -> u32 {
+        self.mode.unwrap_or(if self.read_only_gids.is_empty() { 0o600 } else { 0o660 })
+    }
+}
+
+pub fn config_home() -> PathBuf {
+    env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .unwrap_or_else(|| PathBuf::from(env::var("HOME").unwrap_or_default()).join(".config"))
+}
+
+pub fn data_home() -> PathBuf {
+    env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .unwrap_or_else(|| PathBuf::from(env::var("HOME").unwrap_or_default()).join(".local/share"))
+}
+
+pub fn default_config_path() -> PathBuf {
+    config_home().join("ags-stats").join("config.toml")
+}
+
+impl ServiceConfig {
+    /// Loads the config from `path` (or the default location). A missing file
+    /// yields the defaults; a malformed one is an error.
+    pub fn try_load(path: Option<PathBuf>) -> Result<Self, String> {
+        let path = path.unwrap_or_else(default_config_path);
+        match fs::read_to_string(&path) {
+            Ok(content) => toml::from_str(&content)
+                .map_err(|e| format!("Failed to parse config {}: {}", path.display(), e)),
+            Err(_) => Ok(ServiceConfig::default()),
+        }
+    }
+}
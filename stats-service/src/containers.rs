@@ -0,0 +1,297 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::UnixStream;
+use tokio::time;
+use crate::collectors::ProcReader;
+use crate::units;
+
+const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+
+// Deep enough for rootless podman under user@.service, which is the deepest
+// of the usual layouts
+const MAX_DEPTH: usize = 8;
+
+// Asked off the tick, so a runtime that's slow to answer only delays names
+const API_TIMEOUT: Duration = Duration::from_secs(2);
+// How often containers the runtimes didn't name are asked about again
+const API_RETRY: Duration = Duration::from_secs(10);
+
+/// Whether containers can be found at all: only the unified (v2) hierarchy
+/// is read.
+pub fn available() -> bool {
+    Path::new(CGROUP_ROOT).join("cgroup.controllers").exists()
+}
+
+/// One running container, from its cgroup.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContainerStats {
+    // The first 12 characters of the container ID, as `docker ps` shows it
+    pub id: String,
+    // "docker", "podman" or "containerd"
+    pub runtime: String,
+    // As `docker ps` shows it, from the runtime's API socket when the user
+    // can read it; otherwise the command name of the container's first process
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    // Percent of total machine CPU capacity
+    pub cpu: f64,
+    // KB
+    pub memory: f64,
+    // KB/s through the io controller; 0 when it isn't enabled for the cgroup
+    pub disk_read: f64,
+    pub disk_write: f64,
+    // In the configured network unit; None for containers on the host's
+    // network, whose traffic is already in the totals
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network_download: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network_upload: Option<f64>,
+}
+
+/// (runtime, full ID) for a cgroup directory that holds a container:
+/// `docker-<id>.scope` and friends under the systemd driver, `docker/<id>`
+/// under cgroupfs.
+pub fn container_id(parent: &str, name: &str) -> Option<(&'static str, String)> {
+    let is_id = |id: &str| id.len() == 64 && id.bytes().all(|b| b.is_ascii_hexdigit());
+    let scoped = |prefix: &str| name.strip_prefix(prefix)?.strip_suffix(".scope").filter(|id| is_id(id));
+    if let Some(id) = scoped("docker-") {
+        return Some(("docker", id.to_string()));
+    }
+    // libpod-conmon-<id>.scope is podman's monitor process, not the container
+    if let Some(id) = scoped("libpod-") {
+        return Some(("podman", id.to_string()));
+    }
+    if let Some(id) = scoped("cri-containerd-") {
+        return Some(("containerd", id.to_string()));
+    }
+    (parent == "docker" && is_id(name)).then(|| ("docker", name.to_string()))
+}
+
+fn find_containers(dir: &Path, depth: usize, found: &mut Vec<(&'static str, String, PathBuf)>) {
+    if depth > MAX_DEPTH {
+        return;
+    }
+    let parent = dir.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+    for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+        if !entry.file_type().is_ok_and(|t| t.is_dir()) {
+            continue;
+        }
+        let name = entry.file_name();
+        let Some(name) = name.to_str() else {
+            continue;
+        };
+        match container_id(parent, name) {
+            // A container's own sub-cgroups belong to it
+            Some((runtime, id)) => found.push((runtime, id, entry.path())),
+            None => find_containers(&entry.path(), depth + 1, found),
+        }
+    }
+}
+
+// A process in the container, which may sit in a sub-cgroup (podman puts
+// systemd containers' processes one level down)
+fn first_pid(cgroup: &Path) -> Option<u32> {
+    let own = fs::read_to_string(cgroup.join("cgroup.procs")).ok().and_then(|procs| procs.lines().next()?.parse().ok());
+    own.or_else(|| fs::read_dir(cgroup).ok()?.flatten().filter(|e| e.path().is_dir()).find_map(|e| first_pid(&e.path())))
+}
+
+fn cgroup_value(cgroup: &Path, file: &str, key: &str) -> Option<u64> {
+    let content = fs::read_to_string(cgroup.join(file)).ok()?;
+    content.lines().find_map(|line| line.strip_prefix(key)?.strip_prefix(' ')?.parse().ok())
+}
+
+// (read, written) bytes summed over every device in io.stat
+fn io_bytes(cgroup: &Path) -> (u64, u64) {
+    let content = fs::read_to_string(cgroup.join("io.stat")).unwrap_or_default();
+    let mut totals = (0, 0);
+    for field in content.split_whitespace() {
+        if let Some(bytes) = field.strip_prefix("rbytes=").and_then(|v| v.parse::<u64>().ok()) {
+            totals.0 += bytes;
+        } else if let Some(bytes) = field.strip_prefix("wbytes=").and_then(|v| v.parse::<u64>().ok()) {
+            totals.1 += bytes;
+        }
+    }
+    totals
+}
+
+// Interface names and (rx, tx) bytes summed over every interface but lo, from
+// a net/dev file. Inside the container's namespace these are its veth end's
+// counters, which the host side sees with rx and tx swapped.
+fn net_dev(content: &str) -> (Vec<&str>, u64, u64) {
+    let (mut names, mut rx, mut tx) = (Vec::new(), 0, 0);
+    for (name, values) in content.lines().filter_map(|line| line.split_once(':')) {
+        let name = name.trim();
+        if name == "lo" {
+            continue;
+        }
+        let mut values = values.split_whitespace();
+        rx += values.next().and_then(|v| v.parse::<u64>().ok()).unwrap_or(0);
+        tx += values.nth(7).and_then(|v| v.parse::<u64>().ok()).unwrap_or(0);
+        names.push(name);
+    }
+    names.sort_unstable();
+    (names, rx, tx)
+}
+
+// Sockets that answer Docker's `GET /containers/json`: rootful and rootless
+// Docker, and Podman's compatible API
+fn api_sockets() -> Vec<PathBuf> {
+    let mut sockets = vec![PathBuf::from("/var/run/docker.sock"), PathBuf::from("/run/podman/podman.sock")];
+    if let Some(runtime_dir) = std::env::var_os("XDG_RUNTIME_DIR") {
+        let runtime_dir = Path::new(&runtime_dir);
+        sockets.push(runtime_dir.join("docker.sock"));
+        sockets.push(runtime_dir.join("podman/podman.sock"));
+    }
+    sockets
+}
+
+/// (name, image) by full container ID from a `GET /containers/json` reply,
+/// headers included; empty for anything that isn't one.
+pub fn parse_container_list(reply: &str) -> HashMap<String, (String, String)> {
+    let body = reply.split_once("\r\n\r\n").map_or(reply, |(_, body)| body);
+    let Ok(list) = serde_json::from_str::<Vec<serde_json::Value>>(body) else {
+        return HashMap::new();
+    };
+    list.iter()
+        .filter_map(|container| {
+            let id = container["Id"].as_str()?;
+            let name = container["Names"][0].as_str()?.trim_start_matches('/');
+            Some((id.to_string(), (name.to_string(), container["Image"].as_str().unwrap_or_default().to_string())))
+        })
+        .collect()
+}
+
+// Empty when the socket isn't there or the user isn't allowed on it
+async fn query_api(socket: &Path) -> HashMap<String, (String, String)> {
+    let request = async {
+        let mut stream = UnixStream::connect(socket).await?;
+        // HTTP/1.0, so the reply is neither chunked nor kept alive
+        stream.write_all(b"GET /containers/json HTTP/1.0\r\nHost: localhost\r\n\r\n").await?;
+        let mut reply = String::new();
+        stream.read_to_string(&mut reply).await?;
+        Ok::<_, std::io::Error>(reply)
+    };
+    match time::timeout(API_TIMEOUT, request).await {
+        Ok(Ok(reply)) => parse_container_list(&reply),
+        _ => HashMap::new(),
+    }
+}
+
+/// What the runtimes' API sockets said about the containers, filled in by
+/// [`run_names`] for the collector to read on the tick.
+#[derive(Default)]
+pub struct ContainerNames {
+    // (name, image) by full container ID
+    by_id: HashMap<String, (String, String)>,
+    // Set by the collector while it sees containers the runtimes haven't named
+    wanted: bool,
+}
+
+/// Asks the runtimes for container names whenever the collector has found
+/// one they haven't named, at most every `API_RETRY`.
+pub async fn run_names(names: Arc<Mutex<ContainerNames>>) {
+    let mut interval = time::interval(API_RETRY);
+    loop {
+        interval.tick().await;
+        if !names.lock().unwrap().wanted {
+            continue;
+        }
+        let mut found = HashMap::new();
+        for socket in api_sockets() {
+            found.extend(query_api(&socket).await);
+        }
+        let mut names = names.lock().unwrap();
+        names.by_id = found;
+        names.wanted = false;
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Counters {
+    cpu_usec: u64,
+    read: u64,
+    written: u64,
+    // None on the host's network
+    network: Option<(u64, u64)>,
+    at: Instant,
+}
+
+/// Finds running containers each tick and rates their cgroup counters.
+#[derive(Default)]
+pub struct ContainerCollector {
+    reader: ProcReader,
+    // By full container ID; containers that stopped are dropped each tick
+    prev: HashMap<String, Counters>,
+}
+
+impl ContainerCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every running container, heaviest CPU first. Rates are zero on a
+    /// container's first tick. Names come from `names` where the runtimes
+    /// have given them.
+    pub fn collect(&mut self, names: &Mutex<ContainerNames>) -> Vec<ContainerStats> {
+        let mut found = Vec::new();
+        find_containers(Path::new(CGROUP_ROOT), 0, &mut found);
+        let host_interfaces: Vec<String> =
+            self.reader.read("net/dev").map(|content| net_dev(content).0.into_iter().map(str::to_string).collect()).unwrap_or_default();
+        let cpus = std::thread::available_parallelism().map_or(1, |n| n.get()) as f64;
+        let network_units = units::get();
+        let mut names = names.lock().unwrap();
+        // Only new containers send the runtimes another request
+        names.wanted |= found.iter().any(|(_, id, _)| !names.by_id.contains_key(id));
+
+        let mut prev = std::mem::take(&mut self.prev);
+        let mut containers = Vec::new();
+        for (runtime, id, cgroup) in found {
+            let pid = first_pid(&cgroup);
+            let name = pid.and_then(|pid| self.reader.read_pid(pid, "comm")).map(|comm| comm.trim().to_string());
+            // Same interfaces as the host: --network=host
+            let network = pid.and_then(|pid| {
+                let (names, rx, tx) = net_dev(self.reader.read_pid(pid, "net/dev")?);
+                (names != host_interfaces).then_some((rx, tx))
+            });
+            let (read, written) = io_bytes(&cgroup);
+            let now = Counters { cpu_usec: cgroup_value(&cgroup, "cpu.stat", "usage_usec").unwrap_or(0), read, written, network, at: Instant::now() };
+
+            let named = names.by_id.get(&id);
+            let mut stats = ContainerStats {
+                id: id[..12].to_string(),
+                runtime: runtime.to_string(),
+                name: named.map(|(name, _)| name.clone()).or(name).unwrap_or_else(|| id[..12].to_string()),
+                image: named.map(|(_, image)| image.clone()).filter(|image| !image.is_empty()),
+                cpu: 0.0,
+                memory: fs::read_to_string(cgroup.join("memory.current")).ok().and_then(|v| v.trim().parse::<f64>().ok()).map_or(0.0, |bytes| bytes / 1024.0),
+                disk_read: 0.0,
+                disk_write: 0.0,
+                network_download: network.map(|_| 0.0),
+                network_upload: network.map(|_| 0.0),
+            };
+            if let Some(before) = prev.remove(&id) {
+                let secs = now.at.duration_since(before.at).as_secs_f64();
+                if secs > 0.0 {
+                    let kb_per_sec = |now: u64, before: u64| now.saturating_sub(before) as f64 / 1024.0 / secs;
+                    stats.cpu = (now.cpu_usec.saturating_sub(before.cpu_usec) as f64 / 1e6 / secs / cpus * 100.0).min(100.0);
+                    stats.disk_read = kb_per_sec(now.read, before.read);
+                    stats.disk_write = kb_per_sec(now.written, before.written);
+                    if let (Some((rx, tx)), Some((prev_rx, prev_tx))) = (now.network, before.network) {
+                        stats.network_download = Some(network_units.network_rate(kb_per_sec(rx, prev_rx)));
+                        stats.network_upload = Some(network_units.network_rate(kb_per_sec(tx, prev_tx)));
+                    }
+                }
+            }
+            self.prev.insert(id, now);
+            containers.push(stats);
+        }
+        containers.sort_by(|a, b| b.cpu.total_cmp(&a.cpu).then(b.memory.total_cmp(&a.memory)));
+        containers
+    }
+}
@@ -0,0 +1,78 @@
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Signals are broadcast on the session bus from this object, so desktop
+/// components can follow them with a plain D-Bus subscription, e.g.
+/// `Gio.DBus.session.signal_subscribe(null, "org.ags.Stats", "AlertRaised", ...)`.
+pub const OBJECT_PATH: &str = "/org/ags/Stats";
+pub const INTERFACE: &str = "org.ags.Stats";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Signal<'a> {
+    /// (name, metric, value, since ms)
+    AlertRaised { name: &'a str, metric: &'a str, value: f64, since: i64 },
+    /// (name, metric, at ms)
+    AlertCleared { name: &'a str, metric: &'a str, at: i64 },
+    /// (last sample before, first sample after, ms suspended)
+    SuspendGap { start: i64, end: i64, suspended_ms: i64 },
+    /// (processes killed since the last tick, total since boot)
+    OomKill { killed: u64, total: u64 },
+    /// (kind, e.g. "fan_curve", message, since ms)
+    Advisory { kind: &'a str, message: &'a str, since: i64 },
+}
+
+impl Signal<'_> {
+    fn member(&self) -> &'static str {
+        match self {
+            Signal::AlertRaised { .. } => "AlertRaised",
+            Signal::AlertCleared { .. } => "AlertCleared",
+            Signal::SuspendGap { .. } => "SuspendGap",
+            Signal::OomKill { .. } => "OomKill",
+            Signal::Advisory { .. } => "Advisory",
+        }
+    }
+
+    // Arguments in dbus-send's type:value form
+    fn args(&self) -> Vec<String> {
+        match self {
+            Signal::AlertRaised { name, metric, value, since } => {
+                vec![format!("string:{}", name), format!("string:{}", metric), format!("double:{}", value), format!("int64:{}", since)]
+            }
+            Signal::AlertCleared { name, metric, at } => {
+                vec![format!("string:{}", name), format!("string:{}", metric), format!("int64:{}", at)]
+            }
+            Signal::SuspendGap { start, end, suspended_ms } => {
+                vec![format!("int64:{}", start), format!("int64:{}", end), format!("int64:{}", suspended_ms)]
+            }
+            Signal::OomKill { killed, total } => vec![format!("uint64:{}", killed), format!("uint64:{}", total)],
+            Signal::Advisory { kind, message, since } => {
+                vec![format!("string:{}", kind), format!("string:{}", message), format!("int64:{}", since)]
+            }
+        }
+    }
+}
+
+// Logged once; a machine without dbus-send would otherwise complain on every event
+static UNAVAILABLE: AtomicBool = AtomicBool::new(false);
+
+/// Broadcasts a signal with `dbus-send`. Nothing listens for a reply, so
+/// this never blocks the collection loop.
+pub fn emit(signal: &Signal) {
+    if UNAVAILABLE.load(Ordering::Relaxed) {
+        return;
+    }
+    let result = tokio::process::Command::new("dbus-send")
+        .arg("--session")
+        .arg("--type=signal")
+        .arg(OBJECT_PATH)
+        .arg(format!("{}.{}", INTERFACE, signal.member()))
+        .args(signal.args())
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    if let Err(e) = result {
+        eprintln!("Not emitting D-Bus signals: dbus-send failed: {}", e);
+        UNAVAILABLE.store(true, Ordering::Relaxed);
+    }
+}
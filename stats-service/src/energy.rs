@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use serde::{Deserialize, Serialize};
+use crate::collectors::ProcReader;
+use crate::process::{self, TOP_PROCESS_COUNT};
+
+const POWERCAP_DIR: &str = "/sys/class/powercap";
+const POWER_SUPPLY_DIR: &str = "/sys/class/power_supply";
+
+/// Estimated power draw of one process: its share of the CPU time spent by
+/// all processes since the last tick, applied to the measured power.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessPower {
+    pub pid: u32,
+    pub name: String,
+    // Percent of total machine CPU capacity
+    pub cpu_share: f64,
+    pub watts: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EnergyReport {
+    // CPU package power from RAPL
+    pub package_watts: Option<f64>,
+    // Whole-system draw while discharging
+    pub battery_watts: Option<f64>,
+    pub on_battery: bool,
+    pub processes: Vec<ProcessPower>,
+}
+
+impl EnergyReport {
+    /// The figure to graph: what the battery reports while discharging,
+    /// otherwise the CPU package.
+    pub fn system_watts(&self) -> Option<f64> {
+        if self.on_battery {
+            self.battery_watts.or(self.package_watts)
+        } else {
+            self.package_watts
+        }
+    }
+}
+
+// Top-level RAPL zones only (intel-rapl:0, not intel-rapl:0:0), so subzones
+// aren't counted twice. energy_uj is root-only on most kernels since 5.10.
+fn rapl_zones() -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(POWERCAP_DIR) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter(|e| e.file_name().to_str().is_some_and(|n| n.matches(':').count() == 1))
+        .map(|e| e.path())
+        .filter(|p| fs::read_to_string(p.join("energy_uj")).is_ok())
+        .collect()
+}
+
+fn read_u64(path: impl AsRef<Path>) -> Option<u64> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+fn batteries() -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(POWER_SUPPLY_DIR) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| fs::read_to_string(p.join("type")).is_ok_and(|t| t.trim() == "Battery"))
+        .collect()
+}
+
+// Some batteries report power_now (µW), others only current_now (µA) and voltage_now (µV)
+fn read_battery(path: &Path) -> Option<(bool, f64)> {
+    let status = fs::read_to_string(path.join("status")).ok()?;
+    let watts = match read_u64(path.join("power_now")) {
+        Some(uw) => uw as f64 / 1e6,
+        None => read_u64(path.join("current_now"))? as f64 * read_u64(path.join("voltage_now"))? as f64 / 1e12,
+    };
+    Some((status.trim() == "Discharging", watts))
+}
+
+/// The kernel's status string of the first battery, e.g. "Charging",
+/// "Discharging", "Full" or "Not charging".
+pub fn battery_status() -> Option<String> {
+    let mut batteries = batteries();
+    batteries.sort();
+    batteries.iter().find_map(|b| Some(fs::read_to_string(b.join("status")).ok()?.trim().to_string()))
+}
+
+pub fn available() -> bool {
+    !rapl_zones().is_empty() || !batteries().is_empty()
+}
+
+#[derive(Default)]
+pub struct EnergyCollector {
+    // (energy_uj, max_energy_range_uj) per zone
+    prev_rapl: Option<(Vec<(u64, u64)>, Instant)>,
+    reader: ProcReader,
+    pids: Vec<u32>,
+    prev_ticks: HashMap<u32, u64>,
+    // Swapped with `prev_ticks` each tick so neither map is reallocated
+    current: HashMap<u32, u64>,
+    prev_total_ticks: Option<u64>,
+}
+
+impl EnergyCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn package_watts(&mut self) -> Option<f64> {
+        let zones: Vec<(u64, u64)> = rapl_zones()
+            .iter()
+            .filter_map(|z| Some((read_u64(z.join("energy_uj"))?, read_u64(z.join("max_energy_range_uj")).unwrap_or(u64::MAX))))
+            .collect();
+        let now = Instant::now();
+        let watts = match &self.prev_rapl {
+            Some((prev, prev_time)) if prev.len() == zones.len() && !zones.is_empty() => {
+                let elapsed = now.duration_since(*prev_time).as_secs_f64();
+                // The counter wraps at max_energy_range_uj
+                let joules: f64 = zones
+                    .iter()
+                    .zip(prev)
+                    .map(|((uj, range), (prev_uj, _))| {
+                        if uj >= prev_uj { uj - prev_uj } else { range - prev_uj + uj }
+                    } as f64 / 1e6)
+                    .sum();
+                (elapsed > 0.0).then(|| joules / elapsed)
+            }
+            _ => None,
+        };
+        self.prev_rapl = Some((zones, now));
+        watts
+    }
+
+    /// Measures power and attributes it to the processes that used the most
+    /// CPU since the previous call. The first call only primes the counters.
+    pub fn collect(&mut self) -> EnergyReport {
+        let package_watts = self.package_watts();
+        let (mut on_battery, mut battery_watts) = (false, None);
+        for (discharging, watts) in batteries().iter().filter_map(|b| read_battery(b)) {
+            on_battery |= discharging;
+            if discharging {
+                *battery_watts.get_or_insert(0.0) += watts;
+            }
+        }
+        let mut report = EnergyReport { package_watts, battery_watts, on_battery, processes: Vec::new() };
+
+        let total_ticks = process::read_total_cpu_ticks(&mut self.reader);
+        let total_delta = match (total_ticks, self.prev_total_ticks) {
+            (Some(now), Some(prev)) => now.saturating_sub(prev),
+            _ => 0,
+        };
+        self.prev_total_ticks = total_ticks;
+
+        let mut busy: Vec<(u32, u64)> = Vec::new();
+        self.current.clear();
+        process::list_pids_into(&mut self.pids);
+        for &pid in &self.pids {
+            let Some(ticks) = process::read_process_cpu_ticks(&mut self.reader, pid) else {
+                continue;
+            };
+            self.current.insert(pid, ticks);
+            if let Some(&prev) = self.prev_ticks.get(&pid) {
+                let delta = ticks.saturating_sub(prev);
+                if delta > 0 {
+                    busy.push((pid, delta));
+                }
+            }
+        }
+        std::mem::swap(&mut self.prev_ticks, &mut self.current);
+
+        if total_delta == 0 {
+            return report;
+        }
+        let process_ticks: u64 = busy.iter().map(|b| b.1).sum();
+        let watts = report.package_watts.or(report.battery_watts);
+        busy.sort_by_key(|b| std::cmp::Reverse(b.1));
+        busy.truncate(TOP_PROCESS_COUNT);
+        report.processes = busy
+            .into_iter()
+            .map(|(pid, delta)| ProcessPower {
+                pid,
+                name: process::read_process_name(&mut self.reader, pid),
+                cpu_share: delta as f64 / total_delta as f64 * 100.0,
+                watts: watts.map(|w| w * delta as f64 / process_ticks.max(1) as f64),
+            })
+            .collect();
+        report
+    }
+}
@@ -0,0 +1,317 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use serde::Serialize;
+use crate::config::MetricFilter;
+use crate::{CpuStats, MemoryStats};
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case", tag = "kind", content = "name")]
+pub enum RuntimeKind {
+    Host,
+    Container(String),
+    VirtualMachine(String),
+}
+
+/// Which optional collectors make sense in the detected environment.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct EnabledCollectors {
+    pub per_core: bool,
+    pub cpu_frequency: bool,
+    pub process_io: bool,
+    pub hwmon: bool,
+    pub energy: bool,
+    pub apps: bool,
+    pub processes: bool,
+    pub memory_breakdown: bool,
+    pub network_quality: bool,
+    pub network_interfaces: bool,
+    pub filesystems: bool,
+    pub disk_io: bool,
+    pub fans: bool,
+    pub gpu: bool,
+    pub battery: bool,
+    pub pressure: bool,
+    pub sockets: bool,
+    pub wireless: bool,
+    pub nvme: bool,
+    pub numa: bool,
+    pub containers: bool,
+    pub slices: bool,
+    pub irqs: bool,
+    pub file_descriptors: bool,
+}
+
+impl EnabledCollectors {
+    /// Turns off whatever the config's metric filter excludes.
+    pub fn restrict(&mut self, filter: &MetricFilter) {
+        self.per_core &= filter.enabled("cpu_cores");
+        self.cpu_frequency &= filter.enabled("cpu_frequency");
+        self.memory_breakdown &= filter.enabled("memory_breakdown");
+        self.process_io &= filter.enabled("process_io");
+        self.energy &= filter.enabled("energy");
+        self.network_quality &= filter.enabled("network_quality");
+        self.apps &= filter.enabled("apps");
+        self.processes &= filter.enabled("processes");
+        self.network_interfaces &= filter.enabled("network_interfaces");
+        self.filesystems &= filter.enabled("filesystems");
+        self.hwmon &= filter.enabled("temperature");
+        self.disk_io &= filter.enabled("disk_io");
+        self.fans &= filter.enabled("fans");
+        self.gpu &= filter.enabled("gpu");
+        self.battery &= filter.enabled("battery");
+        self.pressure &= filter.enabled("pressure");
+        self.sockets &= filter.enabled("sockets");
+        self.wireless &= filter.enabled("wireless");
+        self.nvme &= filter.enabled("nvme");
+        self.numa &= filter.enabled("numa");
+        self.containers &= filter.enabled("containers");
+        self.slices &= filter.enabled("slices");
+        self.irqs &= filter.enabled("irqs");
+        self.file_descriptors &= filter.enabled("file_descriptors");
+    }
+}
+
+/// cgroup limits of the service's own cgroup, if any are set.
+#[derive(Debug, Clone, Serialize)]
+pub struct CgroupLimits {
+    // Number of CPUs worth of quota
+    pub cpu_quota: Option<f64>,
+    // KB, matching /proc/meminfo units
+    pub memory_limit: Option<f64>,
+    #[serde(skip)]
+    cpu_usage_path: Option<PathBuf>,
+    #[serde(skip)]
+    cpu_usage_unit_ns: f64,
+    #[serde(skip)]
+    memory_usage_path: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Environment {
+    pub runtime: RuntimeKind,
+    pub collectors: EnabledCollectors,
+    pub cgroup: Option<CgroupLimits>,
+}
+
+fn read_trimmed(path: impl AsRef<Path>) -> Option<String> {
+    fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+}
+
+fn detect_container() -> Option<String> {
+    if let Ok(name) = env::var("container") {
+        return Some(name);
+    }
+    if Path::new("/.dockerenv").exists() {
+        return Some("docker".to_string());
+    }
+    if Path::new("/run/.containerenv").exists() {
+        return Some("podman".to_string());
+    }
+    let cgroup = fs::read_to_string(crate::proc_path("1/cgroup")).unwrap_or_default();
+    ["docker", "kubepods", "lxc", "libpod"]
+        .iter()
+        .find(|marker| cgroup.contains(*marker))
+        .map(|marker| marker.to_string())
+}
+
+fn detect_vm() -> Option<String> {
+    let vendor = read_trimmed("/sys/class/dmi/id/sys_vendor").unwrap_or_default();
+    let product = read_trimmed("/sys/class/dmi/id/product_name").unwrap_or_default();
+    for known in ["QEMU", "KVM", "VirtualBox", "VMware", "Xen", "Microsoft Corporation", "Parallels"] {
+        if vendor.contains(known) || product.contains(known) {
+            return Some(known.to_string());
+        }
+    }
+    let cpuinfo = fs::read_to_string(crate::proc_path("cpuinfo")).unwrap_or_default();
+    if cpuinfo.lines().any(|l| l.starts_with("flags") && l.split_whitespace().any(|f| f == "hypervisor")) {
+        return Some("unknown".to_string());
+    }
+    None
+}
+
+fn has_entries(path: &str) -> bool {
+    fs::read_dir(path).map(|mut d| d.next().is_some()).unwrap_or(false)
+}
+
+// Inside a cgroup namespace the service sees its own cgroup as the root.
+fn at_namespace_root() -> bool {
+    fs::read_to_string(crate::proc_path("self/cgroup")).is_ok_and(|c| c.lines().any(|l| l == "0::/"))
+}
+
+// Supports the unified (v2) hierarchy and the common v1 layouts.
+fn detect_cgroup_limits() -> Option<CgroupLimits> {
+    let self_cgroup = fs::read_to_string(crate::proc_path("self/cgroup")).unwrap_or_default();
+    let mut limits = CgroupLimits {
+        cpu_quota: None,
+        memory_limit: None,
+        cpu_usage_path: None,
+        cpu_usage_unit_ns: 1000.0,
+        memory_usage_path: None,
+    };
+
+    if let Some(rel) = self_cgroup.lines().find_map(|l| l.strip_prefix("0::")) {
+        let base = crate::sys_path("fs/cgroup").join(rel.trim_start_matches('/'));
+        if base.join("cpu.max").exists() {
+            if let Some(max) = read_trimmed(base.join("cpu.max")) {
+                let mut parts = max.split_whitespace();
+                if let (Some(quota), Some(period)) = (parts.next(), parts.next()) {
+                    if let (Ok(quota), Ok(period)) = (quota.parse::<f64>(), period.parse::<f64>()) {
+                        limits.cpu_quota = Some(quota / period);
+                    }
+                }
+            }
+            limits.memory_limit = read_trimmed(base.join("memory.max"))
+                .and_then(|m| m.parse::<f64>().ok())
+                .map(|bytes| bytes / 1024.0);
+            limits.cpu_usage_path = Some(base.join("cpu.stat"));
+            limits.memory_usage_path = Some(base.join("memory.current"));
+        }
+    }
+
+    if limits.cpu_usage_path.is_none() {
+        let cpu_base = ["fs/cgroup/cpu,cpuacct", "fs/cgroup/cpu"]
+            .into_iter()
+            .map(crate::sys_path)
+            .find(|p| p.join("cpu.cfs_quota_us").exists());
+        if let Some(base) = cpu_base {
+            let quota = read_trimmed(base.join("cpu.cfs_quota_us")).and_then(|q| q.parse::<f64>().ok());
+            let period = read_trimmed(base.join("cpu.cfs_period_us")).and_then(|p| p.parse::<f64>().ok());
+            if let (Some(quota), Some(period)) = (quota, period) {
+                if quota > 0.0 && period > 0.0 {
+                    limits.cpu_quota = Some(quota / period);
+                }
+            }
+            limits.cpu_usage_path = Some(base.join("cpuacct.usage"));
+            limits.cpu_usage_unit_ns = 1.0;
+        }
+        let memory_base = crate::sys_path("fs/cgroup/memory");
+        if let Some(limit) = read_trimmed(memory_base.join("memory.limit_in_bytes")).and_then(|m| m.parse::<f64>().ok()) {
+            // v1 reports "unlimited" as a huge page-aligned number
+            if limit < (1u64 << 60) as f64 {
+                limits.memory_limit = Some(limit / 1024.0);
+            }
+            limits.memory_usage_path = Some(memory_base.join("memory.usage_in_bytes"));
+        }
+    }
+
+    if limits.cpu_quota.is_some() || limits.memory_limit.is_some() {
+        Some(limits)
+    } else {
+        None
+    }
+}
+
+impl Environment {
+    pub fn detect() -> Self {
+        let runtime = if let Some(name) = detect_container() {
+            RuntimeKind::Container(name)
+        } else if let Some(name) = detect_vm() {
+            RuntimeKind::VirtualMachine(name)
+        } else {
+            RuntimeKind::Host
+        };
+        // On a host the service's cgroup is a user unit or slice, whose
+        // MemoryMax= or CPUQuota= say nothing about the machine
+        let cgroup = if matches!(runtime, RuntimeKind::Container(_)) || at_namespace_root() { detect_cgroup_limits() } else { None };
+        // Host cores mean little when we only get a slice of them, and
+        // neither do their clocks
+        let per_core = cgroup.as_ref().and_then(|c| c.cpu_quota).is_none();
+
+        let collectors = EnabledCollectors {
+            per_core,
+            cpu_frequency: per_core && crate::collectors::CpuFrequencyCollector::new().available(),
+            process_io: crate::proc_path("self/io").exists(),
+            hwmon: has_entries("/sys/class/hwmon"),
+            energy: crate::energy::available(),
+            apps: crate::proc_path("self/statm").exists(),
+            processes: crate::proc_path("self/statm").exists(),
+            memory_breakdown: true,
+            network_quality: true,
+            network_interfaces: true,
+            filesystems: crate::proc_path("self/mounts").exists(),
+            disk_io: crate::proc_path("diskstats").exists(),
+            fans: crate::collectors::has_fans(),
+            gpu: crate::gpu::available(),
+            battery: crate::battery::available(),
+            pressure: crate::proc_path("pressure/cpu").exists(),
+            sockets: crate::proc_path("net/sockstat").exists(),
+            wireless: crate::proc_path("net/wireless").exists(),
+            nvme: has_entries("/sys/class/nvme"),
+            // One node has nothing to add to the totals
+            numa: crate::collectors::numa_nodes().len() > 1,
+            containers: crate::containers::available(),
+            slices: crate::slices::available(),
+            file_descriptors: crate::proc_path("sys/fs/file-nr").exists(),
+            irqs: crate::proc_path("softirqs").exists() && crate::proc_path("interrupts").exists(),
+        };
+
+        Environment { runtime, collectors, cgroup }
+    }
+
+    pub fn summary(&self) -> String {
+        let runtime = match &self.runtime {
+            RuntimeKind::Host => "host".to_string(),
+            RuntimeKind::Container(name) => format!("container ({})", name),
+            RuntimeKind::VirtualMachine(name) => format!("virtual machine ({})", name),
+        };
+        match self.cgroup.as_ref() {
+            Some(limits) => format!(
+                "{}, cgroup cpu quota {}, memory limit {}",
+                runtime,
+                limits.cpu_quota.map(|q| format!("{:.2} CPUs", q)).unwrap_or_else(|| "none".to_string()),
+                limits.memory_limit.map(|m| format!("{:.0} MB", m / 1024.0)).unwrap_or_else(|| "none".to_string()),
+            ),
+            None => runtime,
+        }
+    }
+}
+
+/// Rescales CPU and memory usage to the cgroup's quota instead of the host.
+#[derive(Default)]
+pub struct CgroupUsage {
+    prev_cpu: Option<(f64, Instant)>,
+}
+
+impl CgroupUsage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn read_cpu_usage_ns(limits: &CgroupLimits) -> Option<f64> {
+        let content = fs::read_to_string(limits.cpu_usage_path.as_ref()?).ok()?;
+        let value = match content.lines().find_map(|l| l.strip_prefix("usage_usec ")) {
+            Some(usec) => usec.trim().parse::<f64>().ok()?,
+            None => content.trim().parse::<f64>().ok()?,
+        };
+        Some(value * limits.cpu_usage_unit_ns)
+    }
+
+    pub fn apply(&mut self, limits: &CgroupLimits, cpu: &mut CpuStats, memory: &mut MemoryStats) {
+        if let Some(quota) = limits.cpu_quota.filter(|q| *q > 0.0) {
+            if let Some(usage_ns) = Self::read_cpu_usage_ns(limits) {
+                let now = Instant::now();
+                if let Some((prev_ns, prev_time)) = self.prev_cpu {
+                    let elapsed_ns = now.duration_since(prev_time).as_nanos() as f64;
+                    if elapsed_ns > 0.0 {
+                        cpu.overall_usage = ((usage_ns - prev_ns) / (elapsed_ns * quota) * 100.0).clamp(0.0, 100.0);
+                    }
+                }
+                self.prev_cpu = Some((usage_ns, now));
+            }
+        }
+
+        if let Some(limit) = limits.memory_limit.filter(|l| *l > 0.0) {
+            let used = limits.memory_usage_path.as_ref()
+                .and_then(read_trimmed)
+                .and_then(|u| u.parse::<f64>().ok())
+                .map(|bytes| bytes / 1024.0);
+            if let Some(used) = used {
+                memory.total = limit;
+                memory.available = (limit - used).max(0.0);
+                memory.used_percentage = (used / limit * 100.0).min(100.0);
+            }
+        }
+    }
+}
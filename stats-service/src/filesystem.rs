@@ -0,0 +1,191 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::ffi::CString;
+use serde::{Deserialize, Serialize};
+use crate::collectors::ProcReader;
+
+/// Disks fill over days, so usage is sampled once a minute and kept for a
+/// day rather than riding along with the per-second history.
+pub const SAMPLE_INTERVAL_MS: i64 = 60_000;
+pub const HISTORY_SAMPLES: usize = 1440;
+
+/// `[filesystems]` in the config.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct FilesystemConfig {
+    /// Mount points to report, e.g. `["/", "/home"]`. Empty reports every
+    /// block-device mount.
+    pub mounts: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MountUsage {
+    pub mount: String,
+    pub fstype: String,
+    // KB; total is what an unprivileged user can fill, i.e. excludes the
+    // blocks reserved for root, so used reaching total means full
+    pub total: f64,
+    pub used: f64,
+    #[serde(default)]
+    pub available: f64,
+    // Of total, as df reports it
+    #[serde(default)]
+    pub used_percentage: f64,
+}
+
+impl MountUsage {
+    fn new(mount: String, fstype: String, (total, used): (f64, f64)) -> Self {
+        MountUsage { mount, fstype, total, used, available: total - used, used_percentage: used / total * 100.0 }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MountHistory {
+    pub fstype: String,
+    pub total: f64,
+    // KB used at each sample time (ms)
+    pub used: VecDeque<f64>,
+    pub timestamps: VecDeque<i64>,
+}
+
+/// Per-mount usage history, served by the `filesystems` command.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FilesystemHistory {
+    pub mounts: BTreeMap<String, MountHistory>,
+}
+
+impl FilesystemHistory {
+    /// Percent of `mount` in use at its latest sample.
+    pub fn used_percentage(&self, mount: &str) -> Option<f64> {
+        let history = self.mounts.get(mount)?;
+        history.used.back().filter(|_| history.total > 0.0).map(|used| used / history.total * 100.0)
+    }
+
+    /// Mounts that are gone (an ejected USB stick) drop out at once rather
+    /// than projecting from stale samples.
+    pub fn record(&mut self, timestamp: i64, mounts: &[MountUsage]) {
+        for usage in mounts {
+            let history = self.mounts.entry(usage.mount.clone()).or_default();
+            history.fstype.clone_from(&usage.fstype);
+            history.total = usage.total;
+            history.used.push_back(usage.used);
+            history.timestamps.push_back(timestamp);
+            if history.used.len() > HISTORY_SAMPLES {
+                history.used.pop_front();
+                history.timestamps.pop_front();
+            }
+        }
+        self.mounts.retain(|mount, _| mounts.iter().any(|m| m.mount == *mount));
+    }
+}
+
+// /proc/self/mounts escapes space, tab, newline and backslash as octal
+fn unescape(field: &str) -> String {
+    let mut out = String::with_capacity(field.len());
+    let mut rest = field;
+    while let Some(i) = rest.find('\\') {
+        out.push_str(&rest[..i]);
+        match rest.get(i + 1..i + 4).and_then(|oct| u8::from_str_radix(oct, 8).ok()) {
+            Some(byte) => {
+                out.push(byte as char);
+                rest = &rest[i + 4..];
+            }
+            None => {
+                out.push('\\');
+                rest = &rest[i + 1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+// (total, used) in KB, counted the way df does
+fn usage(mount: &str) -> Option<(f64, f64)> {
+    let path = CString::new(mount).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    let block_kb = stat.f_frsize as f64 / 1024.0;
+    let used = stat.f_blocks.saturating_sub(stat.f_bfree) as f64 * block_kb;
+    let total = used + stat.f_bavail as f64 * block_kb;
+    (total > 0.0).then_some((total, used))
+}
+
+#[derive(Default)]
+pub struct FilesystemCollector {
+    reader: ProcReader,
+    last_sample: Option<i64>,
+    // Configured mount points; empty for every block device
+    mounts: Vec<String>,
+    latest: Vec<MountUsage>,
+}
+
+impl FilesystemCollector {
+    pub fn new(config: &FilesystemConfig) -> Self {
+        FilesystemCollector { mounts: config.mounts.clone(), ..Self::default() }
+    }
+
+    /// Usage from the most recent sample, for the ticks in between.
+    pub fn latest(&self) -> &[MountUsage] {
+        &self.latest
+    }
+
+    /// Usage of the configured mounts, or None between samples.
+    pub fn collect(&mut self, timestamp: i64) -> Option<Vec<MountUsage>> {
+        if self.last_sample.is_some_and(|last| timestamp - last < SAMPLE_INTERVAL_MS) {
+            return None;
+        }
+        self.last_sample = Some(timestamp);
+        let mounts = if self.mounts.is_empty() { self.block_devices() } else { self.configured() };
+        self.latest.clone_from(&mounts);
+        Some(mounts)
+    }
+
+    // Configured mount points that exist, whatever backs them (tmpfs, NFS)
+    fn configured(&mut self) -> Vec<MountUsage> {
+        let table = self.reader.read("self/mounts").unwrap_or_default();
+        let fstypes: Vec<(String, &str)> = table
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split_whitespace().skip(1);
+                Some((unescape(fields.next()?), fields.next()?))
+            })
+            .collect();
+        self.mounts
+            .iter()
+            .filter_map(|mount| {
+                // The last entry wins when something is mounted over another
+                let fstype = fstypes.iter().rev().find(|(m, _)| m == mount).map_or("", |(_, t)| *t);
+                Some(MountUsage::new(mount.clone(), fstype.to_string(), usage(mount)?))
+            })
+            .collect()
+    }
+
+    // Every block-device mount. Loop devices (snaps, images) are skipped, and
+    // a device mounted twice, like btrfs subvolumes, is listed once under its
+    // first mount point.
+    fn block_devices(&mut self) -> Vec<MountUsage> {
+        let Some(content) = self.reader.read("self/mounts") else {
+            return Vec::new();
+        };
+        let mut devices = Vec::new();
+        let mut mounts = Vec::new();
+        for line in content.lines() {
+            let mut fields = line.split_whitespace();
+            let (Some(device), Some(mount), Some(fstype)) = (fields.next(), fields.next(), fields.next()) else {
+                continue;
+            };
+            if !device.starts_with("/dev/") || device.starts_with("/dev/loop") || devices.contains(&device) {
+                continue;
+            }
+            let mount = unescape(mount);
+            let Some(usage) = usage(&mount) else {
+                continue;
+            };
+            devices.push(device);
+            mounts.push(MountUsage::new(mount, fstype.to_string(), usage));
+        }
+        mounts
+    }
+}
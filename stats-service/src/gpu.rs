@@ -0,0 +1,514 @@
+use std::fs::{self, File};
+use std::io::Read;
+use std::os::fd::{FromRawFd, OwnedFd};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use serde::{Deserialize, Serialize};
+
+/// One GPU, as reported by NVML, amdgpu or i915.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GpuStats {
+    // PCI address, e.g. "0000:01:00.0", which unlike the card number stays
+    // the same across boots; "card0" style when there's no PCI device
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    // Powered up rather than runtime-suspended. A hybrid laptop's discrete
+    // GPU sleeps until something renders on it, and isn't woken to be read:
+    // while suspended its utilization is 0 and the rest unreported
+    #[serde(default = "default_active")]
+    pub active: bool,
+    // The GPU the firmware initialized for the console (boot_vga), usually
+    // the integrated one on a hybrid laptop
+    #[serde(default)]
+    pub primary: bool,
+    // Percent of time a kernel was running
+    pub utilization: f64,
+    // KB, matching /proc/meminfo units
+    pub memory_used: f64,
+    pub memory_total: f64,
+    // °C and W; not every board reports them
+    pub temperature: Option<f64>,
+    pub power: Option<f64>,
+    // Percent busy of the video (decode/encode) engines, where they are
+    // counted apart from the render engine in `utilization`
+    pub video: Option<f64>,
+}
+
+fn default_active() -> bool {
+    true
+}
+
+const PCI_DIR: &str = "/sys/bus/pci/devices";
+
+// The PCI address a DRM card's device links to
+fn pci_slot(device: &Path) -> Option<String> {
+    Some(fs::canonicalize(device).ok()?.file_name()?.to_string_lossy().into_owned())
+}
+
+// Anything but "suspended" counts, including no runtime PM at all; reading
+// this file doesn't wake the device, unlike most of the driver's own files
+fn runtime_active(slot: &str) -> bool {
+    fs::read_to_string(Path::new(PCI_DIR).join(slot).join("power/runtime_status")).map_or(true, |s| s.trim() != "suspended")
+}
+
+fn boot_vga(slot: &str) -> bool {
+    fs::read_to_string(Path::new(PCI_DIR).join(slot).join("boot_vga")).is_ok_and(|s| s.trim() == "1")
+}
+
+#[cfg(feature = "nvidia")]
+mod nvml {
+    use std::ffi::{c_char, c_int, c_uint, c_void, CStr};
+
+    pub type Device = *mut c_void;
+
+    #[repr(C)]
+    #[derive(Default)]
+    pub struct Utilization {
+        pub gpu: c_uint,
+        pub memory: c_uint,
+    }
+
+    // Bytes
+    #[repr(C)]
+    #[derive(Default)]
+    pub struct Memory {
+        pub total: u64,
+        pub free: u64,
+        pub used: u64,
+    }
+
+    #[repr(C)]
+    #[derive(Default)]
+    pub struct PciInfo {
+        pub bus_id_legacy: [c_char; 16],
+        pub domain: c_uint,
+        pub bus: c_uint,
+        pub device: c_uint,
+        pub pci_device_id: c_uint,
+        pub pci_subsystem_id: c_uint,
+        pub bus_id: [c_char; 32],
+    }
+
+    pub const SUCCESS: c_int = 0;
+    pub const TEMPERATURE_GPU: c_int = 0;
+    const LIBRARY: &CStr = c"libnvidia-ml.so.1";
+
+    /// The NVML entry points used here, resolved from the driver's library.
+    /// The library stays loaded for the life of the process.
+    pub struct Nvml {
+        shutdown: unsafe extern "C" fn() -> c_int,
+        pub device_count: unsafe extern "C" fn(*mut c_uint) -> c_int,
+        pub device_handle: unsafe extern "C" fn(c_uint, *mut Device) -> c_int,
+        pub name: unsafe extern "C" fn(Device, *mut c_char, c_uint) -> c_int,
+        pub utilization: unsafe extern "C" fn(Device, *mut Utilization) -> c_int,
+        pub memory: unsafe extern "C" fn(Device, *mut Memory) -> c_int,
+        pub temperature: unsafe extern "C" fn(Device, c_int, *mut c_uint) -> c_int,
+        // mW
+        pub power: unsafe extern "C" fn(Device, *mut c_uint) -> c_int,
+        pub pci_info: unsafe extern "C" fn(Device, *mut PciInfo) -> c_int,
+    }
+
+    // Safety: T must be the function pointer type of the symbol
+    unsafe fn symbol<T: Copy>(library: *mut c_void, name: &CStr) -> Option<T> {
+        let address = libc::dlsym(library, name.as_ptr());
+        (!address.is_null()).then(|| std::mem::transmute_copy(&address))
+    }
+
+    impl Nvml {
+        /// None without the NVIDIA driver, or when NVML fails to initialize.
+        pub fn load() -> Option<Nvml> {
+            unsafe {
+                let library = libc::dlopen(LIBRARY.as_ptr(), libc::RTLD_NOW);
+                if library.is_null() {
+                    return None;
+                }
+                let init: unsafe extern "C" fn() -> c_int = symbol(library, c"nvmlInit_v2")?;
+                let nvml = Nvml {
+                    shutdown: symbol(library, c"nvmlShutdown")?,
+                    device_count: symbol(library, c"nvmlDeviceGetCount_v2")?,
+                    device_handle: symbol(library, c"nvmlDeviceGetHandleByIndex_v2")?,
+                    name: symbol(library, c"nvmlDeviceGetName")?,
+                    utilization: symbol(library, c"nvmlDeviceGetUtilizationRates")?,
+                    memory: symbol(library, c"nvmlDeviceGetMemoryInfo")?,
+                    temperature: symbol(library, c"nvmlDeviceGetTemperature")?,
+                    power: symbol(library, c"nvmlDeviceGetPowerUsage")?,
+                    pci_info: symbol(library, c"nvmlDeviceGetPciInfo_v3")?,
+                };
+                (init() == SUCCESS).then_some(nvml)
+            }
+        }
+    }
+
+    impl Drop for Nvml {
+        fn drop(&mut self) {
+            unsafe { (self.shutdown)() };
+        }
+    }
+}
+
+/// NVIDIA GPUs through NVML. Only built with the `nvidia` feature.
+#[cfg(feature = "nvidia")]
+struct NvidiaGpus {
+    nvml: nvml::Nvml,
+    // Handles, names and PCI addresses don't change while the driver is loaded
+    devices: Vec<(nvml::Device, String, String)>,
+}
+
+#[cfg(feature = "nvidia")]
+impl NvidiaGpus {
+    fn new() -> Option<Self> {
+        use std::ffi::{c_char, CStr};
+        let nvml = nvml::Nvml::load()?;
+        let mut count = 0;
+        if unsafe { (nvml.device_count)(&mut count) } != nvml::SUCCESS {
+            return None;
+        }
+        let devices: Vec<(nvml::Device, String, String)> = (0..count)
+            .filter_map(|index| {
+                let mut device = std::ptr::null_mut();
+                if unsafe { (nvml.device_handle)(index, &mut device) } != nvml::SUCCESS {
+                    return None;
+                }
+                let mut name = [0 as c_char; 96];
+                let name = if unsafe { (nvml.name)(device, name.as_mut_ptr(), name.len() as u32) } == nvml::SUCCESS {
+                    unsafe { CStr::from_ptr(name.as_ptr()) }.to_string_lossy().into_owned()
+                } else {
+                    format!("GPU {}", index)
+                };
+                let mut pci = nvml::PciInfo::default();
+                let slot = if unsafe { (nvml.pci_info)(device, &mut pci) } == nvml::SUCCESS {
+                    format!("{:04x}:{:02x}:{:02x}.0", pci.domain, pci.bus, pci.device)
+                } else {
+                    format!("nvidia{}", index)
+                };
+                Some((device, name, slot))
+            })
+            .collect();
+        (!devices.is_empty()).then_some(NvidiaGpus { nvml, devices })
+    }
+
+    // A GPU that can't be read (e.g. after falling off the bus) reports NaN.
+    // Any NVML query resumes a suspended GPU, so asleep ones aren't asked.
+    fn collect(&self, gpus: &mut Vec<GpuStats>) {
+        let nvml = &self.nvml;
+        for (device, name, slot) in &self.devices {
+            if !runtime_active(slot) {
+                gpus.push(GpuStats::suspended(slot, name, boot_vga(slot)));
+                continue;
+            }
+            let (mut utilization, mut memory) = (nvml::Utilization::default(), nvml::Memory::default());
+            let (mut celsius, mut milliwatts) = (0, 0);
+            unsafe {
+                if (nvml.utilization)(*device, &mut utilization) != nvml::SUCCESS || (nvml.memory)(*device, &mut memory) != nvml::SUCCESS {
+                    gpus.push(GpuStats::unreadable(slot, name, boot_vga(slot)));
+                    continue;
+                }
+                let temperature = ((nvml.temperature)(*device, nvml::TEMPERATURE_GPU, &mut celsius) == nvml::SUCCESS).then_some(celsius as f64);
+                let power = ((nvml.power)(*device, &mut milliwatts) == nvml::SUCCESS).then_some(milliwatts as f64 / 1000.0);
+                gpus.push(GpuStats {
+                    id: slot.clone(),
+                    name: name.clone(),
+                    active: true,
+                    primary: boot_vga(slot),
+                    utilization: utilization.gpu as f64,
+                    memory_used: memory.used as f64 / 1024.0,
+                    memory_total: memory.total as f64 / 1024.0,
+                    temperature,
+                    power,
+                    video: None,
+                });
+            }
+        }
+    }
+}
+
+const DRM_DIR: &str = "/sys/class/drm";
+
+/// An amdgpu card, read from the files its driver exposes in sysfs.
+struct AmdGpu {
+    id: String,
+    name: String,
+    // <card>/device
+    device: PathBuf,
+    // <card>/device/hwmon/hwmonN, for temperature and power
+    hwmon: Option<PathBuf>,
+}
+
+fn read_f64(path: impl AsRef<Path>) -> Option<f64> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+// card0, card1, ... but not their connectors (card0-DP-1)
+fn cards() -> Vec<String> {
+    let mut cards: Vec<String> = fs::read_dir(DRM_DIR)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|e| e.file_name().into_string().ok())
+        .filter(|name| name.strip_prefix("card").is_some_and(|n| n.chars().all(|c| c.is_ascii_digit())))
+        .collect();
+    cards.sort();
+    cards
+}
+
+// The marketing name from the PCI ID database, or e.g. "AMD GPU (card0)"
+fn card_name(device: &Path, card: &str, vendor_name: &str) -> String {
+    let uevent = fs::read_to_string(device.join("uevent")).unwrap_or_default();
+    let pci_id = uevent.lines().find_map(|l| l.strip_prefix("PCI_ID=")).map(str::to_lowercase);
+    pci_id
+        .as_deref()
+        .and_then(|id| id.split_once(':'))
+        .and_then(|(vendor, device)| crate::system_info::pci_name(vendor, device))
+        .unwrap_or_else(|| format!("{} ({})", vendor_name, card))
+}
+
+impl AmdGpu {
+    fn discover() -> Vec<AmdGpu> {
+        cards()
+            .into_iter()
+            .filter_map(|card| {
+                let device = Path::new(DRM_DIR).join(&card).join("device");
+                // Other drivers don't have gpu_busy_percent
+                if !device.join("gpu_busy_percent").exists() {
+                    return None;
+                }
+                let name = card_name(&device, &card, "AMD GPU");
+                let hwmon = fs::read_dir(device.join("hwmon")).into_iter().flatten().flatten().map(|e| e.path()).next();
+                Some(AmdGpu { id: pci_slot(&device).unwrap_or(card), name, device, hwmon })
+            })
+            .collect()
+    }
+
+    // amdgpu resumes the device for any of its sysfs reads, so a suspended
+    // dGPU is left asleep
+    fn collect(&self) -> GpuStats {
+        let primary = boot_vga(&self.id);
+        if !runtime_active(&self.id) {
+            return GpuStats::suspended(&self.id, &self.name, primary);
+        }
+        let Some(utilization) = read_f64(self.device.join("gpu_busy_percent")) else {
+            return GpuStats::unreadable(&self.id, &self.name, primary);
+        };
+        let hwmon = |file: &str| self.hwmon.as_ref().and_then(|h| read_f64(h.join(file)));
+        GpuStats {
+            id: self.id.clone(),
+            name: self.name.clone(),
+            active: true,
+            primary,
+            utilization,
+            memory_used: read_f64(self.device.join("mem_info_vram_used")).map_or(f64::NAN, |b| b / 1024.0),
+            memory_total: read_f64(self.device.join("mem_info_vram_total")).map_or(f64::NAN, |b| b / 1024.0),
+            // temp1 is the edge sensor, which every amdgpu has
+            temperature: hwmon("temp1_input").map(|m| m / 1000.0),
+            // µW; older kernels only average, newer ones also have an instant reading
+            power: hwmon("power1_average").or_else(|| hwmon("power1_input")).map(|uw| uw / 1e6),
+            video: None,
+        }
+    }
+}
+
+const PMU_DIR: &str = "/sys/bus/event_source/devices";
+const PERF_FLAG_FD_CLOEXEC: libc::c_ulong = 8;
+
+// The leading fields of the kernel's struct perf_event_attr, as of its
+// first version; later fields may be left off as long as `size` says so
+#[repr(C)]
+#[derive(Default)]
+struct PerfEventAttr {
+    kind: u32,
+    size: u32,
+    config: u64,
+    sample_period: u64,
+    sample_type: u64,
+    read_format: u64,
+    flags: u64,
+    wakeup_events: u32,
+    bp_type: u32,
+    config1: u64,
+}
+
+/// Turns a cumulative counter of busy (or, with `idle`, idle) time into the
+/// percent busy since the previous reading.
+struct BusyCounter {
+    read: Box<dyn FnMut() -> Option<u64> + Send>,
+    // ns of the counter per ns of wall-clock time
+    scale: f64,
+    idle: bool,
+    last: (u64, Instant),
+}
+
+impl BusyCounter {
+    fn new(mut read: Box<dyn FnMut() -> Option<u64> + Send>, scale: f64, idle: bool) -> Option<Self> {
+        let value = read()?;
+        Some(BusyCounter { read, scale, idle, last: (value, Instant::now()) })
+    }
+
+    fn busy(&mut self) -> Option<f64> {
+        let (value, now) = ((self.read)()?, Instant::now());
+        let (last, at) = std::mem::replace(&mut self.last, (value, now));
+        let elapsed = now.duration_since(at).as_nanos() as f64;
+        if elapsed == 0.0 {
+            return None;
+        }
+        let fraction = (value.saturating_sub(last) as f64 * self.scale / elapsed).clamp(0.0, 1.0);
+        Some(100.0 * if self.idle { 1.0 - fraction } else { fraction })
+    }
+}
+
+/// One i915 PMU engine counter, e.g. `rcs0-busy`: ns the engine was busy.
+fn engine_counter(pmu: &Path, event: &str) -> Option<BusyCounter> {
+    let kind = fs::read_to_string(pmu.join("type")).ok()?.trim().parse().ok()?;
+    let spec = fs::read_to_string(pmu.join("events").join(event)).ok()?;
+    let config = u64::from_str_radix(spec.trim().strip_prefix("config=0x")?, 16).ok()?;
+    // An uncore-style PMU: counted on one CPU for the whole system
+    let cpumask = fs::read_to_string(pmu.join("cpumask")).unwrap_or_default();
+    let cpu: i32 = cpumask.trim().split([',', '-']).next()?.parse().ok()?;
+    let attr = PerfEventAttr { kind, size: std::mem::size_of::<PerfEventAttr>() as u32, config, ..Default::default() };
+    let fd = unsafe { libc::syscall(libc::SYS_perf_event_open, &attr, -1, cpu, -1, PERF_FLAG_FD_CLOEXEC) };
+    if fd < 0 {
+        return None;
+    }
+    let mut file = File::from(unsafe { OwnedFd::from_raw_fd(fd as i32) });
+    let read = move || {
+        let mut value = [0u8; 8];
+        file.read_exact(&mut value).ok()?;
+        Some(u64::from_ne_bytes(value))
+    };
+    BusyCounter::new(Box::new(read), 1.0, false)
+}
+
+/// An i915 GPU. Engine busy time comes from the driver's perf PMU, which
+/// needs CAP_PERFMON or `kernel.perf_event_paranoid` at 0 or below; without
+/// it the render load is estimated from RC6 (idle) residency, and the video
+/// engines go unreported.
+struct IntelGpu {
+    id: String,
+    name: String,
+    render: Option<BusyCounter>,
+    // vcs0, vcs1, ...; reported as the busiest
+    video: Vec<BusyCounter>,
+}
+
+impl IntelGpu {
+    fn discover() -> Vec<IntelGpu> {
+        cards()
+            .into_iter()
+            .filter_map(|card| {
+                let device = Path::new(DRM_DIR).join(&card).join("device");
+                let driver = fs::read_link(device.join("driver")).ok()?;
+                if driver.file_name()? != "i915" {
+                    return None;
+                }
+                // The integrated GPU's PMU is plain "i915"; discrete ones are
+                // named after their PCI slot, e.g. i915_0000_03_00.0
+                let id = pci_slot(&device)?;
+                let pmu = [format!("i915_{}", id.replace(':', "_")), "i915".to_string()]
+                    .into_iter()
+                    .map(|name| Path::new(PMU_DIR).join(name))
+                    .find(|pmu| pmu.exists());
+                let render = pmu.as_deref().and_then(|pmu| engine_counter(pmu, "rcs0-busy"));
+                let video = pmu
+                    .as_deref()
+                    .map(|pmu| (0..).map_while(|i| engine_counter(pmu, &format!("vcs{}-busy", i))).collect())
+                    .unwrap_or_default();
+                let render = render.or_else(|| Self::rc6_counter(&card));
+                Some(IntelGpu { id, name: card_name(&device, &card, "Intel GPU"), render, video })
+            })
+            .collect()
+    }
+
+    // ms in RC6 since boot; the GPU is busy whenever it isn't in it
+    fn rc6_counter(card: &str) -> Option<BusyCounter> {
+        let card = Path::new(DRM_DIR).join(card);
+        let path = [card.join("gt/gt0/rc6_residency_ms"), card.join("power/rc6_residency_ms")].into_iter().find(|p| p.exists())?;
+        let read = move || read_f64(&path).map(|ms| ms as u64);
+        BusyCounter::new(Box::new(read), 1e6, true)
+    }
+
+    // The PMU and RC6 counters don't wake the GPU, so they're read either way
+    fn collect(&mut self) -> GpuStats {
+        let primary = boot_vga(&self.id);
+        let Some(utilization) = self.render.as_mut().and_then(BusyCounter::busy) else {
+            return GpuStats::unreadable(&self.id, &self.name, primary);
+        };
+        GpuStats {
+            id: self.id.clone(),
+            name: self.name.clone(),
+            active: runtime_active(&self.id),
+            primary,
+            utilization,
+            // Integrated GPUs share system memory, with no VRAM of their own
+            memory_used: f64::NAN,
+            memory_total: f64::NAN,
+            temperature: None,
+            power: None,
+            video: self.video.iter_mut().filter_map(BusyCounter::busy).reduce(f64::max),
+        }
+    }
+}
+
+impl GpuStats {
+    fn unreadable(id: &str, name: &str, primary: bool) -> Self {
+        GpuStats {
+            id: id.to_string(),
+            name: name.to_string(),
+            active: true,
+            primary,
+            utilization: f64::NAN,
+            memory_used: f64::NAN,
+            memory_total: f64::NAN,
+            temperature: None,
+            power: None,
+            video: None,
+        }
+    }
+
+    // Powered down: nothing's running on it
+    fn suspended(id: &str, name: &str, primary: bool) -> Self {
+        GpuStats { active: false, utilization: 0.0, ..GpuStats::unreadable(id, name, primary) }
+    }
+}
+
+/// Samples every GPU it can read: NVIDIA through NVML when built with the
+/// `nvidia` feature, AMD through amdgpu's sysfs files and Intel through i915.
+pub struct GpuCollector {
+    #[cfg(feature = "nvidia")]
+    nvidia: Option<NvidiaGpus>,
+    amd: Vec<AmdGpu>,
+    intel: Vec<IntelGpu>,
+}
+
+impl GpuCollector {
+    /// None when there's no GPU to read.
+    pub fn new() -> Option<Self> {
+        let collector = GpuCollector {
+            #[cfg(feature = "nvidia")]
+            nvidia: NvidiaGpus::new(),
+            amd: AmdGpu::discover(),
+            intel: IntelGpu::discover(),
+        };
+        #[cfg(feature = "nvidia")]
+        let any = collector.nvidia.is_some() || !collector.amd.is_empty() || !collector.intel.is_empty();
+        #[cfg(not(feature = "nvidia"))]
+        let any = !collector.amd.is_empty() || !collector.intel.is_empty();
+        any.then_some(collector)
+    }
+
+    /// One entry per GPU, NVIDIA, then AMD, then Intel, in a fixed order so
+    /// each keeps its history slot.
+    pub fn collect(&mut self) -> Vec<GpuStats> {
+        let mut gpus = Vec::new();
+        #[cfg(feature = "nvidia")]
+        if let Some(nvidia) = &self.nvidia {
+            nvidia.collect(&mut gpus);
+        }
+        gpus.extend(self.amd.iter().map(AmdGpu::collect));
+        gpus.extend(self.intel.iter_mut().map(IntelGpu::collect));
+        gpus
+    }
+}
+
+pub fn available() -> bool {
+    GpuCollector::new().is_some()
+}
@@ -0,0 +1,177 @@
+use std::time::Duration;
+use serde::Deserialize;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time;
+use crate::protocol::{self, Command};
+use crate::ServerContext;
+
+// A GET has no body, so everything worth reading fits in the headers
+const MAX_REQUEST_BYTES: usize = 8192;
+const READ_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// `[http]` in the config. Only `/latest` and `/history` are served: the
+/// listener has no peer credentials to hold against the socket's
+/// `read_only_gids`, so anything that reaches the port can read them.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct HttpConfig {
+    pub enabled: bool,
+    /// Anything other than a loopback address exposes the stats to the network.
+    pub listen: String,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        HttpConfig { enabled: false, listen: "127.0.0.1:8787".to_string() }
+    }
+}
+
+// Reads up to the end of the headers; the request line and Host are all that's used
+async fn read_request(stream: &mut TcpStream) -> Option<String> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 1024];
+    while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+        if buf.len() > MAX_REQUEST_BYTES {
+            return None;
+        }
+        let n = stream.read(&mut chunk).await.ok()?;
+        if n == 0 {
+            break;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+    Some(String::from_utf8_lossy(&buf).into_owned())
+}
+
+fn header<'a>(head: &'a str, name: &str) -> Option<&'a str> {
+    head.lines().skip(1).find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        key.trim().eq_ignore_ascii_case(name).then(|| value.trim())
+    })
+}
+
+/// Whether `host` names this listener rather than some other site a browser
+/// resolved to it, which is how DNS rebinding gets a page onto loopback.
+fn host_allowed(host: &str, listen: &str) -> bool {
+    let name = |addr: &str| {
+        addr.rsplit_once(':').filter(|(_, port)| port.parse::<u16>().is_ok()).map_or(addr, |(name, _)| name).to_string()
+    };
+    host == listen || [name(listen).as_str(), "localhost", "127.0.0.1"].contains(&name(host).as_str())
+}
+
+// `%XX` escapes and `+` for a space, as browsers and curl encode query values
+fn percent_decode(value: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut rest = value.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        rest = tail;
+        match byte {
+            b'+' => bytes.push(b' '),
+            b'%' => {
+                let hex = std::str::from_utf8(rest.get(..2)?).ok()?;
+                bytes.push(u8::from_str_radix(hex, 16).ok()?);
+                rest = &rest[2..];
+            }
+            _ => bytes.push(byte),
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+fn query_param(query: &str, key: &str) -> Result<Option<String>, (u16, String)> {
+    match query.split('&').find_map(|pair| pair.strip_prefix(key)?.strip_prefix('=')) {
+        Some(value) => percent_decode(value).map(Some).ok_or_else(|| (400, format!("invalid escape in {}", key))),
+        None => Ok(None),
+    }
+}
+
+/// Maps a path onto the socket command serving it: `/latest`, `/history`
+/// and `/history?metric=cpu&last=300`.
+fn route(target: &str) -> Result<Command, (u16, String)> {
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    match path.trim_start_matches('/') {
+        "latest" => Ok(Command::Latest),
+        "history" => match query_param(query, "metric")? {
+            Some(metric) => {
+                let last = match query_param(query, "last")? {
+                    Some(n) => Some(n.parse().map_err(|_| (400, format!("invalid count: {}", n)))?),
+                    None => None,
+                };
+                Ok(Command::Series { metric, last })
+            }
+            None => Ok(Command::History),
+        },
+        _ => Err((404, format!("not available over HTTP: {}", path))),
+    }
+}
+
+fn response(status: u16, body: &[u8]) -> Vec<u8> {
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Internal Server Error",
+    };
+    let mut bytes = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        reason,
+        body.len()
+    )
+    .into_bytes();
+    bytes.extend_from_slice(body);
+    bytes
+}
+
+async fn handle_connection(mut stream: TcpStream, ctx: ServerContext) {
+    let Ok(Some(head)) = time::timeout(READ_TIMEOUT, read_request(&mut stream)).await else {
+        let _ = stream.write_all(&response(400, protocol::error_response("malformed request").as_bytes())).await;
+        return;
+    };
+    let mut parts = head.lines().next().unwrap_or("").split_whitespace();
+    let (method, target) = (parts.next().unwrap_or(""), parts.next().unwrap_or("/"));
+    let reply = if !header(&head, "host").is_some_and(|host| host_allowed(host, &ctx.config.http.listen)) {
+        response(403, protocol::error_response("unexpected Host").as_bytes())
+    } else if method != "GET" {
+        response(405, protocol::error_response("only GET is supported").as_bytes())
+    } else {
+        match route(target) {
+            Ok(command) => {
+                let body = crate::execute_command(command, &ctx).await;
+                // Both routes fail only on something missing: no stats yet, or no such series
+                response(if protocol::is_error(&body) { 404 } else { 200 }, &body)
+            }
+            Err((status, message)) => response(status, protocol::error_response(&message).as_bytes()),
+        }
+    };
+    let timeout = Duration::from_millis(ctx.config.socket.write_timeout_ms);
+    if time::timeout(timeout, stream.write_all(&reply)).await.is_err() {
+        eprintln!("HTTP client stopped reading");
+    }
+    let _ = stream.shutdown().await;
+}
+
+/// Serves read-only JSON snapshots for curl and for tools that can't open a
+/// Unix socket. Replies come from the same handlers as the socket's, so the
+/// two never disagree.
+pub async fn run_server(ctx: ServerContext) {
+    let listener = match TcpListener::bind(&ctx.config.http.listen).await {
+        Ok(l) => l,
+        Err(e) => {
+            eprintln!("Failed to bind HTTP listener on {}: {}", ctx.config.http.listen, e);
+            return;
+        }
+    };
+    println!("HTTP server listening on {}", ctx.config.http.listen);
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                tokio::spawn(handle_connection(stream, ctx.clone()));
+            }
+            Err(e) => eprintln!("Failed to accept HTTP connection: {}", e),
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use serde::{Deserialize, Serialize};
+
+// A load test starts once usage stays above the threshold for LOAD_TEST_ENTER_MS
+// and ends after it stays below for LOAD_TEST_EXIT_MS.
+pub const LOAD_TEST_THRESHOLD: f64 = 95.0;
+pub const LOAD_TEST_ENTER_MS: i64 = 10_000;
+pub const LOAD_TEST_EXIT_MS: i64 = 5_000;
+pub const LOAD_TEST_INTERVAL_MS: u64 = 250;
+pub const MAX_MARKERS: usize = 32;
+
+/// What the detector is fed: the busier of the CPU and the busiest GPU, so
+/// a GPU burn-in with an idle CPU counts too. Unreadable GPU values are
+/// left out.
+pub fn busiest(cpu: f64, gpus: impl IntoIterator<Item = f64>) -> f64 {
+    gpus.into_iter().filter(|load| load.is_finite()).fold(cpu, f64::max)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryMarker {
+    pub kind: String,
+    pub label: String,
+    pub start: i64,
+    pub end: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LoadTestTransition {
+    Started { since: i64 },
+    Ended { at: i64 },
+}
+
+#[derive(Debug, Default)]
+pub struct LoadTestDetector {
+    high_since: Option<i64>,
+    low_since: Option<i64>,
+    active: bool,
+}
+
+impl LoadTestDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Feeds the current load (the busiest of CPU/GPU, in percent) and reports
+    /// a transition when a sustained load test begins or ends.
+    pub fn update(&mut self, timestamp: i64, load: f64) -> Option<LoadTestTransition> {
+        if load >= LOAD_TEST_THRESHOLD {
+            self.low_since = None;
+            let since = *self.high_since.get_or_insert(timestamp);
+            if !self.active && timestamp - since >= LOAD_TEST_ENTER_MS {
+                self.active = true;
+                return Some(LoadTestTransition::Started { since });
+            }
+        } else {
+            self.high_since = None;
+            let since = *self.low_since.get_or_insert(timestamp);
+            if self.active && timestamp - since >= LOAD_TEST_EXIT_MS {
+                self.active = false;
+                return Some(LoadTestTransition::Ended { at: since });
+            }
+        }
+        None
+    }
+}
@@ -1,33 +1,106 @@
 use std::fs::{self, File};
 use std::io::Write;
-use std::path::Path;
-use std::collections::{VecDeque, HashMap};
-use std::sync::Arc;
-use tokio::sync::Mutex;
-use std::time::{Duration, Instant};
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex as StdMutex, OnceLock};
+use tokio::sync::{broadcast, Mutex, RwLock};
+use std::time::Duration;
+use bytes::Bytes;
 use serde::{Deserialize, Serialize};
+use tokio::signal::unix::{signal, SignalKind};
 use tokio::time;
 use tokio::net::{UnixListener, UnixStream};
-use tokio::io::AsyncWriteExt;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 use chrono::Local;
-use num_cpus;
 
-const HISTORY_SIZE: usize = 60;
+mod advisories;
+mod alerts;
+mod apps;
+mod battery;
+mod chaos;
+mod clock;
+mod collectors;
+mod config;
+mod containers;
+mod dbus;
+mod energy;
+mod environment;
+mod filesystem;
+mod gpu;
+mod http;
+mod load_test;
+mod metadata;
+mod net_quality;
+mod nvme;
+mod patterns;
+mod privacy;
+mod process;
+mod protocol;
+mod recording;
+mod self_check;
+mod series;
+mod slices;
+mod smart;
+mod state;
+mod summary;
+mod system_info;
+mod systemd;
+mod text_outputs;
+mod units;
+mod wireless;
+
+use advisories::{AdvisoryTransition, FanCurveAdvisor};
+use alerts::{AlertEngine, AlertRule, AlertTransition};
+use apps::{AppCollector, AppGroup};
+use battery::{PowerSupplyCollector, PowerSupplyStats};
+use chaos::Chaos;
+use clock::MissedTickPolicy;
+use collectors::{CpuCollector, CpuFrequencyCollector, CpuStats, DiskCollector, DiskRates, FanCollector, FileDescriptors, InterfaceStats, IrqCollector, IrqDistribution, KernelActivity, KernelActivityCollector, LoadAverage, MemoryCollector, MemoryStats, NetworkCollector, NumaCollector, NumaNode, PressureStats, ProcReader, SocketStats, TemperatureCollector, Uptime};
+use config::{HistoryConfig, ServiceConfig};
+use dbus::Signal;
+use energy::{EnergyCollector, EnergyReport};
+use gpu::{GpuCollector, GpuStats};
+use environment::{CgroupUsage, EnabledCollectors, Environment};
+use filesystem::{FilesystemCollector, FilesystemHistory, MountUsage};
+use load_test::{HistoryMarker, LoadTestDetector, LoadTestTransition};
+use net_quality::{NetworkQuality, ProbeResults, QualityCollector};
+use nvme::{NvmeCollector, NvmeHealth};
+use containers::{ContainerCollector, ContainerNames, ContainerStats};
+use patterns::PatternStore;
+use process::{ProcessIo, ProcessIoCollector, TopProcessCollector, TopProcesses};
+use protocol::{Access, Command};
+use series::{MetricId, SeriesRegistry, GPU_FIELDS};
+use series::{CONTEXT_SWITCHES, CPU, CPU_IOWAIT, FORKS, INTERRUPTS, LOAD, MEMORY, MEMORY_APPS, MEMORY_BUFFERS, MEMORY_CACHED, MEMORY_SHMEM, MEMORY_SLAB, SWAP, SWAP_IN, SWAP_OUT};
+use series::{BATTERY, DISK_READ, DISK_WRITE, FAN, NETWORK_DOWNLOAD, NETWORK_QUALITY, NETWORK_UPLOAD, POWER, TEMPERATURE};
+use series::{FILE_DESCRIPTORS, FILE_DESCRIPTORS_USED, PRESSURE_CPU, PRESSURE_IO, PRESSURE_MEMORY, SOCKETS_ORPHANED, SOCKETS_TCP, SOCKETS_TIME_WAIT, SOCKETS_UDP};
+use slices::{SliceCollector, SliceStats};
+use state::{StateTracker, StateValue};
+use summary::SummaryTracker;
+use system_info::SystemInfo;
+use wireless::{WirelessCollector, WirelessLink};
+
 const DATA_DIR: &str = "/tmp/ags-stats";
 const SOCKET_PATH: &str = "/tmp/ags-stats/stats.sock";
 const UPDATE_INTERVAL_MS: u64 = 1000;
+const COMMAND_TIMEOUT_MS: u64 = 100;
+
+// Set once from the config; tests point this at a fixture tree
+static PROC_ROOT: OnceLock<PathBuf> = OnceLock::new();
+
+fn proc_root() -> &'static Path {
+    PROC_ROOT.get().map(PathBuf::as_path).unwrap_or(Path::new("/proc"))
+}
+
+/// Resolves a path under the configured proc root, e.g. `proc_path("net/dev")`.
+fn proc_path(rel: &str) -> PathBuf {
+    proc_root().join(rel)
+}
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
-struct MemoryStats {
-    total: f64,
-    available: f64,
-    used_percentage: f64,
-    // Detailed breakdown in KB
-    apps: f64,
-    cached: f64,
-    buffers: f64,
-    slab: f64,
-    shmem: f64,
+/// Resolves a path under the sysfs beside the proc root, e.g.
+/// `sys_path("fs/cgroup")`, so a fixture or chroot brings its own.
+fn sys_path(rel: &str) -> PathBuf {
+    proc_root().parent().unwrap_or(Path::new("/")).join("sys").join(rel)
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -36,333 +109,671 @@ struct SystemStats {
     cpu_usage: f64,
     cpu_cores: Vec<f64>,
     cpu_iowait: f64,
+    // MHz per core, NaN for cores without cpufreq
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    cpu_frequencies: Vec<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    load: Option<LoadAverage>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    uptime: Option<Uptime>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    kernel_activity: Option<KernelActivity>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    irqs: Option<IrqDistribution>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pressure: Option<PressureStats>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sockets: Option<SocketStats>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    file_descriptors: Option<FileDescriptors>,
     memory: MemoryStats,
+    // In `network_unit`, which `[units]` decides
     network_download: f64,
     network_upload: f64,
+    #[serde(default)]
+    network_unit: String,
+    // By interface name; network_download and network_upload are their sums
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    network_interfaces: BTreeMap<String, InterfaceStats>,
+    // Associated Wi-Fi interfaces by name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    wireless: BTreeMap<String, WirelessLink>,
+    top_io: Vec<ProcessIo>,
+    // KB/s across whole disks
+    #[serde(default)]
+    disk_read: f64,
+    #[serde(default)]
+    disk_write: f64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    disks: Vec<DiskRates>,
+    // °C, hottest CPU sensor
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    temperature: Option<f64>,
+    // °C by sensor name, e.g. "coretemp/Core 0" or "nvme/Composite"
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    temperatures: BTreeMap<String, f64>,
+    // RPM of the fastest fan, and of each by name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    fan: Option<f64>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    fans: BTreeMap<String, f64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    gpu: Vec<GpuStats>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    energy: Option<EnergyReport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    power_supply: Option<PowerSupplyStats>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    network_quality: Option<NetworkQuality>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    apps: Vec<AppGroup>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    top_processes: Option<TopProcesses>,
+    // Sampled once a minute and repeated on the ticks in between
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    filesystems: Vec<MountUsage>,
+    // Whether `filesystems` is a fresh sample, which is all the history keeps
+    #[serde(skip)]
+    filesystems_sampled: bool,
+    // Health log of each NVMe controller, also sampled once a minute
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    nvme: Vec<NvmeHealth>,
+    // Memory per NUMA node, on machines with more than one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    numa: Vec<NumaNode>,
+    // Running Docker, Podman and containerd containers, heaviest CPU first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    containers: Vec<ContainerStats>,
+    // Top-level systemd slices, then the app units inside them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    slices: Vec<SliceStats>,
+    // Discrete metrics, only on the ticks where they changed and on each
+    // heartbeat; see StateTracker
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    states: BTreeMap<String, String>,
+    // Every marker, on the ticks where one began or ended, so subscribers
+    // can show alert bands without refetching the history
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    markers: Option<Vec<HistoryMarker>>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize)]
 struct StatsHistory {
-    cpu: VecDeque<f64>,
-    cpu_cores: Vec<VecDeque<f64>>,
-    cpu_iowait: VecDeque<f64>,
-    memory: VecDeque<f64>,
+    // cpu, memory, network_download, ... at the top level, cpu_cores,
+    // network_interfaces and timestamps; see SeriesRegistry
+    #[serde(flatten)]
+    series: SeriesRegistry,
     memory_total: f64,
-    memory_apps: VecDeque<f64>,
-    memory_cached: VecDeque<f64>,
-    memory_buffers: VecDeque<f64>,
-    memory_slab: VecDeque<f64>,
-    memory_shmem: VecDeque<f64>,
-    network_download: VecDeque<f64>,
-    network_upload: VecDeque<f64>,
+    markers: Vec<HistoryMarker>,
+    // The latest value of each discrete metric rather than a series
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    states: BTreeMap<String, StateValue>,
     last_update: i64,
+    // Served by the `energy` command rather than with the history
+    #[serde(skip)]
+    energy: Option<EnergyReport>,
+    #[serde(skip)]
+    apps: Vec<AppGroup>,
+    #[serde(skip)]
+    filesystems: FilesystemHistory,
+    #[serde(skip)]
+    latest: Option<SystemStats>,
+    // Set from graph windows over the socket; fired like `[[alerts]]`
+    #[serde(skip)]
+    watches: AlertEngine,
+    // Bumped by every change to the serialized fields; see HistoryCache
+    #[serde(skip)]
+    revision: u64,
+    // Bumped with it when the markers change
+    #[serde(skip)]
+    markers_revision: u64,
 }
 
 impl StatsHistory {
-    fn new() -> Self {
-        let num_cores = num_cpus::get();
-        let mut cpu_cores = Vec::new();
-        
-        for _ in 0..num_cores {
-            let mut core_history = VecDeque::with_capacity(HISTORY_SIZE);
-            for _ in 0..HISTORY_SIZE {
-                core_history.push_back(0.0);
+    fn new(collectors: &EnabledCollectors, sizes: &HistoryConfig) -> Self {
+        let mut series = SeriesRegistry::new(sizes);
+        for id in [CPU, CPU_IOWAIT, LOAD, CONTEXT_SWITCHES, INTERRUPTS, FORKS, MEMORY, SWAP, SWAP_IN, SWAP_OUT, NETWORK_DOWNLOAD, NETWORK_UPLOAD, POWER, NETWORK_QUALITY] {
+            series.register(id);
+        }
+        // Optional series are left out entirely when their collector is off
+        if collectors.memory_breakdown {
+            for id in [MEMORY_APPS, MEMORY_CACHED, MEMORY_BUFFERS, MEMORY_SLAB, MEMORY_SHMEM] {
+                series.register(id);
             }
-            cpu_cores.push(core_history);
         }
-        
-        let mut history = StatsHistory {
-            cpu: VecDeque::with_capacity(HISTORY_SIZE),
-            cpu_cores,
-            cpu_iowait: VecDeque::with_capacity(HISTORY_SIZE),
-            memory: VecDeque::with_capacity(HISTORY_SIZE),
+        if collectors.hwmon {
+            series.register(TEMPERATURE);
+        }
+        if collectors.fans {
+            series.register(FAN);
+        }
+        if collectors.battery {
+            series.register(BATTERY);
+        }
+        if collectors.pressure {
+            for id in [PRESSURE_CPU, PRESSURE_MEMORY, PRESSURE_IO] {
+                series.register(id);
+            }
+        }
+        if collectors.sockets {
+            for id in [SOCKETS_TCP, SOCKETS_UDP, SOCKETS_TIME_WAIT, SOCKETS_ORPHANED] {
+                series.register(id);
+            }
+        }
+        if collectors.file_descriptors {
+            series.register(FILE_DESCRIPTORS);
+            series.register(FILE_DESCRIPTORS_USED);
+        }
+        if collectors.disk_io {
+            series.register(DISK_READ);
+            series.register(DISK_WRITE);
+        }
+        if collectors.per_core {
+            for core in 0..num_cpus::get() {
+                series.register(MetricId::Core(core));
+            }
+        }
+        if collectors.cpu_frequency {
+            for core in 0..num_cpus::get() {
+                series.register(MetricId::CoreFrequency(core));
+            }
+        }
+        StatsHistory {
+            series,
             memory_total: 0.0,
-            memory_apps: VecDeque::with_capacity(HISTORY_SIZE),
-            memory_cached: VecDeque::with_capacity(HISTORY_SIZE),
-            memory_buffers: VecDeque::with_capacity(HISTORY_SIZE),
-            memory_slab: VecDeque::with_capacity(HISTORY_SIZE),
-            memory_shmem: VecDeque::with_capacity(HISTORY_SIZE),
-            network_download: VecDeque::with_capacity(HISTORY_SIZE),
-            network_upload: VecDeque::with_capacity(HISTORY_SIZE),
+            markers: Vec::new(),
+            states: BTreeMap::new(),
             last_update: 0,
-        };
-        
-        for _ in 0..HISTORY_SIZE {
-            history.cpu.push_back(0.0);
-            history.cpu_iowait.push_back(0.0);
-            history.memory.push_back(0.0);
-            history.memory_apps.push_back(0.0);
-            history.memory_cached.push_back(0.0);
-            history.memory_buffers.push_back(0.0);
-            history.memory_slab.push_back(0.0);
-            history.memory_shmem.push_back(0.0);
-            history.network_download.push_back(0.0);
-            history.network_upload.push_back(0.0);
+            energy: None,
+            apps: Vec::new(),
+            filesystems: FilesystemHistory::default(),
+            latest: None,
+            watches: AlertEngine::default(),
+            revision: 0,
+            markers_revision: 0,
         }
-        
-        history
     }
     
     fn add_stats(&mut self, stats: &SystemStats) {
-        Self::add_value(&mut self.cpu, stats.cpu_usage);
-        Self::add_value(&mut self.cpu_iowait, stats.cpu_iowait);
+        self.revision += 1;
+        let memory = &stats.memory;
+        // avg10 of "some", NaN while a pressure file is unreadable
+        let pressure = |resource: fn(&PressureStats) -> &Option<collectors::Pressure>| {
+            stats.pressure.as_ref().and_then(|p| resource(p).as_ref()).map_or(f64::NAN, |p| p.some_avg10)
+        };
+        let activity = |rate: fn(&KernelActivity) -> f64| stats.kernel_activity.as_ref().map_or(f64::NAN, rate);
+        let sockets = |count: fn(&SocketStats) -> u64| stats.sockets.as_ref().map_or(f64::NAN, |s| count(s) as f64);
+        let power = stats.energy.as_ref().and_then(EnergyReport::system_watts).unwrap_or(0.0);
+        let values = [
+            (CPU, stats.cpu_usage),
+            (CPU_IOWAIT, stats.cpu_iowait),
+            (LOAD, stats.load.as_ref().map_or(f64::NAN, |l| l.one)),
+            (CONTEXT_SWITCHES, activity(|a| a.context_switches)),
+            (INTERRUPTS, activity(|a| a.interrupts)),
+            (FORKS, activity(|a| a.forks)),
+            (MEMORY, memory.used_percentage),
+            (MEMORY_APPS, memory.apps),
+            (MEMORY_CACHED, memory.cached),
+            (MEMORY_BUFFERS, memory.buffers),
+            (MEMORY_SLAB, memory.slab),
+            (MEMORY_SHMEM, memory.shmem),
+            (SWAP, memory.swap_used_percentage),
+            (SWAP_IN, memory.swap_in),
+            (SWAP_OUT, memory.swap_out),
+            (NETWORK_DOWNLOAD, stats.network_download),
+            (NETWORK_UPLOAD, stats.network_upload),
+            (DISK_READ, stats.disk_read),
+            (DISK_WRITE, stats.disk_write),
+            (TEMPERATURE, stats.temperature.unwrap_or(f64::NAN)),
+            (FAN, stats.fan.unwrap_or(f64::NAN)),
+            (BATTERY, stats.power_supply.as_ref().and_then(|p| p.capacity).unwrap_or(f64::NAN)),
+            (PRESSURE_CPU, pressure(|p| &p.cpu)),
+            (PRESSURE_MEMORY, pressure(|p| &p.memory)),
+            (PRESSURE_IO, pressure(|p| &p.io)),
+            (SOCKETS_TCP, sockets(|s| s.tcp_in_use)),
+            (SOCKETS_UDP, sockets(|s| s.udp_in_use)),
+            (SOCKETS_TIME_WAIT, sockets(|s| s.tcp_time_wait)),
+            (SOCKETS_ORPHANED, sockets(|s| s.tcp_orphaned)),
+            (FILE_DESCRIPTORS, stats.file_descriptors.as_ref().map_or(f64::NAN, |f| f.allocated as f64)),
+            (FILE_DESCRIPTORS_USED, stats.file_descriptors.as_ref().map_or(f64::NAN, |f| f.used_percentage)),
+            (POWER, power),
+            (NETWORK_QUALITY, stats.network_quality.as_ref().map_or(0.0, |q| q.score)),
+        ];
+        for (id, value) in &values {
+            self.series.record(id, *value);
+        }
+        for (core, usage) in stats.cpu_cores.iter().enumerate() {
+            self.series.record(&MetricId::Core(core), *usage);
+        }
+        for (core, mhz) in stats.cpu_frequencies.iter().enumerate() {
+            self.series.record(&MetricId::CoreFrequency(core), *mhz);
+        }
+        self.memory_total = stats.memory.total;
         
-        for (i, core_usage) in stats.cpu_cores.iter().enumerate() {
-            if i < self.cpu_cores.len() {
-                Self::add_value(&mut self.cpu_cores[i], *core_usage);
+        for (index, gpu) in stats.gpu.iter().enumerate() {
+            let values = [
+                gpu.utilization,
+                gpu.memory_used,
+                gpu.temperature.unwrap_or(f64::NAN),
+                gpu.power.unwrap_or(f64::NAN),
+                gpu.video.unwrap_or(f64::NAN),
+            ];
+            for (field, value) in GPU_FIELDS.into_iter().zip(values) {
+                self.series.register(MetricId::Gpu(index, field));
+                self.series.record(&MetricId::Gpu(index, field), value);
             }
         }
         
-        Self::add_value(&mut self.memory, stats.memory.used_percentage);
-        self.memory_total = stats.memory.total;
-        Self::add_value(&mut self.memory_apps, stats.memory.apps);
-        Self::add_value(&mut self.memory_cached, stats.memory.cached);
-        Self::add_value(&mut self.memory_buffers, stats.memory.buffers);
-        Self::add_value(&mut self.memory_slab, stats.memory.slab);
-        Self::add_value(&mut self.memory_shmem, stats.memory.shmem);
-        Self::add_value(&mut self.network_download, stats.network_download);
-        Self::add_value(&mut self.network_upload, stats.network_upload);
-        self.last_update = stats.timestamp;
-    }
-    
-    fn add_value(queue: &mut VecDeque<f64>, value: f64) {
-        queue.push_back(value);
-        if queue.len() > HISTORY_SIZE {
-            queue.pop_front();
+        // Sensors don't come and go, but one can fail to read for a tick
+        for name in stats.temperatures.keys() {
+            self.series.register(MetricId::Temperature(name.clone()));
+        }
+        let sensors: Vec<String> = self.series.temperatures().into_keys().map(str::to_string).collect();
+        for name in sensors {
+            let celsius = stats.temperatures.get(&name).copied().unwrap_or(f64::NAN);
+            self.series.record(&MetricId::Temperature(name), celsius);
         }
-    }
-}
-
-// CPU tracking - overall and per-core
-static mut PREV_CPU_VALUES: Option<(f64, f64, f64)> = None; // (total, idle, iowait)
-static mut PREV_CORE_VALUES: Option<Vec<(f64, f64)>> = None; // per-core (total, idle)
-
-#[derive(Debug)]
-struct CpuStats {
-    overall_usage: f64,
-    core_usage: Vec<f64>,
-    iowait_percentage: f64,
-}
-
-fn read_cpu_stats() -> CpuStats {
-    let mut result = CpuStats {
-        overall_usage: 0.0,
-        core_usage: Vec::new(),
-        iowait_percentage: 0.0,
-    };
-    
-    if let Ok(content) = fs::read_to_string("/proc/stat") {
-        let lines: Vec<&str> = content.lines().collect();
         
-        // Parse overall CPU (first line)
-        if let Some(line) = lines.first() {
-            if line.starts_with("cpu ") {
-                let parts: Vec<&str> = line.split_whitespace().collect();
-                if parts.len() >= 8 {
-                    let user = parts[1].parse::<f64>().unwrap_or(0.0);
-                    let nice = parts[2].parse::<f64>().unwrap_or(0.0);
-                    let system = parts[3].parse::<f64>().unwrap_or(0.0);
-                    let idle = parts[4].parse::<f64>().unwrap_or(0.0);
-                    let iowait = parts[5].parse::<f64>().unwrap_or(0.0);
-                    let irq = parts[6].parse::<f64>().unwrap_or(0.0);
-                    let softirq = parts[7].parse::<f64>().unwrap_or(0.0);
-                    
-                    let idle_time = idle;
-                    let non_idle = user + nice + system + irq + softirq;
-                    let total = idle_time + non_idle + iowait;
-                    
-                    unsafe {
-                        if let Some((prev_total, prev_idle, prev_iowait)) = PREV_CPU_VALUES {
-                            let total_delta = total - prev_total;
-                            let idle_delta = idle_time - prev_idle;
-                            let iowait_delta = iowait - prev_iowait;
-                            
-                            PREV_CPU_VALUES = Some((total, idle_time, iowait));
-                            
-                            if total_delta > 0.0 {
-                                result.overall_usage = ((total_delta - idle_delta - iowait_delta) / total_delta) * 100.0;
-                                result.iowait_percentage = (iowait_delta / total_delta) * 100.0;
-                            }
-                        } else {
-                            PREV_CPU_VALUES = Some((total, idle_time, iowait));
-                        }
-                    }
-                }
-            }
+        // An interface that goes away reports 0 until its traffic has aged
+        // out of the window
+        for name in stats.network_interfaces.keys() {
+            self.series.register(MetricId::InterfaceDownload(name.clone()));
+            self.series.register(MetricId::InterfaceUpload(name.clone()));
+        }
+        let known: Vec<String> = self.series.interfaces().into_keys().map(str::to_string).collect();
+        for name in known {
+            let rates = stats.network_interfaces.get(&name);
+            self.series.record(&MetricId::InterfaceDownload(name.clone()), rates.map_or(0.0, |r| r.download));
+            self.series.record(&MetricId::InterfaceUpload(name), rates.map_or(0.0, |r| r.upload));
         }
+        // Signal is NaN while the interface isn't associated, so a dropout
+        // shows as a gap next to its traffic
+        for name in stats.wireless.keys().filter(|name| stats.network_interfaces.contains_key(*name)) {
+            self.series.register(MetricId::InterfaceSignal(name.clone()));
+        }
+        let wireless: Vec<String> = self
+            .series
+            .interfaces()
+            .into_iter()
+            .filter(|(_, s)| s.signal.is_some())
+            .map(|(name, _)| name.to_string())
+            .collect();
+        for name in wireless {
+            let dbm = stats.wireless.get(&name).map_or(f64::NAN, |link| link.signal_dbm);
+            self.series.record(&MetricId::InterfaceSignal(name), dbm);
+        }
+        let idle: Vec<String> = self
+            .series
+            .interfaces()
+            .into_iter()
+            .filter(|(name, _)| !stats.network_interfaces.contains_key(*name))
+            .filter(|(_, s)| s.download.values().iter().chain(s.upload.values()).all(|v| *v == 0.0))
+            .map(|(name, _)| name.to_string())
+            .collect();
+        self.series.retain(|id, _| match id {
+            MetricId::InterfaceDownload(name) | MetricId::InterfaceUpload(name) | MetricId::InterfaceSignal(name) => {
+                !idle.contains(name)
+            }
+            _ => true,
+        });
         
-        // Parse individual cores
-        let mut core_stats = Vec::new();
-        for line in lines.iter().skip(1) {
-            if line.starts_with("cpu") && line.contains("cpu") {
-                let parts: Vec<&str> = line.split_whitespace().collect();
-                if parts.len() >= 8 {
-                    let user = parts[1].parse::<f64>().unwrap_or(0.0);
-                    let nice = parts[2].parse::<f64>().unwrap_or(0.0);
-                    let system = parts[3].parse::<f64>().unwrap_or(0.0);
-                    let idle = parts[4].parse::<f64>().unwrap_or(0.0);
-                    let iowait = parts[5].parse::<f64>().unwrap_or(0.0);
-                    let irq = parts[6].parse::<f64>().unwrap_or(0.0);
-                    let softirq = parts[7].parse::<f64>().unwrap_or(0.0);
-                    
-                    let idle_time = idle;
-                    let non_idle = user + nice + system + irq + softirq;
-                    let total = idle_time + non_idle + iowait;
-                    
-                    core_stats.push((total, idle_time));
-                }
-            } else {
-                break; // End of CPU lines
+        for (name, value) in &stats.states {
+            if self.states.get(name).is_none_or(|s| s.value != *value) {
+                self.states.insert(name.clone(), StateValue { value: value.clone(), since: stats.timestamp });
             }
         }
         
-        unsafe {
-            if let Some(prev_cores) = &PREV_CORE_VALUES {
-                if prev_cores.len() == core_stats.len() {
-                    for ((total, idle), (prev_total, prev_idle)) in 
-                        core_stats.iter().zip(prev_cores.iter()) {
-                        
-                        let total_delta = total - prev_total;
-                        let idle_delta = idle - prev_idle;
-                        
-                        if total_delta > 0.0 {
-                            let usage = ((total_delta - idle_delta) / total_delta) * 100.0;
-                            result.core_usage.push(usage);
-                        } else {
-                            result.core_usage.push(0.0);
-                        }
-                    }
-                } else {
-                    // Core count mismatch, fill with zeros
-                    result.core_usage = vec![0.0; core_stats.len()];
-                }
-            } else {
-                // No previous data, fill with zeros
-                result.core_usage = vec![0.0; core_stats.len()];
-            }
-            
-            PREV_CORE_VALUES = Some(core_stats);
+        self.energy = stats.energy.clone();
+        self.apps = stats.apps.clone();
+        if stats.filesystems_sampled {
+            self.filesystems.record(stats.timestamp, &stats.filesystems);
         }
+        self.series.push_timestamp(stats.timestamp);
+        self.last_update = stats.timestamp;
+        self.latest = Some(stats.clone());
     }
     
-    result
-}
-
-fn read_memory_stats() -> MemoryStats {
-    let mut stats = MemoryStats::default();
-    if let Ok(content) = fs::read_to_string("/proc/meminfo") {
-        let mut mem_info = HashMap::new();
-        for line in content.lines() {
-            let parts: Vec<&str> = line.split(':').collect();
-            if parts.len() == 2 {
-                if let Ok(value) = parts[1].trim().split_whitespace().next().unwrap_or("0").parse::<f64>() {
-                    mem_info.insert(parts[0], value);
-                }
-            }
+    /// Pads every series with nulls (NaN serializes as null) for ticks that
+    /// never ran, starting one interval after `from`.
+    fn backfill(&mut self, from: i64, count: u64, interval_ms: u64) {
+        self.revision += 1;
+        for i in 1..=count.min(self.series.timestamps().capacity() as u64) {
+            self.series.pad(from + (i * interval_ms) as i64);
         }
-
-        let total = mem_info.get("MemTotal").copied().unwrap_or(0.0);
-        let available = mem_info.get("MemAvailable").copied().unwrap_or(0.0);
-        let active_anon = mem_info.get("Active(anon)").copied().unwrap_or(0.0);
-        let inactive_anon = mem_info.get("Inactive(anon)").copied().unwrap_or(0.0);
-        let shmem = mem_info.get("Shmem").copied().unwrap_or(0.0);
-        let slab = mem_info.get("Slab").copied().unwrap_or(0.0);
-        let buffers = mem_info.get("Buffers").copied().unwrap_or(0.0);
-        let cached = mem_info.get("Cached").copied().unwrap_or(0.0);
-
-        stats.total = total;
-        stats.available = available;
-        if total > 0.0 {
-            stats.used_percentage = ((total - available) / total) * 100.0;
+    }
+    
+    /// Continues a history saved by an earlier run, so clients connecting
+    /// right after a restart see what came before it. The restart itself is
+    /// a null slot one interval after the last saved sample, so graphs break
+    /// there rather than joining across it, and a "gap" marker up to `now`.
+    fn restore(&mut self, saved: &serde_json::Value, collectors: &EnabledCollectors, interval_ms: u64, now: i64) -> bool {
+        // Sensors, interfaces and GPUs register as they're seen; the rest
+        // are registered up front for the collectors that are on
+        let register = |id: &MetricId| match id {
+            MetricId::InterfaceDownload(_) | MetricId::InterfaceUpload(_) => collectors.network_interfaces,
+            MetricId::InterfaceSignal(_) => collectors.network_interfaces && collectors.wireless,
+            MetricId::Temperature(_) => collectors.hwmon,
+            MetricId::Gpu(..) => collectors.gpu,
+            _ => false,
+        };
+        let newest = saved["last_update"].as_i64().unwrap_or(0);
+        if newest <= 0 || newest >= now {
+            return false;
         }
-
-        stats.apps = active_anon + inactive_anon;
-        stats.cached = cached;
-        stats.buffers = buffers;
-        stats.slab = slab;
-        stats.shmem = shmem;
+        let Some(newest) = self.series.restore(saved, register) else {
+            return false;
+        };
+        self.revision += 1;
+        self.memory_total = saved["memory_total"].as_f64().unwrap_or(0.0);
+        if let Ok(markers) = serde_json::from_value::<Vec<HistoryMarker>>(saved["markers"].clone()) {
+            self.markers = markers;
+        }
+        let boundary = newest + interval_ms as i64;
+        self.series.pad(boundary);
+        self.last_update = boundary;
+        self.begin_marker("gap", "restart", boundary);
+        self.end_marker("gap", now.max(boundary));
+        true
+    }
+    
+    /// A scalar series by its name in the history, e.g. `series("cpu")`.
+    fn series(&self, name: &str) -> Option<&VecDeque<f64>> {
+        self.series.named(name)
+    }
+    
+    fn begin_marker(&mut self, kind: &str, label: &str, start: i64) {
+        self.revision += 1;
+        self.markers_revision += 1;
+        self.markers.push(HistoryMarker {
+            kind: kind.to_string(),
+            label: label.to_string(),
+            start,
+            end: None,
+        });
+        if self.markers.len() > load_test::MAX_MARKERS {
+            self.markers.remove(0);
+        }
+    }
+    
+    fn end_marker(&mut self, kind: &str, end: i64) {
+        self.end_marker_labeled(kind, None, end);
+    }
+    
+    // For kinds that can be open several at once, like one alert per rule
+    fn end_marker_labeled(&mut self, kind: &str, label: Option<&str>, end: i64) {
+        let open = |m: &&mut HistoryMarker| m.kind == kind && m.end.is_none() && label.is_none_or(|l| m.label == l);
+        if let Some(marker) = self.markers.iter_mut().rev().find(open) {
+            marker.end = Some(end);
+            self.revision += 1;
+            self.markers_revision += 1;
+        }
+    }
+    
+    fn clear_markers(&mut self) {
+        self.markers.clear();
+        self.revision += 1;
+        self.markers_revision += 1;
     }
-    stats
 }
 
-// Network tracking
-static mut PREV_NET_VALUES: Option<(f64, f64, Instant)> = None;
+/// The `history` reply, serialized at most once per change to the history
+/// and shared between every client that asks for it in the meantime.
+#[derive(Default)]
+struct HistoryCache {
+    // (revision, serialized history)
+    cached: StdMutex<Option<(u64, Bytes)>>,
+}
 
-fn read_network_stats() -> (f64, f64) {
-    if let Ok(content) = fs::read_to_string("/proc/net/dev") {
-        let mut rx_bytes = 0u64;
-        let mut tx_bytes = 0u64;
-        
-        for line in content.lines() {
-            // Skip loopback and header lines
-            if line.contains(':') && !line.contains("lo:") {
-                let parts: Vec<&str> = line.split(':').collect();
-                if parts.len() == 2 {
-                    let values: Vec<&str> = parts[1].split_whitespace().collect();
-                    if values.len() >= 9 {
-                        rx_bytes += values[0].parse::<u64>().unwrap_or(0);
-                        tx_bytes += values[8].parse::<u64>().unwrap_or(0);
-                    }
-                }
-            }
-        }
-        
-        let now = Instant::now();
-        
-        unsafe {
-            if let Some((prev_rx, prev_tx, prev_time)) = PREV_NET_VALUES {
-                let time_diff = now.duration_since(prev_time).as_secs_f64();
-                
-                if time_diff > 0.0 {
-                    let download = ((rx_bytes as f64 - prev_rx) / 1024.0) / time_diff; // KB/s
-                    let upload = ((tx_bytes as f64 - prev_tx) / 1024.0) / time_diff;
-                    
-                    PREV_NET_VALUES = Some((rx_bytes as f64, tx_bytes as f64, now));
-                    
-                    return (download, upload);
-                }
+impl HistoryCache {
+    fn get(&self, history: &StatsHistory) -> Bytes {
+        // Held while serializing so concurrent misses don't all redo the work
+        let mut cached = self.cached.lock().unwrap();
+        match &*cached {
+            Some((revision, bytes)) if *revision == history.revision => bytes.clone(),
+            _ => {
+                let bytes = Bytes::from(serde_json::to_vec(history).unwrap_or_default());
+                *cached = Some((history.revision, bytes.clone()));
+                bytes
             }
-            
-            PREV_NET_VALUES = Some((rx_bytes as f64, tx_bytes as f64, now));
         }
     }
-    
-    (0.0, 0.0)
 }
 
-fn write_history(history: &StatsHistory) -> Result<(), Box<dyn std::error::Error>> {
+fn write_history(history: &StatsHistory, data_dir: &str) -> Result<(), Box<dyn std::error::Error>> {
     let json = serde_json::to_string_pretty(history)?;
-    let mut file = File::create(format!("{}/history.json", DATA_DIR))?;
+    let mut file = File::create(format!("{}/history.json", data_dir))?;
     file.write_all(json.as_bytes())?;
     Ok(())
 }
 
-fn write_latest(stats: &SystemStats) -> Result<(), Box<dyn std::error::Error>> {
+// The history this run continues: whichever of the per-tick history.json in
+// the data dir (a restarted service) and the copy saved at shutdown (a
+// reboot, which usually clears the data dir) is newer
+fn load_saved_history(data_dir: &str, persist_dir: &str) -> Option<serde_json::Value> {
+    [Path::new(data_dir), Path::new(persist_dir)]
+        .into_iter()
+        .filter_map(|dir| serde_json::from_str::<serde_json::Value>(&fs::read_to_string(dir.join("history.json")).ok()?).ok())
+        .max_by_key(|history| history["last_update"].as_i64().unwrap_or(0))
+}
+
+fn write_latest(stats: &serde_json::Value, data_dir: &str) -> Result<(), Box<dyn std::error::Error>> {
     let json = serde_json::to_string_pretty(stats)?;
-    let mut file = File::create(format!("{}/latest.json", DATA_DIR))?;
+    let mut file = File::create(format!("{}/latest.json", data_dir))?;
     file.write_all(json.as_bytes())?;
     Ok(())
 }
 
-async fn handle_client(mut stream: UnixStream, history: Arc<Mutex<StatsHistory>>) {
-    // Send the full history immediately when client connects
-    let hist = history.lock().await;
-    let json = serde_json::to_string(&*hist).unwrap_or_default();
-    drop(hist); // Release lock before async operation
-    
-    if let Err(e) = stream.write_all(json.as_bytes()).await {
-        eprintln!("Failed to send history to client: {}", e);
+async fn read_command(stream: &mut UnixStream) -> Result<Command, String> {
+    // Legacy clients never write, so fall back to the history after a short wait
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    match time::timeout(Duration::from_millis(COMMAND_TIMEOUT_MS), reader.read_line(&mut line)).await {
+        Ok(Ok(_)) => Command::parse(&line),
+        Ok(Err(e)) => Err(e.to_string()),
+        Err(_) => Ok(Command::History),
+    }
+}
+
+#[derive(Clone)]
+struct ServerContext {
+    // Clients only ever read; the collection loop and control commands are
+    // the only writers
+    history: Arc<RwLock<StatsHistory>>,
+    history_cache: Arc<HistoryCache>,
+    config: Arc<ServiceConfig>,
+    environment: Arc<Environment>,
+    // Collected once at startup
+    system_info: Arc<SystemInfo>,
+    patterns: Arc<Mutex<PatternStore>>,
+    summary: Arc<StdMutex<SummaryTracker>>,
+    chaos: Option<Arc<Chaos>>,
+    // Each tick's exported stats as a newline-terminated JSON line,
+    // serialized once for every subscriber
+    updates: broadcast::Sender<Arc<str>>,
+    started_at: i64,
+}
+
+async fn handle_client(mut stream: UnixStream, ctx: ServerContext, owner_uid: u32) {
+    let access = match stream.peer_cred() {
+        Ok(cred) => {
+            let read_only_gids = &ctx.config.socket.read_only_gids;
+            // Spares every connection an NSS lookup when no group is granted access
+            let groups = if read_only_gids.is_empty() { vec![cred.gid()] } else { protocol::groups_of(cred.uid(), cred.gid()) };
+            protocol::access_for(cred.uid(), &groups, owner_uid, read_only_gids)
+        }
+        Err(e) => {
+            eprintln!("Failed to read peer credentials: {}", e);
+            Access::Denied
+        }
+    };
+    
+    let response = if access == Access::Denied {
+        protocol::error_response("access denied").into()
+    } else {
+        match read_command(&mut stream).await {
+            Ok(command) if command.is_control() && access != Access::Control => {
+                protocol::error_response("control commands are restricted to the service owner").into()
+            }
+            Ok(Command::Subscribe) => {
+                stream_updates(&mut stream, &ctx).await;
+                return;
+            }
+            Ok(command) => execute_command(command, &ctx).await,
+            Err(e) => protocol::error_response(&e).into(),
+        }
+    };
+    
+    let response: Bytes = match &ctx.chaos {
+        Some(chaos) => chaos.mangle(response.to_vec()).into(),
+        None => response,
+    };
+    if let Err(e) = write_with_timeout(&mut stream, &response, &ctx).await {
+        eprintln!("Failed to send response to client: {}", e);
     }
     
     // Close connection after sending
     let _ = stream.shutdown().await;
 }
 
-async fn run_socket_server(history: Arc<Mutex<StatsHistory>>) {
+/// A client that stops reading only stalls its own task; after the timeout
+/// the write is abandoned and the connection dropped.
+async fn write_with_timeout(stream: &mut UnixStream, bytes: &[u8], ctx: &ServerContext) -> std::io::Result<()> {
+    let timeout = Duration::from_millis(ctx.config.socket.write_timeout_ms);
+    match time::timeout(timeout, stream.write_all(bytes)).await {
+        Ok(result) => result,
+        Err(_) => Err(std::io::Error::new(std::io::ErrorKind::TimedOut, "client stopped reading")),
+    }
+}
+
+// The broadcast channel holds at most `subscriber_queue` updates, so the
+// collection loop never waits on a subscriber; one that falls behind by more
+// than that sees `Lagged` and is disconnected.
+async fn stream_updates(stream: &mut UnixStream, ctx: &ServerContext) {
+    let mut updates = ctx.updates.subscribe();
+    loop {
+        let frame = match updates.recv().await {
+            Ok(frame) => frame,
+            Err(broadcast::error::RecvError::Lagged(missed)) => {
+                eprintln!("Disconnecting subscriber that fell {} updates behind", missed);
+                break;
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+        if let Err(e) = write_with_timeout(stream, frame.as_bytes(), ctx).await {
+            eprintln!("Disconnecting subscriber: {}", e);
+            break;
+        }
+    }
+    let _ = stream.shutdown().await;
+}
+
+async fn execute_command(command: Command, ctx: &ServerContext) -> Bytes {
+    if command.is_control() {
+        return execute_control(command, ctx).await.into();
+    }
+    let hist = ctx.history.read().await;
+    let reply = match command {
+        Command::History => return ctx.history_cache.get(&hist),
+        Command::Latest => match &hist.latest {
+            Some(stats) => privacy::export(stats, &ctx.config.privacy).to_string(),
+            None => protocol::error_response("no data collected yet"),
+        },
+        Command::Series { metric, last } => match hist.series(&metric) {
+            Some(values) => {
+                let timestamps = hist.series.timestamps().values();
+                let count = last.unwrap_or(values.len()).min(values.len());
+                serde_json::json!({
+                    "metric": metric,
+                    "timestamps": timestamps.iter().skip(timestamps.len() - count).collect::<Vec<_>>(),
+                    "values": values.iter().skip(values.len() - count).collect::<Vec<_>>(),
+                }).to_string()
+            }
+            None => protocol::error_response(&format!("no such series: {}", metric)),
+        },
+        Command::Status => serde_json::json!({
+            "pid": std::process::id(),
+            "version": env!("CARGO_PKG_VERSION"),
+            "started_at": ctx.started_at,
+            "last_update": hist.last_update,
+            "environment": &*ctx.environment,
+            "system": &*ctx.system_info,
+        }).to_string(),
+        Command::Energy => match &hist.energy {
+            Some(report) => privacy::export(report, &ctx.config.privacy).to_string(),
+            None => protocol::error_response("no power source readable (RAPL or battery)"),
+        },
+        Command::Apps => serde_json::json!({ "apps": privacy::export(&hist.apps, &ctx.config.privacy) }).to_string(),
+        Command::Filesystems => serde_json::to_string(&hist.filesystems).unwrap_or_default(),
+        Command::Summaries(count) => {
+            let count = count.unwrap_or(summary::DEFAULT_SUMMARY_COUNT);
+            serde_json::json!({ "boots": ctx.summary.lock().unwrap().recent(count) }).to_string()
+        }
+        Command::Metadata => metadata::describe(&ctx.environment.collectors).to_string(),
+        Command::Process(pid) => match process::read_process_memory(pid, Local::now().timestamp_millis()) {
+            Some(memory) => privacy::export(&memory, &ctx.config.privacy).to_string(),
+            None => protocol::error_response("no such process"),
+        },
+        // Streams rather than replying once; see handle_client
+        Command::Subscribe => protocol::error_response("subscribe must be the first command"),
+        Command::Hourly(weekday) => {
+            let patterns = ctx.patterns.lock().await;
+            serde_json::json!({ "weekday": weekday, "hours": patterns.hourly(weekday) }).to_string()
+        }
+        Command::Watches => {
+            let watches: Vec<_> = hist
+                .watches
+                .rules()
+                .iter()
+                .map(|rule| serde_json::json!({ "metric": rule.metric, "above": rule.above, "view": rule.open }))
+                .collect();
+            serde_json::json!({ "watches": watches }).to_string()
+        }
+        Command::Mark(_) | Command::ClearMarkers | Command::Watch { .. } | Command::Unwatch(_) => {
+            unreachable!("control commands take the write lock")
+        }
+    };
+    reply.into()
+}
+
+async fn execute_control(command: Command, ctx: &ServerContext) -> String {
+    let mut hist = ctx.history.write().await;
+    match command {
+        Command::Mark(label) => {
+            let now = Local::now().timestamp_millis();
+            hist.begin_marker("user", &label, now);
+            hist.end_marker("user", now);
+            protocol::ok_response()
+        }
+        Command::ClearMarkers => {
+            hist.clear_markers();
+            protocol::ok_response()
+        }
+        Command::Watch { metric, above, view } => {
+            if !metric.starts_with("filesystem:") && hist.series(&metric).is_none() {
+                return protocol::error_response(&format!("unknown metric: {}", metric));
+            }
+            hist.watches.set(AlertRule::watch(&metric, above, &view));
+            protocol::ok_response()
+        }
+        Command::Unwatch(metric) => {
+            let name = AlertRule::watch(&metric, 0.0, "").name;
+            if hist.watches.remove(&name) {
+                protocol::ok_response()
+            } else {
+                protocol::error_response(&format!("not watching {}", metric))
+            }
+        }
+        other => protocol::error_response(&format!("not a control command: {:?}", other)),
+    }
+}
+
+async fn run_socket_server(ctx: ServerContext) {
+    let socket_path = ctx.config.socket_path.as_str();
+    
     // Remove old socket if it exists
-    let _ = fs::remove_file(SOCKET_PATH);
+    let _ = fs::remove_file(socket_path);
     
-    let listener = match UnixListener::bind(SOCKET_PATH) {
+    let listener = match UnixListener::bind(socket_path) {
         Ok(l) => l,
         Err(e) => {
             eprintln!("Failed to bind socket: {}", e);
@@ -370,14 +781,20 @@ async fn run_socket_server(history: Arc<Mutex<StatsHistory>>) {
         }
     };
     
-    println!("Socket server listening on {}", SOCKET_PATH);
+    let mode = ctx.config.socket.effective_mode();
+    if let Err(e) = fs::set_permissions(socket_path, fs::Permissions::from_mode(mode)) {
+        eprintln!("Failed to set socket permissions: {}", e);
+    }
+    let owner_uid = unsafe { libc::geteuid() };
+    
+    println!("Socket server listening on {} (mode {:o})", socket_path, mode);
     
     loop {
         match listener.accept().await {
             Ok((stream, _)) => {
-                let hist_clone = history.clone();
+                let ctx_clone = ctx.clone();
                 tokio::spawn(async move {
-                    handle_client(stream, hist_clone).await;
+                    handle_client(stream, ctx_clone, owner_uid).await;
                 });
             }
             Err(e) => {
@@ -389,13 +806,61 @@ async fn run_socket_server(history: Arc<Mutex<StatsHistory>>) {
 
 #[tokio::main]
 async fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let config_path = args.iter().position(|a| a == "--config")
+        .and_then(|i| args.get(i + 1))
+        .map(PathBuf::from);
+    let (config, config_error) = match ServiceConfig::try_load(config_path.clone()) {
+        Ok(config) => (config, None),
+        Err(e) => {
+            eprintln!("{}", e);
+            (ServiceConfig::default(), Some(e))
+        }
+    };
+    
+    let _ = PROC_ROOT.set(PathBuf::from(&config.proc_root));
+    units::init(config.units);
+    
+    if args.iter().any(|a| a == "--check") {
+        let passed = self_check::run(&config, config_error.as_deref());
+        std::process::exit(if passed { 0 } else { 1 });
+    }
+    
+    if args.get(1).map(String::as_str) == Some("generate-unit") {
+        // Print the unit for the binary being run unless told otherwise
+        let exec = args.iter().position(|a| a == "--exec")
+            .and_then(|i| args.get(i + 1))
+            .map(PathBuf::from)
+            .or_else(|| std::env::current_exe().ok())
+            .unwrap_or_else(|| PathBuf::from("stats-service"));
+        let config_path = config_path.and_then(|p| fs::canonicalize(&p).ok().or(Some(p)));
+        print!("{}", systemd::generate_unit(&config, config_path.as_deref(), &exec));
+        return;
+    }
+    
+    if args.get(1).map(String::as_str) == Some("compare") {
+        let (Some(a), Some(b)) = (args.get(2), args.get(3)) else {
+            eprintln!("Usage: stats-service compare <recording-a> <recording-b>");
+            std::process::exit(2);
+        };
+        if let Err(e) = recording::compare(a, b) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    
     println!("Starting Enhanced AGS Stats Service...");
     
+    let config = Arc::new(config);
+    let data_dir = config.data_dir.clone();
+    
     // Create data directory
-    fs::create_dir_all(DATA_DIR).expect("Failed to create data directory");
+    fs::create_dir_all(&data_dir).expect("Failed to create data directory");
+    self_check::install_crash_log(&data_dir);
     
     // Check if service is already running
-    let pid_file = format!("{}/service.pid", DATA_DIR);
+    let pid_file = format!("{}/service.pid", data_dir);
     if Path::new(&pid_file).exists() {
         if let Ok(pid_str) = fs::read_to_string(&pid_file) {
             if let Ok(pid) = pid_str.trim().parse::<u32>() {
@@ -412,49 +877,385 @@ async fn main() {
     let mut pid_file = File::create(&pid_file).expect("Failed to create PID file");
     writeln!(pid_file, "{}", std::process::id()).expect("Failed to write PID");
     
-    let history = Arc::new(Mutex::new(StatsHistory::new()));
-    
     // Start socket server in background
-    let history_socket = history.clone();
+    let mut environment = Environment::detect();
+    environment.collectors.restrict(&config.metrics);
+    for metric in config.metrics.unknown() {
+        eprintln!("Unknown metric in config: {}", metric);
+    }
+    let environment = Arc::new(environment);
+    println!("Environment: {}", environment.summary());
+    
+    let mut history = StatsHistory::new(&environment.collectors, &config.history);
+    if let Some(saved) = load_saved_history(&data_dir, &config.persist_dir).filter(|_| !config.privacy.ram_only) {
+        if history.restore(&saved, &environment.collectors, UPDATE_INTERVAL_MS, Local::now().timestamp_millis()) {
+            println!("Restored history up to {}", history.last_update);
+        }
+    }
+    let history = Arc::new(RwLock::new(history));
+    
+    let patterns = if config.privacy.ram_only {
+        PatternStore::in_memory(config.pattern_weeks)
+    } else {
+        PatternStore::open(PathBuf::from(&config.persist_dir), config.pattern_weeks)
+    };
+    let patterns = Arc::new(Mutex::new(patterns));
+    let summary_dir = (!config.privacy.ram_only).then(|| PathBuf::from(&config.persist_dir));
+    let summary = Arc::new(StdMutex::new(SummaryTracker::new(summary_dir, Local::now().timestamp_millis())));
+    
+    let mut recorder = args.iter().position(|a| a == "--record")
+        .and_then(|i| args.get(i + 1))
+        .filter(|_| {
+            if config.privacy.ram_only {
+                eprintln!("Not recording: privacy.ram_only is set");
+            }
+            !config.privacy.ram_only
+        })
+        .and_then(|path| match recording::Recorder::open(Path::new(path)) {
+            Ok(recorder) => {
+                println!("Recording every tick to {}", path);
+                Some(recorder)
+            }
+            Err(e) => {
+                eprintln!("Failed to open recording {}: {}", path, e);
+                None
+            }
+        });
+    
+    let chaos = Chaos::from_args(&args).map(Arc::new);
+    if let Some(chaos) = &chaos {
+        println!("Chaos mode enabled (seed {}): expect delayed ticks and corrupt responses", chaos.seed());
+    }
+    
+    let (updates, _) = broadcast::channel(config.socket.subscriber_queue.max(1));
+    let server_ctx = ServerContext {
+        history: history.clone(),
+        history_cache: Arc::new(HistoryCache::default()),
+        config: config.clone(),
+        environment: environment.clone(),
+        system_info: Arc::new(SystemInfo::collect()),
+        patterns: patterns.clone(),
+        summary: summary.clone(),
+        chaos: chaos.clone(),
+        updates: updates.clone(),
+        started_at: Local::now().timestamp_millis(),
+    };
+    if config.http.enabled {
+        tokio::spawn(http::run_server(server_ctx.clone()));
+    }
     tokio::spawn(async move {
-        run_socket_server(history_socket).await;
+        run_socket_server(server_ctx).await;
     });
     
     // Main collection loop
-    let mut interval = time::interval(Duration::from_millis(UPDATE_INTERVAL_MS));
+    let mut interval_ms = UPDATE_INTERVAL_MS;
+    let mut interval = clock::aligned_interval(interval_ms);
+    // Cleared when the interval changes, since spacing to it no longer means anything
+    let mut previous_sample: Option<i64> = None;
+    // The markers revision last sent to subscribers
+    let mut markers_sent = 0;
+    let mut load_detector = LoadTestDetector::new();
+    let mut cpu_collector = CpuCollector::new();
+    let mut memory_collector = MemoryCollector::new().with_page_size(process::page_size());
+    let mut network_collector = NetworkCollector::new();
+    let mut process_io = ProcessIoCollector::new();
+    let mut top_processes = TopProcessCollector::new(&config.processes);
+    let mut cgroup_usage = CgroupUsage::new();
+    let mut energy_collector = EnergyCollector::new();
+    let mut quality_collector = QualityCollector::new();
+    let mut app_collector = AppCollector::new();
+    let mut filesystem_collector = FilesystemCollector::new(&config.filesystems);
+    let mut temperature_collector = TemperatureCollector::new();
+    let mut fan_collector = FanCollector::new();
+    let mut power_supply_collector = PowerSupplyCollector::new();
+    let mut gpu_collector = if environment.collectors.gpu { GpuCollector::new() } else { None };
+    let mut disk_collector = DiskCollector::new();
+    let mut frequency_collector = CpuFrequencyCollector::new();
+    let mut oom_reader = ProcReader::default();
+    let mut loadavg_reader = ProcReader::default();
+    let mut uptime_reader = ProcReader::default();
+    let mut activity_collector = KernelActivityCollector::new();
+    let mut irq_collector = IrqCollector::new();
+    let mut pressure_reader = ProcReader::default();
+    let mut sockstat_reader = ProcReader::default();
+    let mut wireless_collector = WirelessCollector::new();
+    let mut nvme_collector = NvmeCollector::new();
+    let mut numa_collector = NumaCollector::new();
+    let mut container_collector = ContainerCollector::new();
+    let mut slice_collector = SliceCollector::new();
+    let mut oom_kills = collectors::read_oom_kills(&mut oom_reader);
+    let mut suspended = clock::suspended_ms();
+    let mut alert_engine = AlertEngine::new(config.alerts.clone());
+    let mut state_tracker = StateTracker::new();
+    let mut fan_advisor = FanCurveAdvisor::new(&config.advisories);
+    let probe_results = Arc::new(StdMutex::new(ProbeResults::default()));
+    let probing = environment.collectors.network_quality && !config.network_probe.targets.is_empty();
+    if probing {
+        tokio::spawn(net_quality::run_probes(config.network_probe.clone(), probe_results.clone()));
+    }
+    let smart_results = Arc::new(StdMutex::new(BTreeMap::new()));
+    if config.smart.enabled && config.metrics.enabled("temperature") {
+        tokio::spawn(smart::run(config.smart.clone(), smart_results.clone()));
+    }
+    let container_names = Arc::new(StdMutex::new(ContainerNames::default()));
+    if environment.collectors.containers {
+        tokio::spawn(containers::run_names(container_names.clone()));
+    }
+    // A user service is stopped with SIGTERM at logout and shutdown, which
+    // is when the session summary is finalized
+    let mut terminate = signal(SignalKind::terminate()).expect("Failed to install SIGTERM handler");
+    let mut interrupt = signal(SignalKind::interrupt()).expect("Failed to install SIGINT handler");
+    systemd::notify("READY=1");
     
     loop {
-        interval.tick().await;
+        tokio::select! {
+            _ = interval.tick() => {}
+            _ = terminate.recv() => break,
+            _ = interrupt.recv() => break,
+        }
+        systemd::notify("WATCHDOG=1");
+        if let Some(delay) = chaos.as_ref().and_then(|c| c.tick_delay()) {
+            time::sleep(delay).await;
+        }
+        
+        let mut cpu_stats = cpu_collector.collect();
+        let mut memory_stats = memory_collector.collect();
+        let (download, upload) = network_collector.collect();
+        let network_units = units::get();
+        let (download, upload) = (network_units.network_rate(download), network_units.network_rate(upload));
+        let (disk_read, disk_write) = if environment.collectors.disk_io { disk_collector.collect() } else { (0.0, 0.0) };
         
-        let cpu_stats = read_cpu_stats();
-        let memory_stats = read_memory_stats();
-        let (download, upload) = read_network_stats();
+        if let Some(limits) = &environment.cgroup {
+            cgroup_usage.apply(limits, &mut cpu_stats, &mut memory_stats);
+        }
+        if !environment.collectors.per_core {
+            cpu_stats.core_usage.clear();
+        }
         
-        let stats = SystemStats {
-            timestamp: Local::now().timestamp_millis(),
+        let timestamp = clock::align(Local::now().timestamp_millis(), interval_ms);
+        let gpus = gpu_collector.as_mut().map(GpuCollector::collect).unwrap_or_default();
+        // On a hybrid laptop this is when the discrete GPU wakes and sleeps
+        let gpu_states: Vec<(String, &str)> =
+            gpus.iter().map(|gpu| (format!("gpu:{}", gpu.id), if gpu.active { "active" } else { "suspended" })).collect();
+        let states = state_tracker.update(
+            timestamp,
+            [
+                ("battery_status", environment.collectors.energy.then(energy::battery_status).flatten()),
+                ("vpn", Some(state::vpn_state(network_collector.interfaces().keys()))),
+            ]
+            .into_iter()
+            .chain(gpu_states.iter().map(|(name, value)| (name.as_str(), Some(value.to_string())))),
+        );
+        let mut stats = SystemStats {
+            timestamp,
             cpu_usage: cpu_stats.overall_usage,
             cpu_cores: cpu_stats.core_usage,
             cpu_iowait: cpu_stats.iowait_percentage,
+            cpu_frequencies: if environment.collectors.cpu_frequency { frequency_collector.collect() } else { Vec::new() },
+            load: collectors::read_loadavg(&mut loadavg_reader),
+            uptime: collectors::read_uptime(&mut uptime_reader, timestamp),
+            kernel_activity: activity_collector.collect(),
+            irqs: environment.collectors.irqs.then(|| irq_collector.collect()).flatten(),
+            pressure: environment.collectors.pressure.then(|| collectors::read_pressure_stats(&mut pressure_reader)),
+            sockets: environment.collectors.sockets.then(|| collectors::read_socket_stats(&mut sockstat_reader)).flatten(),
+            file_descriptors: environment.collectors.file_descriptors.then(|| collectors::read_file_descriptors(&mut sockstat_reader)).flatten(),
             memory: memory_stats,
             network_download: download,
             network_upload: upload,
+            network_unit: network_units.network_label().to_string(),
+            network_interfaces: if environment.collectors.network_interfaces {
+                network_collector
+                    .interfaces()
+                    .iter()
+                    .map(|(name, rates)| {
+                        let rates = InterfaceStats {
+                            download: network_units.network_rate(rates.download),
+                            upload: network_units.network_rate(rates.upload),
+                            ..rates.clone()
+                        };
+                        (name.clone(), rates)
+                    })
+                    .collect()
+            } else {
+                BTreeMap::new()
+            },
+            wireless: if environment.collectors.wireless { wireless_collector.collect().clone() } else { BTreeMap::new() },
+            disk_read,
+            disk_write,
+            disks: if environment.collectors.disk_io { disk_collector.disks().to_vec() } else { Vec::new() },
+            temperature: if environment.collectors.hwmon { temperature_collector.collect() } else { None },
+            temperatures: {
+                let mut temperatures = if environment.collectors.hwmon { temperature_collector.temperatures().clone() } else { BTreeMap::new() };
+                temperatures.extend(smart_results.lock().unwrap().iter().map(|(disk, celsius)| (format!("smart/{}", disk), *celsius)));
+                temperatures
+            },
+            fan: if environment.collectors.fans { fan_collector.collect() } else { None },
+            fans: if environment.collectors.fans { fan_collector.speeds().clone() } else { BTreeMap::new() },
+            gpu: gpus,
+            top_io: if environment.collectors.process_io { process_io.collect() } else { Vec::new() },
+            energy: environment.collectors.energy.then(|| energy_collector.collect()),
+            power_supply: environment.collectors.battery.then(|| power_supply_collector.collect()),
+            network_quality: if probing { quality_collector.collect(&probe_results) } else { None },
+            apps: if environment.collectors.apps { app_collector.collect() } else { Vec::new() },
+            top_processes: environment.collectors.processes.then(|| top_processes.collect()),
+            filesystems_sampled: environment.collectors.filesystems && filesystem_collector.collect(timestamp).is_some(),
+            filesystems: filesystem_collector.latest().to_vec(),
+            nvme: if environment.collectors.nvme { nvme_collector.collect(timestamp).to_vec() } else { Vec::new() },
+            numa: if environment.collectors.numa { numa_collector.collect() } else { Vec::new() },
+            containers: if environment.collectors.containers { container_collector.collect(&container_names) } else { Vec::new() },
+            slices: if environment.collectors.slices { slice_collector.collect() } else { Vec::new() },
+            states,
+            markers: None,
         };
         
+        let load = load_test::busiest(stats.cpu_usage, stats.gpu.iter().filter(|gpu| gpu.active).map(|gpu| gpu.utilization));
+        let transition = load_detector.update(stats.timestamp, load);
+        
+        let now_suspended = clock::suspended_ms();
+        let slept = now_suspended - std::mem::replace(&mut suspended, now_suspended);
+        if let Some(prev) = previous_sample.filter(|_| slept > interval_ms as i64) {
+            println!("Resumed after {} ms suspended", slept);
+            dbus::emit(&Signal::SuspendGap { start: prev, end: stats.timestamp, suspended_ms: slept });
+        }
+        let kills = collectors::read_oom_kills(&mut oom_reader);
+        if let (Some(total), Some(before)) = (kills, oom_kills) {
+            if total > before {
+                println!("OOM killer ran: {} process(es) killed", total - before);
+                dbus::emit(&Signal::OomKill { killed: total - before, total });
+            }
+        }
+        oom_kills = kills;
+        
         // Update history
         {
-            let mut hist = history.lock().await;
+            let mut hist = history.write().await;
+            let missed = previous_sample.map_or(0, |prev| clock::missed_ticks(prev, stats.timestamp, interval_ms));
+            if missed > 0 {
+                let prev = previous_sample.unwrap_or(stats.timestamp);
+                println!("Missed {} tick(s) before {}", missed, stats.timestamp);
+                match config.missed_ticks {
+                    MissedTickPolicy::Backfill => hist.backfill(prev, missed, interval_ms),
+                    MissedTickPolicy::Annotate => {
+                        hist.begin_marker("gap", &format!("{} missed ticks", missed), prev + interval_ms as i64);
+                        hist.end_marker("gap", stats.timestamp);
+                    }
+                    MissedTickPolicy::Ignore => {}
+                }
+            }
+            previous_sample = Some(stats.timestamp);
             hist.add_stats(&stats);
             
-            // Write to files
-            if let Err(e) = write_history(&hist) {
-                eprintln!("Failed to write history: {}", e);
+            match transition {
+                Some(LoadTestTransition::Started { since }) => {
+                    hist.begin_marker("load_test", "load test", since);
+                }
+                Some(LoadTestTransition::Ended { at }) => {
+                    hist.end_marker("load_test", at);
+                }
+                None => {}
+            }
+            
+            // Put back once their transitions have been handled below
+            let mut watches = std::mem::take(&mut hist.watches);
+            let latest = |metric: &str| match metric.strip_prefix("filesystem:") {
+                Some(mount) => hist.filesystems.used_percentage(mount),
+                None => hist.series(metric)?.back().copied(),
+            };
+            let mut alerts = alert_engine.update(stats.timestamp, latest);
+            alerts.extend(watches.update(stats.timestamp, latest));
+            for (rule, transition) in alerts {
+                match transition {
+                    AlertTransition::Raised { since, value, rate } => {
+                        println!("Alert {}: {} at {:.1}", rule.name, rule.metric, value);
+                        hist.begin_marker("alert", &rule.name, since);
+                        summary.lock().unwrap().alert_fired(&rule.name);
+                        let samples: Vec<f64> = hist.series(&rule.metric).into_iter().flatten().copied().collect();
+                        let timestamps: Vec<i64> = hist.series.timestamps().values().iter().copied().collect();
+                        // Skipped in RAM-only mode along with everything else on disk,
+                        // and for filesystems, which have no per-tick series
+                        let thumbnail = if config.privacy.ram_only || samples.is_empty() {
+                            None
+                        } else {
+                            alerts::render_thumbnail(rule, since, &samples, &timestamps, &data_dir)
+                                .map_err(|e| eprintln!("Failed to write alert thumbnail: {}", e))
+                                .ok()
+                        };
+                        alerts::notify(rule, value, rate, thumbnail.as_deref(), Path::new(&config.graph_window));
+                        dbus::emit(&Signal::AlertRaised { name: &rule.name, metric: &rule.metric, value, since });
+                    }
+                    AlertTransition::Cleared { at } => {
+                        println!("Alert {} cleared", rule.name);
+                        hist.end_marker_labeled("alert", Some(&rule.name), at);
+                        dbus::emit(&Signal::AlertCleared { name: &rule.name, metric: &rule.metric, at });
+                    }
+                }
+            }
+            hist.watches = watches;
+            
+            let duty = if environment.collectors.fans { fan_collector.duty() } else { None };
+            match fan_advisor.update(stats.timestamp, stats.temperature, duty) {
+                Some(AdvisoryTransition::Raised { since, message }) => {
+                    println!("Advisory: {}", message);
+                    hist.begin_marker("advisory", &message, since);
+                    advisories::notify(&message);
+                    dbus::emit(&Signal::Advisory { kind: "fan_curve", message: &message, since });
+                }
+                Some(AdvisoryTransition::Cleared { at }) => hist.end_marker("advisory", at),
+                None => {}
+            }
+            
+            if hist.markers_revision != markers_sent {
+                markers_sent = hist.markers_revision;
+                stats.markers = Some(hist.markers.clone());
             }
+            
+            // Serializing and writing the file only needs to read, so let
+            // clients back in while it happens
+            let hist = hist.downgrade();
+            if !config.privacy.ram_only {
+                if let Err(e) = write_history(&hist, &data_dir) {
+                    eprintln!("Failed to write history: {}", e);
+                }
+            }
+        }
+        
+        patterns.lock().await.record(&stats);
+        {
+            let mut summary = summary.lock().unwrap();
+            summary.record(&stats);
+            summary.save_if_due(stats.timestamp);
         }
         
-        if let Err(e) = write_latest(&stats) {
+        let exported = privacy::export(&stats, &config.privacy);
+        if let Err(e) = write_latest(&exported, &data_dir) {
             eprintln!("Failed to write latest stats: {}", e);
         }
         
+        if let Some(recorder) = recorder.as_mut() {
+            recorder.append(&exported);
+        }
+        
+        if !config.text_outputs.is_empty() {
+            text_outputs::write_all(&config.text_outputs, &data_dir, &exported);
+        }
+        
+        if updates.receiver_count() > 0 {
+            let _ = updates.send(Arc::from(format!("{}\n", exported)));
+        }
+        
+        // Sample faster while a load test is running so benchmarks get finer detail
+        if transition.is_some() {
+            interval_ms = if load_detector.is_active() {
+                println!("Load test detected, sampling every {} ms", load_test::LOAD_TEST_INTERVAL_MS);
+                load_test::LOAD_TEST_INTERVAL_MS
+            } else {
+                println!("Load test ended, sampling every {} ms", UPDATE_INTERVAL_MS);
+                UPDATE_INTERVAL_MS
+            };
+            interval = clock::aligned_interval(interval_ms);
+            previous_sample = None;
+        }
+        
         // Print current stats for debugging
         let core_summary = if stats.cpu_cores.len() <= 4 {
             format!("[{}]", stats.cpu_cores.iter().map(|c| format!("{:.1}", c)).collect::<Vec<_>>().join(","))
@@ -463,7 +1264,7 @@ async fn main() {
                    stats.cpu_cores[0], stats.cpu_cores[1], 
                    stats.cpu_cores[stats.cpu_cores.len()-2], stats.cpu_cores[stats.cpu_cores.len()-1])
         };
-        println!("CPU: {:.1}% {} | IO: {:.1}% | MEM: {:.1}% (A:{:.1} C:{:.1} B:{:.1} L:{:.1} S:{:.1}) | NET: ↓{:.1} ↑{:.1} KB/s", 
+        println!("CPU: {:.1}% {} | IO: {:.1}% | MEM: {:.1}% (A:{:.1} C:{:.1} B:{:.1} L:{:.1} S:{:.1}) | NET: ↓{:.1} ↑{:.1} {}", 
                  stats.cpu_usage, core_summary, stats.cpu_iowait, 
                  stats.memory.used_percentage,
                  stats.memory.apps / 1024.0, // to MB
@@ -471,6 +1272,18 @@ async fn main() {
                  stats.memory.buffers / 1024.0,
                  stats.memory.slab / 1024.0,
                  stats.memory.shmem / 1024.0,
-                 download, upload);
+                 download, upload, stats.network_unit);
+    }
+    
+    println!("Shutting down, saving session summary");
+    summary.lock().unwrap().save(Local::now().timestamp_millis());
+    // The data dir is usually on tmpfs; this copy is what survives a reboot
+    if !config.privacy.ram_only {
+        let hist = history.read().await;
+        let saved = fs::create_dir_all(&config.persist_dir).map_err(Into::into).and_then(|_| write_history(&hist, &config.persist_dir));
+        if let Err(e) = saved {
+            eprintln!("Failed to save history: {}", e);
+        }
     }
+    systemd::notify("STOPPING=1");
 }
@@ -0,0 +1,113 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+use crate::environment::EnabledCollectors;
+use crate::units;
+
+/// `series` values are sampled every tick and belong on a graph; `state`
+/// values are discrete, published only when they change, and are shown as a
+/// status instead.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MetricKind {
+    Series,
+    State,
+}
+
+/// How a history series should be rendered when the client has no opinion.
+#[derive(Debug, Serialize)]
+pub struct SeriesInfo {
+    #[serde(skip)]
+    pub key: &'static str,
+    pub kind: MetricKind,
+    pub label: &'static str,
+    pub unit: &'static str,
+    pub color: &'static str,
+    // None for series without a natural ceiling, e.g. network rates
+    pub max: Option<f64>,
+}
+
+const fn series(key: &'static str, label: &'static str, unit: &'static str, color: &'static str, max: Option<f64>) -> SeriesInfo {
+    SeriesInfo { key, kind: MetricKind::Series, label, unit, color, max }
+}
+
+const fn state(key: &'static str, label: &'static str, color: &'static str) -> SeriesInfo {
+    SeriesInfo { key, kind: MetricKind::State, label, unit: "", color, max: None }
+}
+
+const SERIES: &[SeriesInfo] = &[
+    series("cpu", "CPU", "%", "#89dceb", Some(100.0)),
+    series("cpu_iowait", "IO wait", "%", "#f9e2af", Some(100.0)),
+    series("load", "Load", "", "#cba6f7", None),
+    series("memory", "Memory", "%", "#a6e3a1", Some(100.0)),
+    series("memory_apps", "Apps", "KB", "#89b4fa", None),
+    series("memory_cached", "Cached", "KB", "#a6e3a1", None),
+    series("memory_buffers", "Buffers", "KB", "#f9e2af", None),
+    series("memory_slab", "Slab", "KB", "#fab387", None),
+    series("memory_shmem", "Shmem", "KB", "#cba6f7", None),
+    series("swap", "Swap", "%", "#f5c2e7", Some(100.0)),
+    series("swap_in", "Swap in", "KB/s", "#89b4fa", None),
+    series("swap_out", "Swap out", "KB/s", "#f38ba8", None),
+    series("pressure_cpu", "CPU pressure", "%", "#89dceb", Some(100.0)),
+    series("pressure_memory", "Memory pressure", "%", "#a6e3a1", Some(100.0)),
+    series("pressure_io", "IO pressure", "%", "#f9e2af", Some(100.0)),
+    series("context_switches", "Context switches", "/s", "#f5c2e7", None),
+    series("interrupts", "Interrupts", "/s", "#fab387", None),
+    series("forks", "Forks", "/s", "#a6e3a1", None),
+    series("sockets_tcp", "TCP sockets", "", "#89b4fa", None),
+    series("sockets_udp", "UDP sockets", "", "#94e2d5", None),
+    series("sockets_time_wait", "TIME_WAIT", "", "#f9e2af", None),
+    series("sockets_orphaned", "Orphaned sockets", "", "#f38ba8", None),
+    series("file_descriptors", "File descriptors", "", "#cba6f7", None),
+    series("file_descriptors_used", "File descriptors used", "%", "#cba6f7", Some(100.0)),
+    series("network_download", "Download", "KB/s", "#89dceb", None),
+    series("network_upload", "Upload", "KB/s", "#f38ba8", None),
+    series("disk_read", "Disk read", "KB/s", "#89b4fa", None),
+    series("disk_write", "Disk write", "KB/s", "#f38ba8", None),
+    series("temperature", "CPU temperature", "°C", "#fab387", Some(100.0)),
+    // A typical ceiling; `[fan] max_value` in the graph window config fits it to the machine
+    series("fan", "Fan", "RPM", "#94e2d5", Some(5000.0)),
+    series("power", "Power", "W", "#fab387", None),
+    series("battery", "Battery", "%", "#a6e3a1", Some(100.0)),
+    series("network_quality", "Network quality", "score", "#94e2d5", Some(100.0)),
+    state("battery_status", "Battery", "#a6e3a1"),
+    state("vpn", "VPN", "#cba6f7"),
+];
+
+impl SeriesInfo {
+    /// The unit values are in: `unit`, or for network rates the one `[units]`
+    /// chose.
+    pub fn unit(&self) -> &'static str {
+        match self.key {
+            "network_download" | "network_upload" => units::get().network_label(),
+            _ => self.unit,
+        }
+    }
+}
+
+/// Rendering hints for one series, e.g. `info("cpu")`.
+pub fn info(key: &str) -> Option<&'static SeriesInfo> {
+    SERIES.iter().find(|s| s.key == key)
+}
+
+/// The `metadata` reply: rendering hints for every series and state the
+/// history will contain, keyed by name.
+pub fn describe(collectors: &EnabledCollectors) -> Value {
+    let series: Map<String, Value> = SERIES
+        .iter()
+        .filter(|s| collectors.memory_breakdown || !s.key.starts_with("memory_"))
+        .filter(|s| collectors.hwmon || s.key != "temperature")
+        .filter(|s| collectors.disk_io || !s.key.starts_with("disk_"))
+        .filter(|s| collectors.fans || s.key != "fan")
+        .filter(|s| collectors.battery || s.key != "battery")
+        .filter(|s| collectors.pressure || !s.key.starts_with("pressure_"))
+        .filter(|s| collectors.sockets || !s.key.starts_with("sockets_"))
+        .filter(|s| collectors.file_descriptors || !s.key.starts_with("file_descriptors"))
+        .filter(|s| collectors.energy || s.key != "battery_status")
+        .map(|s| {
+            let mut info = serde_json::to_value(s).unwrap_or_default();
+            info["unit"] = s.unit().into();
+            (s.key.to_string(), info)
+        })
+        .collect();
+    serde_json::json!({ "version": env!("CARGO_PKG_VERSION"), "series": series })
+}
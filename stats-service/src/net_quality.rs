@@ -0,0 +1,160 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use tokio::net::TcpStream;
+use tokio::time;
+use crate::collectors::ProcReader;
+
+// Probes remembered for latency and loss, across all targets
+const PROBE_WINDOW: usize = 30;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ProbeConfig {
+    /// `host:port` targets; a TCP connect stands in for a ping since ICMP
+    /// sockets need privileges. Empty disables probing and the score.
+    pub targets: Vec<String>,
+    pub interval_secs: u64,
+    pub timeout_ms: u64,
+}
+
+impl Default for ProbeConfig {
+    fn default() -> Self {
+        ProbeConfig {
+            targets: vec!["1.1.1.1:53".to_string(), "8.8.8.8:53".to_string()],
+            interval_secs: 10,
+            timeout_ms: 1000,
+        }
+    }
+}
+
+/// Composite 0–100 score plus the inputs it was derived from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkQuality {
+    pub score: f64,
+    pub latency_ms: Option<f64>,
+    pub loss_percent: f64,
+    // Errors and drops per 100 packets since the previous tick
+    pub error_rate: f64,
+}
+
+/// Shared between the probe task and the collection loop.
+#[derive(Default)]
+pub struct ProbeResults {
+    // None for a probe that failed or timed out
+    rtts: VecDeque<Option<f64>>,
+}
+
+impl ProbeResults {
+    fn push(&mut self, rtt: Option<f64>) {
+        self.rtts.push_back(rtt);
+        if self.rtts.len() > PROBE_WINDOW {
+            self.rtts.pop_front();
+        }
+    }
+
+    fn latency_ms(&self) -> Option<f64> {
+        let mut ok: Vec<f64> = self.rtts.iter().flatten().copied().collect();
+        if ok.is_empty() {
+            return None;
+        }
+        ok.sort_by(|a, b| a.total_cmp(b));
+        Some(ok[ok.len() / 2])
+    }
+
+    fn loss_percent(&self) -> f64 {
+        if self.rtts.is_empty() {
+            return 0.0;
+        }
+        self.rtts.iter().filter(|r| r.is_none()).count() as f64 / self.rtts.len() as f64 * 100.0
+    }
+}
+
+pub async fn run_probes(config: ProbeConfig, results: Arc<Mutex<ProbeResults>>) {
+    let timeout = Duration::from_millis(config.timeout_ms);
+    let mut interval = time::interval(Duration::from_secs(config.interval_secs.max(1)));
+    loop {
+        interval.tick().await;
+        for target in &config.targets {
+            let start = Instant::now();
+            let rtt = match time::timeout(timeout, TcpStream::connect(target.as_str())).await {
+                Ok(Ok(_)) => Some(start.elapsed().as_secs_f64() * 1000.0),
+                _ => None,
+            };
+            results.lock().unwrap().push(rtt);
+        }
+    }
+}
+
+// Sums packets and errors+drops over non-loopback interfaces
+fn read_interface_counters(reader: &mut ProcReader) -> Option<(u64, u64)> {
+    let content = reader.read("net/dev")?;
+    let mut packets = 0;
+    let mut errors = 0;
+    for line in content.lines() {
+        let Some((name, values)) = line.split_once(':') else {
+            continue;
+        };
+        if name.trim() == "lo" {
+            continue;
+        }
+        // rx: bytes packets errs drop ...; tx starts at field 8
+        let mut fields = [0u64; 12];
+        let mut count = 0;
+        for (field, value) in fields.iter_mut().zip(values.split_whitespace()) {
+            *field = value.parse().unwrap_or(0);
+            count += 1;
+        }
+        if count == fields.len() {
+            packets += fields[1] + fields[9];
+            errors += fields[2] + fields[3] + fields[10] + fields[11];
+        }
+    }
+    Some((packets, errors))
+}
+
+fn score(latency_ms: Option<f64>, loss_percent: f64, error_rate: f64) -> f64 {
+    // Under 20 ms costs nothing; 300 ms and up costs the full 40 points
+    let latency_penalty = latency_ms.map_or(40.0, |ms| ((ms - 20.0) / 280.0).clamp(0.0, 1.0) * 40.0);
+    let loss_penalty = (loss_percent * 2.0).min(40.0);
+    let error_penalty = (error_rate * 10.0).min(20.0);
+    (100.0 - latency_penalty - loss_penalty - error_penalty).clamp(0.0, 100.0)
+}
+
+#[derive(Default)]
+pub struct QualityCollector {
+    reader: ProcReader,
+    prev_counters: Option<(u64, u64)>,
+}
+
+impl QualityCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// None until the first probe round has finished.
+    pub fn collect(&mut self, results: &Mutex<ProbeResults>) -> Option<NetworkQuality> {
+        let counters = read_interface_counters(&mut self.reader);
+        let error_rate = match (counters, self.prev_counters) {
+            (Some((packets, errors)), Some((prev_packets, prev_errors))) if packets > prev_packets => {
+                errors.saturating_sub(prev_errors) as f64 / (packets - prev_packets) as f64 * 100.0
+            }
+            _ => 0.0,
+        };
+        self.prev_counters = counters;
+
+        let results = results.lock().unwrap();
+        if results.rtts.is_empty() {
+            return None;
+        }
+        let latency_ms = results.latency_ms();
+        let loss_percent = results.loss_percent();
+        Some(NetworkQuality {
+            score: score(latency_ms, loss_percent, error_rate),
+            latency_ms,
+            loss_percent,
+            error_rate,
+        })
+    }
+}
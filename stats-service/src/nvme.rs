@@ -0,0 +1,139 @@
+use std::fs::{self, File};
+use std::os::fd::AsRawFd;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+
+/// Wear changes over months, so the health log is read once a minute rather
+/// than every tick.
+pub const SAMPLE_INTERVAL_MS: i64 = 60_000;
+
+const NVME_CLASS: &str = "/sys/class/nvme";
+
+/// The SMART / Health Information log of one NVMe controller.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NvmeHealth {
+    // Controller name, e.g. "nvme0"
+    pub name: String,
+    pub model: String,
+    // °C, the composite temperature
+    pub temperature: f64,
+    // Percent of the rated endurance used; may pass 100
+    pub percentage_used: u8,
+    // Percent of spare blocks left, and the level the drive warns below
+    pub available_spare: u8,
+    pub available_spare_threshold: u8,
+    // Bit field; 0 when the drive reports nothing wrong
+    pub critical_warning: u8,
+    // Bytes, from the log's count of 512,000-byte units
+    pub data_written: u64,
+    pub power_on_hours: u64,
+    pub media_errors: u64,
+}
+
+const LOG_SIZE: usize = 512;
+
+// Little-endian; the log's 128-bit counters are read as their low 64 bits
+fn counter(log: &[u8; LOG_SIZE], offset: usize) -> u64 {
+    u64::from_le_bytes(log[offset..offset + 8].try_into().unwrap())
+}
+
+/// Decodes a SMART / Health log page (NVMe base spec, log identifier 02h).
+pub fn parse_health_log(name: &str, model: &str, log: &[u8; LOG_SIZE]) -> NvmeHealth {
+    let kelvin = u16::from_le_bytes([log[1], log[2]]);
+    NvmeHealth {
+        name: name.to_string(),
+        model: model.to_string(),
+        temperature: kelvin as f64 - 273.15,
+        percentage_used: log[5],
+        available_spare: log[3],
+        available_spare_threshold: log[4],
+        critical_warning: log[0],
+        data_written: counter(log, 48).saturating_mul(512_000),
+        power_on_hours: counter(log, 128),
+        media_errors: counter(log, 160),
+    }
+}
+
+// struct nvme_admin_cmd from linux/nvme_ioctl.h
+#[repr(C)]
+#[derive(Default)]
+struct AdminCommand {
+    opcode: u8,
+    flags: u8,
+    rsvd1: u16,
+    nsid: u32,
+    cdw2: u32,
+    cdw3: u32,
+    metadata: u64,
+    addr: u64,
+    metadata_len: u32,
+    data_len: u32,
+    cdw10: u32,
+    cdw11: u32,
+    cdw12: u32,
+    cdw13: u32,
+    cdw14: u32,
+    cdw15: u32,
+    timeout_ms: u32,
+    result: u32,
+}
+
+// _IOWR('N', 0x41, struct nvme_admin_cmd)
+const NVME_IOCTL_ADMIN_CMD: libc::c_ulong = 0xC048_4E41;
+const GET_LOG_PAGE: u8 = 0x02;
+const SMART_LOG: u32 = 0x02;
+
+// Needs read access to /dev/nvmeN, which is usually root's alone
+fn read_health_log(device: &Path) -> Option<[u8; LOG_SIZE]> {
+    let file = File::open(device).ok()?;
+    let mut log = [0u8; LOG_SIZE];
+    let mut command = AdminCommand {
+        opcode: GET_LOG_PAGE,
+        // The controller-wide log rather than one namespace's
+        nsid: 0xFFFF_FFFF,
+        addr: log.as_mut_ptr() as u64,
+        data_len: LOG_SIZE as u32,
+        // Number of dwords less one, then the log identifier
+        cdw10: ((LOG_SIZE as u32 / 4 - 1) << 16) | SMART_LOG,
+        ..Default::default()
+    };
+    let status = unsafe { libc::ioctl(file.as_raw_fd(), NVME_IOCTL_ADMIN_CMD as _, &mut command) };
+    (status == 0).then_some(log)
+}
+
+#[derive(Default)]
+pub struct NvmeCollector {
+    last_sample: Option<i64>,
+    latest: Vec<NvmeHealth>,
+}
+
+impl NvmeCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Health of every controller whose log is readable, refreshed once per
+    /// `SAMPLE_INTERVAL_MS` and repeated in between.
+    pub fn collect(&mut self, timestamp: i64) -> &[NvmeHealth] {
+        if self.last_sample.is_some_and(|last| timestamp - last < SAMPLE_INTERVAL_MS) {
+            return &self.latest;
+        }
+        self.last_sample = Some(timestamp);
+        let mut names: Vec<String> = fs::read_dir(NVME_CLASS)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|e| e.file_name().into_string().ok())
+            .collect();
+        names.sort();
+        self.latest = names
+            .iter()
+            .filter_map(|name| {
+                let log = read_health_log(&Path::new("/dev").join(name))?;
+                let model = fs::read_to_string(Path::new(NVME_CLASS).join(name).join("model")).unwrap_or_default();
+                Some(parse_health_log(name, model.trim(), &log))
+            })
+            .collect();
+        &self.latest
+    }
+}
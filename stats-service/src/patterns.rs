@@ -0,0 +1,180 @@
+use std::collections::VecDeque;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use chrono::{Datelike, Local, TimeZone, Timelike};
+use serde::{Deserialize, Serialize};
+use crate::SystemStats;
+
+const SAMPLES_FILE: &str = "samples.jsonl";
+const MS_PER_MINUTE: i64 = 60_000;
+const MS_PER_DAY: i64 = 24 * 60 * MS_PER_MINUTE;
+const MS_PER_WEEK: i64 = 7 * 24 * 60 * MS_PER_MINUTE;
+
+/// One persisted per-minute average, tagged with local weekday (0 = Monday) and hour.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaggedSample {
+    pub timestamp: i64,
+    pub weekday: u32,
+    pub hour: u32,
+    pub cpu: f64,
+    pub memory: f64,
+    // KiB/s whatever `[units]` says, so changing it doesn't mix units on disk
+    pub network_download: f64,
+    pub network_upload: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct HourAverage {
+    pub hour: u32,
+    pub samples: usize,
+    pub cpu: f64,
+    pub memory: f64,
+    pub network_download: f64,
+    pub network_upload: f64,
+}
+
+#[derive(Default)]
+struct MinuteAccumulator {
+    minute: i64,
+    count: usize,
+    cpu: f64,
+    memory: f64,
+    network_download: f64,
+    network_upload: f64,
+}
+
+pub struct PatternStore {
+    // None keeps samples in memory only
+    path: Option<PathBuf>,
+    weeks: u32,
+    samples: VecDeque<TaggedSample>,
+    current: MinuteAccumulator,
+    // When the file last held only the kept samples; appends since add a
+    // line per minute while as many fall out of the window
+    compacted_at: i64,
+}
+
+impl PatternStore {
+    /// Loads persisted samples from `dir`, dropping anything older than `weeks`.
+    pub fn open(dir: PathBuf, weeks: u32) -> Self {
+        let path = dir.join(SAMPLES_FILE);
+        let now = Local::now().timestamp_millis();
+        let cutoff = now - weeks as i64 * MS_PER_WEEK;
+        let samples: VecDeque<TaggedSample> = fs::read_to_string(&path)
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| serde_json::from_str::<TaggedSample>(line).ok())
+            .filter(|s| s.timestamp >= cutoff)
+            .collect();
+
+        let store = PatternStore { path: Some(path), weeks, samples, current: MinuteAccumulator::default(), compacted_at: now };
+        // Rewrite once so the file doesn't grow without bound across restarts
+        if let Err(e) = store.rewrite() {
+            eprintln!("Failed to write pattern samples: {}", e);
+        }
+        store
+    }
+
+    /// A store that never touches disk, so patterns only cover this run.
+    pub fn in_memory(weeks: u32) -> Self {
+        PatternStore { path: None, weeks, samples: VecDeque::new(), current: MinuteAccumulator::default(), compacted_at: 0 }
+    }
+
+    fn rewrite(&self) -> std::io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut content = String::new();
+        for sample in &self.samples {
+            content.push_str(&serde_json::to_string(sample).unwrap_or_default());
+            content.push('\n');
+        }
+        let tmp = path.with_extension("jsonl.tmp");
+        fs::write(&tmp, content)?;
+        fs::rename(&tmp, path)
+    }
+
+    fn append(&self, sample: &TaggedSample) -> std::io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        writeln!(file, "{}", serde_json::to_string(sample).unwrap_or_default())
+    }
+
+    /// Accumulates a tick; completes and persists the previous minute when the
+    /// minute rolls over, and rewrites the file once a day so a long-running
+    /// service doesn't keep weeks it has already dropped.
+    pub fn record(&mut self, stats: &SystemStats) {
+        let minute = stats.timestamp / MS_PER_MINUTE;
+        if self.current.count > 0 && minute != self.current.minute {
+            let acc = std::mem::take(&mut self.current);
+            let n = acc.count as f64;
+            let timestamp = acc.minute * MS_PER_MINUTE;
+            let local = Local.timestamp_millis_opt(timestamp).single().unwrap_or_else(Local::now);
+            let sample = TaggedSample {
+                timestamp,
+                weekday: local.weekday().num_days_from_monday(),
+                hour: local.hour(),
+                cpu: acc.cpu / n,
+                memory: acc.memory / n,
+                network_download: acc.network_download / n,
+                network_upload: acc.network_upload / n,
+            };
+            if let Err(e) = self.append(&sample) {
+                eprintln!("Failed to persist pattern sample: {}", e);
+            }
+            self.samples.push_back(sample);
+
+            let cutoff = timestamp - self.weeks as i64 * MS_PER_WEEK;
+            while self.samples.front().is_some_and(|s| s.timestamp < cutoff) {
+                self.samples.pop_front();
+            }
+            if self.path.is_some() && timestamp - self.compacted_at >= MS_PER_DAY {
+                // Tried again tomorrow rather than every minute
+                self.compacted_at = timestamp;
+                if let Err(e) = self.rewrite() {
+                    eprintln!("Failed to compact pattern samples: {}", e);
+                }
+            }
+        }
+
+        self.current.minute = minute;
+        self.current.count += 1;
+        self.current.cpu += stats.cpu_usage;
+        self.current.memory += stats.memory.used_percentage;
+        // The stats carry the configured unit
+        let kib_per_unit = crate::units::get().kib_per_unit();
+        self.current.network_download += stats.network_download * kib_per_unit;
+        self.current.network_upload += stats.network_upload * kib_per_unit;
+    }
+
+    /// Average usage for each hour of the day, optionally limited to one
+    /// weekday. Network rates are in the configured unit, like the history's.
+    pub fn hourly(&self, weekday: Option<u32>) -> Vec<HourAverage> {
+        let units = crate::units::get();
+        let mut hours: Vec<HourAverage> = (0..24).map(|hour| HourAverage { hour, ..Default::default() }).collect();
+        for sample in self.samples.iter().filter(|s| weekday.is_none_or(|d| s.weekday == d)) {
+            let entry = &mut hours[sample.hour as usize % 24];
+            entry.samples += 1;
+            entry.cpu += sample.cpu;
+            entry.memory += sample.memory;
+            entry.network_download += sample.network_download;
+            entry.network_upload += sample.network_upload;
+        }
+        for entry in &mut hours {
+            if entry.samples > 0 {
+                let n = entry.samples as f64;
+                entry.cpu /= n;
+                entry.memory /= n;
+                entry.network_download = units.network_rate(entry.network_download / n);
+                entry.network_upload = units.network_rate(entry.network_upload / n);
+            }
+        }
+        hours
+    }
+}
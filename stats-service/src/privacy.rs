@@ -0,0 +1,64 @@
+use serde::Deserialize;
+use serde_json::Value;
+
+const REDACTED: &str = "redacted";
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct PrivacyConfig {
+    /// Keep history and pattern samples in memory only: no history.json, no
+    /// samples.jsonl and no `--record`. latest.json is still written because
+    /// the widgets poll it; keep `data_dir` on a tmpfs.
+    pub ram_only: bool,
+    /// Replace process and application names in everything the service
+    /// writes or serves.
+    pub scrub_process_names: bool,
+    /// Replace wireless network names in everything the service writes or
+    /// serves.
+    pub scrub_ssids: bool,
+}
+
+impl PrivacyConfig {
+    pub fn scrubs(&self) -> bool {
+        self.scrub_process_names || self.scrub_ssids
+    }
+}
+
+// Process entries carry a pid, app groups a process count
+fn is_process_entry(map: &serde_json::Map<String, Value>) -> bool {
+    map.contains_key("pid") || map.contains_key("processes")
+}
+
+fn scrub_value(value: &mut Value, config: &PrivacyConfig) {
+    match value {
+        Value::Object(map) => {
+            if config.scrub_process_names && is_process_entry(map) {
+                for key in ["name", "id"] {
+                    if let Some(field) = map.get_mut(key).filter(|f| f.is_string()) {
+                        *field = Value::String(REDACTED.to_string());
+                    }
+                }
+            }
+            for (key, field) in map.iter_mut() {
+                if config.scrub_ssids && key == "ssid" && field.is_string() {
+                    *field = Value::String(REDACTED.to_string());
+                } else {
+                    scrub_value(field, config);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|item| scrub_value(item, config)),
+        _ => {}
+    }
+}
+
+/// Serializes `data` for anything that leaves the process (files, recordings,
+/// socket and HTTP replies, the subscription stream), with names scrubbed as
+/// configured.
+pub fn export<T: serde::Serialize>(data: &T, config: &PrivacyConfig) -> Value {
+    let mut value = serde_json::to_value(data).unwrap_or_default();
+    if config.scrubs() {
+        scrub_value(&mut value, config);
+    }
+    value
+}
@@ -0,0 +1,272 @@
+use std::collections::HashMap;
+use std::fs;
+use std::time::Instant;
+use serde::{Deserialize, Serialize};
+use crate::collectors::{MemoryCollector, ProcReader};
+
+pub const TOP_PROCESS_COUNT: usize = 5;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessIo {
+    pub pid: u32,
+    pub name: String,
+    // KB/s
+    pub read_rate: f64,
+    pub write_rate: f64,
+}
+
+/// Fills `pids` with the numeric entries of /proc, i.e. the currently running
+/// pids, reusing its allocation.
+pub fn list_pids_into(pids: &mut Vec<u32>) {
+    pids.clear();
+    if let Ok(entries) = fs::read_dir(crate::proc_path("")) {
+        for entry in entries.flatten() {
+            if let Some(pid) = entry.file_name().to_str().and_then(|n| n.parse::<u32>().ok()) {
+                pids.push(pid);
+            }
+        }
+    }
+}
+
+pub fn read_process_name(reader: &mut ProcReader, pid: u32) -> String {
+    reader.read_pid(pid, "comm").map(|s| s.trim_end().to_string()).unwrap_or_default()
+}
+
+/// utime + stime in clock ticks from /proc/[pid]/stat.
+pub fn read_process_cpu_ticks(reader: &mut ProcReader, pid: u32) -> Option<u64> {
+    let content = reader.read_pid(pid, "stat")?;
+    // The command name may contain spaces or parens, so split after the last ')'
+    let mut fields = content.get(content.rfind(')')? + 1..)?.split_whitespace().skip(11);
+    let utime = fields.next()?.parse::<u64>().ok()?;
+    let stime = fields.next()?.parse::<u64>().ok()?;
+    Some(utime + stime)
+}
+
+/// All CPU time on the machine in clock ticks, the denominator for per-process shares.
+pub fn read_total_cpu_ticks(reader: &mut ProcReader) -> Option<u64> {
+    let content = reader.read("stat")?;
+    let line = content.lines().find(|l| l.starts_with("cpu "))?;
+    Some(line.split_whitespace().skip(1).filter_map(|v| v.parse::<u64>().ok()).sum())
+}
+
+/// Bytes per page, the unit of statm and of vmstat's swap counters.
+pub fn page_size() -> u64 {
+    unsafe { libc::sysconf(libc::_SC_PAGESIZE) }.max(4096) as u64
+}
+
+/// Resident set size in KB from /proc/[pid]/statm.
+pub fn read_process_rss_kb(reader: &mut ProcReader, pid: u32) -> Option<f64> {
+    let content = reader.read_pid(pid, "statm")?;
+    let pages = content.split_whitespace().nth(1)?.parse::<u64>().ok()?;
+    Some(pages as f64 * page_size() as f64 / 1024.0)
+}
+
+// Storage-layer bytes from /proc/[pid]/io; only readable for our own processes
+// unless running as root.
+fn read_process_io(reader: &mut ProcReader, pid: u32) -> Option<(u64, u64)> {
+    let content = reader.read_pid(pid, "io")?;
+    let mut read_bytes = None;
+    let mut write_bytes = None;
+    for line in content.lines() {
+        if let Some(value) = line.strip_prefix("read_bytes:") {
+            read_bytes = value.trim().parse::<u64>().ok();
+        } else if let Some(value) = line.strip_prefix("write_bytes:") {
+            write_bytes = value.trim().parse::<u64>().ok();
+        }
+    }
+    Some((read_bytes?, write_bytes?))
+}
+
+#[derive(Default)]
+pub struct ProcessIoCollector {
+    reader: ProcReader,
+    pids: Vec<u32>,
+    prev: HashMap<u32, (u64, u64)>,
+    // Swapped with `prev` each tick so neither map is reallocated
+    current: HashMap<u32, (u64, u64)>,
+    prev_time: Option<Instant>,
+}
+
+impl ProcessIoCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the processes with the highest combined read+write rate since the
+    /// previous call. The first call only primes the counters.
+    pub fn collect(&mut self) -> Vec<ProcessIo> {
+        let now = Instant::now();
+        let elapsed = self.prev_time.map(|t| now.duration_since(t).as_secs_f64()).unwrap_or(0.0);
+        let mut top = Vec::new();
+
+        self.current.clear();
+        list_pids_into(&mut self.pids);
+        for &pid in &self.pids {
+            let Some((read_bytes, write_bytes)) = read_process_io(&mut self.reader, pid) else {
+                continue;
+            };
+            self.current.insert(pid, (read_bytes, write_bytes));
+
+            if elapsed <= 0.0 {
+                continue;
+            }
+            if let Some(&(prev_read, prev_write)) = self.prev.get(&pid) {
+                let read_rate = read_bytes.saturating_sub(prev_read) as f64 / 1024.0 / elapsed;
+                let write_rate = write_bytes.saturating_sub(prev_write) as f64 / 1024.0 / elapsed;
+                if read_rate + write_rate > 0.0 {
+                    top.push(ProcessIo {
+                        pid,
+                        name: String::new(),
+                        read_rate,
+                        write_rate,
+                    });
+                }
+            }
+        }
+
+        std::mem::swap(&mut self.prev, &mut self.current);
+        self.prev_time = Some(now);
+
+        top.sort_by(|a, b| (b.read_rate + b.write_rate).total_cmp(&(a.read_rate + a.write_rate)));
+        top.truncate(TOP_PROCESS_COUNT);
+        // Only resolve names for the processes that made the list
+        for entry in &mut top {
+            entry.name = read_process_name(&mut self.reader, entry.pid);
+        }
+        top
+    }
+}
+
+/// `[processes]` in the config.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ProcessConfig {
+    /// Scan /proc for the top processes every this many ticks; the lists are
+    /// repeated in between. A scan reads two files per process.
+    pub every_ticks: u32,
+}
+
+impl Default for ProcessConfig {
+    fn default() -> Self {
+        ProcessConfig { every_ticks: 1 }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TopProcess {
+    pub pid: u32,
+    pub name: String,
+    // Percent of total machine CPU capacity since the previous scan
+    pub cpu: f64,
+    // KB
+    pub rss: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TopProcesses {
+    pub by_cpu: Vec<TopProcess>,
+    pub by_memory: Vec<TopProcess>,
+}
+
+#[derive(Default)]
+pub struct TopProcessCollector {
+    reader: ProcReader,
+    pids: Vec<u32>,
+    prev_ticks: HashMap<u32, u64>,
+    // Swapped with `prev_ticks` each scan so neither map is reallocated
+    current: HashMap<u32, u64>,
+    prev_total_ticks: Option<u64>,
+    every_ticks: u32,
+    ticks_since_scan: u32,
+    last: TopProcesses,
+}
+
+impl TopProcessCollector {
+    pub fn new(config: &ProcessConfig) -> Self {
+        TopProcessCollector { every_ticks: config.every_ticks.max(1), ..Self::default() }
+    }
+
+    /// The processes using the most CPU and the most memory, TOP_PROCESS_COUNT
+    /// of each. CPU is zero on the first scan.
+    pub fn collect(&mut self) -> TopProcesses {
+        self.ticks_since_scan += 1;
+        if self.ticks_since_scan < self.every_ticks && self.prev_total_ticks.is_some() {
+            return self.last.clone();
+        }
+        self.ticks_since_scan = 0;
+
+        let total_ticks = read_total_cpu_ticks(&mut self.reader);
+        let total_delta = match (total_ticks, self.prev_total_ticks) {
+            (Some(now), Some(prev)) => now.saturating_sub(prev),
+            _ => 0,
+        };
+        self.prev_total_ticks = total_ticks;
+
+        let mut processes = Vec::new();
+        self.current.clear();
+        list_pids_into(&mut self.pids);
+        for &pid in &self.pids {
+            let Some(ticks) = read_process_cpu_ticks(&mut self.reader, pid) else {
+                continue;
+            };
+            self.current.insert(pid, ticks);
+            let rss = read_process_rss_kb(&mut self.reader, pid).unwrap_or(0.0);
+            // Kernel threads have no memory of their own
+            if rss == 0.0 {
+                continue;
+            }
+            let delta = self.prev_ticks.get(&pid).map_or(0, |prev| ticks.saturating_sub(*prev));
+            let cpu = if total_delta > 0 { delta as f64 / total_delta as f64 * 100.0 } else { 0.0 };
+            processes.push(TopProcess { pid, name: String::new(), cpu, rss });
+        }
+        std::mem::swap(&mut self.prev_ticks, &mut self.current);
+
+        let mut by_cpu = processes.clone();
+        by_cpu.sort_by(|a, b| b.cpu.total_cmp(&a.cpu).then(b.rss.total_cmp(&a.rss)));
+        by_cpu.truncate(TOP_PROCESS_COUNT);
+        let mut by_memory = processes;
+        by_memory.sort_by(|a, b| b.rss.total_cmp(&a.rss));
+        by_memory.truncate(TOP_PROCESS_COUNT);
+        // Only resolve names for the processes that made a list
+        for entry in by_cpu.iter_mut().chain(&mut by_memory) {
+            entry.name = read_process_name(&mut self.reader, entry.pid);
+        }
+        self.last = TopProcesses { by_cpu, by_memory };
+        self.last.clone()
+    }
+}
+
+/// One process's memory next to what the machine has left, for tracking a
+/// suspected leak.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessMemory {
+    pub pid: u32,
+    pub name: String,
+    // KB
+    pub rss: f64,
+    pub memory_available: f64,
+    pub timestamp: i64,
+}
+
+/// Reads `pid`, or the process with the largest RSS when None. None if the
+/// process doesn't exist (any more).
+pub fn read_process_memory(pid: Option<u32>, timestamp: i64) -> Option<ProcessMemory> {
+    let mut reader = ProcReader::default();
+    let (pid, rss) = match pid {
+        Some(pid) => (pid, read_process_rss_kb(&mut reader, pid)?),
+        None => {
+            let mut pids = Vec::new();
+            list_pids_into(&mut pids);
+            pids.into_iter()
+                .filter_map(|pid| Some((pid, read_process_rss_kb(&mut reader, pid)?)))
+                .max_by(|a, b| a.1.total_cmp(&b.1))?
+        }
+    };
+    Some(ProcessMemory {
+        pid,
+        name: read_process_name(&mut reader, pid),
+        rss,
+        memory_available: MemoryCollector::new().collect().available,
+        timestamp,
+    })
+}
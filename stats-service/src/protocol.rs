@@ -0,0 +1,183 @@
+// Clients may send a single command line after connecting. Clients that send
+// nothing (the original protocol) get the full history. `subscribe` keeps the
+// connection open and streams each tick's stats as one JSON object per line.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    History,
+    // The most recent tick's stats
+    Latest,
+    // One named series, optionally only its newest values
+    Series { metric: String, last: Option<usize> },
+    Status,
+    Hourly(Option<u32>),
+    Energy,
+    Apps,
+    Filesystems,
+    // Number of boots to return, the current one first
+    Summaries(Option<usize>),
+    Metadata,
+    // A pid, or None for the process with the largest RSS
+    Process(Option<u32>),
+    Subscribe,
+    Mark(String),
+    ClearMarkers,
+    // Notify when `metric` goes above `above`, offering to open `view`
+    Watch { metric: String, above: f64, view: String },
+    Unwatch(String),
+    Watches,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Access {
+    Control,
+    ReadOnly,
+    Denied,
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Command, String> {
+        let line = line.trim();
+        let (name, arg) = match line.split_once(char::is_whitespace) {
+            Some((name, arg)) => (name, arg.trim()),
+            None => (line, ""),
+        };
+        match name {
+            "" | "history" => Ok(Command::History),
+            "status" => Ok(Command::Status),
+            "latest" => Ok(Command::Latest),
+            "series" => parse_series(arg),
+            "energy" => Ok(Command::Energy),
+            "apps" => Ok(Command::Apps),
+            "filesystems" => Ok(Command::Filesystems),
+            "summaries" if arg.is_empty() => Ok(Command::Summaries(None)),
+            "summaries" => match arg.parse() {
+                Ok(count) if count > 0 => Ok(Command::Summaries(Some(count))),
+                _ => Err(format!("invalid count: {}", arg)),
+            },
+            "metadata" => Ok(Command::Metadata),
+            "process" if arg.is_empty() => Ok(Command::Process(None)),
+            "process" => arg.parse().map(|pid| Command::Process(Some(pid))).map_err(|_| format!("invalid pid: {}", arg)),
+            "subscribe" => Ok(Command::Subscribe),
+            "hourly" if arg.is_empty() => Ok(Command::Hourly(None)),
+            "hourly" => parse_weekday(arg).map(|d| Command::Hourly(Some(d))),
+            "mark" if !arg.is_empty() => Ok(Command::Mark(arg.to_string())),
+            "mark" => Err("mark requires a label".to_string()),
+            "clear-markers" => Ok(Command::ClearMarkers),
+            "watch" => parse_watch(arg),
+            "unwatch" if !arg.is_empty() => Ok(Command::Unwatch(arg.to_string())),
+            "unwatch" => Err("unwatch requires a metric".to_string()),
+            "watches" => Ok(Command::Watches),
+            _ => Err(format!("unknown command: {}", name)),
+        }
+    }
+
+    /// Control commands change service state and are limited to the owner.
+    pub fn is_control(&self) -> bool {
+        !matches!(
+            self,
+            Command::History
+                | Command::Latest
+                | Command::Series { .. }
+                | Command::Status
+                | Command::Hourly(_)
+                | Command::Energy
+                | Command::Apps
+                | Command::Filesystems
+                | Command::Summaries(_)
+                | Command::Metadata
+                | Command::Process(_)
+                | Command::Subscribe
+                | Command::Watches
+        )
+    }
+}
+
+// `<metric> [last]`, e.g. `cpu 300`
+fn parse_series(arg: &str) -> Result<Command, String> {
+    let mut words = arg.split_whitespace();
+    let metric = words.next().ok_or("series requires a metric")?.to_string();
+    let last = match words.next() {
+        Some(n) => Some(n.parse().map_err(|_| format!("invalid count: {}", n))?),
+        None => None,
+    };
+    Ok(Command::Series { metric, last })
+}
+
+// `<metric> <above> [view]`, e.g. `temperature 85 temp`; the view defaults
+// to the metric
+fn parse_watch(arg: &str) -> Result<Command, String> {
+    let mut words = arg.split_whitespace();
+    let metric = words.next().ok_or("watch requires a metric")?.to_string();
+    let above = words.next().ok_or("watch requires a threshold")?;
+    let above = above.parse::<f64>().ok().filter(|v| v.is_finite()).ok_or_else(|| format!("invalid threshold: {}", above))?;
+    let view = words.next().unwrap_or(&metric).to_string();
+    Ok(Command::Watch { metric, above, view })
+}
+
+// Accepts 0-6 (Monday first) or an English day name/abbreviation.
+fn parse_weekday(arg: &str) -> Result<u32, String> {
+    if let Ok(day) = arg.parse::<u32>() {
+        if day < 7 {
+            return Ok(day);
+        }
+    }
+    let lower = arg.to_ascii_lowercase();
+    ["mon", "tue", "wed", "thu", "fri", "sat", "sun"]
+        .iter()
+        .position(|d| lower.starts_with(d))
+        .map(|d| d as u32)
+        .ok_or_else(|| format!("invalid weekday: {}", arg))
+}
+
+/// `groups` is every group the client is in; see [`groups_of`].
+pub fn access_for(uid: u32, groups: &[u32], owner_uid: u32, read_only_gids: &[u32]) -> Access {
+    if uid == owner_uid || uid == 0 {
+        Access::Control
+    } else if groups.iter().any(|gid| read_only_gids.contains(gid)) {
+        Access::ReadOnly
+    } else {
+        Access::Denied
+    }
+}
+
+/// `gid` and the groups `uid`'s passwd entry is a member of. Peer credentials
+/// carry only the primary group, while read-only access is usually granted by
+/// adding the user to a supplementary one.
+pub fn groups_of(uid: u32, gid: u32) -> Vec<u32> {
+    let mut groups = vec![gid];
+    let mut passwd: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut found = std::ptr::null_mut();
+    let mut buf = vec![0 as libc::c_char; 16 * 1024];
+    let status = unsafe { libc::getpwuid_r(uid, &mut passwd, buf.as_mut_ptr(), buf.len(), &mut found) };
+    if status != 0 || found.is_null() {
+        return groups;
+    }
+    let mut count: libc::c_int = 64;
+    loop {
+        let mut list = vec![0 as libc::gid_t; count as usize];
+        let asked = count;
+        // -1 with count raised to what's needed when the list is too short
+        if unsafe { libc::getgrouplist(passwd.pw_name, gid, list.as_mut_ptr(), &mut count) } >= 0 {
+            list.truncate(count as usize);
+            groups.extend(list);
+            return groups;
+        }
+        if count <= asked {
+            return groups;
+        }
+    }
+}
+
+pub fn error_response(message: &str) -> String {
+    serde_json::json!({ "error": message }).to_string()
+}
+
+/// Whether `reply` came from [`error_response`].
+pub fn is_error(reply: &[u8]) -> bool {
+    reply.starts_with(br#"{"error":"#)
+}
+
+pub fn ok_response() -> String {
+    serde_json::json!({ "ok": true }).to_string()
+}
@@ -0,0 +1,183 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use serde_json::Value;
+use crate::units::UnitsConfig;
+
+const SPARK_CHARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+const SPARK_WIDTH: usize = 40;
+
+/// Appends every tick to an NDJSON file (one `latest.json` object per line)
+/// for later comparison with `stats-service compare`.
+pub struct Recorder {
+    path: PathBuf,
+    writer: BufWriter<File>,
+}
+
+impl Recorder {
+    pub fn open(path: &Path) -> std::io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Recorder { path: path.to_path_buf(), writer: BufWriter::new(file) })
+    }
+
+    pub fn append(&mut self, stats: &Value) {
+        let result = serde_json::to_writer(&mut self.writer, stats)
+            .map_err(std::io::Error::from)
+            .and_then(|_| self.writer.write_all(b"\n"))
+            .and_then(|_| self.writer.flush());
+        if let Err(e) = result {
+            eprintln!("Failed to append to recording {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// A recorded session as raw JSON so recordings from other versions still load.
+struct Session {
+    name: String,
+    samples: Vec<(i64, Value)>,
+}
+
+impl Session {
+    fn load(path: &str) -> Result<Self, String> {
+        let content = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let mut samples: Vec<(i64, Value)> = content
+            .lines()
+            .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+            .filter_map(|v| Some((v["timestamp"].as_i64()?, v)))
+            .collect();
+        samples.sort_by_key(|(ts, _)| *ts);
+        if samples.len() < 2 {
+            return Err(format!("{}: need at least two samples", path));
+        }
+        Ok(Session { name: path.to_string(), samples })
+    }
+
+    fn duration_ms(&self) -> i64 {
+        self.samples.last().map_or(0, |s| s.0) - self.samples[0].0
+    }
+
+    /// Samples within the first `window_ms`, with time relative to the start.
+    fn aligned(&self, window_ms: i64) -> Vec<(i64, &Value)> {
+        let start = self.samples[0].0;
+        self.samples
+            .iter()
+            .map(|(ts, v)| (ts - start, v))
+            .take_while(|(t, _)| *t <= window_ms)
+            .collect()
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Aggregate {
+    Mean,
+    P95,
+    Max,
+    /// Integrates a network rate over time, reported in MB.
+    TotalMb,
+}
+
+struct Metric {
+    label: &'static str,
+    path: &'static str,
+    aggregate: Aggregate,
+}
+
+const METRICS: &[Metric] = &[
+    Metric { label: "avg cpu %", path: "cpu_usage", aggregate: Aggregate::Mean },
+    Metric { label: "p95 cpu %", path: "cpu_usage", aggregate: Aggregate::P95 },
+    Metric { label: "avg iowait %", path: "cpu_iowait", aggregate: Aggregate::Mean },
+    Metric { label: "avg memory %", path: "memory.used_percentage", aggregate: Aggregate::Mean },
+    Metric { label: "max memory %", path: "memory.used_percentage", aggregate: Aggregate::Max },
+    Metric { label: "p95 temp °C", path: "temperature", aggregate: Aggregate::P95 },
+    Metric { label: "download MB", path: "network_download", aggregate: Aggregate::TotalMb },
+    Metric { label: "upload MB", path: "network_upload", aggregate: Aggregate::TotalMb },
+];
+
+fn lookup(value: &Value, path: &str) -> Option<f64> {
+    path.split('.').try_fold(value, |v, key| v.get(key))?.as_f64().filter(|x| x.is_finite())
+}
+
+// Recordings made under another `[units]` setting compare in KiB/s all the same
+fn kib_per_unit(sample: &Value) -> f64 {
+    sample["network_unit"].as_str().and_then(UnitsConfig::from_label).unwrap_or_default().kib_per_unit()
+}
+
+fn aggregate(samples: &[(i64, &Value)], metric: &Metric) -> Option<f64> {
+    let points: Vec<(i64, f64)> = samples
+        .iter()
+        .filter_map(|(t, v)| {
+            let value = lookup(v, metric.path)?;
+            Some((*t, if matches!(metric.aggregate, Aggregate::TotalMb) { value * kib_per_unit(v) } else { value }))
+        })
+        .collect();
+    if points.is_empty() {
+        return None;
+    }
+    let mut values: Vec<f64> = points.iter().map(|p| p.1).collect();
+    Some(match metric.aggregate {
+        Aggregate::Mean => values.iter().sum::<f64>() / values.len() as f64,
+        Aggregate::Max => values.iter().copied().fold(f64::MIN, f64::max),
+        Aggregate::P95 => {
+            values.sort_by(|a, b| a.total_cmp(b));
+            values[((values.len() - 1) as f64 * 0.95).round() as usize]
+        }
+        Aggregate::TotalMb => {
+            points.windows(2).map(|w| w[1].1 * (w[1].0 - w[0].0) as f64 / 1000.0).sum::<f64>() / 1024.0
+        }
+    })
+}
+
+// CPU over the aligned window, bucketed so both sessions line up column for column
+fn sparkline(samples: &[(i64, &Value)], window_ms: i64, path: &str, max: f64) -> String {
+    let mut buckets = vec![(0.0, 0usize); SPARK_WIDTH];
+    for (t, v) in samples {
+        if let Some(x) = lookup(v, path) {
+            let i = ((*t as f64 / window_ms.max(1) as f64) * SPARK_WIDTH as f64) as usize;
+            let bucket = &mut buckets[i.min(SPARK_WIDTH - 1)];
+            bucket.0 += x;
+            bucket.1 += 1;
+        }
+    }
+    buckets
+        .iter()
+        .map(|(sum, n)| match n {
+            0 => ' ',
+            n => {
+                let level = (sum / *n as f64 / max.max(f64::EPSILON)).clamp(0.0, 1.0);
+                SPARK_CHARS[(level * (SPARK_CHARS.len() - 1) as f64).round() as usize]
+            }
+        })
+        .collect()
+}
+
+/// `stats-service compare <a> <b>`: aligns the two recordings from their
+/// first sample, trims both to the shorter one and prints per-metric deltas.
+pub fn compare(path_a: &str, path_b: &str) -> Result<(), String> {
+    let a = Session::load(path_a)?;
+    let b = Session::load(path_b)?;
+    let window_ms = a.duration_ms().min(b.duration_ms());
+    let aligned_a = a.aligned(window_ms);
+    let aligned_b = b.aligned(window_ms);
+
+    println!("A: {} ({} samples, {:.0}s)", a.name, a.samples.len(), a.duration_ms() as f64 / 1000.0);
+    println!("B: {} ({} samples, {:.0}s)", b.name, b.samples.len(), b.duration_ms() as f64 / 1000.0);
+    println!("Compared over the first {:.0}s of each", window_ms as f64 / 1000.0);
+    println!();
+    println!("{:<14} {:>10} {:>10} {:>10} {:>8}", "metric", "A", "B", "B - A", "change");
+    for metric in METRICS {
+        let (Some(va), Some(vb)) = (aggregate(&aligned_a, metric), aggregate(&aligned_b, metric)) else {
+            continue;
+        };
+        let change = if va.abs() > f64::EPSILON {
+            format!("{:+.1}%", (vb - va) / va.abs() * 100.0)
+        } else {
+            "-".to_string()
+        };
+        println!("{:<14} {:>10.2} {:>10.2} {:>+10.2} {:>8}", metric.label, va, vb, vb - va, change);
+    }
+
+    println!();
+    println!("cpu A |{}|", sparkline(&aligned_a, window_ms, "cpu_usage", 100.0));
+    println!("cpu B |{}|", sparkline(&aligned_b, window_ms, "cpu_usage", 100.0));
+    Ok(())
+}
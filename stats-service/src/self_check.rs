@@ -0,0 +1,199 @@
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::panic;
+use crate::collectors::{CpuCollector, DiskCollector, MemoryCollector, NetworkCollector, TemperatureCollector};
+use crate::config::ServiceConfig;
+use crate::environment::Environment;
+use crate::process::ProcessIoCollector;
+
+// Older crashes were presumably dealt with; crash.log itself is never trimmed
+const CRASH_REPORT_DAYS: i64 = 7;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Status {
+    Pass,
+    Warn,
+    Fail,
+}
+
+struct Report {
+    failed: bool,
+}
+
+impl Report {
+    fn record(&mut self, status: Status, name: &str, detail: &str) {
+        let tag = match status {
+            Status::Pass => "PASS",
+            Status::Warn => "WARN",
+            Status::Fail => "FAIL",
+        };
+        if status == Status::Fail {
+            self.failed = true;
+        }
+        println!("[{}] {:<24} {}", tag, name, detail);
+    }
+
+    fn check_file(&mut self, name: &str, path: &str, required: bool, expect: &str) {
+        match fs::read_to_string(path) {
+            Ok(content) if content.contains(expect) => self.record(Status::Pass, name, path),
+            Ok(_) => self.record(Status::Fail, name, &format!("{} has unexpected contents", path)),
+            Err(e) => {
+                let status = if required { Status::Fail } else { Status::Warn };
+                self.record(status, name, &format!("{}: {}", path, e));
+            }
+        }
+    }
+}
+
+/// Installs a panic hook that appends the panic message to `crash.log` in the
+/// data dir, so crashes during startup or collection leave a trace for `--check`.
+pub fn install_crash_log(data_dir: &str) {
+    let crash_log = format!("{}/crash.log", data_dir);
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        if let Ok(mut file) = fs::OpenOptions::new().create(true).append(true).open(&crash_log) {
+            let _ = writeln!(file, "{} {}", chrono::Local::now().to_rfc3339(), info);
+        }
+        default_hook(info);
+    }));
+}
+
+fn run_collector<T>(report: &mut Report, name: &str, collect: impl FnOnce() -> T + panic::UnwindSafe) {
+    match panic::catch_unwind(collect) {
+        Ok(_) => report.record(Status::Pass, name, "collected"),
+        Err(_) => report.record(Status::Fail, name, "collector panicked"),
+    }
+}
+
+fn check_data_dir(report: &mut Report, data_dir: &str) {
+    if let Err(e) = fs::create_dir_all(data_dir) {
+        report.record(Status::Fail, "data dir", &format!("{}: {}", data_dir, e));
+        return;
+    }
+    let probe = format!("{}/.write-check", data_dir);
+    match fs::write(&probe, b"ok") {
+        Ok(()) => {
+            let _ = fs::remove_file(&probe);
+            report.record(Status::Pass, "data dir", &format!("{} is writable", data_dir));
+        }
+        Err(e) => report.record(Status::Fail, "data dir", &format!("{}: {}", data_dir, e)),
+    }
+
+    let crash_log = format!("{}/crash.log", data_dir);
+    let since = chrono::Local::now() - chrono::Duration::days(CRASH_REPORT_DAYS);
+    let last = fs::read_to_string(&crash_log).ok().and_then(|content| {
+        let last = content.lines().last()?.to_string();
+        let at = chrono::DateTime::parse_from_rfc3339(last.split_whitespace().next()?).ok()?;
+        (at >= since).then_some(last)
+    });
+    if let Some(last) = last {
+        report.record(Status::Warn, "previous crash", &last);
+    }
+}
+
+fn check_socket(report: &mut Report, socket_path: &str) {
+    // A live service already owns the path, so connecting is the check
+    let error = match UnixStream::connect(socket_path) {
+        Ok(_) => {
+            report.record(Status::Pass, "socket", &format!("{} accepts connections", socket_path));
+            return;
+        }
+        Err(e) => e,
+    };
+    match fs::symlink_metadata(socket_path) {
+        // Left behind by a service that's gone; binding needs the path free
+        Ok(meta) if meta.file_type().is_socket() && error.kind() == ErrorKind::ConnectionRefused => {
+            if let Err(e) = fs::remove_file(socket_path) {
+                report.record(Status::Fail, "socket", &format!("stale {}: {}", socket_path, e));
+                return;
+            }
+        }
+        Ok(meta) if meta.file_type().is_socket() => {
+            report.record(Status::Fail, "socket", &format!("{}: {}", socket_path, error));
+            return;
+        }
+        Ok(_) => {
+            report.record(Status::Fail, "socket", &format!("{} exists and is not a socket", socket_path));
+            return;
+        }
+        Err(_) => {}
+    }
+    match UnixListener::bind(socket_path) {
+        Ok(listener) => {
+            drop(listener);
+            let _ = fs::remove_file(socket_path);
+            report.record(Status::Pass, "socket", &format!("{} can be bound", socket_path));
+        }
+        Err(e) => report.record(Status::Fail, "socket", &format!("{}: {}", socket_path, e)),
+    }
+}
+
+/// Runs every collector once and validates the environment, printing a report.
+/// Returns true when no check failed.
+pub fn run(config: &ServiceConfig, config_error: Option<&str>) -> bool {
+    let mut report = Report { failed: false };
+    println!("AGS stats service self-test");
+
+    match config_error {
+        Some(e) => report.record(Status::Fail, "config", e),
+        None => report.record(Status::Pass, "config", "loaded"),
+    }
+    let unknown = config.metrics.unknown();
+    if !unknown.is_empty() {
+        report.record(
+            Status::Warn,
+            "metrics",
+            &format!("unknown metric(s) {}; known: {}", unknown.join(", "), crate::config::METRICS.join(", ")),
+        );
+    }
+
+    for (name, rel, required, expect) in [
+        ("/proc/stat", "stat", true, "cpu "),
+        ("/proc/meminfo", "meminfo", true, "MemTotal"),
+        ("/proc/net/dev", "net/dev", true, "|"),
+        ("/proc/self/io", "self/io", false, "read_bytes"),
+    ] {
+        report.check_file(name, &crate::proc_path(rel).to_string_lossy(), required, expect);
+    }
+    report.record(Status::Pass, "environment", &Environment::detect().summary());
+    match fs::read_dir("/sys/class") {
+        Ok(_) => report.record(Status::Pass, "/sys/class", "readable"),
+        Err(e) => report.record(Status::Warn, "/sys/class", &e.to_string()),
+    }
+
+    run_collector(&mut report, "cpu collector", || CpuCollector::new().collect());
+    run_collector(&mut report, "memory collector", || MemoryCollector::new().collect());
+    run_collector(&mut report, "network collector", || NetworkCollector::new().collect());
+    run_collector(&mut report, "process io collector", || ProcessIoCollector::new().collect());
+    run_collector(&mut report, "energy collector", || crate::energy::EnergyCollector::new().collect());
+    run_collector(&mut report, "app collector", || crate::apps::AppCollector::new().collect());
+    run_collector(&mut report, "cpu frequency collector", || crate::collectors::CpuFrequencyCollector::new().collect());
+    run_collector(&mut report, "disk collector", || DiskCollector::new().collect());
+    run_collector(&mut report, "temperature collector", || TemperatureCollector::new().collect());
+    run_collector(&mut report, "fan collector", || crate::collectors::FanCollector::new().collect());
+    run_collector(&mut report, "pressure", || crate::collectors::read_pressure_stats(&mut crate::collectors::ProcReader::default()));
+    run_collector(&mut report, "sockets", || crate::collectors::read_socket_stats(&mut crate::collectors::ProcReader::default()));
+    run_collector(&mut report, "file descriptors", || crate::collectors::read_file_descriptors(&mut crate::collectors::ProcReader::default()));
+    run_collector(&mut report, "nvme", || crate::nvme::NvmeCollector::new().collect(0).len());
+    run_collector(&mut report, "numa", || crate::collectors::NumaCollector::new().collect());
+    run_collector(&mut report, "irqs", || crate::collectors::IrqCollector::new().collect());
+    run_collector(&mut report, "containers", || crate::containers::ContainerCollector::new().collect(&Default::default()));
+    run_collector(&mut report, "slices", || crate::slices::SliceCollector::new().collect());
+    run_collector(&mut report, "wireless", || crate::wireless::WirelessCollector::new().collect().len());
+    run_collector(&mut report, "power supply collector", || crate::battery::PowerSupplyCollector::new().collect());
+    run_collector(&mut report, "gpu collector", || crate::gpu::GpuCollector::new().map(|mut c| c.collect()));
+    run_collector(&mut report, "top process collector", || crate::process::TopProcessCollector::new(&config.processes).collect());
+    run_collector(&mut report, "filesystem collector", || crate::filesystem::FilesystemCollector::new(&config.filesystems).collect(0));
+
+    check_data_dir(&mut report, &config.data_dir);
+    check_socket(&mut report, &config.socket_path);
+
+    if report.failed {
+        println!("Self-test failed");
+    } else {
+        println!("Self-test passed");
+    }
+    !report.failed
+}
@@ -0,0 +1,394 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Serialize, Serializer};
+
+// Points per series unless `[history]` in the config says otherwise
+pub const HISTORY_SIZE: usize = 60;
+
+/// How many points each history series keeps.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct HistoryConfig {
+    pub size: usize,
+    /// Overrides keyed by series name (`network_download`, `cpu_cores`) or by
+    /// its group, the part before the first `_` (`network` covers both rates
+    /// and the per-interface series).
+    pub metrics: BTreeMap<String, usize>,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        HistoryConfig { size: HISTORY_SIZE, metrics: BTreeMap::new() }
+    }
+}
+
+impl HistoryConfig {
+    pub fn size_for(&self, series: &str) -> usize {
+        let group = series.split('_').next().unwrap_or(series);
+        self.metrics.get(series).or_else(|| self.metrics.get(group)).copied().unwrap_or(self.size).max(1)
+    }
+}
+
+/// A series that holds at most `capacity` values, dropping the oldest once
+/// full. Serializes as a plain array, oldest first.
+#[derive(Debug, Clone)]
+pub struct RingBuffer<T = f64> {
+    capacity: usize,
+    values: VecDeque<T>,
+}
+
+impl<T: Copy + Default> RingBuffer<T> {
+    /// Full of default values, so every series starts at its full length.
+    pub fn padded(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        RingBuffer { capacity, values: std::iter::repeat_n(T::default(), capacity).collect() }
+    }
+
+    pub fn push(&mut self, value: T) {
+        if self.values.len() == self.capacity {
+            self.values.pop_front();
+        }
+        self.values.push_back(value);
+    }
+
+    // Extends at the old end, so existing values keep their newest slots
+    fn grow(&mut self, capacity: usize) {
+        while self.capacity < capacity {
+            self.values.push_front(T::default());
+            self.capacity += 1;
+        }
+    }
+
+    pub fn values(&self) -> &VecDeque<T> {
+        &self.values
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+impl<T: Serialize> Serialize for RingBuffer<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(&self.values)
+    }
+}
+
+/// Names a history series. Named series appear as top-level keys of the
+/// history; cores, core clocks, interfaces, temperature sensors and GPUs are
+/// grouped under `cpu_cores`, `cpu_frequencies`, `network_interfaces`,
+/// `temperatures` and `gpu`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum MetricId {
+    Named(Cow<'static, str>),
+    Core(usize),
+    // MHz of one core, NaN when it has no cpufreq policy
+    CoreFrequency(usize),
+    InterfaceDownload(String),
+    InterfaceUpload(String),
+    // dBm of one wireless interface, NaN while it isn't associated
+    InterfaceSignal(String),
+    // °C of one hwmon sensor, e.g. "coretemp/Package id 0"
+    Temperature(String),
+    // One of GPU_FIELDS of the GPU at this index of `SystemStats.gpu`
+    Gpu(usize, &'static str),
+}
+
+/// The per-GPU series, named as in `GpuStats`.
+pub const GPU_FIELDS: [&str; 5] = ["utilization", "memory_used", "temperature", "power", "video"];
+
+impl MetricId {
+    pub fn named(name: &str) -> Self {
+        MetricId::Named(Cow::Owned(name.to_string()))
+    }
+
+    // The `[history.metrics]` key its length is looked up by
+    fn size_key(&self) -> &str {
+        match self {
+            MetricId::Named(name) => name,
+            // As long as the usage series, so the two line up per core
+            MetricId::Core(_) | MetricId::CoreFrequency(_) => "cpu_cores",
+            MetricId::InterfaceDownload(_) | MetricId::InterfaceUpload(_) | MetricId::InterfaceSignal(_) => "network_interfaces",
+            MetricId::Temperature(_) => "temperatures",
+            MetricId::Gpu(..) => "gpu",
+        }
+    }
+}
+
+const fn builtin(name: &'static str) -> MetricId {
+    MetricId::Named(Cow::Borrowed(name))
+}
+
+pub const CPU: MetricId = builtin("cpu");
+pub const CPU_IOWAIT: MetricId = builtin("cpu_iowait");
+// 1-minute load average, NaN when unreadable
+pub const LOAD: MetricId = builtin("load");
+pub const MEMORY: MetricId = builtin("memory");
+// KB
+pub const MEMORY_APPS: MetricId = builtin("memory_apps");
+pub const MEMORY_CACHED: MetricId = builtin("memory_cached");
+pub const MEMORY_BUFFERS: MetricId = builtin("memory_buffers");
+pub const MEMORY_SLAB: MetricId = builtin("memory_slab");
+pub const MEMORY_SHMEM: MetricId = builtin("memory_shmem");
+// Percent of swap in use, 0 without swap
+pub const SWAP: MetricId = builtin("swap");
+// KB/s
+pub const SWAP_IN: MetricId = builtin("swap_in");
+pub const SWAP_OUT: MetricId = builtin("swap_out");
+// Percent of the last 10 s some task was stalled on the resource
+pub const PRESSURE_CPU: MetricId = builtin("pressure_cpu");
+pub const PRESSURE_MEMORY: MetricId = builtin("pressure_memory");
+pub const PRESSURE_IO: MetricId = builtin("pressure_io");
+// Per second, from the /proc/stat counters
+pub const CONTEXT_SWITCHES: MetricId = builtin("context_switches");
+pub const INTERRUPTS: MetricId = builtin("interrupts");
+pub const FORKS: MetricId = builtin("forks");
+// Open sockets; see `SocketStats`
+pub const SOCKETS_TCP: MetricId = builtin("sockets_tcp");
+pub const SOCKETS_UDP: MetricId = builtin("sockets_udp");
+pub const SOCKETS_TIME_WAIT: MetricId = builtin("sockets_time_wait");
+pub const SOCKETS_ORPHANED: MetricId = builtin("sockets_orphaned");
+// Allocated system-wide, and as a percentage of fs.file-max
+pub const FILE_DESCRIPTORS: MetricId = builtin("file_descriptors");
+pub const FILE_DESCRIPTORS_USED: MetricId = builtin("file_descriptors_used");
+pub const NETWORK_DOWNLOAD: MetricId = builtin("network_download");
+pub const NETWORK_UPLOAD: MetricId = builtin("network_upload");
+pub const DISK_READ: MetricId = builtin("disk_read");
+pub const DISK_WRITE: MetricId = builtin("disk_write");
+// °C, NaN when unreadable
+pub const TEMPERATURE: MetricId = builtin("temperature");
+// RPM of the fastest fan, NaN when unreadable
+pub const FAN: MetricId = builtin("fan");
+// Percent charge of all batteries together, NaN when unreadable
+pub const BATTERY: MetricId = builtin("battery");
+// Estimated system watts, 0 when no power source is readable
+pub const POWER: MetricId = builtin("power");
+// 0-100, 0 until the first probe round completes
+pub const NETWORK_QUALITY: MetricId = builtin("network_quality");
+
+/// Every history series, with the sample times they share. A collector adds
+/// its series with `register` and feeds it with `record`; nothing else needs
+/// to know about it.
+#[derive(Debug, Clone)]
+pub struct SeriesRegistry {
+    sizes: HistoryConfig,
+    series: HashMap<MetricId, RingBuffer>,
+    // Sample time (ms) for each slot, 0 for the initial padding. As long as
+    // the longest series; shorter ones line up with its newest entries.
+    timestamps: RingBuffer<i64>,
+}
+
+impl SeriesRegistry {
+    pub fn new(sizes: &HistoryConfig) -> Self {
+        SeriesRegistry { sizes: sizes.clone(), series: HashMap::new(), timestamps: RingBuffer::padded(1) }
+    }
+
+    /// Adds a series padded to its configured length. Registering one that
+    /// exists keeps its values.
+    pub fn register(&mut self, id: MetricId) {
+        let size = self.sizes.size_for(id.size_key());
+        self.timestamps.grow(size);
+        self.series.entry(id).or_insert_with(|| RingBuffer::padded(size));
+    }
+
+    /// Appends to a series. Values for unregistered series are dropped, so a
+    /// disabled collector stays out of the history without checks at every
+    /// call site.
+    pub fn record(&mut self, id: &MetricId, value: f64) {
+        if let Some(series) = self.series.get_mut(id) {
+            series.push(value);
+        }
+    }
+
+    /// Closes a tick: every series should have been recorded once.
+    pub fn push_timestamp(&mut self, timestamp: i64) {
+        self.timestamps.push(timestamp);
+    }
+
+    /// A tick that never ran: a NaN (serialized as null) in every series.
+    pub fn pad(&mut self, timestamp: i64) {
+        for series in self.series.values_mut() {
+            series.push(f64::NAN);
+        }
+        self.timestamps.push(timestamp);
+    }
+
+    /// Refills the buffers from a saved history, the shape this serializes
+    /// to, as if its ticks had just run: each slot is appended oldest first,
+    /// NaN for series the saved history doesn't have. Saved series are only
+    /// restored into registered ones, or into new ones `register` accepts,
+    /// so a collector turned off since stays off. Returns the newest saved
+    /// sample time, None when there was nothing to restore.
+    pub fn restore(&mut self, saved: &serde_json::Value, register: impl Fn(&MetricId) -> bool) -> Option<i64> {
+        let timestamps: Vec<i64> = saved["timestamps"].as_array()?.iter().map(|t| t.as_i64().unwrap_or(0)).collect();
+        let mut restored: Vec<(MetricId, &Vec<serde_json::Value>)> = Vec::new();
+        for (key, values) in saved.as_object()? {
+            if let Some(values) = values.as_array().filter(|_| self.series.contains_key(&MetricId::named(key))) {
+                restored.push((MetricId::named(key), values));
+            }
+        }
+        for (group, id) in [("cpu_cores", MetricId::Core as fn(usize) -> MetricId), ("cpu_frequencies", MetricId::CoreFrequency)] {
+            for (core, values) in saved[group].as_array().into_iter().flatten().enumerate() {
+                restored.extend(values.as_array().map(|values| (id(core), values)));
+            }
+        }
+        for (name, interface) in saved["network_interfaces"].as_object().into_iter().flatten() {
+            let ids = [MetricId::InterfaceDownload(name.clone()), MetricId::InterfaceUpload(name.clone()), MetricId::InterfaceSignal(name.clone())];
+            for (field, id) in ["download", "upload", "signal"].into_iter().zip(ids) {
+                restored.extend(interface[field].as_array().map(|values| (id, values)));
+            }
+        }
+        for (name, values) in saved["temperatures"].as_object().into_iter().flatten() {
+            restored.extend(values.as_array().map(|values| (MetricId::Temperature(name.clone()), values)));
+        }
+        for (index, gpu) in saved["gpu"].as_array().into_iter().flatten().enumerate() {
+            for field in GPU_FIELDS {
+                restored.extend(gpu[field].as_array().map(|values| (MetricId::Gpu(index, field), values)));
+            }
+        }
+        restored.retain(|(id, _)| self.series.contains_key(id) || register(id));
+        for (id, _) in &restored {
+            self.register(id.clone());
+        }
+
+        // Saved series are as long as their own config said, aligned with
+        // the newest timestamps; the padding slots (time 0) are skipped
+        let mut newest = None;
+        for (slot, &timestamp) in timestamps.iter().enumerate().filter(|(_, t)| **t > 0) {
+            for series in self.series.values_mut() {
+                series.push(f64::NAN);
+            }
+            for (id, values) in &restored {
+                let value = (slot + values.len()).checked_sub(timestamps.len()).and_then(|i| values.get(i)?.as_f64());
+                if let (Some(value), Some(series)) = (value, self.series.get_mut(id)) {
+                    *series.values.back_mut().unwrap() = value;
+                }
+            }
+            self.timestamps.push(timestamp);
+            newest = Some(timestamp);
+        }
+        newest
+    }
+
+    pub fn retain(&mut self, keep: impl FnMut(&MetricId, &mut RingBuffer) -> bool) {
+        self.series.retain(keep);
+    }
+
+    pub fn get(&self, id: &MetricId) -> Option<&VecDeque<f64>> {
+        self.series.get(id).map(RingBuffer::values)
+    }
+
+    /// A named series by the key it has in the history, e.g. `"cpu"`.
+    pub fn named(&self, name: &str) -> Option<&VecDeque<f64>> {
+        self.get(&MetricId::named(name))
+    }
+
+    pub fn timestamps(&self) -> &RingBuffer<i64> {
+        &self.timestamps
+    }
+
+    // Per-core series in core order
+    fn cores(&self, frequency: bool) -> Vec<&RingBuffer> {
+        let mut cores: Vec<(usize, &RingBuffer)> = self
+            .series
+            .iter()
+            .filter_map(|(id, series)| match id {
+                MetricId::Core(i) if !frequency => Some((*i, series)),
+                MetricId::CoreFrequency(i) if frequency => Some((*i, series)),
+                _ => None,
+            })
+            .collect();
+        cores.sort_by_key(|(i, _)| *i);
+        cores.into_iter().map(|(_, series)| series).collect()
+    }
+
+    /// Interfaces that have both rates registered, by name.
+    pub fn interfaces(&self) -> BTreeMap<&str, InterfaceSeries<'_>> {
+        self.series
+            .iter()
+            .filter_map(|(id, download)| match id {
+                MetricId::InterfaceDownload(name) => {
+                    let upload = self.series.get(&MetricId::InterfaceUpload(name.clone()))?;
+                    let signal = self.series.get(&MetricId::InterfaceSignal(name.clone()));
+                    Some((name.as_str(), InterfaceSeries { download, upload, signal }))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    // Per-GPU series in index order, each keyed by field
+    fn gpus(&self) -> Vec<BTreeMap<&str, &RingBuffer>> {
+        let mut gpus: Vec<BTreeMap<&str, &RingBuffer>> = Vec::new();
+        for (id, series) in &self.series {
+            if let MetricId::Gpu(index, field) = id {
+                if gpus.len() <= *index {
+                    gpus.resize_with(index + 1, BTreeMap::new);
+                }
+                gpus[*index].insert(field, series);
+            }
+        }
+        gpus
+    }
+
+    /// Per-sensor temperature series, by sensor name.
+    pub fn temperatures(&self) -> BTreeMap<&str, &RingBuffer> {
+        self.series
+            .iter()
+            .filter_map(|(id, series)| match id {
+                MetricId::Temperature(name) => Some((name.as_str(), series)),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct InterfaceSeries<'a> {
+    pub download: &'a RingBuffer,
+    pub upload: &'a RingBuffer,
+    // Wireless interfaces only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signal: Option<&'a RingBuffer>,
+}
+
+// Keeps the shape the history has always had: named series at the top
+// level, then the grouped ones and the timestamps
+impl Serialize for SeriesRegistry {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut named: Vec<(&str, &RingBuffer)> = self
+            .series
+            .iter()
+            .filter_map(|(id, series)| match id {
+                MetricId::Named(name) => Some((name.as_ref(), series)),
+                _ => None,
+            })
+            .collect();
+        named.sort_by_key(|(name, _)| *name);
+        let interfaces = self.interfaces();
+        let temperatures = self.temperatures();
+        let gpus = self.gpus();
+
+        let mut map = serializer.serialize_map(None)?;
+        for (name, series) in named {
+            map.serialize_entry(name, series)?;
+        }
+        map.serialize_entry("cpu_cores", &self.cores(false))?;
+        let frequencies = self.cores(true);
+        if !frequencies.is_empty() {
+            map.serialize_entry("cpu_frequencies", &frequencies)?;
+        }
+        if !interfaces.is_empty() {
+            map.serialize_entry("network_interfaces", &interfaces)?;
+        }
+        if !temperatures.is_empty() {
+            map.serialize_entry("temperatures", &temperatures)?;
+        }
+        if !gpus.is_empty() {
+            map.serialize_entry("gpu", &gpus)?;
+        }
+        map.serialize_entry("timestamps", &self.timestamps)?;
+        map.end()
+    }
+}
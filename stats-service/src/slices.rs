@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use serde::{Deserialize, Serialize};
+
+const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+
+// user.slice/user-1000.slice/user@1000.service/app.slice/app-….scope, with
+// room for a nested app.slice
+const MAX_DEPTH: usize = 6;
+
+/// Whether systemd lays out the unified hierarchy, which is what gives the
+/// slices their names.
+pub fn available() -> bool {
+    let root = Path::new(CGROUP_ROOT);
+    root.join("cgroup.controllers").exists() && root.join("system.slice").is_dir()
+}
+
+/// CPU and memory of one systemd slice or app unit, from its cgroup.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SliceStats {
+    // user.slice, or the application ID of an app unit, e.g. org.mozilla.firefox
+    pub name: String,
+    // The cgroup directory, e.g. app-flatpak-org.mozilla.firefox-2413.scope
+    pub unit: String,
+    // "slice" for the top-level slices, "app" for app units inside them
+    pub kind: String,
+    // Percent of total machine CPU capacity
+    pub cpu: f64,
+    // KB, page cache included, as memory.current counts it
+    pub memory: f64,
+}
+
+/// The application ID of a unit started by a desktop launcher, following
+/// systemd's `app[-<launcher>]-<ApplicationID>[@<RANDOM>].service` and
+/// `app[-<launcher>]-<ApplicationID>-<RANDOM>.scope` conventions. Dashes
+/// inside the ID are escaped as `\x2d`, so unescaped ones separate the parts.
+pub fn app_name(unit: &str) -> Option<String> {
+    let rest = unit.strip_prefix("app-")?;
+    let id = match (rest.strip_suffix(".service"), rest.strip_suffix(".scope")) {
+        (Some(service), _) => service.split_once('@').map_or(service, |(id, _)| id),
+        (_, Some(scope)) => scope.rsplit_once('-')?.0,
+        _ => return None,
+    };
+    // The launcher, e.g. gnome or flatpak, when there is one
+    let id = id.split_once('-').map_or(id, |(_, id)| id);
+    (!id.is_empty()).then(|| id.replace("\\x2d", "-"))
+}
+
+fn find_apps(dir: &Path, depth: usize, found: &mut Vec<(String, PathBuf)>) {
+    if depth > MAX_DEPTH {
+        return;
+    }
+    for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+        if !entry.file_type().is_ok_and(|t| t.is_dir()) {
+            continue;
+        }
+        let name = entry.file_name();
+        let Some(name) = name.to_str() else {
+            continue;
+        };
+        // An app's own sub-cgroups belong to it
+        if app_name(name).is_some() {
+            found.push((name.to_string(), entry.path()));
+        } else if name.ends_with(".slice") || name.ends_with(".service") {
+            find_apps(&entry.path(), depth + 1, found);
+        }
+    }
+}
+
+fn usage_usec(cgroup: &Path) -> Option<u64> {
+    let content = fs::read_to_string(cgroup.join("cpu.stat")).ok()?;
+    content.lines().find_map(|line| line.strip_prefix("usage_usec ")?.trim().parse().ok())
+}
+
+fn memory_kb(cgroup: &Path) -> f64 {
+    fs::read_to_string(cgroup.join("memory.current"))
+        .ok()
+        .and_then(|v| v.trim().parse::<f64>().ok())
+        .map_or(0.0, |bytes| bytes / 1024.0)
+}
+
+/// Reads the top-level slices and the app units below them each tick, so
+/// per-application usage comes from a handful of files rather than a scan
+/// of every process.
+#[derive(Default)]
+pub struct SliceCollector {
+    // CPU microseconds by cgroup path; units that went away are dropped each tick
+    prev: HashMap<PathBuf, (u64, Instant)>,
+}
+
+impl SliceCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The top-level slices in name order, then app units heaviest CPU
+    /// first. Rates are zero on a unit's first tick.
+    pub fn collect(&mut self) -> Vec<SliceStats> {
+        let root = Path::new(CGROUP_ROOT);
+        let mut slices: Vec<(String, String, PathBuf)> = fs::read_dir(root)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|entry| {
+                let name = entry.file_name().into_string().ok()?;
+                name.ends_with(".slice").then(|| (name.clone(), "slice".to_string(), entry.path()))
+            })
+            .collect();
+        slices.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut apps = Vec::new();
+        find_apps(root, 0, &mut apps);
+        let units = slices.into_iter().chain(apps.into_iter().map(|(unit, path)| (unit, "app".to_string(), path)));
+
+        let cpus = std::thread::available_parallelism().map_or(1, |n| n.get()) as f64;
+        let mut prev = std::mem::take(&mut self.prev);
+        let mut stats: Vec<SliceStats> = units
+            .map(|(unit, kind, path)| {
+                let mut cpu = 0.0;
+                if let Some(usec) = usage_usec(&path) {
+                    let now = Instant::now();
+                    if let Some((before, at)) = prev.remove(&path) {
+                        let secs = now.duration_since(at).as_secs_f64();
+                        if secs > 0.0 {
+                            cpu = (usec.saturating_sub(before) as f64 / 1e6 / secs / cpus * 100.0).min(100.0);
+                        }
+                    }
+                    self.prev.insert(path.clone(), (usec, now));
+                }
+                let name = if kind == "app" { app_name(&unit).unwrap_or_else(|| unit.clone()) } else { unit.clone() };
+                SliceStats { name, unit, kind, cpu, memory: memory_kb(&path) }
+            })
+            .collect();
+        let first_app = stats.iter().position(|slice| slice.kind == "app").unwrap_or(stats.len());
+        stats[first_app..].sort_by(|a, b| b.cpu.total_cmp(&a.cpu).then(b.memory.total_cmp(&a.memory)));
+        stats
+    }
+}
@@ -0,0 +1,94 @@
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::fs;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use serde::Deserialize;
+use tokio::process::Command;
+use tokio::time;
+use crate::collectors;
+
+/// `[smart]` in the config: drive temperatures from `smartctl` for disks
+/// without a hwmon sensor (no drivetemp module, USB bridges). Off by default
+/// since smartctl usually needs root or a `disk` group member.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SmartConfig {
+    pub enabled: bool,
+    /// Drive temperatures change slowly and each query is a round trip to
+    /// the drive, so they're polled far less often than the tick.
+    pub interval_secs: u64,
+    /// Disks to query, e.g. `["sdb"]`. Empty queries every whole disk that
+    /// has no hwmon sensor.
+    pub devices: Vec<String>,
+}
+
+impl Default for SmartConfig {
+    fn default() -> Self {
+        SmartConfig { enabled: false, interval_secs: 300, devices: Vec::new() }
+    }
+}
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Deserialize)]
+struct SmartTemperature {
+    current: f64,
+}
+
+#[derive(Deserialize)]
+struct SmartReport {
+    temperature: Option<SmartTemperature>,
+}
+
+/// °C from `smartctl --json -A`; None for a report without a temperature.
+pub fn parse_report(json: &str) -> Option<f64> {
+    serde_json::from_str::<SmartReport>(json).ok()?.temperature.map(|t| t.current)
+}
+
+fn disks_without_hwmon() -> Vec<String> {
+    let mut disks: Vec<String> = fs::read_dir("/sys/block")
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|e| e.file_name().into_string().ok())
+        .filter(|disk| collectors::is_whole_disk(disk) && !collectors::disk_has_hwmon(disk))
+        .collect();
+    disks.sort();
+    disks
+}
+
+// -n standby leaves a spun-down disk alone; smartctl then exits without a
+// report and the disk drops out until it's awake. Err only when smartctl
+// couldn't be run at all.
+async fn query(disk: &str) -> std::io::Result<Option<f64>> {
+    let device = format!("/dev/{}", disk);
+    let args = ["--json", "-A", "-n", "standby", device.as_str()].map(OsStr::new);
+    match time::timeout(TIMEOUT, Command::new("smartctl").args(args).kill_on_drop(true).output()).await {
+        Ok(output) => Ok(parse_report(&String::from_utf8_lossy(&output?.stdout))),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Polls smartctl for ever, keeping °C by disk name in `results`.
+pub async fn run(config: SmartConfig, results: Arc<Mutex<BTreeMap<String, f64>>>) {
+    let mut interval = time::interval(Duration::from_secs(config.interval_secs.max(1)));
+    loop {
+        interval.tick().await;
+        let disks = if config.devices.is_empty() { disks_without_hwmon() } else { config.devices.clone() };
+        let mut temperatures = BTreeMap::new();
+        for disk in disks {
+            match query(&disk).await {
+                Ok(Some(celsius)) => {
+                    temperatures.insert(disk, celsius);
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    eprintln!("Not polling SMART temperatures: smartctl: {}", e);
+                    return;
+                }
+            }
+        }
+        *results.lock().unwrap() = temperatures;
+    }
+}
@@ -0,0 +1,65 @@
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+
+/// How often every state is published again, changed or not, so a client
+/// that missed a change (or started after it) still catches up.
+pub const HEARTBEAT_MS: i64 = 60_000;
+
+// Interfaces that only exist while a tunnel is up
+const VPN_PREFIXES: &[&str] = &["wg", "tun", "tap", "ppp", "tailscale", "proton", "nordlynx"];
+
+/// A discrete metric's current value and when it took it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StateValue {
+    pub value: String,
+    pub since: i64,
+}
+
+/// Discrete metrics, e.g. battery status, change rarely, so rather than a
+/// series with the same value every tick they are published only when they
+/// change and on each heartbeat.
+#[derive(Debug, Default)]
+pub struct StateTracker {
+    current: BTreeMap<String, StateValue>,
+    last_heartbeat: Option<i64>,
+}
+
+impl StateTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Takes this tick's readings and returns what to publish: the states
+    /// that changed, or all of them when a heartbeat is due. A state that
+    /// can't be read this tick keeps its last value.
+    pub fn update<'a>(&mut self, timestamp: i64, readings: impl IntoIterator<Item = (&'a str, Option<String>)>) -> BTreeMap<String, String> {
+        let heartbeat = self.last_heartbeat.is_none_or(|last| timestamp - last >= HEARTBEAT_MS);
+        if heartbeat {
+            self.last_heartbeat = Some(timestamp);
+        }
+        let mut changed = BTreeMap::new();
+        for (name, value) in readings {
+            let Some(value) = value else {
+                continue;
+            };
+            match self.current.get_mut(name) {
+                Some(state) if state.value == value => continue,
+                Some(state) => *state = StateValue { value: value.clone(), since: timestamp },
+                None => {
+                    self.current.insert(name.to_string(), StateValue { value: value.clone(), since: timestamp });
+                }
+            }
+            changed.insert(name.to_string(), value);
+        }
+        if heartbeat {
+            return self.current.iter().map(|(name, state)| (name.clone(), state.value.clone())).collect();
+        }
+        changed
+    }
+}
+
+/// `connected` while any tunnel interface exists, otherwise `disconnected`.
+pub fn vpn_state<'a>(interfaces: impl IntoIterator<Item = &'a String>) -> String {
+    let connected = interfaces.into_iter().any(|name| VPN_PREFIXES.iter().any(|p| name.starts_with(p)));
+    if connected { "connected" } else { "disconnected" }.to_string()
+}
@@ -0,0 +1,202 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use crate::SystemStats;
+
+const SUMMARY_DIR: &str = "summaries";
+// A longer gap between samples is a suspend, not power drawn
+const MAX_ENERGY_STEP_MS: i64 = 10_000;
+// How often the running session is saved, so a crash loses little
+pub const SAVE_INTERVAL_MS: i64 = 5 * 60_000;
+pub const DEFAULT_SUMMARY_COUNT: usize = 7;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MetricSummary {
+    pub avg: f64,
+    pub max: f64,
+    pub samples: u64,
+}
+
+impl MetricSummary {
+    fn add(&mut self, value: f64) {
+        self.samples += 1;
+        self.avg += (value - self.avg) / self.samples as f64;
+        self.max = if self.samples == 1 { value } else { self.max.max(value) };
+    }
+
+    fn merge(&mut self, other: &MetricSummary) {
+        let samples = self.samples + other.samples;
+        if samples == 0 {
+            return;
+        }
+        self.avg = (self.avg * self.samples as f64 + other.avg * other.samples as f64) / samples as f64;
+        self.max = if self.samples == 0 { other.max } else { self.max.max(other.max) };
+        self.samples = samples;
+    }
+}
+
+/// One run of the service, which for a user service is one login session.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SessionSummary {
+    pub started_at: i64,
+    pub ended_at: i64,
+    // Keyed by history series name: cpu, memory, temperature, power, ...
+    pub metrics: BTreeMap<String, MetricSummary>,
+    // Times each alert rule fired
+    pub alerts: BTreeMap<String, u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub energy_wh: Option<f64>,
+}
+
+impl SessionSummary {
+    fn merge(&mut self, other: &SessionSummary) {
+        self.started_at = if self.started_at == 0 { other.started_at } else { self.started_at.min(other.started_at) };
+        self.ended_at = self.ended_at.max(other.ended_at);
+        for (key, metric) in &other.metrics {
+            self.metrics.entry(key.clone()).or_default().merge(metric);
+        }
+        for (name, count) in &other.alerts {
+            *self.alerts.entry(name.clone()).or_default() += count;
+        }
+        if let Some(wh) = other.energy_wh {
+            *self.energy_wh.get_or_insert(0.0) += wh;
+        }
+    }
+}
+
+/// Everything recorded during one boot, stored as
+/// `<persist_dir>/summaries/<boot_id>.json`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BootSummary {
+    pub boot_id: String,
+    pub sessions: Vec<SessionSummary>,
+    // The sessions combined
+    pub total: SessionSummary,
+}
+
+impl BootSummary {
+    // Replaces the session with the same start, or adds it
+    fn update(&mut self, session: &SessionSummary) {
+        match self.sessions.iter_mut().find(|s| s.started_at == session.started_at) {
+            Some(existing) => existing.clone_from(session),
+            None => self.sessions.push(session.clone()),
+        }
+        self.total = SessionSummary::default();
+        for session in &self.sessions {
+            self.total.merge(session);
+        }
+    }
+}
+
+/// The kernel's random ID for this boot, or "unknown" when hidden.
+pub fn boot_id() -> String {
+    fs::read_to_string(crate::proc_path("sys/kernel/random/boot_id"))
+        .map(|id| id.trim().to_string())
+        .ok()
+        .filter(|id| !id.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+pub struct SummaryTracker {
+    boot_id: String,
+    // None keeps summaries in memory only
+    dir: Option<PathBuf>,
+    session: SessionSummary,
+    last_timestamp: Option<i64>,
+    last_save: i64,
+}
+
+impl SummaryTracker {
+    pub fn new(dir: Option<PathBuf>, started_at: i64) -> Self {
+        SummaryTracker {
+            boot_id: boot_id(),
+            dir: dir.map(|d| d.join(SUMMARY_DIR)),
+            session: SessionSummary { started_at, ended_at: started_at, ..Default::default() },
+            last_timestamp: None,
+            last_save: started_at,
+        }
+    }
+
+    pub fn record(&mut self, stats: &SystemStats) {
+        let watts = stats.energy.as_ref().and_then(|e| e.system_watts());
+        let values = [
+            ("cpu", Some(stats.cpu_usage)),
+            ("memory", Some(stats.memory.used_percentage)),
+            ("network_download", Some(stats.network_download)),
+            ("network_upload", Some(stats.network_upload)),
+            ("temperature", stats.temperature),
+            ("power", watts),
+        ];
+        for (key, value) in values {
+            if let Some(value) = value.filter(|v| v.is_finite()) {
+                self.session.metrics.entry(key.to_string()).or_default().add(value);
+            }
+        }
+        if let (Some(watts), Some(last)) = (watts, self.last_timestamp) {
+            let step = stats.timestamp - last;
+            if step > 0 && step <= MAX_ENERGY_STEP_MS {
+                *self.session.energy_wh.get_or_insert(0.0) += watts * step as f64 / 3_600_000.0;
+            }
+        }
+        self.last_timestamp = Some(stats.timestamp);
+        self.session.ended_at = stats.timestamp;
+    }
+
+    pub fn alert_fired(&mut self, name: &str) {
+        *self.session.alerts.entry(name.to_string()).or_default() += 1;
+    }
+
+    /// Saves when the last save is older than SAVE_INTERVAL_MS.
+    pub fn save_if_due(&mut self, now: i64) {
+        if now - self.last_save >= SAVE_INTERVAL_MS {
+            self.save(now);
+        }
+    }
+
+    pub fn save(&mut self, now: i64) {
+        self.last_save = now;
+        if let Err(e) = self.write() {
+            eprintln!("Failed to write session summary: {}", e);
+        }
+    }
+
+    fn write(&self) -> io::Result<()> {
+        let Some(dir) = &self.dir else {
+            return Ok(());
+        };
+        fs::create_dir_all(dir)?;
+        let path = dir.join(format!("{}.json", self.boot_id));
+        let mut boot = self.load(&self.boot_id).unwrap_or_else(|| BootSummary { boot_id: self.boot_id.clone(), ..Default::default() });
+        boot.update(&self.session);
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string(&boot).map_err(io::Error::from)?)?;
+        fs::rename(&tmp, &path)
+    }
+
+    fn load(&self, boot_id: &str) -> Option<BootSummary> {
+        let path = self.dir.as_ref()?.join(format!("{}.json", boot_id));
+        serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+    }
+
+    /// The `summaries` reply: the current boot, including the running
+    /// session, followed by up to `count - 1` earlier boots, newest first.
+    pub fn recent(&self, count: usize) -> Vec<BootSummary> {
+        let mut current = self.load(&self.boot_id).unwrap_or_else(|| BootSummary { boot_id: self.boot_id.clone(), ..Default::default() });
+        current.update(&self.session);
+        let mut past: Vec<BootSummary> = self
+            .dir
+            .iter()
+            .flat_map(fs::read_dir)
+            .flatten()
+            .flatten()
+            .filter(|e| e.path().extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|e| serde_json::from_str::<BootSummary>(&fs::read_to_string(e.path()).ok()?).ok())
+            .filter(|b| b.boot_id != self.boot_id)
+            .collect();
+        past.sort_by_key(|b| std::cmp::Reverse(b.total.ended_at));
+        past.truncate(count.saturating_sub(1));
+        std::iter::once(current).chain(past).collect()
+    }
+}
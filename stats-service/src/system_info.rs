@@ -0,0 +1,136 @@
+use std::collections::BTreeSet;
+use std::fs;
+use serde::Serialize;
+
+const OS_RELEASE: &[&str] = &["/etc/os-release", "/usr/lib/os-release"];
+const DRM_DIR: &str = "/sys/class/drm";
+const PCI_IDS: &[&str] = &["/usr/share/hwdata/pci.ids", "/usr/share/misc/pci.ids", "/usr/share/pci.ids"];
+
+/// Facts about the machine that don't change while the service runs,
+/// collected once at startup and served with `status`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SystemInfo {
+    pub kernel: Option<String>,
+    pub distro: Option<String>,
+    pub cpu_model: Option<String>,
+    // Physical cores, falling back to threads when the topology is hidden
+    pub cpu_cores: usize,
+    pub cpu_threads: usize,
+    // KB, matching /proc/meminfo units
+    pub memory_total: f64,
+    pub gpus: Vec<String>,
+}
+
+// PRETTY_NAME, or NAME when a distro leaves it out
+fn distro() -> Option<String> {
+    let content = OS_RELEASE.iter().find_map(|path| fs::read_to_string(path).ok())?;
+    let field = |key: &str| {
+        content
+            .lines()
+            .find_map(|line| line.strip_prefix(key)?.strip_prefix('='))
+            .map(|value| value.trim().trim_matches('"').to_string())
+            .filter(|value| !value.is_empty())
+    };
+    field("PRETTY_NAME").or_else(|| field("NAME"))
+}
+
+// (model, cores, threads) from /proc/cpuinfo
+fn cpu() -> (Option<String>, usize, usize) {
+    let content = fs::read_to_string(crate::proc_path("cpuinfo")).unwrap_or_default();
+    let mut model = None;
+    let mut threads = 0;
+    let mut cores = BTreeSet::new();
+    let mut physical_id = "";
+    for line in content.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match key.trim() {
+            "processor" => threads += 1,
+            // "Model" is what ARM boards report in place of a model name
+            "model name" | "Model" if model.is_none() => model = Some(value.to_string()),
+            "physical id" => physical_id = value,
+            "core id" => {
+                cores.insert((physical_id, value));
+            }
+            _ => {}
+        }
+    }
+    let cores = if cores.is_empty() { threads } else { cores.len() };
+    (model, cores, threads)
+}
+
+fn memory_total() -> f64 {
+    fs::read_to_string(crate::proc_path("meminfo"))
+        .unwrap_or_default()
+        .lines()
+        .find_map(|line| line.strip_prefix("MemTotal:")?.split_whitespace().next()?.parse().ok())
+        .unwrap_or(0.0)
+}
+
+// Device name from the pci.ids database: a vendor line, then its devices
+// indented by one tab. IDs are lowercase hex.
+pub fn pci_name(vendor: &str, device: &str) -> Option<String> {
+    let content = PCI_IDS.iter().find_map(|path| fs::read_to_string(path).ok())?;
+    let mut vendor_name = None;
+    for line in content.lines() {
+        if line.starts_with('#') || line.is_empty() {
+            continue;
+        }
+        if !line.starts_with('\t') {
+            if vendor_name.is_some() {
+                break;
+            }
+            vendor_name = line.strip_prefix(vendor).map(|name| name.trim().to_string());
+        } else if let Some(vendor_name) = &vendor_name {
+            // Subsystem lines are indented twice and never match
+            if let Some(name) = line.strip_prefix('\t').and_then(|l| l.strip_prefix(device)) {
+                return Some(format!("{} {}", vendor_name, name.trim()));
+            }
+        }
+    }
+    None
+}
+
+// One entry per DRM card (not per connector), named from pci.ids when
+// available and otherwise by driver and PCI ID
+fn gpus() -> Vec<String> {
+    let mut cards: Vec<String> = fs::read_dir(DRM_DIR)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|e| e.file_name().into_string().ok())
+        .filter(|name| name.strip_prefix("card").is_some_and(|n| n.chars().all(|c| c.is_ascii_digit())))
+        .collect();
+    cards.sort();
+    cards
+        .iter()
+        .filter_map(|card| {
+            let uevent = fs::read_to_string(format!("{}/{}/device/uevent", DRM_DIR, card)).ok()?;
+            let field = |key: &str| uevent.lines().find_map(|l| l.strip_prefix(key)?.strip_prefix('=')).map(str::to_string);
+            let driver = field("DRIVER").unwrap_or_else(|| "unknown".to_string());
+            let pci_id = field("PCI_ID").map(|id| id.to_lowercase());
+            let Some((vendor, device)) = pci_id.as_deref().and_then(|id| id.split_once(':')) else {
+                // Platform GPUs (ARM SoCs) have no PCI ID
+                return Some(driver);
+            };
+            Some(pci_name(vendor, device).unwrap_or_else(|| format!("{} ({}:{})", driver, vendor, device)))
+        })
+        .collect()
+}
+
+impl SystemInfo {
+    pub fn collect() -> Self {
+        let (cpu_model, cpu_cores, cpu_threads) = cpu();
+        SystemInfo {
+            kernel: fs::read_to_string(crate::proc_path("sys/kernel/osrelease")).ok().map(|s| s.trim().to_string()),
+            distro: distro(),
+            cpu_model,
+            cpu_cores,
+            cpu_threads,
+            memory_total: memory_total(),
+            gpus: gpus(),
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use std::collections::BTreeSet;
+use std::env;
+use std::os::unix::net::UnixDatagram;
+use std::path::{Path, PathBuf};
+use crate::config::ServiceConfig;
+
+/// The service pings the watchdog every tick, so this leaves plenty of slack
+/// for a slow /proc read without letting a hung collector go unnoticed.
+pub const WATCHDOG_SEC: u64 = 30;
+
+/// Builds a hardened user unit whose sandbox matches the configured paths.
+/// Anything the service writes (data dir, socket, persisted samples, text
+/// outputs) is listed in `ReadWritePaths`; everything else is read-only.
+pub fn generate_unit(config: &ServiceConfig, config_path: Option<&Path>, exec: &Path) -> String {
+    let mut writable = BTreeSet::new();
+    writable.insert(PathBuf::from(&config.data_dir));
+    if !config.privacy.ram_only {
+        writable.insert(PathBuf::from(&config.persist_dir));
+    }
+    if let Some(dir) = Path::new(&config.socket_path).parent() {
+        writable.insert(dir.to_path_buf());
+    }
+    for output in &config.text_outputs {
+        if let Some(dir) = Path::new(&output.path).parent().filter(|_| Path::new(&output.path).is_absolute()) {
+            writable.insert(dir.to_path_buf());
+        }
+    }
+
+    // A private /tmp would hide the socket and latest.json from the widgets,
+    // so it's only safe once nothing the clients read lives there.
+    let uses_tmp = writable.iter().any(|p| p.starts_with("/tmp") || p.starts_with("/var/tmp"));
+
+    let mut exec_start = exec.display().to_string();
+    if let Some(path) = config_path {
+        exec_start.push_str(&format!(" --config {}", path.display()));
+    }
+
+    let mut unit = String::new();
+    unit.push_str("# Generated by `stats-service generate-unit`; regenerate after changing paths in the config.\n");
+    unit.push_str("[Unit]\n");
+    unit.push_str("Description=System stats collector for AGS widgets\n");
+    unit.push_str("After=graphical-session.target\n");
+    unit.push_str("PartOf=graphical-session.target\n\n");
+
+    unit.push_str("[Service]\n");
+    unit.push_str("Type=notify\n");
+    unit.push_str(&format!("ExecStart={}\n", exec_start));
+    unit.push_str("Restart=on-failure\n");
+    unit.push_str("RestartSec=2\n");
+    unit.push_str(&format!("WatchdogSec={}\n\n", WATCHDOG_SEC));
+
+    unit.push_str("NoNewPrivileges=yes\n");
+    unit.push_str("ProtectSystem=strict\n");
+    unit.push_str("ProtectHome=read-only\n");
+    if uses_tmp {
+        unit.push_str("# Socket or data dir is under /tmp, which clients must be able to see\n");
+        unit.push_str("PrivateTmp=no\n");
+    } else {
+        unit.push_str("PrivateTmp=yes\n");
+    }
+    for dir in &writable {
+        // "-" keeps the unit starting if a directory hasn't been created yet
+        unit.push_str(&format!("ReadWritePaths=-{}\n", dir.display()));
+    }
+    unit.push_str("ProtectKernelTunables=yes\n");
+    unit.push_str("ProtectKernelModules=yes\n");
+    unit.push_str("ProtectControlGroups=yes\n");
+    unit.push_str("RestrictAddressFamilies=AF_UNIX AF_INET AF_INET6\n");
+    unit.push_str("RestrictNamespaces=yes\n");
+    unit.push_str("RestrictRealtime=yes\n");
+    unit.push_str("LockPersonality=yes\n");
+    unit.push_str("MemoryDenyWriteExecute=yes\n");
+    unit.push_str("SystemCallArchitectures=native\n\n");
+
+    unit.push_str("[Install]\n");
+    unit.push_str("WantedBy=graphical-session.target\n");
+    unit
+}
+
+/// Sends a state string (`READY=1`, `WATCHDOG=1`, ...) to systemd. A no-op
+/// when not started by systemd.
+pub fn notify(state: &str) {
+    let Some(path) = env::var_os("NOTIFY_SOCKET") else {
+        return;
+    };
+    let Ok(socket) = UnixDatagram::unbound() else {
+        return;
+    };
+    let bytes = path.as_encoded_bytes();
+    let result = match bytes.strip_prefix(b"@") {
+        // Abstract namespace socket
+        Some(name) => {
+            use std::os::linux::net::SocketAddrExt;
+            std::os::unix::net::SocketAddr::from_abstract_name(name)
+                .and_then(|addr| socket.send_to_addr(state.as_bytes(), &addr))
+        }
+        None => socket.send_to(state.as_bytes(), &path),
+    };
+    if let Err(e) = result {
+        eprintln!("Failed to notify systemd: {}", e);
+    }
+}
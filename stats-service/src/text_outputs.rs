@@ -0,0 +1,94 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::Deserialize;
+use serde_json::Value;
+
+/// A small text file rendered from a template each tick, for consumers like
+/// conky or polybar that just `cat` a file.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TextOutput {
+    /// Relative paths are resolved against the data dir.
+    pub path: String,
+    /// `{field}` / `{field:.N}` placeholders name dotted paths into the latest
+    /// stats, e.g. `{cpu_usage:.0}%` or `{memory.used_percentage:.1}`.
+    /// Use `{{` and `}}` for literal braces.
+    pub template: String,
+}
+
+fn lookup<'a>(stats: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(stats, |value, key| match value {
+        Value::Array(items) => key.parse::<usize>().ok().and_then(|i| items.get(i)),
+        _ => value.get(key),
+    })
+}
+
+fn format_value(value: &Value, precision: Option<usize>) -> String {
+    match value {
+        Value::Number(n) => match (n.as_i64(), precision) {
+            (Some(i), None) => i.to_string(),
+            _ => format!("{:.*}", precision.unwrap_or(1), n.as_f64().unwrap_or(0.0)),
+        },
+        Value::String(s) => s.clone(),
+        Value::Bool(b) => b.to_string(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+pub fn render(template: &str, stats: &Value) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                out.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                out.push('}');
+            }
+            '{' => {
+                let placeholder: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                let (path, precision) = match placeholder.split_once(":.") {
+                    Some((path, precision)) => (path, precision.parse::<usize>().ok()),
+                    None => (placeholder.as_str(), None),
+                };
+                match lookup(stats, path.trim()) {
+                    Some(value) => out.push_str(&format_value(value, precision)),
+                    None => out.push('?'),
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+fn resolve(data_dir: &str, path: &str) -> PathBuf {
+    let path = Path::new(path);
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        Path::new(data_dir).join(path)
+    }
+}
+
+// Write to a sibling temp file and rename so readers never see a partial file
+fn write_atomic(path: &Path, content: &str) -> std::io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    fs::write(&tmp, content)?;
+    fs::rename(&tmp, path)
+}
+
+pub fn write_all(outputs: &[TextOutput], data_dir: &str, stats: &Value) {
+    for output in outputs {
+        let path = resolve(data_dir, &output.path);
+        let mut content = render(&output.template, stats);
+        content.push('\n');
+        if let Err(e) = write_atomic(&path, &content) {
+            eprintln!("Failed to write text output {}: {}", path.display(), e);
+        }
+    }
+}
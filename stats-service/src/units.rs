@@ -0,0 +1,88 @@
+use std::sync::OnceLock;
+use serde::Deserialize;
+
+/// Bytes or bits for network rates.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RateUnit {
+    #[default]
+    Bytes,
+    /// As ISPs and speed tests quote them.
+    Bits,
+}
+
+/// What the "k" in a rate means.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Prefix {
+    /// 1024, the service's historical KB/s.
+    #[default]
+    Binary,
+    /// 1000.
+    Si,
+}
+
+/// `[units]` in the config: the unit network rates are reported in, the
+/// same everywhere they appear (latest.json, the history, metadata, text
+/// outputs, alerts and the console line). Defaults to KB/s of 1024 bytes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct UnitsConfig {
+    pub network: RateUnit,
+    pub prefix: Prefix,
+}
+
+impl UnitsConfig {
+    /// Converts a collector's rate, always KiB/s, to the configured unit.
+    pub fn network_rate(&self, kib_per_sec: f64) -> f64 {
+        kib_per_sec / self.kib_per_unit()
+    }
+
+    /// How many KiB one unit·second of network rate is.
+    pub fn kib_per_unit(&self) -> f64 {
+        let bytes = match self.prefix {
+            Prefix::Binary => 1024.0,
+            Prefix::Si => 1000.0,
+        };
+        let bytes = match self.network {
+            RateUnit::Bytes => bytes,
+            RateUnit::Bits => bytes / 8.0,
+        };
+        bytes / 1024.0
+    }
+
+    pub fn network_label(&self) -> &'static str {
+        match (self.network, self.prefix) {
+            // Strictly KiB/s, but the label predates the setting
+            (RateUnit::Bytes, Prefix::Binary) => "KB/s",
+            (RateUnit::Bytes, Prefix::Si) => "kB/s",
+            (RateUnit::Bits, Prefix::Binary) => "Kibit/s",
+            (RateUnit::Bits, Prefix::Si) => "kbit/s",
+        }
+    }
+
+    /// The settings behind a label from `network_label`, e.g. one stored in a
+    /// recording; None for anything else.
+    pub fn from_label(label: &str) -> Option<Self> {
+        let (network, prefix) = match label {
+            "KB/s" => (RateUnit::Bytes, Prefix::Binary),
+            "kB/s" => (RateUnit::Bytes, Prefix::Si),
+            "Kibit/s" => (RateUnit::Bits, Prefix::Binary),
+            "kbit/s" => (RateUnit::Bits, Prefix::Si),
+            _ => return None,
+        };
+        Some(UnitsConfig { network, prefix })
+    }
+}
+
+static UNITS: OnceLock<UnitsConfig> = OnceLock::new();
+
+/// Fixes the units for the rest of the run; later calls are ignored.
+pub fn init(units: UnitsConfig) {
+    let _ = UNITS.set(units);
+}
+
+/// The configured units, or the defaults before `init`.
+pub fn get() -> UnitsConfig {
+    UNITS.get().copied().unwrap_or_default()
+}
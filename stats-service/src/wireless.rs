@@ -0,0 +1,120 @@
+use std::collections::BTreeMap;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use serde::{Deserialize, Serialize};
+use crate::collectors::ProcReader;
+
+/// Link of one wireless interface, from /proc/net/wireless plus the
+/// driver's current TX bitrate.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WirelessLink {
+    // 0-100; the kernel reports cfg80211 links out of 70
+    pub quality: f64,
+    pub signal_dbm: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub noise_dbm: Option<f64>,
+    // Mbit/s; None when the driver doesn't report one or isn't associated
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bitrate: Option<f64>,
+}
+
+const QUALITY_MAX: f64 = 70.0;
+
+/// Links by interface name. Interfaces that aren't associated are left out,
+/// so an empty map means no Wi-Fi link rather than an error.
+pub fn parse_wireless(content: &str) -> BTreeMap<String, WirelessLink> {
+    // Two header lines, then "wlan0: 0000   54.  -56.  -256. ..."
+    content
+        .lines()
+        .skip(2)
+        .filter_map(|line| {
+            let (name, values) = line.split_once(':')?;
+            let mut values = values.split_whitespace().skip(1).map(|v| v.trim_end_matches('.').parse::<f64>());
+            let (Some(Ok(link)), Some(Ok(level)), Some(Ok(noise))) = (values.next(), values.next(), values.next()) else {
+                return None;
+            };
+            // A level of 0 is an interface that's up but not associated
+            if level == 0.0 {
+                return None;
+            }
+            let link = WirelessLink {
+                quality: (link / QUALITY_MAX * 100.0).clamp(0.0, 100.0),
+                signal_dbm: level,
+                // -256 is the kernel's "not reported"
+                noise_dbm: (noise > -256.0 && noise < 0.0).then_some(noise),
+                bitrate: None,
+            };
+            Some((name.trim().to_string(), link))
+        })
+        .collect()
+}
+
+// SIOCGIWRATE from linux/wireless.h; cfg80211 answers it for every nl80211
+// driver (CONFIG_CFG80211_WEXT), which saves a netlink client for one value
+const SIOCGIWRATE: libc::c_ulong = 0x8B21;
+
+#[repr(C)]
+struct IwParam {
+    value: i32,
+    fixed: u8,
+    disabled: u8,
+    flags: u16,
+}
+
+#[repr(C)]
+struct IwReq {
+    name: [libc::c_char; libc::IFNAMSIZ],
+    param: IwParam,
+    // iwreq_data is a 16-byte union; only iw_param is read here
+    _rest: [u8; 8],
+}
+
+#[derive(Default)]
+pub struct WirelessCollector {
+    reader: ProcReader,
+    // Datagram socket the rate ioctl goes through, opened on first use
+    socket: Option<OwnedFd>,
+    links: BTreeMap<String, WirelessLink>,
+}
+
+impl WirelessCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Associated wireless interfaces by name; empty when there are none or
+    /// /proc/net/wireless is unreadable.
+    pub fn collect(&mut self) -> &BTreeMap<String, WirelessLink> {
+        self.links = self.reader.read("net/wireless").map(parse_wireless).unwrap_or_default();
+        let names: Vec<String> = self.links.keys().cloned().collect();
+        for name in names {
+            let bitrate = self.bitrate(&name);
+            if let Some(link) = self.links.get_mut(&name) {
+                link.bitrate = bitrate;
+            }
+        }
+        &self.links
+    }
+
+    // Mbit/s the driver last transmitted at
+    fn bitrate(&mut self, interface: &str) -> Option<f64> {
+        if interface.len() >= libc::IFNAMSIZ {
+            return None;
+        }
+        if self.socket.is_none() {
+            let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, 0) };
+            if fd < 0 {
+                return None;
+            }
+            self.socket = Some(unsafe { OwnedFd::from_raw_fd(fd) });
+        }
+        let mut request = IwReq { name: [0; libc::IFNAMSIZ], param: IwParam { value: 0, fixed: 0, disabled: 0, flags: 0 }, _rest: [0; 8] };
+        for (dst, src) in request.name.iter_mut().zip(interface.bytes()) {
+            *dst = src as libc::c_char;
+        }
+        let fd = self.socket.as_ref()?.as_raw_fd();
+        if unsafe { libc::ioctl(fd, SIOCGIWRATE as _, &mut request) } != 0 {
+            return None;
+        }
+        (request.param.disabled == 0 && request.param.value > 0).then(|| request.param.value as f64 / 1e6)
+    }
+}
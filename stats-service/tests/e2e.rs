@@ -0,0 +1,1079 @@
+//! Runs the service against the procfs fixture in tests/fixtures/proc and
+//! reads its history back through graph-window's data layer, so a change to
+//! either side of the socket contract fails here instead of in the UI.
+
+#[allow(dead_code)]
+#[path = "../../graph-window/src/data.rs"]
+mod data;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const MEM_TOTAL_KB: f64 = 16_000_000.0;
+// Active(anon) + Inactive(anon) in the fixture
+const MEM_APPS_KB: f64 = 4_000_000.0;
+
+struct Service {
+    child: Child,
+    dir: PathBuf,
+}
+
+fn spawn_service(dir: &Path, args: &[&str]) -> Child {
+    spawn_service_env(dir, args, &[])
+}
+
+/// `env` is set on top of the test's own, e.g. to look like a container.
+fn spawn_service_env(dir: &Path, args: &[&str], env: &[(&str, &str)]) -> Child {
+    // Stand-ins for external tools (smartctl) go in <dir>/bin
+    let path = format!("{}:{}", dir.join("bin").display(), std::env::var("PATH").unwrap_or_default());
+    Command::new(env!("CARGO_BIN_EXE_stats-service"))
+        .env("PATH", path)
+        // Not a unit, whatever runs the tests
+        .env_remove("INVOCATION_ID")
+        .envs(env.iter().copied())
+        .arg("--config")
+        .arg(dir.join("config.toml"))
+        .args(args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("failed to start stats-service")
+}
+
+impl Drop for Service {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn start_service(name: &str, args: &[&str]) -> Service {
+    start_service_with(name, args, "")
+}
+
+/// `extra_config` is appended to the generated config, e.g. a `[privacy]` table.
+fn start_service_with(name: &str, args: &[&str], extra_config: &str) -> Service {
+    let dir = write_config(name, extra_config);
+    let child = spawn_service(&dir, args);
+    Service { child, dir }
+}
+
+// A fresh directory holding config.toml, which puts everything else under it
+fn write_config(name: &str, extra_config: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("stats-service-e2e-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/proc");
+    let config = format!(
+        // No probes: tests shouldn't depend on the network
+        "data_dir = {:?}\nsocket_path = {:?}\npersist_dir = {:?}\nproc_root = {:?}\n\n[network_probe]\ntargets = []\n",
+        dir.join("data"),
+        dir.join("stats.sock"),
+        dir.join("persist"),
+        fixture,
+    ) + extra_config;
+    fs::write(dir.join("config.toml"), config).unwrap();
+    dir
+}
+
+impl Service {
+    /// Kills the service and starts it again on the same config.
+    fn restart(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        self.child = spawn_service(&self.dir, &[]);
+    }
+
+    fn socket(&self) -> String {
+        self.dir.join("stats.sock").to_string_lossy().into_owned()
+    }
+
+    /// Sends one command and parses the reply.
+    fn query(&self, command: &str) -> serde_json::Value {
+        use std::io::{Read, Write};
+        let mut stream = std::os::unix::net::UnixStream::connect(self.socket()).unwrap();
+        stream.write_all(format!("{}\n", command).as_bytes()).unwrap();
+        let mut reply = String::new();
+        stream.read_to_string(&mut reply).unwrap();
+        serde_json::from_str(&reply).unwrap_or_else(|e| panic!("bad reply to {}: {}", command, e))
+    }
+
+    fn history_file(&self) -> String {
+        self.dir.join("data/history.json").to_string_lossy().into_owned()
+    }
+
+    /// Polls until the service has collected at least one tick.
+    fn wait_for_history(&self) -> serde_json::Value {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        loop {
+            if Path::new(&self.socket()).exists() {
+                let history = data::load_history_from(&self.socket(), "/nonexistent");
+                if history["last_update"].as_i64().unwrap_or(0) > 0 {
+                    return history;
+                }
+            }
+            assert!(Instant::now() < deadline, "service produced no history within 10s");
+            thread::sleep(Duration::from_millis(100));
+        }
+    }
+}
+
+#[test]
+fn history_round_trips_through_window_data_layer() {
+    let service = start_service("history", &[]);
+    let history = service.wait_for_history();
+
+    let memory = data::series(&history, "memory");
+    assert_eq!(memory.len(), 60, "history keeps a full window of samples");
+    assert_eq!(memory.last().copied(), Some(50.0), "used percentage from MemAvailable");
+
+    let mut mem_data = data::AdvancedMemoryData::new(60);
+    data::update_memory(&mut mem_data, &history);
+    assert_eq!(mem_data.total, MEM_TOTAL_KB);
+    assert_eq!(mem_data.apps.values.last().copied(), Some(MEM_APPS_KB));
+    assert_eq!(mem_data.cached.values.last().copied(), Some(3_000_000.0));
+    assert_eq!(mem_data.buffers.values.last().copied(), Some(200_000.0));
+    assert_eq!(mem_data.slab.values.last().copied(), Some(400_000.0));
+    assert_eq!(mem_data.shmem.values.last().copied(), Some(300_000.0));
+
+    assert_eq!(data::series(&history, "power").len(), 60);
+    assert_eq!(data::series(&history, "load").last().copied(), Some(1.25));
+    // A quarter of swap in use, and nothing paging while the counters stand still
+    assert_eq!(data::series(&history, "swap").last().copied(), Some(25.0));
+    assert_eq!(data::series(&history, "swap_out").last().copied(), Some(0.0));
+    // PSI "some" avg10 per resource
+    assert_eq!(data::series(&history, "pressure_memory").last().copied(), Some(3.0));
+    assert_eq!(data::series(&history, "pressure_io").last().copied(), Some(0.5));
+    let latest = data::load_latest_from(&service.dir.join("data/latest.json").to_string_lossy()).expect("latest.json");
+    assert_eq!((latest["load"]["fifteen"].as_f64(), latest["load"]["running"].as_u64(), latest["load"]["total"].as_u64()), (Some(0.5), Some(3), Some(412)));
+    assert_eq!((latest["pressure"]["cpu"]["some_avg60"].as_f64(), latest["pressure"]["memory"]["full_avg10"].as_f64()), (Some(8.0), Some(1.25)));
+    // Counters that stand still: one rate per CPU, all zero
+    let irqs = &latest["irqs"];
+    assert_eq!(irqs["cpus"], serde_json::json!([0, 1]));
+    assert_eq!(irqs["net_rx"], serde_json::json!([0.0, 0.0]));
+    assert_eq!(irqs["hardware"].as_array().map(Vec::len), Some(2));
+    // 512 pages of 2 MB, a quarter free and an eighth reserved
+    let memory = &latest["memory"];
+    assert_eq!((memory["hugepages_total"].as_f64(), memory["hugepages_free"].as_f64()), (Some(1_048_576.0), Some(262_144.0)));
+    assert_eq!((memory["hugepages_reserved"].as_f64(), memory["anon_hugepages"].as_f64()), (Some(131_072.0), Some(1_048_576.0)));
+    // Boot time is the sample's timestamp less the uptime, to the second
+    let sampled = latest["timestamp"].as_i64().expect("timestamp");
+    assert_eq!(latest["uptime"]["seconds"].as_f64(), Some(3725.5));
+    assert_eq!(latest["uptime"]["boot_time"].as_i64(), Some((sampled - 3_725_500).div_euclid(1000) * 1000));
+    // The fixture's /proc/stat counters stand still
+    assert_eq!(latest["kernel_activity"]["context_switches"].as_f64(), Some(0.0));
+    assert_eq!(data::series(&history, "forks").last().copied(), Some(0.0));
+    // In-use counts add the IPv6 sockets; TIME_WAIT and orphans are shared
+    assert_eq!((latest["sockets"]["tcp_in_use"].as_u64(), latest["sockets"]["udp_in_use"].as_u64()), (Some(50), Some(10)));
+    assert_eq!(data::series(&history, "sockets_time_wait").last().copied(), Some(17.0));
+    assert_eq!(data::series(&history, "sockets_orphaned").last().copied(), Some(2.0));
+    // 4096 of 1048576 handles
+    assert_eq!(data::series(&history, "file_descriptors").last().copied(), Some(4096.0));
+    assert_eq!(data::series(&history, "file_descriptors_used").last().copied(), Some(0.390625));
+    assert_eq!(latest["file_descriptors"]["max"].as_u64(), Some(1_048_576));
+
+    // One timestamp per slot, on whole-second boundaries
+    let timestamps = data::series(&history, "timestamps");
+    assert_eq!(timestamps.len(), 60);
+    let newest = timestamps.last().copied().unwrap() as i64;
+    assert_eq!(Some(newest), history["last_update"].as_i64());
+    assert_eq!(newest % 1000, 0);
+}
+
+#[test]
+fn energy_report_reaches_the_panel() {
+    let service = start_service("energy", &[]);
+    service.wait_for_history();
+
+    // Either a report or an explanation, depending on the host's power sources
+    let report = data::load_energy_from(&service.socket());
+    assert!(report["error"].is_string() || report["processes"].is_array(), "unexpected reply: {}", report);
+    assert!(!data::energy_summary(&report).is_empty());
+}
+
+#[test]
+fn window_downselects_to_its_own_size() {
+    let service = start_service("downselect", &[]);
+    let history = service.wait_for_history();
+
+    let mut graph = data::GraphData::new_with_zeros(10);
+    graph.set_from_history(&history, "memory_apps");
+    assert_eq!(graph.values.len(), 10);
+    assert_eq!(graph.values.last().copied(), Some(MEM_APPS_KB));
+
+    // Fewer samples than the window keeps them all
+    let mut wide = data::GraphData::new_with_zeros(500);
+    wide.set_from_history(&history, "memory_apps");
+    assert_eq!(wide.values.len(), data::series(&history, "memory_apps").len());
+
+    // One chart per core the service reports, each cut to the window
+    let mut cores = Vec::new();
+    let mut iowait = data::GraphData::new_with_zeros(10);
+    data::update_cpu_cores(&mut cores, &mut iowait, &history);
+    assert_eq!(cores.len(), history["cpu_cores"].as_array().unwrap().len());
+    assert!(cores.iter().all(|c| c.values.len() == 10));
+    assert_eq!(iowait.values.len(), 10);
+
+    assert!(data::series(&history, "no_such_series").is_empty());
+}
+
+#[test]
+fn window_falls_back_to_history_file() {
+    let service = start_service("fallback", &[]);
+    service.wait_for_history();
+
+    let history = data::load_history_from("/nonexistent/stats.sock", &service.history_file());
+    assert_eq!(history["memory_total"].as_f64(), Some(MEM_TOTAL_KB));
+}
+
+#[test]
+fn window_gives_up_on_a_service_that_never_answers() {
+    let dir = std::env::temp_dir().join(format!("stats-e2e-hung-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let socket = dir.join("stats.sock");
+    let _ = fs::remove_file(&socket);
+    // Accepts the connection and then says nothing
+    let _listener = std::os::unix::net::UnixListener::bind(&socket).unwrap();
+
+    let started = Instant::now();
+    let report = data::load_energy_from(&socket.to_string_lossy());
+    assert_eq!(report["error"], "stats service unavailable");
+    assert!(started.elapsed() < Duration::from_secs(5), "took {:?}", started.elapsed());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn window_data_layer_survives_chaos_mode() {
+    let service = start_service("chaos", &["--chaos=7"]);
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while !Path::new(&service.socket()).exists() {
+        assert!(Instant::now() < deadline, "service never opened its socket");
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    // Truncated and malformed frames must degrade to empty data, never a panic
+    let mut mem_data = data::AdvancedMemoryData::new(60);
+    for _ in 0..100 {
+        let history = data::load_history_from(&service.socket(), "/nonexistent");
+        assert!(history.is_object() || history.is_array());
+        data::update_memory(&mut mem_data, &history);
+        assert!(mem_data.apps.values.len() <= 60);
+    }
+}
+
+#[test]
+fn processes_are_grouped_by_desktop_app() {
+    let service = start_service("apps", &[]);
+    service.wait_for_history();
+
+    // Both fixture processes live in the same app scope
+    let apps = data::load_apps_from(&service.socket());
+    let groups = apps["apps"].as_array().expect("apps reply");
+    let firefox = groups.iter().find(|g| g["id"] == "org.mozilla.firefox").expect("firefox group");
+    assert_eq!(firefox["processes"], 2);
+    assert!(firefox["memory"].as_f64().unwrap() > 0.0);
+    assert!(data::apps_summary(&apps).contains("(2 processes)"));
+
+    // The same processes ranked individually, biggest resident set first
+    let latest = data::load_latest_from(&service.dir.join("data/latest.json").to_string_lossy()).expect("latest.json");
+    let by_memory: Vec<&str> = latest["top_processes"]["by_memory"].as_array().expect("top processes").iter().filter_map(|p| p["name"].as_str()).collect();
+    assert_eq!(by_memory, vec!["firefox", "Web Content"]);
+    let mut history = serde_json::json!({});
+    data::append_latest(&mut history, &latest, 60);
+    assert!(data::top_processes_summary(&history, "by_memory", 3).unwrap().starts_with("Top: firefox "));
+}
+
+#[test]
+fn privacy_mode_keeps_history_off_disk_and_scrubs_names() {
+    let service = start_service_with(
+        "privacy",
+        &[],
+        "\n[privacy]\nram_only = true\nscrub_process_names = true\n",
+    );
+    // History is still served over the socket
+    service.wait_for_history();
+
+    assert!(!Path::new(&service.history_file()).exists(), "ram_only must not write history.json");
+    assert!(!service.dir.join("persist/samples.jsonl").exists());
+
+    let latest = fs::read_to_string(service.dir.join("data/latest.json")).unwrap();
+    assert!(!latest.contains("firefox"), "process names leaked into latest.json");
+    assert!(latest.contains("redacted"));
+    // The socket follows the same rule as the files
+    for command in ["latest", "apps"] {
+        let reply = service.query(command).to_string();
+        assert!(!reply.contains("firefox"), "process names leaked into the {} reply", command);
+        assert!(reply.contains("redacted"), "{}", reply);
+    }
+}
+
+#[test]
+fn stalled_subscriber_does_not_hold_up_others() {
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::UnixStream;
+
+    let service = start_service("subscribe", &[]);
+    service.wait_for_history();
+
+    // Subscribes and then never reads
+    let mut stalled = UnixStream::connect(service.socket()).unwrap();
+    stalled.write_all(b"subscribe\n").unwrap();
+
+    let mut live = UnixStream::connect(service.socket()).unwrap();
+    live.write_all(b"subscribe\n").unwrap();
+    live.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    let mut lines = BufReader::new(live).lines();
+    let first: serde_json::Value = serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap();
+    let second: serde_json::Value = serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap();
+    assert!(second["timestamp"].as_i64() > first["timestamp"].as_i64());
+    assert_eq!(second["memory"]["total"].as_f64(), Some(MEM_TOTAL_KB));
+    drop(stalled);
+}
+
+#[test]
+fn excluded_metrics_are_left_out_of_history() {
+    let service = start_service_with(
+        "metrics",
+        &[],
+        "\n[metrics]\nexclude = [\"cpu_cores\", \"memory_breakdown\", \"temperature\", \"disk_io\"]\n",
+    );
+    let history = service.wait_for_history();
+
+    assert_eq!(history["cpu_cores"].as_array().map(Vec::len), Some(0));
+    assert!(history.get("memory_apps").is_none(), "breakdown still serialized");
+    assert!(history.get("temperature").is_none());
+    assert!(history.get("disk_read").is_none());
+    assert_eq!(data::series(&history, "memory").len(), 60, "totals are always kept");
+
+    let mut mem_data = data::AdvancedMemoryData::new(60);
+    data::update_memory(&mut mem_data, &history);
+    assert_eq!(mem_data.total, MEM_TOTAL_KB);
+
+    // Metadata only describes series the history will contain
+    let metadata = data::load_metadata_from(&service.socket());
+    assert_eq!(metadata["series"]["cpu"]["unit"], "%");
+    assert_eq!(metadata["series"]["network_download"]["max"], serde_json::Value::Null);
+    assert!(metadata["series"].get("memory_apps").is_none());
+    assert!(metadata["series"].get("temperature").is_none());
+    assert!(metadata["series"].get("disk_write").is_none());
+}
+
+#[test]
+fn window_follows_latest_json_without_the_socket() {
+    let service = start_service("latest", &[]);
+    let mut history = service.wait_for_history();
+    let latest_file = service.dir.join("data/latest.json").to_string_lossy().into_owned();
+
+    // A sample the history already has is ignored
+    let latest = data::load_latest_from(&latest_file).expect("latest.json");
+    let before = data::series(&history, "memory_apps").len();
+    assert!(!data::append_latest(&mut history, &latest, 60));
+
+    let mut next = latest.clone();
+    next["timestamp"] = (latest["timestamp"].as_i64().unwrap() + 1000).into();
+    assert!(data::append_latest(&mut history, &next, 60));
+    assert_eq!(data::series(&history, "memory_apps").len(), before, "trimmed to the window");
+    assert_eq!(data::series(&history, "memory_apps").last().copied(), Some(MEM_APPS_KB));
+    assert_eq!(history["last_update"], next["timestamp"]);
+
+    // Starting from nothing builds the series up one sample at a time
+    let mut empty = serde_json::json!({});
+    assert!(data::append_latest(&mut empty, &latest, 60));
+    assert_eq!(data::series(&empty, "memory").len(), 1);
+    assert_eq!(empty["memory_total"].as_f64(), Some(MEM_TOTAL_KB));
+}
+
+#[test]
+fn brushed_range_summarizes_only_the_selected_samples() {
+    let service = start_service("brush", &[]);
+    let history = service.wait_for_history();
+
+    let memory = data::series(&history, "memory");
+    let timestamps = data::plotted_timestamps(&history, memory.len());
+    assert_eq!(timestamps.len(), memory.len());
+    let newest = *timestamps.last().unwrap();
+    // Dragging right to left over the whole window skips the startup padding
+    let stats = data::range_stats(&memory, &timestamps, newest, 0).expect("samples in range");
+    assert_eq!((stats.min, stats.max, stats.avg), (50.0, 50.0, 50.0));
+    assert_eq!(stats.end, newest);
+    assert_eq!(stats.samples, timestamps.iter().filter(|&&t| t != 0).count());
+    assert!(data::range_stats(&memory, &timestamps, newest + 1000, newest + 5000).is_none());
+
+    // Rates add up over the seconds each sample covers
+    let rates = [0.0, 512.0, 1536.0];
+    let stats = data::range_stats(&rates, &[0, 1000, 3000], 0, 3000).unwrap();
+    assert_eq!((stats.total, stats.samples), (512.0 + 1536.0 * 2.0, 2));
+    assert_eq!(data::range_summary(&stats, "KB/s"), "Selection 2 s: min 512.0 | max 1536.0 | avg 1024.0KB/s | total 4 MB");
+}
+
+#[test]
+fn network_view_switches_between_interfaces() {
+    let service = start_service("interfaces", &[]);
+    let mut history = service.wait_for_history();
+
+    // Loopback is left out of the per-interface series as it is of the totals
+    assert_eq!(data::interface_names(&history), vec!["eth0".to_string()]);
+    let (total_down, total_up) = data::network_series(&history, None);
+    let (eth0_down, eth0_up) = data::network_series(&history, Some("eth0"));
+    assert_eq!(eth0_down.len(), total_down.len());
+    assert_eq!(eth0_up.len(), total_up.len());
+    assert_eq!(data::network_series(&history, Some("wlan0")), (vec![], vec![]));
+
+    let latest_file = service.dir.join("data/latest.json").to_string_lossy().into_owned();
+    let mut next = data::load_latest_from(&latest_file).expect("latest.json");
+    // Each interface carries its counters next to its rates
+    let eth0 = &next["network_interfaces"]["eth0"];
+    assert_eq!((eth0["rx_total"].as_u64(), eth0["tx_total"].as_u64()), (Some(1_048_576), Some(524_288)));
+    assert!(next["network_interfaces"]["lo"].is_null());
+    next["timestamp"] = (history["last_update"].as_i64().unwrap() + 1000).into();
+    next["network_interfaces"] = serde_json::json!({ "eth0": { "download": 12.5, "upload": 2.0 } });
+    assert!(data::append_latest(&mut history, &next, 60));
+    let (eth0_down, eth0_up) = data::network_series(&history, Some("eth0"));
+    assert_eq!((eth0_down.last().copied(), eth0_up.last().copied()), (Some(12.5), Some(2.0)));
+
+    // The fixture's wlan0 is associated but has no traffic counters, so it
+    // has a link without a signal series; unknown noise is left out
+    let wlan0 = &next["wireless"]["wlan0"];
+    assert_eq!((wlan0["quality"].as_f64(), wlan0["signal_dbm"].as_f64()), (Some(70.0), Some(-61.0)));
+    assert!(wlan0["noise_dbm"].is_null());
+    assert_eq!(data::network_signal(&history, None), Vec::<f64>::new());
+    // eth0 standing in for Wi-Fi: it gets a signal series once it reports
+    // one, then keeps it with a gap when the link drops
+    next["wireless"] = serde_json::json!({ "eth0": { "quality": 50.0, "signal_dbm": -58.0 } });
+    next["timestamp"] = (next["timestamp"].as_i64().unwrap() + 1000).into();
+    assert!(data::append_latest(&mut history, &next, 60));
+    next["wireless"] = serde_json::json!({});
+    next["timestamp"] = (next["timestamp"].as_i64().unwrap() + 1000).into();
+    assert!(data::append_latest(&mut history, &next, 60));
+    let signal = data::network_signal(&history, None);
+    assert_eq!((signal.len(), signal[0]), (2, -58.0));
+    assert!(signal[1].is_nan());
+    assert_eq!(data::signal_fraction(-60.0), 0.5);
+}
+
+#[test]
+fn core_frequencies_and_fan_speed_follow_the_stream() {
+    let service = start_service("frequencies", &[]);
+    let mut history = service.wait_for_history();
+
+    let latest_file = service.dir.join("data/latest.json").to_string_lossy().into_owned();
+    let mut next = data::load_latest_from(&latest_file).expect("latest.json");
+    next["timestamp"] = (history["last_update"].as_i64().unwrap() + 1000).into();
+    // The second core has no cpufreq policy
+    next["cpu_frequencies"] = serde_json::json!([3400.0, null]);
+    next["fan"] = serde_json::json!(2400.0);
+    assert!(data::append_latest(&mut history, &next, 60));
+    assert_eq!(data::series(&history, "fan").last().copied(), Some(2400.0));
+    let frequencies = data::core_frequencies(&history);
+    assert_eq!(frequencies.len(), 2);
+    assert_eq!(data::format_frequency(frequencies[0]), "3.40 GHz");
+    assert!(frequencies[1].is_nan());
+    assert_eq!(data::format_frequency(800.0), "800 MHz");
+}
+
+#[test]
+fn storage_view_lists_block_device_mounts() {
+    let service = start_service("storage", &[]);
+    service.wait_for_history();
+
+    // The fixture mounts / twice, a squashfs loop, a tmpfs and a drive that
+    // isn't attached; only the first block-device mount of / is kept
+    let filesystems = data::load_filesystems_from(&service.socket());
+    let mounts: Vec<&String> = filesystems["mounts"].as_object().expect("mounts").keys().collect();
+    assert_eq!(mounts, vec!["/"]);
+
+    let rows = data::mount_rows(&filesystems);
+    assert_eq!(rows.len(), 1);
+    assert!(rows[0].used > 0.0 && rows[0].used <= rows[0].total);
+    assert_eq!(rows[0].full_in, None, "one sample has no trend");
+
+    // Growing by 1 GB a day with 10 GB left is ten days from full
+    let gb = 1024.0 * 1024.0;
+    let day_ms = 86_400_000;
+    let growing = serde_json::json!({ "mounts": { "/home": {
+        "fstype": "ext4",
+        "total": 100.0 * gb,
+        "used": [88.0 * gb, 89.0 * gb, 90.0 * gb],
+        "timestamps": [0, day_ms, 2 * day_ms],
+    } } });
+    let rows = data::mount_rows(&growing);
+    let days = rows[0].full_in.expect("growing mount") / 86_400.0;
+    assert!((days - 10.0).abs() < 1e-6, "{}", days);
+    assert_eq!(data::storage_summary(&rows), "/home full in ~10 days");
+}
+
+#[test]
+fn window_resubscribes_after_a_service_restart() {
+    use std::sync::mpsc;
+
+    let mut service = start_service("restart", &[]);
+    service.wait_for_history();
+
+    let (tx, events) = mpsc::channel();
+    let socket = service.socket();
+    thread::spawn(move || data::follow_service_at(&socket, tx));
+    let next = || events.recv_timeout(Duration::from_secs(10)).expect("no event from the subscription");
+
+    assert!(matches!(next(), data::StreamEvent::History(_)));
+    let last_seen = match next() {
+        data::StreamEvent::Sample(sample) => sample["timestamp"].as_i64().unwrap(),
+        _ => panic!("expected a sample after the history"),
+    };
+
+    service.restart();
+    assert!(matches!(next(), data::StreamEvent::Disconnected));
+    let data::StreamEvent::History(history) = next() else {
+        panic!("expected a fresh history on reconnect");
+    };
+    assert!(history.is_object());
+    assert!(matches!(next(), data::StreamEvent::Sample(_)));
+
+    assert_eq!(data::reconnect_label(data::reconnect_gap_ms(last_seen, &serde_json::json!({
+        "timestamps": [last_seen - 1000, last_seen + 6000, last_seen + 7000],
+    }))), "reconnected, 6 s gap");
+    assert_eq!(data::reconnect_label(Some(1000)), "reconnected");
+    assert!(data::reconnect_delay(1) > data::reconnect_delay(0));
+    assert_eq!(data::reconnect_delay(40), Duration::from_secs(30));
+}
+
+#[test]
+fn leak_view_tracks_one_process_and_fits_a_trend() {
+    let service = start_service("leak", &[]);
+    service.wait_for_history();
+
+    // Without a pid the largest process is picked
+    let reply = data::load_process_from(&service.socket(), None);
+    assert_eq!(reply["pid"], 1);
+    assert_eq!(reply["name"], "firefox");
+    assert_eq!(data::load_process_from(&service.socket(), Some(2))["name"], "Web Content");
+    assert!(data::load_process_from(&service.socket(), Some(999_999))["error"].is_string());
+
+    let mut tracker = data::LeakTracker::new(None, 600);
+    assert!(tracker.push(&reply));
+    assert_eq!(tracker.pid, Some(1));
+    assert!(tracker.trend().is_none());
+
+    // 1 MB/s of growth with 60 MB left is a minute to OOM
+    let mut tracker = data::LeakTracker::new(Some(7), 600);
+    for i in 0..10 {
+        tracker.push(&serde_json::json!({
+            "pid": 7, "name": "leaky", "rss": 100_000.0 + 1024.0 * i as f64,
+            "memory_available": 61_440.0, "timestamp": 1_000_000 + i * 1000,
+        }));
+    }
+    let (slope, intercept) = tracker.trend().unwrap();
+    assert!((slope - 1024.0).abs() < 1e-6);
+    assert!((intercept - 100_000.0).abs() < 1e-6);
+    assert!((tracker.seconds_to_oom().unwrap() - 60.0).abs() < 1e-6);
+    assert_eq!(data::leak_summary(&tracker), "leaky (7): 107 MB, +60.0 MB/min, OOM in ~1 min");
+}
+
+#[test]
+fn alert_attaches_a_rendered_thumbnail() {
+    // The fixture sits at 50% memory, so this fires on the first tick
+    let rules = "\n[[alerts]]\nname = \"memory high\"\nmetric = \"memory\"\nabove = 40\n\n[[alerts]]\nname = \"memory full\"\nmetric = \"memory\"\nabove = 90\n";
+    let service = start_service_with("alerts", &[], rules);
+    let history = service.wait_for_history();
+
+    let markers: Vec<(&str, &str)> = history["markers"]
+        .as_array()
+        .expect("markers")
+        .iter()
+        .map(|m| (m["kind"].as_str().unwrap_or(""), m["label"].as_str().unwrap_or("")))
+        .collect();
+    assert_eq!(markers, vec![("alert", "memory high")]);
+
+    // Still firing, so the band runs to the newest slot
+    let mut history = history;
+    let timestamps = data::plotted_timestamps(&history, 60);
+    let bands = data::alert_bands(&history, &timestamps);
+    assert_eq!(bands.len(), 1);
+    assert_eq!((bands[0].label.as_str(), bands[0].last, bands[0].raised, bands[0].cleared), ("memory high", 59, true, false));
+
+    // A clear arrives with the markers on the stream
+    let newest = history["last_update"].as_i64().unwrap();
+    let mut cleared = history["markers"].clone();
+    cleared[0]["end"] = (newest + 1000).into();
+    let next = serde_json::json!({ "timestamp": newest + 1000, "markers": cleared });
+    assert!(data::append_latest(&mut history, &next, 60));
+    let bands = data::alert_bands(&history, &data::plotted_timestamps(&history, 60));
+    assert!(bands[0].cleared);
+
+    let thumbnail = fs::read_to_string(service.dir.join("data/alerts/memory_high.svg")).expect("thumbnail");
+    assert!(thumbnail.starts_with("<svg"), "{}", thumbnail);
+    assert!(!service.dir.join("data/alerts/memory_full.svg").exists());
+}
+
+#[test]
+fn clicked_alert_opens_the_window_in_its_own_unit() {
+    use std::os::unix::fs::PermissionsExt;
+    let dir = write_config("alert-open", "\n[[alerts]]\nname = \"memory high\"\nmetric = \"memory\"\nabove = 40\nopen = \"memory\"\n");
+    let window = dir.join("graph-window");
+    let config = fs::read_to_string(dir.join("config.toml")).unwrap();
+    fs::write(dir.join("config.toml"), format!("graph_window = {:?}\n{}", window, config)).unwrap();
+    // The notification is clicked at once; systemd-run notes how it was asked
+    fs::create_dir_all(dir.join("bin")).unwrap();
+    for (name, script) in [
+        ("notify-send", "#!/bin/sh\necho default\n".to_string()),
+        ("systemd-run", format!("#!/bin/sh\necho \"$@\" > {:?}\n", dir.join("systemd-run.args"))),
+    ] {
+        fs::write(dir.join("bin").join(name), script).unwrap();
+        fs::set_permissions(dir.join("bin").join(name), fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    // As systemd starts it
+    let child = spawn_service_env(&dir, &[], &[("INVOCATION_ID", "0123456789abcdef")]);
+    let service = Service { child, dir };
+    service.wait_for_history();
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let args = loop {
+        if let Ok(args) = fs::read_to_string(service.dir.join("systemd-run.args")) {
+            break args;
+        }
+        assert!(Instant::now() < deadline, "the window was never started");
+        thread::sleep(Duration::from_millis(100));
+    };
+    assert_eq!(args.trim(), format!("--user --collect --quiet -- {} memory", window.display()));
+}
+
+#[test]
+fn rate_rules_fire_once_their_window_is_covered() {
+    // Fixture memory stands still: a rate above -1/min holds, one above 0
+    // doesn't, and a flat trend never reaches 100% however long it's given
+    let rules = concat!(
+        "\n[[alerts]]\nname = \"steady\"\nmetric = \"memory\"\nrising_faster_than = -1\nwindow_secs = 2\n",
+        "\n[[alerts]]\nname = \"growing\"\nmetric = \"memory\"\nrising_faster_than = 0\nwindow_secs = 2\n",
+        "\n[[alerts]]\nname = \"filling\"\nmetric = \"memory\"\nreaches = 100\nwithin_secs = 86400\nwindow_secs = 2\n",
+        "\n[[alerts]]\nname = \"no condition\"\nmetric = \"memory\"\n",
+    );
+    let service = start_service_with("rate-alerts", &[], rules);
+    service.wait_for_history();
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        let history = data::load_history_from(&service.socket(), "/nonexistent");
+        let labels: Vec<&str> = history["markers"].as_array().into_iter().flatten().filter_map(|m| m["label"].as_str()).collect();
+        if !labels.is_empty() {
+            assert_eq!(labels, vec!["steady"]);
+            break;
+        }
+        assert!(Instant::now() < deadline, "rate alert never fired");
+        thread::sleep(Duration::from_millis(200));
+    }
+}
+
+#[test]
+fn watches_set_from_a_window_fire_like_alerts() {
+    let service = start_service("watches", &[]);
+    service.wait_for_history();
+    let socket = service.socket();
+
+    assert_eq!(data::watch_from(&socket, "nonexistent", 1.0, "cpu"), Err("unknown metric: nonexistent".to_string()));
+    // The fixture sits at 50% memory; setting a watch again replaces it
+    data::watch_from(&socket, "memory", 90.0, "memory").unwrap();
+    data::watch_from(&socket, "memory", 40.0, "memory").unwrap();
+    let watches = data::load_watches_from(&socket);
+    assert_eq!(watches["watches"].as_array().unwrap().len(), 1);
+    assert_eq!(data::watched_above(&watches, "memory"), Some(40.0));
+    assert_eq!(watches["watches"][0]["view"], "memory");
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        let history = data::load_history_from(&socket, "/nonexistent");
+        let labels: Vec<&str> = history["markers"].as_array().into_iter().flatten().filter_map(|m| m["label"].as_str()).collect();
+        if !labels.is_empty() {
+            assert_eq!(labels, vec!["Watch: Memory"]);
+            break;
+        }
+        assert!(Instant::now() < deadline, "watch never fired");
+        thread::sleep(Duration::from_millis(200));
+    }
+
+    data::unwatch_from(&socket, "memory").unwrap();
+    assert_eq!(data::watched_above(&data::load_watches_from(&socket), "memory"), None);
+    assert_eq!(data::unwatch_from(&socket, "memory"), Err("not watching memory".to_string()));
+}
+
+#[test]
+fn session_summaries_are_kept_per_boot_across_restarts() {
+    const BOOT_ID: &str = "2f0d9a4e-6b1c-4c8e-9a57-3e1f0b6d8c21";
+    let terminate = |service: &mut Service| {
+        let status = Command::new("kill").arg(service.child.id().to_string()).status().unwrap();
+        assert!(status.success());
+        let _ = service.child.wait();
+    };
+
+    let mut service = start_service("summaries", &[]);
+    service.wait_for_history();
+    let reply = service.query("summaries");
+    let boot = &reply["boots"][0];
+    assert_eq!(boot["boot_id"], BOOT_ID);
+    assert_eq!(boot["sessions"].as_array().map(Vec::len), Some(1));
+    assert_eq!(boot["total"]["metrics"]["memory"]["avg"], 50.0);
+
+    // SIGTERM finalizes the session on disk; the next run adds a second one
+    terminate(&mut service);
+    let file = service.dir.join(format!("persist/summaries/{}.json", BOOT_ID));
+    assert!(file.exists(), "no summary written on shutdown");
+    service.restart();
+    service.wait_for_history();
+    let reply = service.query("summaries");
+    assert_eq!(reply["boots"].as_array().map(Vec::len), Some(1));
+    assert_eq!(reply["boots"][0]["sessions"].as_array().map(Vec::len), Some(2));
+}
+
+#[test]
+fn history_survives_a_restart_with_a_gap_at_the_boundary() {
+    let mut service = start_service("restore", &[]);
+    let before = service.wait_for_history();
+    let saved_last = before["last_update"].as_i64().unwrap();
+
+    // A reboot clears the data dir; the copy written at SIGTERM is what's left
+    let status = Command::new("kill").arg(service.child.id().to_string()).status().unwrap();
+    assert!(status.success());
+    let _ = service.child.wait();
+    assert!(service.dir.join("persist/history.json").exists(), "no history saved on shutdown");
+    fs::remove_dir_all(service.dir.join("data")).unwrap();
+    service.restart();
+    service.wait_for_history();
+
+    let history = service.query("history");
+    let timestamps: Vec<i64> = history["timestamps"].as_array().unwrap().iter().filter_map(|t| t.as_i64()).collect();
+    let last = timestamps.iter().position(|t| *t == saved_last).expect("saved samples restored");
+    let cpu = history["cpu"].as_array().unwrap();
+    let offset = timestamps.len() - cpu.len();
+    assert_eq!(cpu[last - offset].as_f64(), before["cpu"].as_array().unwrap().last().unwrap().as_f64());
+    // One null slot an interval later, then whatever the new run sampled
+    assert_eq!(timestamps[last + 1], saved_last + 1000);
+    assert!(cpu[last + 1 - offset].is_null());
+    let markers = history["markers"].as_array().unwrap();
+    assert!(markers.iter().any(|m| m["kind"] == "gap" && m["label"] == "restart" && m["start"] == saved_last + 1000), "{:?}", markers);
+}
+
+#[test]
+fn smartctl_fills_in_drive_temperatures() {
+    use std::os::unix::fs::PermissionsExt;
+    let dir = std::env::temp_dir().join(format!("stats-service-e2e-smart-bin-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    // sdb answers; sdc is asleep, for which smartctl prints no temperature
+    let script = "#!/bin/sh\ncase \"$5\" in\n/dev/sdb) echo '{\"temperature\": {\"current\": 38}}' ;;\n*) echo '{}'; exit 2 ;;\nesac\n";
+    fs::write(dir.join("smartctl"), script).unwrap();
+    fs::set_permissions(dir.join("smartctl"), fs::Permissions::from_mode(0o755)).unwrap();
+
+    let mut service = start_service_with("smart", &[], "\n[smart]\nenabled = true\ndevices = [\"sdb\", \"sdc\"]\n");
+    fs::rename(&dir, service.dir.join("bin")).unwrap();
+    service.restart();
+    service.wait_for_history();
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let latest_file = service.dir.join("data/latest.json").to_string_lossy().into_owned();
+    loop {
+        let latest = data::load_latest_from(&latest_file).unwrap_or_default();
+        if latest["temperatures"]["smart/sdb"].as_f64() == Some(38.0) {
+            assert!(latest["temperatures"]["smart/sdc"].is_null());
+            break;
+        }
+        assert!(Instant::now() < deadline, "no SMART temperature in latest.json: {}", latest);
+        thread::sleep(Duration::from_millis(200));
+    }
+}
+
+#[test]
+fn status_reports_static_system_info() {
+    let service = start_service("system-info", &[]);
+    service.wait_for_history();
+
+    let system = &service.query("status")["system"];
+    assert_eq!(system["kernel"], "6.9.0-fixture");
+    assert_eq!(system["cpu_model"], "Fixture CPU @ 3.00GHz");
+    assert_eq!(system["cpu_cores"], 2);
+    assert_eq!(system["cpu_threads"], 4);
+    assert_eq!(system["memory_total"], MEM_TOTAL_KB);
+    assert!(system["gpus"].is_array());
+}
+
+#[test]
+fn a_containers_cgroup_limits_stand_in_for_the_machine() {
+    use std::os::unix::fs::symlink;
+    let dir = write_config("cgroup", "");
+    // The procfs fixture, but in a cgroup; its sysfs sits beside it
+    let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/proc");
+    let proc_root = dir.join("root/proc");
+    fs::create_dir_all(proc_root.join("self")).unwrap();
+    for entry in fs::read_dir(&fixture).unwrap().chain(fs::read_dir(fixture.join("self")).unwrap()) {
+        let entry = entry.unwrap().path();
+        let link = proc_root.join(entry.strip_prefix(&fixture).unwrap());
+        if !link.exists() {
+            symlink(&entry, link).unwrap();
+        }
+    }
+    fs::write(proc_root.join("self/cgroup"), "0::/app.scope\n").unwrap();
+    let cgroup = dir.join("root/sys/fs/cgroup/app.scope");
+    fs::create_dir_all(&cgroup).unwrap();
+    for (name, content) in [("cpu.max", "50000 100000"), ("memory.max", "8192000000"), ("cpu.stat", "usage_usec 0"), ("memory.current", "2048000000")] {
+        fs::write(cgroup.join(name), content).unwrap();
+    }
+    let config = fs::read_to_string(dir.join("config.toml")).unwrap();
+    let config = config.replace(&format!("proc_root = {:?}", fixture), &format!("proc_root = {:?}", proc_root));
+    fs::write(dir.join("config.toml"), config).unwrap();
+
+    let child = spawn_service_env(&dir, &[], &[("container", "fixture")]);
+    let service = Service { child, dir };
+    let history = service.wait_for_history();
+
+    let environment = &service.query("status")["environment"];
+    assert_eq!(environment["runtime"], serde_json::json!({ "kind": "container", "name": "fixture" }));
+    assert_eq!(environment["cgroup"]["cpu_quota"], 0.5);
+    assert_eq!(environment["cgroup"]["memory_limit"], 8_000_000.0);
+    // Half a CPU's quota: the host's cores and clocks aren't ours
+    assert!(history["cpu_cores"].as_array().is_none_or(Vec::is_empty), "{}", history["cpu_cores"]);
+    assert!(history["cpu_frequencies"].as_array().is_none_or(Vec::is_empty));
+    let latest = service.query("latest");
+    assert_eq!(latest["memory"]["total"], 8_000_000.0);
+    assert_eq!(latest["memory"]["used_percentage"], 25.0);
+}
+
+#[test]
+fn hour_of_day_patterns_keep_kib_on_disk_and_serve_the_configured_unit() {
+    let dir = write_config("patterns-units", "\n[units]\nnetwork = \"bits\"\n");
+    // Written under the default KB/s an hour ago
+    let timestamp = chrono::Local::now().timestamp_millis() / 60_000 * 60_000 - 3_600_000;
+    let sample = serde_json::json!({
+        "timestamp": timestamp, "weekday": 0, "hour": 7, "cpu": 10.0, "memory": 20.0,
+        "network_download": 100.0, "network_upload": 25.0,
+    });
+    fs::create_dir_all(dir.join("persist")).unwrap();
+    fs::write(dir.join("persist/samples.jsonl"), format!("{}\n", sample)).unwrap();
+    let child = spawn_service(&dir, &[]);
+    let service = Service { child, dir };
+    service.wait_for_history();
+
+    let hours = service.query("hourly");
+    let hour = &hours["hours"][7];
+    assert_eq!(hour["samples"], 1);
+    // 100 KiB/s is 800 kbit/s of 1024
+    assert_eq!(hour["network_download"], 800.0);
+    assert_eq!(hour["network_upload"], 200.0);
+    // Rewritten at startup, still in KiB/s
+    let saved = fs::read_to_string(service.dir.join("persist/samples.jsonl")).unwrap();
+    let saved: serde_json::Value = serde_json::from_str(saved.lines().next().unwrap()).unwrap();
+    assert_eq!(saved["network_download"], 100.0);
+}
+
+#[test]
+fn history_length_is_configurable_per_metric() {
+    let sizes = "\n[history]\nsize = 30\n\n[history.metrics]\nnetwork = 90\ncpu_cores = 45\nmemory = 20\n";
+    let service = start_service_with("history-sizes", &[], sizes);
+    let history = service.wait_for_history();
+    let len = |series: &serde_json::Value| series.as_array().map(Vec::len);
+
+    assert_eq!(len(&history["cpu"]), Some(30));
+    assert_eq!(len(&history["cpu_iowait"]), Some(30));
+    assert_eq!(len(&history["memory"]), Some(20));
+    // A group override covers every series in it
+    assert_eq!(len(&history["memory_apps"]), Some(20));
+    assert_eq!(len(&history["network_download"]), Some(90));
+    assert_eq!(len(&history["network_upload"]), Some(90));
+    assert!(history["cpu_cores"].as_array().unwrap().iter().all(|core| len(core) == Some(45)));
+    // Timestamps cover the longest series
+    assert_eq!(len(&history["timestamps"]), Some(90));
+    assert_eq!(history["timestamps"].as_array().unwrap().last(), Some(&history["last_update"]));
+}
+
+#[test]
+fn disk_io_is_reported_per_whole_disk() {
+    let service = start_service("disk-io", &[]);
+    let history = service.wait_for_history();
+    assert!(history["disk_read"].is_array() && history["disk_write"].is_array());
+
+    // Partitions, device-mapper and loop devices would double count
+    let latest = data::load_latest_from(&service.dir.join("data/latest.json").to_string_lossy()).expect("latest.json");
+    let disks: Vec<&str> = latest["disks"].as_array().expect("disks").iter().filter_map(|d| d["name"].as_str()).collect();
+    assert_eq!(disks, vec!["nvme0n1", "sda"]);
+    // The fixture's counters never move
+    assert_eq!(latest["disk_read"], 0.0);
+    assert_eq!(latest["disk_write"], 0.0);
+}
+
+#[test]
+fn configured_mounts_are_reported_every_tick() {
+    let mounts = "\n[filesystems]\nmounts = [\"/\", \"/nonexistent-mount\"]\n";
+    let service = start_service_with("mounts", &[], mounts);
+    service.wait_for_history();
+    // Usage is sampled once a minute; later ticks repeat the sample
+    thread::sleep(Duration::from_millis(1500));
+
+    let latest = data::load_latest_from(&service.dir.join("data/latest.json").to_string_lossy()).expect("latest.json");
+    let filesystems = latest["filesystems"].as_array().expect("filesystems");
+    assert_eq!(filesystems.len(), 1, "{:?}", filesystems);
+    let root = &filesystems[0];
+    assert_eq!(root["mount"], "/");
+    assert_eq!(root["fstype"], "ext4");
+    let (total, used, available) = (root["total"].as_f64().unwrap(), root["used"].as_f64().unwrap(), root["available"].as_f64().unwrap());
+    assert!((used + available - total).abs() < 1e-6);
+    let percentage = root["used_percentage"].as_f64().unwrap();
+    assert!((percentage - used / total * 100.0).abs() < 1e-9);
+}
+
+/// A free loopback port and the `[http]` table that listens on it.
+fn http_config() -> (u16, String) {
+    let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    (port, format!("\n[http]\nenabled = true\nlisten = \"127.0.0.1:{}\"\n", port))
+}
+
+/// The status line and JSON body of a GET sent with `host` as its Host header.
+fn http_get(port: u16, host: &str, target: &str) -> (String, serde_json::Value) {
+    use std::io::{Read, Write};
+    let mut stream = std::net::TcpStream::connect(("127.0.0.1", port)).unwrap();
+    write!(stream, "GET {} HTTP/1.1\r\nHost: {}\r\n\r\n", target, host).unwrap();
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply).unwrap();
+    let (head, body) = reply.split_once("\r\n\r\n").expect("headers");
+    (head.lines().next().unwrap().to_string(), serde_json::from_str(body).unwrap())
+}
+
+#[test]
+fn http_endpoint_serves_the_socket_replies() {
+    let (port, http) = http_config();
+    let service = start_service_with("http", &[], &http);
+    service.wait_for_history();
+    let get = |target: &str| http_get(port, "localhost", target);
+
+    let (status, latest) = get("/latest");
+    assert_eq!(status, "HTTP/1.1 200 OK");
+    assert_eq!(latest["memory"]["used_percentage"], 50.0);
+
+    let (status, cpu) = get("/history?metric=cpu&last=5");
+    assert_eq!(status, "HTTP/1.1 200 OK");
+    assert_eq!(cpu["metric"], "cpu");
+    assert_eq!(cpu["values"].as_array().unwrap().len(), 5);
+    assert_eq!(cpu["timestamps"].as_array().unwrap().len(), 5);
+    // The socket serves the same series
+    assert_eq!(service.query("series cpu 5")["metric"], "cpu");
+    // Query values are percent-decoded, as ids with spaces need
+    let (status, decoded) = get("/history?metric=%63pu&last=1");
+    assert_eq!(status, "HTTP/1.1 200 OK");
+    assert_eq!(decoded["metric"], "cpu");
+    let (status, _) = get("/history?metric=%6");
+    assert_eq!(status, "HTTP/1.1 400 Bad Request");
+
+    // Only hosts naming the listener, so a rebound DNS name can't reach it
+    for host in [format!("127.0.0.1:{}", port), "localhost".to_string(), format!("localhost:{}", port)] {
+        assert_eq!(http_get(port, &host, "/latest").0, "HTTP/1.1 200 OK", "{}", host);
+    }
+    let (status, _) = http_get(port, &format!("attacker.example:{}", port), "/latest");
+    assert_eq!(status, "HTTP/1.1 403 Forbidden");
+
+    // Error replies carry an error status, not 200
+    let (status, missing) = get("/history?metric=no-such-metric");
+    assert_eq!(status, "HTTP/1.1 404 Not Found");
+    assert_eq!(missing["error"], "no such series: no-such-metric");
+    let (status, _) = get("/history?metric=cpu&last=some");
+    assert_eq!(status, "HTTP/1.1 400 Bad Request");
+
+    // Nothing past latest and history, read-only or not: the port has no ACL
+    for target in ["/clear-markers", "/status", "/process?pid=1", "/apps"] {
+        let (status, _) = get(target);
+        assert_eq!(status, "HTTP/1.1 404 Not Found", "{}", target);
+    }
+}
+
+#[test]
+fn http_latest_is_scrubbed_like_latest_json() {
+    let (port, http) = http_config();
+    let service = start_service_with("http-privacy", &[], &(http + "\n[privacy]\nscrub_process_names = true\n"));
+    service.wait_for_history();
+
+    let (status, latest) = http_get(port, "localhost", "/latest");
+    assert_eq!(status, "HTTP/1.1 200 OK");
+    let latest = latest.to_string();
+    assert!(!latest.contains("firefox"), "process names leaked over HTTP");
+    assert!(latest.contains("redacted"));
+}
+
+#[test]
+fn state_metrics_are_published_only_on_change() {
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::UnixStream;
+
+    let service = start_service("states", &[]);
+    let history = service.wait_for_history();
+    // The fixture has no tunnel interface
+    assert_eq!(history["states"]["vpn"]["value"], "disconnected");
+    let since = history["states"]["vpn"]["since"].as_i64().unwrap();
+    assert!(since <= history["last_update"].as_i64().unwrap());
+
+    let metadata = data::load_metadata_from(&service.socket());
+    assert_eq!(metadata["series"]["vpn"]["kind"], "state");
+    assert_eq!(metadata["series"]["cpu"]["kind"], "series");
+
+    // Already published once, so later ticks leave it out until the heartbeat
+    let mut stream = UnixStream::connect(service.socket()).unwrap();
+    stream.write_all(b"subscribe\n").unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    let mut lines = BufReader::new(stream).lines();
+    for _ in 0..2 {
+        let update: serde_json::Value = serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap();
+        assert!(update["states"].is_null(), "{}", update["states"]);
+    }
+    let history = service.query("history");
+    assert_eq!(history["states"]["vpn"]["since"].as_i64(), Some(since));
+}
+
+#[test]
+fn network_rates_follow_the_configured_units() {
+    let service = start_service_with("units", &[], "\n[units]\nnetwork = \"bits\"\nprefix = \"si\"\n");
+    service.wait_for_history();
+
+    let latest = data::load_latest_from(&service.dir.join("data/latest.json").to_string_lossy()).expect("latest.json");
+    assert_eq!(latest["network_unit"], "kbit/s");
+    let metadata = data::load_metadata_from(&service.socket());
+    assert_eq!(metadata["series"]["network_download"]["unit"], "kbit/s");
+    assert_eq!(metadata["series"]["network_upload"]["unit"], "kbit/s");
+    // Disk rates keep their unit
+    assert_eq!(metadata["series"]["disk_read"]["unit"], "KB/s");
+}
+
+#[test]
+fn self_check_removes_only_a_stale_socket_and_recent_crashes() {
+    let dir = write_config("check", "");
+    let check = || -> String {
+        let output = Command::new(env!("CARGO_BIN_EXE_stats-service"))
+            .arg("--config")
+            .arg(dir.join("config.toml"))
+            .arg("--check")
+            .output()
+            .expect("failed to run --check");
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+    // `[PASS] <name padded to 24> <detail>`
+    let line = |report: &str, name: &str| {
+        report.lines().find(|l| l[7..].strip_prefix(name).is_some_and(|rest| rest.starts_with(' '))).map(str::to_string)
+    };
+    let socket = dir.join("stats.sock");
+    fs::create_dir_all(dir.join("data")).unwrap();
+    let old = chrono::Local::now() - chrono::Duration::days(30);
+    fs::write(dir.join("data/crash.log"), format!("{} panicked at 'old'\n", old.to_rfc3339())).unwrap();
+
+    // Whatever is at the socket path stays unless it's a dead socket
+    fs::write(&socket, "not a socket").unwrap();
+    let report = check();
+    assert!(line(&report, "socket").unwrap().starts_with("[FAIL]"), "{}", report);
+    assert_eq!(fs::read_to_string(&socket).unwrap(), "not a socket");
+    assert_eq!(line(&report, "previous crash"), None, "{}", report);
+
+    fs::remove_file(&socket).unwrap();
+    drop(std::os::unix::net::UnixListener::bind(&socket).unwrap());
+    let recent = format!("{} panicked at 'recent'", chrono::Local::now().to_rfc3339());
+    let mut log = fs::OpenOptions::new().append(true).open(dir.join("data/crash.log")).unwrap();
+    std::io::Write::write_all(&mut log, format!("{}\n", recent).as_bytes()).unwrap();
+    let report = check();
+    assert!(line(&report, "socket").unwrap().starts_with("[PASS]"), "{}", report);
+    assert!(!socket.exists());
+    assert!(line(&report, "previous crash").unwrap().ends_with(&recent), "{}", report);
+
+    let _ = fs::remove_dir_all(&dir);
+}
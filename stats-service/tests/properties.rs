@@ -0,0 +1,573 @@
+//! Randomized invariants for the history buffers, the counter deltas the
+//! collectors turn into rates and the selection math the graph window runs
+//! over them. Numeric edge cases (counters that stand still, shrink or wrap,
+//! series of different lengths) are where a graph goes quietly wrong, so
+//! each property is checked over a few hundred seeded cases.
+
+#[allow(dead_code)]
+#[path = "../src/collectors.rs"]
+mod collectors;
+#[allow(dead_code)]
+#[path = "../src/series.rs"]
+mod series;
+#[allow(dead_code)]
+#[path = "../../graph-window/src/data.rs"]
+mod data;
+#[allow(dead_code)]
+#[path = "../src/load_test.rs"]
+mod load_test;
+#[allow(dead_code)]
+#[path = "../src/protocol.rs"]
+mod protocol;
+
+use collectors::{CpuCollector, KernelActivityCollector, NetworkCollector};
+use load_test::{LoadTestDetector, LoadTestTransition, LOAD_TEST_ENTER_MS, LOAD_TEST_INTERVAL_MS};
+use protocol::Access;
+use series::{HistoryConfig, MetricId, RingBuffer, SeriesRegistry};
+use std::collections::{BTreeMap, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const CASES: u64 = 300;
+
+// xorshift64*, seeded so failures are reproducible
+struct Rng(u64);
+
+impl Rng {
+    fn new(case: u64) -> Self {
+        Rng(0x9e37_79b9_7f4a_7c15 ^ (case + 1).wrapping_mul(0xbf58_476d_1ce4_e5b9))
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n.max(1)
+    }
+
+    fn chance(&mut self, percent: u64) -> bool {
+        self.below(100) < percent
+    }
+}
+
+// A procfs tree in the temp dir, removed when its thread exits
+struct ProcDir {
+    path: &'static Path,
+}
+
+impl ProcDir {
+    fn new() -> Self {
+        let dir = std::env::temp_dir().join(format!(
+            "stats-service-properties-{}-{:?}",
+            std::process::id(),
+            std::thread::current().id()
+        ));
+        fs::create_dir_all(dir.join("net")).unwrap();
+        ProcDir { path: Box::leak(dir.into_boxed_path()) }
+    }
+}
+
+impl Drop for ProcDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(self.path);
+    }
+}
+
+thread_local! {
+    // Each test runs on its own thread, so each gets its own tree
+    static PROC_ROOT: ProcDir = ProcDir::new();
+}
+
+// What collectors.rs reads through `crate::proc_root`
+fn proc_root() -> &'static Path {
+    PROC_ROOT.with(|root| root.path)
+}
+
+fn write_proc(rel: &str, content: &str) -> PathBuf {
+    let path = proc_root().join(rel);
+    fs::write(&path, content).unwrap();
+    path
+}
+
+#[test]
+fn ring_buffers_keep_the_newest_values_at_their_capacity() {
+    for case in 0..CASES {
+        let mut rng = Rng::new(case);
+        let capacity = rng.below(20) as usize;
+        let mut buffer = RingBuffer::<f64>::padded(capacity);
+        let mut model: VecDeque<f64> = std::iter::repeat_n(0.0, capacity.max(1)).collect();
+        for _ in 0..rng.below(60) {
+            let value = rng.below(1000) as f64;
+            buffer.push(value);
+            model.push_back(value);
+            model.pop_front();
+            assert_eq!(buffer.values().len(), buffer.capacity(), "case {}", case);
+        }
+        assert_eq!(buffer.capacity(), capacity.max(1), "case {}", case);
+        assert_eq!(buffer.values(), &model, "case {}", case);
+    }
+}
+
+#[test]
+fn registered_series_stay_at_their_configured_lengths() {
+    let names = ["cpu", "memory", "network_download", "disk_read"];
+    for case in 0..CASES {
+        let mut rng = Rng::new(case);
+        let mut metrics = BTreeMap::new();
+        for name in names {
+            if rng.chance(50) {
+                metrics.insert(name.to_string(), rng.below(12) as usize);
+            }
+        }
+        let sizes = HistoryConfig { size: 1 + rng.below(12) as usize, metrics };
+        let mut registry = SeriesRegistry::new(&sizes);
+        let mut registered: Vec<&str> = Vec::new();
+        let mut last: BTreeMap<&str, f64> = BTreeMap::new();
+        let mut ticks = 0;
+
+        for _ in 0..rng.below(80) {
+            let name = names[rng.below(names.len() as u64) as usize];
+            match rng.below(10) {
+                // Registering again keeps what was recorded
+                0 | 1 => {
+                    registry.register(MetricId::named(name));
+                    if !registered.contains(&name) {
+                        registered.push(name);
+                    }
+                }
+                // A tick that never ran fills every series with NaN
+                2 => {
+                    registry.pad(ticks);
+                    last.clear();
+                    for name in &registered {
+                        assert!(registry.named(name).unwrap().back().unwrap().is_nan(), "case {}", case);
+                    }
+                    ticks += 1;
+                }
+                3 => {
+                    registry.push_timestamp(ticks);
+                    ticks += 1;
+                }
+                _ => {
+                    let value = rng.below(100) as f64;
+                    registry.record(&MetricId::named(name), value);
+                    if registered.contains(&name) {
+                        last.insert(name, value);
+                    }
+                }
+            }
+        }
+
+        let longest = registered.iter().map(|name| sizes.size_for(name)).max().unwrap_or(1);
+        assert_eq!(registry.timestamps().values().len(), longest, "case {}", case);
+        for name in names {
+            let series = registry.named(name);
+            if !registered.contains(&name) {
+                assert!(series.is_none(), "case {}: unregistered {} was recorded", case, name);
+                continue;
+            }
+            let series = series.unwrap();
+            assert_eq!(series.len(), sizes.size_for(name), "case {}: {}", case, name);
+            if let Some(value) = last.get(name) {
+                assert_eq!(series.back(), Some(value), "case {}: {}", case, name);
+            }
+        }
+    }
+}
+
+#[test]
+fn restored_history_serializes_as_it_was_saved() {
+    let names = ["cpu", "memory", "network_download"];
+    for case in 0..CASES {
+        let mut rng = Rng::new(case);
+        let mut metrics = BTreeMap::new();
+        for name in names {
+            if rng.chance(50) {
+                metrics.insert(name.to_string(), 1 + rng.below(12) as usize);
+            }
+        }
+        let sizes = HistoryConfig { size: 1 + rng.below(12) as usize, metrics };
+        let mut saved = SeriesRegistry::new(&sizes);
+        for name in names {
+            saved.register(MetricId::named(name));
+        }
+        saved.register(MetricId::Core(0));
+        saved.register(MetricId::InterfaceDownload("wlan0".to_string()));
+        saved.register(MetricId::InterfaceUpload("wlan0".to_string()));
+        // Fewer ticks than the longest series leaves padding to skip
+        for tick in 1..=rng.below(30) as i64 {
+            if rng.chance(10) {
+                saved.pad(tick * 1000);
+                continue;
+            }
+            for name in names {
+                saved.record(&MetricId::named(name), rng.below(100) as f64);
+            }
+            saved.record(&MetricId::Core(0), rng.below(100) as f64);
+            saved.record(&MetricId::InterfaceDownload("wlan0".to_string()), rng.below(100) as f64);
+            saved.record(&MetricId::InterfaceUpload("wlan0".to_string()), rng.below(100) as f64);
+            saved.push_timestamp(tick * 1000);
+        }
+        let json = serde_json::to_value(&saved).unwrap();
+        let newest = saved.timestamps().values().back().copied().filter(|t| *t > 0);
+
+        let mut restored = SeriesRegistry::new(&sizes);
+        for name in names {
+            restored.register(MetricId::named(name));
+        }
+        restored.register(MetricId::Core(0));
+        assert_eq!(restored.restore(&json, |id| matches!(id, MetricId::InterfaceDownload(_) | MetricId::InterfaceUpload(_))), newest, "case {}", case);
+        // Both padded the same, so only the NaN/null spelling differs
+        let restored = serde_json::to_value(&restored).unwrap();
+        assert_eq!(restored, json, "case {}", case);
+    }
+}
+
+// /proc/stat with the aggregate line summing the per-core ones
+fn stat_file(cores: &[[u64; 7]]) -> String {
+    let total: Vec<u64> = (0..7).map(|field| cores.iter().map(|core| core[field]).sum()).collect();
+    let line = |name: &str, fields: &[u64]| {
+        let fields: Vec<String> = fields.iter().map(u64::to_string).collect();
+        format!("{} {} 0 0 0\n", name, fields.join(" "))
+    };
+    let mut content = line("cpu ", &total);
+    for (i, core) in cores.iter().enumerate() {
+        content.push_str(&line(&format!("cpu{}", i), core));
+    }
+    content.push_str("intr 0\nctxt 0\nprocesses 1\n");
+    content
+}
+
+#[test]
+fn cpu_usage_stays_a_percentage_whatever_the_counters_do() {
+    for case in 0..CASES {
+        let mut rng = Rng::new(case);
+        let mut collector = CpuCollector::new();
+        let mut cores = vec![[0u64; 7]; 1 + rng.below(4) as usize];
+        for tick in 0..20 {
+            if rng.chance(5) {
+                // A core went offline or came back
+                cores.resize(1 + rng.below(4) as usize, [0; 7]);
+            }
+            for core in &mut cores {
+                for field in core.iter_mut() {
+                    *field = match rng.below(10) {
+                        // Standing still, and going backwards the way iowait
+                        // and NO_HZ idle accounting can
+                        0 | 1 => *field,
+                        2 => field.saturating_sub(rng.below(50)),
+                        _ => *field + rng.below(500),
+                    };
+                }
+            }
+            write_proc("stat", &stat_file(&cores));
+            let stats = collector.collect();
+            let context = format!("case {} tick {}: {:?} from {:?}", case, tick, stats, cores);
+            for value in [stats.overall_usage, stats.iowait_percentage].iter().chain(&stats.core_usage) {
+                assert!(value.is_finite() && (0.0..=100.0).contains(value), "{}", context);
+            }
+            assert!(stats.overall_usage + stats.iowait_percentage <= 100.0 + 1e-9, "{}", context);
+            assert_eq!(stats.core_usage.len(), cores.len(), "{}", context);
+            if tick == 0 {
+                assert_eq!(stats.overall_usage, 0.0, "{}", context);
+            }
+        }
+    }
+}
+
+#[test]
+fn cpu_usage_is_zero_while_the_counters_stand_still() {
+    let mut collector = CpuCollector::new();
+    write_proc("stat", &stat_file(&[[100, 0, 50, 800, 10, 0, 0]; 2]));
+    collector.collect();
+    let stats = collector.collect();
+    assert_eq!((stats.overall_usage, stats.iowait_percentage), (0.0, 0.0));
+    assert_eq!(stats.core_usage, vec![0.0, 0.0]);
+}
+
+fn net_dev(interfaces: &BTreeMap<String, (u64, u64)>) -> String {
+    let mut content = String::from("Inter-|   Receive\n face |bytes    packets\n");
+    content.push_str("    lo: 999999 0 0 0 0 0 0 0 999999 0 0 0 0 0 0 0\n");
+    for (name, (rx, tx)) in interfaces {
+        content.push_str(&format!("{:>6}: {} 0 0 0 0 0 0 0 {} 0 0 0 0 0 0 0\n", name, rx, tx));
+    }
+    content
+}
+
+#[test]
+fn network_rates_never_go_negative_or_spike_on_a_counter_reset() {
+    for case in 0..CASES {
+        let mut rng = Rng::new(case);
+        let mut collector = NetworkCollector::new();
+        let mut interfaces: BTreeMap<String, (u64, u64)> = BTreeMap::new();
+        let mut previous: BTreeMap<String, (u64, u64)> = BTreeMap::new();
+        for tick in 0..20 {
+            if interfaces.is_empty() || rng.chance(10) {
+                interfaces.insert(format!("eth{}", rng.below(3)), (rng.below(1 << 20), rng.below(1 << 20)));
+            }
+            if rng.chance(5) {
+                let name = format!("eth{}", rng.below(3));
+                interfaces.remove(&name);
+            }
+            for counters in interfaces.values_mut() {
+                for counter in [&mut counters.0, &mut counters.1] {
+                    *counter = match rng.below(10) {
+                        0 => *counter,
+                        // The interface was reset, or a 32-bit counter wrapped
+                        1 => rng.below(1000),
+                        2 => u32::MAX as u64 - rng.below(1000),
+                        _ => *counter + rng.below(1 << 16),
+                    };
+                }
+            }
+            write_proc("net/dev", &net_dev(&interfaces));
+            let (download, upload) = collector.collect();
+            let per_interface = collector.interfaces();
+            let context = format!("case {} tick {}: {:?}", case, tick, per_interface);
+
+            assert!(download.is_finite() && download >= 0.0 && upload.is_finite() && upload >= 0.0, "{}", context);
+            assert!(!per_interface.contains_key("lo"), "{}", context);
+            assert_eq!(per_interface.keys().collect::<Vec<_>>(), interfaces.keys().collect::<Vec<_>>(), "{}", context);
+            let sum: f64 = per_interface.values().map(|i| i.download).sum();
+            assert!((sum - download).abs() <= 1e-6 * download.max(1.0), "{}", context);
+            for (name, stats) in per_interface {
+                let (rx, tx) = interfaces[name];
+                assert_eq!((stats.rx_total, stats.tx_total), (rx, tx), "{}", context);
+                match previous.get(name) {
+                    Some(&(prev_rx, _)) if rx <= prev_rx => assert_eq!(stats.download, 0.0, "{}", context),
+                    None => assert_eq!((stats.download, stats.upload), (0.0, 0.0), "{}", context),
+                    _ => {}
+                }
+            }
+            previous = interfaces.clone();
+        }
+    }
+}
+
+#[test]
+fn kernel_activity_rates_never_go_negative() {
+    for case in 0..CASES {
+        let mut rng = Rng::new(case);
+        let mut collector = KernelActivityCollector::new();
+        let mut counters = [rng.below(1 << 30), rng.below(1 << 30), rng.below(1 << 20)];
+        for tick in 0..10 {
+            for counter in &mut counters {
+                *counter = match rng.below(10) {
+                    0 => *counter,
+                    1 => counter.saturating_sub(rng.below(100)),
+                    _ => *counter + rng.below(100_000),
+                };
+            }
+            write_proc("stat", &format!("cpu  1 0 1 1 0 0 0 0 0 0\nintr {} 3 0 7\nctxt {}\nprocesses {}\n", counters[1], counters[0], counters[2]));
+            let activity = collector.collect().expect("all three counters are present");
+            for rate in [activity.context_switches, activity.interrupts, activity.forks] {
+                assert!(rate.is_finite() && rate >= 0.0, "case {} tick {}: {:?}", case, tick, activity);
+            }
+        }
+    }
+    write_proc("stat", "cpu  1 0 1 1 0 0 0 0 0 0\nctxt 5\n");
+    assert!(KernelActivityCollector::new().collect().is_none(), "a missing counter is unreadable, not zero");
+}
+
+#[test]
+fn selection_stats_agree_with_the_samples_they_cover() {
+    for case in 0..CASES {
+        let mut rng = Rng::new(case);
+        let len = rng.below(40) as usize;
+        let padding = rng.below(len as u64 + 1) as usize;
+        let mut timestamps = vec![0i64; padding];
+        let mut time = 1_700_000_000_000i64;
+        while timestamps.len() < len {
+            time += 500 + rng.below(2000) as i64;
+            timestamps.push(time);
+        }
+        // Series can be shorter than the timestamps they line up with
+        let values: Vec<f64> = (0..len.saturating_sub(rng.below(3) as usize))
+            .map(|_| if rng.chance(15) { f64::NAN } else { rng.below(10_000) as f64 / 10.0 })
+            .collect();
+        let (a, b) = (time - rng.below(60_000) as i64, time - rng.below(60_000) as i64);
+
+        let stats = data::range_stats(&values, &timestamps, a, b);
+        let (start, end) = (a.min(b), a.max(b));
+        let covered: Vec<f64> = timestamps
+            .iter()
+            .zip(&values)
+            .filter(|(&t, v)| t != 0 && (start..=end).contains(&t) && !v.is_nan())
+            .map(|(_, &v)| v)
+            .collect();
+        let context = format!("case {}: {:?}", case, stats);
+        let Some(stats) = stats else {
+            assert!(covered.is_empty(), "{}", context);
+            continue;
+        };
+        assert_eq!(stats.samples, covered.len(), "{}", context);
+        assert_eq!(stats.min, covered.iter().copied().fold(f64::INFINITY, f64::min), "{}", context);
+        assert_eq!(stats.max, covered.iter().copied().fold(f64::NEG_INFINITY, f64::max), "{}", context);
+        let avg = covered.iter().sum::<f64>() / covered.len() as f64;
+        assert!((stats.avg - avg).abs() < 1e-9 * avg.max(1.0), "{}", context);
+        assert!(stats.min <= stats.avg && stats.avg <= stats.max, "{}", context);
+        assert!(stats.total >= 0.0 && start <= stats.start && stats.start <= stats.end && stats.end <= end, "{}", context);
+    }
+}
+
+#[test]
+fn streamed_samples_keep_the_history_window() {
+    for case in 0..CASES {
+        let mut rng = Rng::new(case);
+        let max_len = 1 + rng.below(30) as usize;
+        let mut history = serde_json::json!({
+            "cpu": vec![0.0; rng.below(40) as usize],
+            "timestamps": [],
+        });
+        let mut timestamp = 1_700_000_000_000i64;
+        for _ in 0..rng.below(60) {
+            timestamp += 1000;
+            let latest = serde_json::json!({"timestamp": timestamp, "cpu_usage": rng.below(100), "memory": {"used_percentage": 40}});
+            assert!(data::append_latest(&mut history, &latest, max_len), "case {}", case);
+            for key in ["cpu", "memory", "timestamps"] {
+                assert!(data::series(&history, key).len() <= max_len, "case {}: {} over {}", case, key, max_len);
+            }
+            assert_eq!(data::series(&history, "timestamps").last().copied(), Some(timestamp as f64), "case {}", case);
+            assert!(!data::append_latest(&mut history, &latest, max_len), "case {}: a repeated sample", case);
+        }
+    }
+}
+
+#[test]
+fn series_gaps_keep_their_slots() {
+    for case in 0..CASES {
+        let mut rng = Rng::new(case);
+        let len = 1 + rng.below(80) as usize;
+        let timestamps: Vec<i64> = (0..len as i64).map(|i| 1_000_000 + i * 1000).collect();
+        // Padded ticks and unreadable sensors come as null
+        let values: Vec<Option<f64>> = (0..len).map(|_| (!rng.chance(25)).then(|| rng.below(100) as f64)).collect();
+        let history = serde_json::json!({ "timestamps": timestamps, "temperature": values });
+
+        let series = data::series(&history, "temperature");
+        assert_eq!(series.len(), len, "case {}", case);
+        for (slot, (plotted, sent)) in series.iter().zip(&values).enumerate() {
+            assert_eq!(plotted.is_nan(), sent.is_none(), "case {}: slot {}", case, slot);
+        }
+        assert_eq!(data::plotted_timestamps(&history, series.len()), timestamps, "case {}", case);
+        assert_eq!(data::newest(&series), values.iter().rev().find_map(|v| *v), "case {}", case);
+
+        // The gaps are skipped, not shifted into their neighbours' slots
+        let first = rng.below(len as u64) as usize;
+        let last = first + rng.below((len - first) as u64) as usize;
+        let taken: Vec<f64> = values[first..=last].iter().filter_map(|v| *v).collect();
+        let stats = data::range_stats(&series, &timestamps, timestamps[first], timestamps[last]);
+        match stats {
+            Some(stats) => {
+                assert_eq!(stats.samples, taken.len(), "case {}", case);
+                assert_eq!(stats.max, taken.iter().copied().fold(f64::NEG_INFINITY, f64::max), "case {}", case);
+            }
+            None => assert!(taken.is_empty(), "case {}", case),
+        }
+    }
+}
+
+#[test]
+fn touch_gestures_stay_inside_the_plotted_samples() {
+    for case in 0..CASES {
+        let mut rng = Rng::new(case);
+        let full = rng.below(120) as usize;
+        let start = rng.below(full as u64 + 1) as usize;
+        let scale = match rng.below(4) {
+            0 => 0.0,
+            1 => f64::NAN,
+            _ => rng.below(1000) as f64 / 100.0,
+        };
+        let slots = data::zoom_slots(start, scale, full);
+        let context = format!("case {}: {} slots of {} at scale {}", case, start, full, scale);
+        if scale.is_finite() && scale > 0.0 {
+            assert!(slots <= full && slots >= data::MIN_ZOOM_SLOTS.min(full), "{} gave {}", context, slots);
+            // Spreading the fingers never widens the span, pinching never narrows it
+            if scale >= 1.0 {
+                assert!(slots <= start.max(data::MIN_ZOOM_SLOTS.min(full)), "{} gave {}", context, slots);
+            }
+        } else {
+            assert_eq!(slots, start, "{}", context);
+        }
+
+        let width = rng.below(800) as f64;
+        let x = rng.below(1000) as f64 - 100.0;
+        let slot = data::slot_at(x, width, slots);
+        assert!(slot < slots.max(1), "{}: x {} on {} px gave slot {}", context, x, width, slot);
+    }
+}
+
+#[test]
+fn gauges_stay_on_their_dials() {
+    let metadata = serde_json::json!({});
+    for case in 0..CASES {
+        let mut rng = Rng::new(case);
+        let mut history = serde_json::json!({});
+        let mut present = 0;
+        for key in ["cpu", "memory", "temperature", "network_download", "network_upload"] {
+            let len = if rng.chance(20) { 0 } else { 1 + rng.below(60) as usize };
+            // Counter glitches and sensors reading far past their scale
+            let values: Vec<f64> = (0..len).map(|_| rng.below(5000) as f64 / 10.0 - 100.0).collect();
+            present += usize::from(len > 0 && !key.starts_with("network"));
+            history[key] = values.into();
+        }
+        let network = !data::series(&history, "network_download").is_empty() && !data::series(&history, "network_upload").is_empty();
+        let gauges = data::gauges(&history, &metadata, |celsius| (celsius, "°C"));
+        assert_eq!(gauges.len(), present + usize::from(network), "case {}", case);
+        for gauge in &gauges {
+            assert!((0.0..=1.0).contains(&gauge.fraction), "case {}: {} at {}", case, gauge.label, gauge.fraction);
+        }
+    }
+}
+
+#[test]
+fn a_pinned_gpu_is_a_load_test_however_idle_the_cpu() {
+    for case in 0..CASES {
+        let mut rng = Rng::new(case);
+        let mut detector = LoadTestDetector::new();
+        let cpu = rng.below(50) as f64;
+        // Other GPUs idle or unreadable; the busiest one is pinned
+        let mut gpus: Vec<f64> = (0..rng.below(3)).map(|_| if rng.chance(30) { f64::NAN } else { rng.below(50) as f64 }).collect();
+        gpus.insert(rng.below(gpus.len() as u64 + 1) as usize, 100.0);
+        let load = load_test::busiest(cpu, gpus.iter().copied());
+        assert_eq!(load, 100.0, "case {}: cpu {} gpus {:?}", case, cpu, gpus);
+
+        let mut started = None;
+        let mut timestamp = 0;
+        while timestamp <= LOAD_TEST_ENTER_MS {
+            if let Some(transition) = detector.update(timestamp, load) {
+                started = Some(transition);
+            }
+            timestamp += LOAD_TEST_INTERVAL_MS as i64;
+        }
+        assert_eq!(started, Some(LoadTestTransition::Started { since: 0 }), "case {}", case);
+        assert!(detector.is_active(), "case {}", case);
+        // Without the GPU the same CPU never gets there
+        assert_eq!(load_test::busiest(cpu, []), cpu, "case {}", case);
+    }
+}
+
+#[test]
+fn a_supplementary_group_grants_read_only_access() {
+    const OWNER: u32 = 1000;
+    for case in 0..CASES {
+        let mut rng = Rng::new(case);
+        let uid = 1001 + rng.below(100) as u32;
+        let read_only: Vec<u32> = (0..1 + rng.below(3)).map(|_| 100 + rng.below(20) as u32).collect();
+        // A primary group that isn't granted, then supplementary ones
+        let mut groups = vec![uid];
+        groups.extend((0..rng.below(4)).map(|_| 200 + rng.below(20) as u32));
+        assert_eq!(protocol::access_for(uid, &groups, OWNER, &read_only), Access::Denied, "case {}", case);
+
+        let granted = read_only[rng.below(read_only.len() as u64) as usize];
+        groups.insert(1 + rng.below(groups.len() as u64) as usize, granted);
+        assert_eq!(protocol::access_for(uid, &groups, OWNER, &read_only), Access::ReadOnly, "case {}: {:?}", case, groups);
+        // The owner's groups don't matter
+        assert_eq!(protocol::access_for(OWNER, &groups, OWNER, &read_only), Access::Control, "case {}", case);
+    }
+    // root's passwd entry is there everywhere; its primary group leads
+    assert_eq!(protocol::groups_of(0, 0).first(), Some(&0));
+}
@@ -2,8 +2,6 @@ import Gtk from "gi://Gtk?version=4.0"
 import GLib from "gi://GLib"
 import { exec } from "ags/process"
 
-let graphCounter = 0
-
 export function SvgGraph({ 
   history, 
   maxValue = 100, 
@@ -22,36 +20,36 @@ export function SvgGraph({
   // Ensure we have some data
   const dataToPlot = history.length > 0 ? history : [0]
   
-  // Use the Rust graph renderer
-  const tmpPath = `/tmp/ags-graph-${graphCounter++}.svg`
-  
+  // Use the Rust graph renderer; frames go to its managed cache dir, which
+  // it prunes itself, so nothing piles up in /tmp
   const graphData = {
     data: dataToPlot,
     max_value: maxValue,
     color: color,
     width: width,
     height: height,
-    output_path: tmpPath
+    managed: { name: "ags-graph" }
   }
   
   try {
-    // Write JSON to temp file to avoid shell escaping issues
-    const jsonPath = `/tmp/ags-graph-${graphCounter}.json`
-    GLib.file_set_contents(jsonPath, JSON.stringify(graphData))
-    
-    // Call the Rust binary with JSON file
-    const [success, stdout, stderr] = GLib.spawn_command_line_sync(
-      `/home/psi/.config/ags/graph-renderer/target/release/graph-renderer < ${jsonPath}`
+    // Pass the JSON as an argument to avoid shell escaping issues
+    const [success, stdout, stderr] = GLib.spawn_sync(
+      null,
+      ["/home/psi/.config/ags/graph-renderer/target/release/graph-renderer", JSON.stringify(graphData)],
+      null,
+      GLib.SpawnFlags.SEARCH_PATH,
+      null
     )
     
     if (!success) {
       console.error("Graph renderer failed:", stderr)
     }
+    const outputPath = new TextDecoder().decode(stdout).trim()
     
     // Return the image widget pointing to the generated SVG
     return (
       <image 
-        file={tmpPath}
+        file={outputPath}
         widthRequest={width}
         heightRequest={height}
         cssClasses={["graph-image"]}
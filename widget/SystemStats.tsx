@@ -3,9 +3,18 @@ import GLib from "gi://GLib"
 import { createPoll } from "ags/time"
 import { readFile } from "ags/file"
 
+interface NetQuality {
+  score: number
+  latency_ms: number | null
+  loss_percent: number
+  error_rate: number
+}
+
 interface NetInfo {
   download: number
   upload: number
+  unit: string
+  quality?: NetQuality
 }
 
 function readSharedStats() {
@@ -42,7 +51,8 @@ export function CpuWidget() {
       data_source: "cpu",
       position_x: Math.floor(x),
       position_y: Math.floor(y),
-      multi_chart: false
+      multi_chart: false,
+      watch_parent: true
     }
     try {
       const jsonStr = JSON.stringify(config).replace(/'/g, "\'")
@@ -65,7 +75,8 @@ export function CpuWidget() {
       data_source: "cpu",
       position_x: Math.floor(x),
       position_y: Math.floor(y),
-      multi_chart: true
+      multi_chart: true,
+      watch_parent: true
     }
     try {
       const jsonStr = JSON.stringify(config).replace(/'/g, "\'")
@@ -122,7 +133,8 @@ export function MemoryWidget() {
       data_source: "memory",
       advanced: true,
       position_x: Math.floor(x),
-      position_y: Math.floor(y)
+      position_y: Math.floor(y),
+      watch_parent: true
     }
     try {
       const jsonStr = JSON.stringify(config).replace(/'/g, "\'")
@@ -148,25 +160,38 @@ export function MemoryWidget() {
 
 export function NetworkWidget() {
   const netInfo = createPoll<NetInfo>(
-    { download: 0, upload: 0 },
+    { download: 0, upload: 0, unit: "KB/s" },
     1000,
     () => {
       const shared = readSharedStats()
       if (shared) {
         return {
           download: shared.network_download,
-          upload: shared.network_upload
+          upload: shared.network_upload,
+          unit: shared.network_unit ?? "KB/s",
+          quality: shared.network_quality
         }
       }
-      return { download: 0, upload: 0 }
+      return { download: 0, upload: 0, unit: "KB/s" }
     }
   )
   
-  function formatSpeed(kbps: number): string {
-    if (kbps > 1024) {
-      return `${(kbps / 1024).toFixed(1)} MB/s`
+  // The unit follows the service's [units] setting: KB/s, kB/s, Kibit/s or kbit/s
+  function formatSpeed(rate: number, unit: string): string {
+    const step = unit === "KB/s" || unit.startsWith("Ki") ? 1024 : 1000
+    if (rate > step) {
+      return `${(rate / step).toFixed(1)} ${unit.replace(/^[kK]/, "M")}`
     }
-    return `${kbps.toFixed(0)} KB/s`
+    return `${rate.toFixed(0)} ${unit}`
+  }
+  
+  function describeQuality(quality?: NetQuality): string {
+    if (!quality) return "Network quality: waiting for probes"
+    const latency = quality.latency_ms === null ? "unreachable" : `${quality.latency_ms.toFixed(0)} ms`
+    return `Network quality ${quality.score.toFixed(0)}/100\n` +
+      `Latency: ${latency}\n` +
+      `Loss: ${quality.loss_percent.toFixed(0)}%\n` +
+      `Interface errors: ${quality.error_rate.toFixed(2)}%`
   }
   
   const openGraphWindow = (event: any) => {
@@ -181,7 +206,8 @@ export function NetworkWidget() {
       height: 150,
       data_source: "network",
       position_x: Math.floor(x),
-      position_y: Math.floor(y)
+      position_y: Math.floor(y),
+      watch_parent: true
     }
     try {
       const jsonStr = JSON.stringify(config).replace(/'/g, "\'")
@@ -196,15 +222,20 @@ export function NetworkWidget() {
     <button
       cssClasses={["network-widget"]}
       onClicked={openGraphWindow}
+      tooltipText={netInfo((info) => describeQuality(info.quality))}
     >
       <box spacing={8}>
+        <box spacing={2} visible={netInfo((info) => info.quality !== undefined)}>
+          <label label="󰖩" cssClasses={["icon"]} />
+          <label label={netInfo((info) => info.quality ? info.quality.score.toFixed(0) : "")} />
+        </box>
         <box spacing={2}>
           <label label="󰇚" cssClasses={["icon"]} />
-          <label label={netInfo((info) => formatSpeed(info.download))} />
+          <label label={netInfo((info) => formatSpeed(info.download, info.unit))} />
         </box>
         <box spacing={2}>
           <label label="󰕒" cssClasses={["icon"]} />
-          <label label={netInfo((info) => formatSpeed(info.upload))} />
+          <label label={netInfo((info) => formatSpeed(info.upload, info.unit))} />
         </box>
       </box>
     </button>
@@ -0,0 +1,47 @@
+// Example AGS widget using the installed binaries. Copy into your AGS config
+// and add <StatsExample /> to a bar.
+import GLib from "gi://GLib"
+import { createPoll } from "ags/time"
+import { readFile } from "ags/file"
+
+const BIN_DIR = "{bin_dir}"
+
+function latest() {
+  try {
+    return JSON.parse(readFile("/tmp/ags-stats/latest.json"))
+  } catch {
+    return null
+  }
+}
+
+function renderGraph(data: number[]) {
+  const request = { data, max_value: 100, width: 120, height: 32, managed: { name: "example" } }
+  const [success, stdout] = GLib.spawn_sync(
+    null,
+    [`${BIN_DIR}/graph-renderer`, JSON.stringify(request)],
+    null,
+    GLib.SpawnFlags.SEARCH_PATH,
+    null
+  )
+  return success ? new TextDecoder().decode(stdout).trim() : ""
+}
+
+export function StatsExample() {
+  const history: number[] = []
+  const cpu = createPoll<number>(0, 1000, () => latest()?.cpu_usage ?? 0)
+
+  return (
+    <box cssClasses={["stats-example"]}>
+      <label label={cpu((v) => `CPU ${v.toFixed(0)}%`)} />
+      <image
+        file={cpu((v) => {
+          history.push(v)
+          if (history.length > 60) history.shift()
+          return renderGraph(history)
+        })}
+        widthRequest={120}
+        heightRequest={32}
+      />
+    </box>
+  )
+}
@@ -0,0 +1,206 @@
+//! Release packaging and versioning for the crates in this repository.
+//!
+//! `cargo xtask install` builds stats-service, graph-renderer and graph-window
+//! in release mode, copies the binaries into `~/.local/bin`, writes a systemd
+//! user unit for stats-service (generated by the installed binary itself, so
+//! its sandbox matches the configured paths) and drops an example AGS widget
+//! that uses the installed paths.
+//!
+//! `cargo xtask release <crate> <version>` and `cargo xtask tag <crate>`
+//! bump and tag versions; see `release`.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+
+mod release;
+
+const CRATES: [&str; 3] = ["stats-service", "graph-renderer", "graph-window"];
+const EXAMPLE_TEMPLATE: &str = include_str!("../assets/StatsExample.tsx");
+
+const USAGE: &str = "Usage: cargo xtask install [options]
+       cargo xtask release <crate> <version>
+       cargo xtask tag <crate>
+
+Install options:
+  --bin-dir <dir>   Install binaries here (default ~/.local/bin)
+  --skip-build      Install whatever is already in each crate's target/release
+  --no-unit         Don't write the systemd user unit
+  --only <crate>    Build and install a single crate (repeatable)
+
+release sets a crate's version and the requirements other crates have on it;
+tag marks the committed version as <crate>-v<version>.";
+
+struct InstallOptions {
+    bin_dir: PathBuf,
+    skip_build: bool,
+    write_unit: bool,
+    only: Vec<String>,
+}
+
+fn home() -> Result<PathBuf, String> {
+    env::var_os("HOME").map(PathBuf::from).ok_or_else(|| "HOME is not set".to_string())
+}
+
+// Same fallbacks as the service's own config lookup
+fn xdg_dir(var: &str, fallback: &str) -> Result<PathBuf, String> {
+    match env::var_os(var).map(PathBuf::from).filter(|p| p.is_absolute()) {
+        Some(dir) => Ok(dir),
+        None => Ok(home()?.join(fallback)),
+    }
+}
+
+fn repo_root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).parent().map(Path::to_path_buf).unwrap_or_default()
+}
+
+fn parse_install_args(args: &[String]) -> Result<InstallOptions, String> {
+    let mut options = InstallOptions {
+        bin_dir: home()?.join(".local/bin"),
+        skip_build: false,
+        write_unit: true,
+        only: Vec::new(),
+    };
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--bin-dir" => {
+                let dir = iter.next().ok_or("--bin-dir requires a path")?;
+                options.bin_dir = PathBuf::from(dir);
+            }
+            "--skip-build" => options.skip_build = true,
+            "--no-unit" => options.write_unit = false,
+            "--only" => {
+                let name = iter.next().ok_or("--only requires a crate name")?;
+                if !CRATES.contains(&name.as_str()) {
+                    return Err(format!("unknown crate: {} (expected one of {})", name, CRATES.join(", ")));
+                }
+                options.only.push(name.clone());
+            }
+            other => return Err(format!("unknown option: {}", other)),
+        }
+    }
+    Ok(options)
+}
+
+// The one crate name `release` and `tag` take, and what follows it
+fn parse_crate(args: &[String]) -> Result<(&str, &[String]), String> {
+    let name = args.first().ok_or("expected a crate name")?;
+    if !release::RELEASABLE.contains(&name.as_str()) {
+        return Err(format!("unknown crate: {} (expected one of {})", name, release::RELEASABLE.join(", ")));
+    }
+    Ok((name, &args[1..]))
+}
+
+fn build(root: &Path, name: &str) -> Result<(), String> {
+    println!("Building {} (release)", name);
+    let manifest = root.join(name).join("Cargo.toml");
+    let status = Command::new(env::var("CARGO").unwrap_or_else(|_| "cargo".to_string()))
+        .args(["build", "--release", "--manifest-path"])
+        .arg(&manifest)
+        .status()
+        .map_err(|e| format!("failed to run cargo: {}", e))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("building {} failed ({})", name, status))
+    }
+}
+
+// Copy next to the destination then rename, so a running binary (the service
+// in particular) is replaced rather than overwritten in place
+fn install_binary(root: &Path, name: &str, bin_dir: &Path) -> Result<PathBuf, String> {
+    let source = root.join(name).join("target/release").join(name);
+    if !source.is_file() {
+        return Err(format!("{} not found; build it first or drop --skip-build", source.display()));
+    }
+    let dest = bin_dir.join(name);
+    let tmp = bin_dir.join(format!(".{}.tmp", name));
+    fs::copy(&source, &tmp).map_err(|e| format!("failed to copy {}: {}", source.display(), e))?;
+    fs::rename(&tmp, &dest).map_err(|e| format!("failed to install {}: {}", dest.display(), e))?;
+    Ok(dest)
+}
+
+fn generate_unit(service: &Path) -> Result<String, String> {
+    let output = Command::new(service)
+        .arg("generate-unit")
+        .output()
+        .map_err(|e| format!("failed to run {}: {}", service.display(), e))?;
+    if !output.status.success() {
+        return Err(format!("{} generate-unit failed: {}", service.display(), String::from_utf8_lossy(&output.stderr)));
+    }
+    String::from_utf8(output.stdout).map_err(|e| format!("generated unit is not UTF-8: {}", e))
+}
+
+fn write_file(path: &Path, content: &str) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("failed to create {}: {}", parent.display(), e))?;
+    }
+    fs::write(path, content).map_err(|e| format!("failed to write {}: {}", path.display(), e))
+}
+
+fn install(options: InstallOptions) -> Result<(), String> {
+    let root = repo_root();
+    let selected: Vec<&str> = CRATES
+        .iter()
+        .copied()
+        .filter(|name| options.only.is_empty() || options.only.iter().any(|o| o == name))
+        .collect();
+
+    if !options.skip_build {
+        for name in &selected {
+            build(&root, name)?;
+        }
+    }
+
+    fs::create_dir_all(&options.bin_dir)
+        .map_err(|e| format!("failed to create {}: {}", options.bin_dir.display(), e))?;
+    for name in &selected {
+        let dest = install_binary(&root, name, &options.bin_dir)?;
+        println!("Installed {}", dest.display());
+    }
+
+    let bin_dir = options.bin_dir.to_string_lossy();
+    if options.write_unit && selected.contains(&"stats-service") {
+        let unit = xdg_dir("XDG_CONFIG_HOME", ".config")?.join("systemd/user/stats-service.service");
+        write_file(&unit, &generate_unit(&options.bin_dir.join("stats-service"))?)?;
+        println!("Wrote {}", unit.display());
+    }
+
+    let example = xdg_dir("XDG_DATA_HOME", ".local/share")?.join("ags-stats/examples/StatsExample.tsx");
+    write_file(&example, &EXAMPLE_TEMPLATE.replace("{bin_dir}", &bin_dir))?;
+    println!("Wrote {}", example.display());
+
+    if options.write_unit && selected.contains(&"stats-service") {
+        println!();
+        println!("Enable the service with:");
+        println!("  systemctl --user daemon-reload");
+        println!("  systemctl --user enable --now stats-service.service");
+    }
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("install") => parse_install_args(&args[1..]).and_then(install),
+        Some("release") => parse_crate(&args[1..]).and_then(|(name, rest)| match rest {
+            [version] => release::release(&repo_root(), name, version),
+            _ => Err("release takes a crate and a version, e.g. release graph-renderer 0.2.0".to_string()),
+        }),
+        Some("tag") => parse_crate(&args[1..]).and_then(|(name, rest)| match rest {
+            [] => release::tag(&repo_root(), name),
+            _ => Err("tag takes only a crate name".to_string()),
+        }),
+        Some("-h") | Some("--help") | None => {
+            println!("{}", USAGE);
+            return;
+        }
+        Some(other) => Err(format!("unknown task: {}\n\n{}", other, USAGE)),
+    };
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,155 @@
+//! Version bumps and release tags. `release` sets a crate's version and the
+//! requirement every sibling crate has on it, after checking the bump is
+//! forward and, for the libraries, that CHANGELOG.md has a section for it.
+//! `tag` then marks the committed release as `<crate>-v<version>`, which
+//! downstream configs pin git dependencies to.
+//!
+//! Only graph-theme and graph-renderer are libraries. Tags on stats-service
+//! and graph-window version the executables, the socket protocol and the
+//! history format; the collectors, series and socket client those two
+//! compile in from each other's sources are not a public API.
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// Every crate with a version of its own.
+pub const RELEASABLE: [&str; 4] = ["graph-theme", "graph-renderer", "stats-service", "graph-window"];
+
+// Crates other code links against, which keep a changelog
+const LIBRARIES: [&str; 2] = ["graph-theme", "graph-renderer"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Version {
+    major: u64,
+    minor: u64,
+    patch: u64,
+}
+
+impl Version {
+    /// `MAJOR.MINOR.PATCH`; pre-release and build suffixes aren't used here.
+    pub fn parse(text: &str) -> Result<Version, String> {
+        let parts: Vec<&str> = text.split('.').collect();
+        let number = |part: &str| part.parse::<u64>().ok().filter(|_| !part.starts_with('0') || part == "0");
+        match parts[..] {
+            [major, minor, patch] => match (number(major), number(minor), number(patch)) {
+                (Some(major), Some(minor), Some(patch)) => Ok(Version { major, minor, patch }),
+                _ => Err(format!("invalid version: {} (expected MAJOR.MINOR.PATCH)", text)),
+            },
+            _ => Err(format!("invalid version: {} (expected MAJOR.MINOR.PATCH)", text)),
+        }
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+fn read(path: &Path) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|e| format!("failed to read {}: {}", path.display(), e))
+}
+
+fn write(path: &Path, content: &str) -> Result<(), String> {
+    fs::write(path, content).map_err(|e| format!("failed to write {}: {}", path.display(), e))
+}
+
+/// The `version` of the `[package]` section.
+pub fn package_version(root: &Path, name: &str) -> Result<Version, String> {
+    let manifest = root.join(name).join("Cargo.toml");
+    let content = read(&manifest)?;
+    let line = content
+        .lines()
+        .skip_while(|line| line.trim() != "[package]")
+        .find(|line| line.starts_with("version = "))
+        .ok_or_else(|| format!("{} has no package version", manifest.display()))?;
+    Version::parse(line.trim_start_matches("version = ").trim_matches('"'))
+}
+
+// Rewrites lines that start with `prefix` and hold `old` in quotes. Only
+// lines are touched so comments and layout survive.
+fn replace_quoted(content: &str, prefix: &str, old: &str, new: &str) -> String {
+    let mut out: String = content
+        .lines()
+        .map(|line| {
+            if line.starts_with(prefix) {
+                line.replacen(&format!("\"{}\"", old), &format!("\"{}\"", new), 1)
+            } else {
+                line.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    if content.ends_with('\n') {
+        out.push('\n');
+    }
+    out
+}
+
+fn has_changelog_section(root: &Path, name: &str, version: Version) -> Result<bool, String> {
+    let changelog = read(&root.join(name).join("CHANGELOG.md"))?;
+    Ok(changelog.lines().any(|line| line.trim() == format!("## {}", version)))
+}
+
+/// Sets `name`'s version and its siblings' requirements on it.
+pub fn release(root: &Path, name: &str, version: &str) -> Result<(), String> {
+    let new = Version::parse(version)?;
+    let old = package_version(root, name)?;
+    if new <= old {
+        return Err(format!("{} is already at {}; the new version must be higher", name, old));
+    }
+    if LIBRARIES.contains(&name) && !has_changelog_section(root, name, new)? {
+        return Err(format!(
+            "{}/CHANGELOG.md has no \"## {}\" section; move the Unreleased entries there first",
+            name, new
+        ));
+    }
+
+    let manifest = root.join(name).join("Cargo.toml");
+    let content = read(&manifest)?;
+    write(&manifest, &replace_quoted(&content, "version = ", &old.to_string(), &new.to_string()))?;
+    println!("{} {} -> {}", name, old, new);
+
+    for sibling in RELEASABLE.iter().filter(|sibling| **sibling != name) {
+        let manifest = root.join(sibling).join("Cargo.toml");
+        let content = read(&manifest)?;
+        let updated = replace_quoted(&content, &format!("{} = {{ path", name), &old.to_string(), &new.to_string());
+        if updated != content {
+            write(&manifest, &updated)?;
+            println!("{} now requires {} {}", sibling, name, new);
+        }
+    }
+    println!();
+    println!("Commit the bump, then run: cargo xtask tag {}", name);
+    Ok(())
+}
+
+fn git(root: &Path, args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(args)
+        .output()
+        .map_err(|e| format!("failed to run git: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("git {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Tags HEAD as `<name>-v<version>` with the version in its Cargo.toml.
+pub fn tag(root: &Path, name: &str) -> Result<(), String> {
+    let version = package_version(root, name)?;
+    let tag = format!("{}-v{}", name, version);
+    if !git(root, &["status", "--porcelain", "--", name])?.trim().is_empty() {
+        return Err(format!("{} has uncommitted changes; commit them before tagging {}", name, tag));
+    }
+    if !git(root, &["tag", "--list", &tag])?.trim().is_empty() {
+        return Err(format!("{} already exists; bump the version with cargo xtask release", tag));
+    }
+    git(root, &["tag", "-a", &tag, "-m", &format!("{} {}", name, version)])?;
+    println!("Tagged {}; push it with: git push origin {}", tag, tag);
+    Ok(())
+}